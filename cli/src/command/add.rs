@@ -0,0 +1,80 @@
+use anyhow::Result;
+use clap::Args;
+use dialoguer::theme::ColorfulTheme;
+
+use crate::i18n::{t, tf};
+use crate::interactive::{ServiceFlags, ServiceKind};
+use crate::prompt::Input;
+
+#[derive(Debug, Args)]
+pub struct Add {
+    /// The command to run; asked for if not given. The program is resolved to
+    /// an absolute path
+    command: Vec<String>,
+    #[command(flatten)]
+    flags: ServiceFlags,
+    /// Create the service without starting it
+    #[arg(long)]
+    no_start: bool,
+    /// Open the ports it declares in the firewall (ufw or firewalld; the
+    /// application firewall on macOS), closed again when it's removed
+    #[arg(long)]
+    open_firewall: bool,
+    #[command(flatten)]
+    capture: crate::command::CaptureEnv,
+}
+
+impl Add {
+    pub fn run(&self) -> Result<()> {
+        let manager = crate::command::manager();
+        if !manager.is_native() && (self.open_firewall || self.flags.create_user) {
+            anyhow::bail!(
+                "--open-firewall and --create-user change this machine, not the {} files in --dir",
+                manager.name()
+            );
+        }
+        let theme = ColorfulTheme::default();
+        let mut command = self.command.clone();
+        if command.is_empty() {
+            let line: String = Input::with_theme(&theme)
+                .with_prompt(t("Command to execute"))
+                .interact_text()?;
+            command = serlib::systemd::split_words(&line);
+            if command.is_empty() {
+                anyhow::bail!(t("No command given"));
+            }
+        }
+        let kind = if self.flags.schedule.is_some() {
+            ServiceKind::Timer
+        } else {
+            ServiceKind::Service
+        };
+        let mut details = crate::interactive::collect_service_details(
+            &theme,
+            command,
+            true,
+            kind,
+            &self.flags,
+            false,
+        )?;
+        self.capture.apply(&mut details);
+
+        crate::interactive::create_run_as_user(&self.flags, &mut details)?;
+        manager.create(&details)?;
+        println!(
+            "{}",
+            tf(
+                "Created '{}' running {}",
+                &[&details.name, &details.program]
+            )
+        );
+        if self.open_firewall {
+            crate::command::open_firewall(&manager.resolve(&details.name)?)?;
+        }
+        if !self.no_start {
+            manager.start(&details.name)?;
+            println!("{}", tf("Started '{}'", &[&details.name]));
+        }
+        Ok(())
+    }
+}
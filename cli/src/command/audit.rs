@@ -0,0 +1,102 @@
+use anyhow::Result;
+use clap::Args;
+
+#[cfg(target_os = "linux")]
+use serlib::platform::{self, ListLevel};
+
+/// Installed services whose setup deserves a second look. Unlike `ser
+/// doctor`, which checks that ser itself can work here, these are choices
+/// someone made (or forgot to make) about the services.
+#[derive(Debug, Args)]
+pub struct Audit {}
+
+// Only constructed by platform-specific audits.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+enum Finding {
+    Warn(String),
+    Note(String),
+}
+
+impl Audit {
+    pub fn run(&self) -> Result<()> {
+        // Only filled in by platform-specific audits.
+        #[allow(unused_mut)]
+        let mut findings = Vec::new();
+        #[cfg(target_os = "linux")]
+        preset_drift(&mut findings);
+        #[cfg(target_os = "linux")]
+        unrestricted_network(&mut findings)?;
+
+        let mut warnings = 0;
+        for finding in &findings {
+            match finding {
+                Finding::Warn(msg) => {
+                    warnings += 1;
+                    println!("warn  {}", msg);
+                }
+                Finding::Note(msg) => println!("note  {}", msg),
+            }
+        }
+        if warnings == 0 {
+            println!("\nNo problems found.");
+        } else {
+            println!("\n{} warning(s).", warnings);
+        }
+        Ok(())
+    }
+}
+
+/// ser's services that wait for the network but don't limit what they can
+/// reach there. launchd has no network sandbox, so this is Linux-only.
+#[cfg(target_os = "linux")]
+fn unrestricted_network(findings: &mut Vec<Finding>) -> Result<()> {
+    for service in platform::list_services(ListLevel::System)? {
+        if !platform::is_managed(&service) || !service.path.ends_with(".service") {
+            continue;
+        }
+        let Ok(details) = platform::parse_service_file(std::path::Path::new(&service.path)) else {
+            continue;
+        };
+        if details.is_network_facing() && !details.restricts_network() {
+            findings.push(Finding::Warn(format!(
+                "{} is network-facing with no network restriction; consider ser set {} ip_address_allow=..., restrict_address_families=..., or private_network=true",
+                service.name, service.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Units enabled or disabled against their vendor preset, typically distro
+/// services toggled by hand and forgotten. Only units a preset rule names are
+/// listed: where the distro enables everything else by default (Debian,
+/// Ubuntu), every disabled unit would otherwise count, so those are only
+/// counted.
+#[cfg(target_os = "linux")]
+fn preset_drift(findings: &mut Vec<Finding>) {
+    if platform::backend_unavailable().is_some() {
+        return;
+    }
+    // The user manager may not be running (e.g. under sudo); that scope is
+    // then just skipped.
+    let (named, defaulted): (Vec<_>, Vec<_>) = [false, true]
+        .into_iter()
+        .flat_map(|user| platform::unit_file_states(user).unwrap_or_default())
+        .filter(|state| state.drifted())
+        .partition(|state| state.explicit_preset);
+    for state in named {
+        findings.push(Finding::Warn(format!(
+            "{} is {} against its vendor preset ({}) (fix: ser preset {}, if that wasn't intended)",
+            state.unit,
+            state.state,
+            state.preset.unwrap_or_default(),
+            state.unit
+        )));
+    }
+    if !defaulted.is_empty() {
+        findings.push(Finding::Note(format!(
+            "{} more unit(s) differ from the catch-all preset, which covers every unit no preset file names",
+            defaulted.len()
+        )));
+    }
+}
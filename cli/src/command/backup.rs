@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::Password;
+
+use serlib::backup::{self, PASSPHRASE_ENV};
+
+#[derive(Debug, Args)]
+pub struct Backup {
+    /// Archive to write [default: ser-backup-<host>-<date>.tar.gz]
+    pub output: Option<PathBuf>,
+    /// Encrypt the archive with a passphrase (prompted, or from SER_BACKUP_PASSPHRASE)
+    #[arg(long)]
+    pub encrypt: bool,
+}
+
+impl Backup {
+    pub fn run(&self) -> Result<()> {
+        let passphrase = if self.encrypt {
+            Some(passphrase(true)?)
+        } else {
+            None
+        };
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| backup::default_archive_name(self.encrypt));
+
+        let manifest = backup::create(&output, passphrase.as_deref())?;
+        for entry in &manifest.services {
+            let state = if entry.running { "running" } else { "stopped" };
+            println!("  {} ({state})", entry.name);
+        }
+        println!(
+            "Backed up {} service(s) to {}",
+            manifest.services.len(),
+            output.display()
+        );
+        Ok(())
+    }
+}
+
+/// The backup passphrase from the environment, or prompted for (twice when
+/// `confirm`, i.e. when creating an archive).
+pub fn passphrase(confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    let theme = ColorfulTheme::default();
+    let mut prompt = Password::with_theme(&theme);
+    prompt = prompt.with_prompt("Backup passphrase");
+    if confirm {
+        prompt = prompt.with_confirmation("Repeat passphrase", "Passphrases don't match");
+    }
+    Ok(prompt.interact()?)
+}
@@ -0,0 +1,66 @@
+use anyhow::{bail, Result};
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+
+use serlib::check::{self, Level};
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum Format {
+    /// One `path:line: level: message` line per problem
+    Text,
+    /// A JSON array of problems
+    Json,
+    /// SARIF 2.1.0, for code scanning tools
+    Sarif,
+}
+
+#[derive(Debug, Args)]
+pub struct Check {
+    /// Directory (searched recursively) or file to check
+    #[arg(default_value = ".")]
+    path: PathBuf,
+
+    #[arg(long, default_value = "text", help = "Output format")]
+    format: Format,
+
+    /// Fail on warnings too, not just errors
+    #[arg(long)]
+    strict: bool,
+}
+
+impl Check {
+    pub fn run(&self) -> Result<()> {
+        let diagnostics = if self.path.is_dir() {
+            check::check_dir(&self.path)?
+        } else {
+            check::check_file(&self.path)?
+        };
+        match self.format {
+            Format::Text => {
+                for d in &diagnostics {
+                    let location = match d.line {
+                        Some(line) => format!("{}:{line}", d.path.display()),
+                        None => d.path.display().to_string(),
+                    };
+                    let level = match d.level {
+                        Level::Error => "error",
+                        Level::Warning => "warning",
+                    };
+                    println!("{location}: {level}: {} [{}]", d.message, d.rule);
+                }
+            }
+            Format::Json => println!("{}", check::to_json(&diagnostics)?),
+            Format::Sarif => println!("{}", check::to_sarif(&diagnostics)?),
+        }
+
+        let errors = diagnostics
+            .iter()
+            .filter(|d| d.level == Level::Error)
+            .count();
+        let warnings = diagnostics.len() - errors;
+        if errors > 0 || (self.strict && warnings > 0) {
+            bail!("{errors} error(s), {warnings} warning(s)");
+        }
+        Ok(())
+    }
+}
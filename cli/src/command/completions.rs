@@ -0,0 +1,256 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args, Command, CommandFactory, Subcommand};
+use clap_complete::Shell;
+use std::path::PathBuf;
+
+use serlib::platform::{self, ListLevel};
+use serlib::state::{self, NameCache, Registry};
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Completions {
+    /// Shell to print the completion script for (default: from $SHELL)
+    #[arg(value_enum)]
+    pub shell: Option<Shell>,
+    #[command(subcommand)]
+    pub command: Option<CompletionsCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CompletionsCommand {
+    /// Write the script where the shell loads completions from
+    Install {
+        /// Shell to install for (default: from $SHELL)
+        #[arg(value_enum)]
+        shell: Option<Shell>,
+    },
+}
+
+impl Completions {
+    pub fn run(&self) -> Result<()> {
+        match &self.command {
+            Some(CompletionsCommand::Install { shell }) => install(shell_or_default(*shell)?),
+            None => {
+                print!("{}", script(shell_or_default(self.shell)?));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Service names for the completion scripts, one per line. Prints the cached
+/// names right away; if they're stale and no refresh is running yet, a
+/// detached `--refresh` rescans for the next completion, so pressing tab
+/// never waits on a scan of every unit directory.
+#[derive(Debug, Args)]
+pub struct CompleteNames {
+    /// Rescan the services and update the cache before printing
+    #[arg(long)]
+    pub refresh: bool,
+}
+
+impl CompleteNames {
+    pub fn run(&self) -> Result<()> {
+        let cache = NameCache::load();
+        if self.refresh || cache.scanned_at == 0 {
+            return refresh();
+        }
+        for name in cache.completion_names(&Registry::load()?) {
+            println!("{name}");
+        }
+        // The lock is only checked here; the refresh takes it for itself.
+        if cache.is_stale() && NameCache::try_lock_refresh()?.is_some() {
+            // Failing to refresh only means the names stay old a while longer.
+            let _ = std::process::Command::new(std::env::current_exe()?)
+                .args(["__complete-names", "--refresh"])
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn();
+        }
+        Ok(())
+    }
+}
+
+/// Rescan and cache the names, then print them. If another refresh is
+/// already scanning, wait for its result instead of scanning again.
+fn refresh() -> Result<()> {
+    match NameCache::try_lock_refresh()? {
+        Some(_lock) => {
+            let names = platform::list_services(ListLevel::System)?
+                .into_iter()
+                .map(|s| s.name)
+                .collect();
+            state::cache_names(names)?;
+        }
+        None => NameCache::wait_for_refresh()?,
+    }
+    for name in NameCache::load().completion_names(&Registry::load()?) {
+        println!("{name}");
+    }
+    Ok(())
+}
+
+fn shell_or_default(shell: Option<Shell>) -> Result<Shell> {
+    shell
+        .or_else(Shell::from_env)
+        .context("Couldn't tell the shell from $SHELL; name it, e.g. `ser completions zsh`")
+}
+
+/// clap's completion script for `shell`, with service names completed for
+/// the subcommands that take them (bash, zsh, and fish).
+fn script(shell: Shell) -> String {
+    let mut cli = crate::Cli::command();
+    let mut out = Vec::new();
+    clap_complete::generate(shell, &mut cli, "ser", &mut out);
+    let script = String::from_utf8_lossy(&out).into_owned();
+    let commands = name_commands(&cli);
+    match shell {
+        Shell::Bash => bash_names(&script, &commands),
+        Shell::Zsh => zsh_names(&script),
+        Shell::Fish => script + &fish_names(&commands),
+        _ => script,
+    }
+}
+
+/// Subcommands (and their aliases) whose positional `name`/`names` argument
+/// is a service.
+fn name_commands(cli: &Command) -> Vec<String> {
+    cli.get_subcommands()
+        .filter(|sub| {
+            sub.get_positionals()
+                .any(|arg| matches!(arg.get_id().as_str(), "name" | "names"))
+        })
+        .flat_map(|sub| {
+            std::iter::once(sub.get_name().to_string())
+                .chain(sub.get_all_aliases().map(str::to_string))
+        })
+        .collect()
+}
+
+/// Run clap's `_ser`, then replace its candidates with service names after
+/// a subcommand that takes them.
+fn bash_names(script: &str, commands: &[String]) -> String {
+    let wrapper = format!(
+        r#"
+_ser_names() {{
+    _ser "$@"
+    local i cmd="" cur="${{COMP_WORDS[COMP_CWORD]}}"
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        if [[ ${{COMP_WORDS[i]}} != -* ]]; then
+            cmd="${{COMP_WORDS[i]}}"
+            break
+        fi
+    done
+    case "$cmd" in
+        {})
+            if [[ $cur != -* ]]; then
+                COMPREPLY=($(compgen -W "$(ser __complete-names 2>/dev/null)" -- "$cur"))
+            fi
+            ;;
+    esac
+}}
+"#,
+        commands.join("|")
+    );
+    // Bash looks the function up when tab is pressed, so it can follow the
+    // `complete` lines that name it.
+    script.replace("complete -F _ser ", "complete -F _ser_names ") + &wrapper
+}
+
+/// Point the `name`/`names` positionals, which clap leaves to `_default`
+/// (files), at a function listing the services.
+fn zsh_names(script: &str) -> String {
+    let mut out = String::new();
+    for line in script.lines() {
+        let trimmed = line.trim_start();
+        let is_name = [":name -- ", "::name -- ", ":names -- ", "::names -- "]
+            .iter()
+            .any(|prefix| trimmed.trim_start_matches(['\'', '*']).starts_with(prefix));
+        match line.strip_suffix(":_default' \\") {
+            Some(head) if is_name => {
+                out.push_str(head);
+                out.push_str(":_ser_service_names' \\");
+            }
+            _ => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    out + r#"
+(( $+functions[_ser_service_names] )) ||
+_ser_service_names() {
+    local -a names
+    names=(${(f)"$(ser __complete-names 2>/dev/null)"})
+    _describe -t services 'service' names
+}
+"#
+}
+
+fn fish_names(commands: &[String]) -> String {
+    format!(
+        "complete -c ser -n \"__fish_seen_subcommand_from {}\" -f -a \"(ser __complete-names 2>/dev/null)\"\n",
+        commands.join(" ")
+    )
+}
+
+fn install(shell: Shell) -> Result<()> {
+    let home = dirs::home_dir().context("No home directory to install completions into")?;
+    let env_dir = |var: &str, default: &str| {
+        std::env::var_os(var)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(default))
+    };
+    let (path, note) = match shell {
+        Shell::Bash => (
+            std::env::var_os("BASH_COMPLETION_USER_DIR")
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| env_dir("XDG_DATA_HOME", ".local/share").join("bash-completion"))
+                .join("completions/ser"),
+            "Needs the bash-completion package; open a new shell to use it.",
+        ),
+        Shell::Zsh => (
+            home.join(".zfunc/_ser"),
+            "If ~/.zfunc isn't on your fpath yet, add `fpath+=~/.zfunc` to ~/.zshrc before `compinit`, then open a new shell.",
+        ),
+        Shell::Fish => (
+            env_dir("XDG_CONFIG_HOME", ".config").join("fish/completions/ser.fish"),
+            "Open a new shell to use it.",
+        ),
+        other => bail!(
+            "Don't know where {other} loads completions from; load the output of `ser completions {other}` from your shell's profile"
+        ),
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    serlib::record_change("write", &path);
+    std::fs::write(&path, script(shell))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Installed {shell} completions to {}.", path.display());
+    println!("{note}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_service_names() {
+        let commands = name_commands(&crate::Cli::command());
+        for command in ["start", "stop", "rm", "logs"] {
+            assert!(commands.iter().any(|c| c == command), "{command}");
+        }
+        assert!(!commands.iter().any(|c| c == "list"));
+
+        let zsh = script(Shell::Zsh);
+        assert!(zsh.contains(":name -- Name of the service to start:_ser_service_names'"));
+        assert!(zsh.contains("_ser_service_names() {"));
+        let bash = script(Shell::Bash);
+        assert!(bash.contains("complete -F _ser_names "));
+        assert!(!bash.contains("complete -F _ser "));
+    }
+}
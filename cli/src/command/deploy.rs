@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+
+use serlib::hooks::{HookPoint, Hooks};
+use serlib::platform;
+use serlib::ports;
+use serlib::readiness::{self, Probe};
+
+#[derive(Debug, Args)]
+pub struct Deploy {
+    /// The service to deploy a new binary of
+    name: String,
+
+    /// The binary to run instead of the current one
+    #[arg(long, value_name = "PATH")]
+    new_binary: String,
+
+    /// Port for the green instance [default: the service's port + 1]
+    #[arg(long)]
+    green_port: Option<u16>,
+
+    /// Health check path, e.g. /health: ready once it answers 2xx [default:
+    /// once the port accepts connections]
+    #[arg(long, value_name = "PATH")]
+    health: Option<String>,
+
+    /// Seconds to wait for each instance to be healthy
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+
+    /// Command that points the reverse proxy at $SER_PORT [default: the
+    /// service's switch hook]
+    #[arg(long, value_name = "COMMAND")]
+    switch: Option<String>,
+
+    #[command(flatten)]
+    project: crate::command::ProjectHooks,
+}
+
+impl Deploy {
+    /// Blue/green: a green copy of the service on the new binary takes
+    /// traffic while the service itself (blue) restarts on it, so the proxy
+    /// never points at an instance that's down.
+    pub fn run(&self) -> Result<()> {
+        let name = platform::resolve_service_name(&self.name)?;
+        platform::require_backend(&format!("deploy '{}'", self.name))?;
+        let base = platform::normalize_service_name(&name).to_string();
+        let new_binary = std::fs::canonicalize(&self.new_binary)
+            .with_context(|| format!("No such binary: {}", self.new_binary))?
+            .to_string_lossy()
+            .into_owned();
+
+        let mut hooks = self.project.hooks_for(&name)?;
+        if let Some(switch) = &self.switch {
+            hooks.switch = Some(switch.clone());
+        }
+        if hooks.command(HookPoint::Switch).is_none() {
+            bail!(
+                "'{base}' has no switch hook to repoint its proxy; pass --switch or set \
+                 `switch` under [hooks.{base}] in the config"
+            );
+        }
+
+        let _lock = crate::command::lock_service(&name)?;
+        let blue = platform::get_service_details(&name)?;
+        if blue.trigger.is_some() {
+            bail!(
+                "'{base}' is socket- or timer-activated; deploy needs a service with its own port"
+            );
+        }
+        let port = match ports::declared_ports(&blue, &[]).as_slice() {
+            [port] => *port,
+            [] => bail!("Can't tell which port '{base}' listens on (no --port argument or PORT)"),
+            many => bail!("'{base}' declares several ports ({many:?}); deploy needs exactly one"),
+        };
+        let green_port = self.green_port.unwrap_or(port.saturating_add(1));
+        if let Some(listener) = ports::listener(green_port) {
+            bail!("Port {green_port} for the green instance is in use by {listener}; pick another with --green-port");
+        }
+
+        let green_name = format!("{base}-green");
+        let mut green = blue.service.clone();
+        green.name = green_name.clone();
+        green.program = new_binary.clone();
+        green.run_at_load = false;
+        if !ports::rewrite_port(&mut green, port, green_port) {
+            bail!("Couldn't move '{base}' to port {green_port}: port {port} isn't in its arguments or PORT");
+        }
+
+        println!("Starting '{green_name}' on port {green_port}...");
+        platform::create_service(&green)?;
+        let healthy = platform::start_service(&green_name)
+            .and_then(|()| self.wait_healthy(&green_name, green_port));
+        if let Err(e) = healthy {
+            let _ = platform::delete_service(&green_name);
+            return Err(e.context(format!(
+                "'{green_name}' never became healthy; '{base}' is untouched"
+            )));
+        }
+
+        if let Err(e) = switch(&hooks, &base, green_port) {
+            // The proxy may not have moved; nothing depends on green yet.
+            let _ = platform::delete_service(&green_name);
+            return Err(e.context(format!(
+                "Switching to '{green_name}' failed; '{base}' is untouched"
+            )));
+        }
+        println!("Restarting '{base}' on {new_binary}...");
+        let stays_on_green = || {
+            format!("traffic stays on '{green_name}' (port {green_port}) until it's switched back")
+        };
+        let healthy = platform::override_program(&name, &new_binary)
+            .and_then(|_| platform::restart_service(&name))
+            .and_then(|()| self.wait_healthy(&name, port));
+        if let Err(e) = healthy {
+            return Err(e.context(format!(
+                "'{base}' failed on the new binary; {}",
+                stays_on_green()
+            )));
+        }
+        if let Err(e) = switch(&hooks, &base, port) {
+            return Err(e.context(format!(
+                "Switching back to '{base}' failed; {}",
+                stays_on_green()
+            )));
+        }
+
+        println!("Retiring '{green_name}'...");
+        platform::delete_service(&green_name)?;
+        println!("Deployed '{base}'.");
+        Ok(())
+    }
+
+    fn wait_healthy(&self, name: &str, port: u16) -> Result<()> {
+        let probe = match &self.health {
+            Some(path) => Probe::Url(format!(
+                "http://127.0.0.1:{port}/{}",
+                path.trim_start_matches('/')
+            )),
+            None => Probe::Port(format!("127.0.0.1:{port}")),
+        };
+        readiness::wait_for(
+            name,
+            &[probe],
+            Duration::from_secs(self.timeout),
+            &Default::default(),
+        )
+    }
+}
+
+fn switch(hooks: &Hooks, service: &str, port: u16) -> Result<()> {
+    println!("Switching traffic to port {port}...");
+    hooks.run_with_env(
+        HookPoint::Switch,
+        service,
+        &[("SER_PORT", &port.to_string())],
+    )
+}
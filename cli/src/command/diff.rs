@@ -0,0 +1,86 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::path::Path;
+
+use serlib::diff::{self, FieldDiff, Impact};
+use serlib::{platform, ServiceDetails};
+
+#[derive(Debug, Args)]
+pub struct Diff {
+    /// Two unit files/plists (or local service names); with --host, the
+    /// service name
+    #[arg(required = true, num_args = 1..=2)]
+    targets: Vec<String>,
+
+    /// Compare the service on these two hosts, read over ssh (give twice)
+    #[arg(long, value_name = "HOST", num_args = 1)]
+    host: Vec<String>,
+
+    /// Exit 1 if there are runtime differences
+    #[arg(long)]
+    exit_code: bool,
+}
+
+impl Diff {
+    pub fn run(&self) -> Result<()> {
+        let (left_label, left, right_label, right) =
+            match (self.host.as_slice(), self.targets.as_slice()) {
+                ([], [left, right]) => (left.clone(), load(left)?, right.clone(), load(right)?),
+                ([], _) => bail!("Give two files or services to compare, or --host twice"),
+                ([first, second], [name]) => (
+                    first.clone(),
+                    diff::fetch_remote(first, name)?,
+                    second.clone(),
+                    diff::fetch_remote(second, name)?,
+                ),
+                _ => bail!("With --host, give it twice and a single service name"),
+            };
+
+        let diffs = diff::diff(&left, &right);
+        if diffs.is_empty() {
+            println!("No differences.");
+            return Ok(());
+        }
+        println!("--- {left_label}");
+        println!("+++ {right_label}");
+        let runtime: Vec<&FieldDiff> = diffs
+            .iter()
+            .filter(|d| d.impact == Impact::Runtime)
+            .collect();
+        let cosmetic: Vec<&FieldDiff> = diffs
+            .iter()
+            .filter(|d| d.impact == Impact::Cosmetic)
+            .collect();
+        print_section("Affects what runs:", &runtime);
+        print_section("Cosmetic:", &cosmetic);
+
+        if self.exit_code && !runtime.is_empty() {
+            return Err(crate::command::ExitStatus(1).into());
+        }
+        Ok(())
+    }
+}
+
+/// A file path, or the name of a service on this machine.
+fn load(target: &str) -> Result<ServiceDetails> {
+    let path = Path::new(target);
+    if path.is_file() {
+        return diff::load_file(path);
+    }
+    let name = platform::resolve_service_name(target)?;
+    Ok(platform::get_service_details(&name)?.service)
+}
+
+fn print_section(title: &str, diffs: &[&FieldDiff]) {
+    if diffs.is_empty() {
+        return;
+    }
+    println!();
+    println!("{title}");
+    let unset = "(unset)".to_string();
+    for diff in diffs {
+        println!("  {}:", diff.field);
+        println!("    - {}", diff.left.as_ref().unwrap_or(&unset));
+        println!("    + {}", diff.right.as_ref().unwrap_or(&unset));
+    }
+}
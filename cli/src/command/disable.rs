@@ -1,22 +1,28 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use clap::Args;
 
-use serlib::platform;
-
 #[derive(Debug, Args)]
 pub struct Disable {
     #[arg(help = "Name of the service or timer to disable")]
     pub name: String,
+    /// Also stop the service now (timers are always disarmed right away)
+    #[arg(long)]
+    pub now: bool,
 }
 
 impl Disable {
     pub fn run(&self) -> Result<()> {
-        let resolved_name = platform::resolve_service_name(&self.name)?;
+        let manager = crate::command::manager();
+        let resolved_name = manager.resolve(&self.name)?;
+        manager.require_backend(&format!("disable '{}'", self.name))?;
+        let _lock = crate::command::lock_service(&resolved_name)?;
 
-        // If the unit has a schedule, disabling it disarms the timer; otherwise
-        // it disables the service. `stop_service` routes to the timer when present.
-        let is_timer = platform::get_service_details(&resolved_name)
-            .map_err(|_| anyhow!("Service '{}' not found.", self.name))?
+        // If the unit has a schedule, disabling it disarms the timer, now and
+        // at boot. Otherwise the service won't start at boot, and keeps
+        // running unless --now is given.
+        let is_timer = manager
+            .get(&resolved_name)
+            .map_err(crate::command::not_found(&self.name))?
             .service
             .schedule
             .is_some();
@@ -26,7 +32,10 @@ impl Disable {
         } else {
             print!("Disabling service '{}'...", self.name);
         }
-        platform::stop_service(&resolved_name)?;
+        manager.disable(&resolved_name)?;
+        if is_timer || self.now {
+            manager.stop(&resolved_name)?;
+        }
         println!(" done.");
 
         Ok(())
@@ -0,0 +1,207 @@
+use anyhow::{bail, Context, Result};
+use clap::{Arg, Args, Command, CommandFactory};
+use roff::{bold, roman, Roff};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Args)]
+pub struct Docs {
+    /// Print a roff manual page (e.g. `ser docs --man > ser.1`)
+    #[arg(long, conflicts_with = "markdown")]
+    man: bool,
+    /// With --man, write `ser.1` and a page per subcommand into this directory
+    #[arg(long, value_name = "DIR", requires = "man")]
+    out_dir: Option<PathBuf>,
+    /// Print a markdown command reference
+    #[arg(long)]
+    markdown: bool,
+}
+
+/// Shown in the EXAMPLES section of both formats.
+const EXAMPLES: &[(&str, &str)] = &[
+    ("List all services", "ser list --all"),
+    ("Show details for a service", "ser show nginx"),
+    ("Create a service interactively", "ser new"),
+    (
+        "Install a service from a TOML definition and start it",
+        "ser install deploy/web.toml --start",
+    ),
+    (
+        "Restart only if the definition changed",
+        "ser restart --if-changed web",
+    ),
+    ("Follow a service's logs", "ser logs -f web"),
+    (
+        "Check unit files are formatted in CI",
+        "ser fmt --check deploy/*.service",
+    ),
+];
+
+impl Docs {
+    pub fn run(&self) -> Result<()> {
+        let mut cli = crate::Cli::command().disable_help_subcommand(true);
+        cli.build();
+        if let Some(dir) = &self.out_dir {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            write_man_pages(&cli, dir, true)?;
+        } else if self.man {
+            print!("{}", render_man(&cli, true)?);
+        } else if self.markdown {
+            print!("{}", render_markdown(&cli));
+        } else {
+            bail!("Specify --man or --markdown");
+        }
+        Ok(())
+    }
+}
+
+/// Subcommands to document, without clap's generated `help` or hidden ones.
+fn subcommands(cmd: &Command) -> impl Iterator<Item = &Command> {
+    cmd.get_subcommands()
+        .filter(|c| c.get_name() != "help" && !c.is_hide_set())
+}
+
+/// Arguments to document, without clap's generated `--help`/`--version`.
+fn global_arguments(cli: &Command) -> impl Iterator<Item = &Arg> {
+    cli.get_arguments()
+        .filter(|a| !matches!(a.get_id().as_str(), "help" | "version"))
+}
+
+/// A subcommand's own arguments; global options are documented once, on the
+/// top-level command.
+fn arguments(cmd: &Command) -> impl Iterator<Item = &Arg> {
+    global_arguments(cmd).filter(|a| !a.is_global_set())
+}
+
+/// One-line synopsis of a subcommand, e.g. `ser show [OPTIONS] <NAME>`.
+fn synopsis(cli: &Command, sub: &Command) -> String {
+    let mut parts = vec![cli.get_name().to_string(), sub.get_name().to_string()];
+    if arguments(sub).any(|a| !a.is_positional()) {
+        parts.push("[OPTIONS]".to_string());
+    }
+    parts.extend(arguments(sub).filter(|a| a.is_positional()).map(usage));
+    parts.join(" ")
+}
+
+/// How an argument is written on the command line, e.g. `-v, --verbose` or `<NAME>`.
+fn usage(arg: &Arg) -> String {
+    let value = arg
+        .get_value_names()
+        .and_then(|names| names.first())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| arg.get_id().as_str().to_uppercase());
+    if arg.is_positional() {
+        return format!("<{value}>");
+    }
+    let mut parts = Vec::new();
+    if let Some(short) = arg.get_short() {
+        parts.push(format!("-{short}"));
+    }
+    if let Some(long) = arg.get_long() {
+        parts.push(format!("--{long}"));
+    }
+    let mut usage = parts.join(", ");
+    if arg.get_action().takes_values() {
+        usage.push_str(&format!(" <{value}>"));
+    }
+    usage
+}
+
+fn help(arg: &Arg) -> String {
+    arg.get_help().map(|h| h.to_string()).unwrap_or_default()
+}
+
+fn about(cmd: &Command) -> String {
+    cmd.get_about().map(|a| a.to_string()).unwrap_or_default()
+}
+
+/// clap_mangen's page for `cmd`, labelled like the top-level one.
+fn man(cmd: &Command) -> clap_mangen::Man {
+    clap_mangen::Man::new(cmd.clone())
+        .source(format!("ser {}", env!("CARGO_PKG_VERSION")))
+        .manual("User Commands")
+}
+
+/// The manual page for `cmd`, from clap_mangen. The top-level page also gets
+/// the EXAMPLES section.
+fn render_man(cmd: &Command, examples: bool) -> std::io::Result<String> {
+    let mut out = Vec::new();
+    man(cmd).render(&mut out)?;
+    if examples {
+        let mut page = Roff::new();
+        page.control("SH", ["EXAMPLES"]);
+        for (description, example) in EXAMPLES {
+            page.control("TP", [])
+                .text([roman(*description)])
+                .text([bold(*example)]);
+        }
+        page.to_writer(&mut out)?;
+    }
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+/// Write `ser.1` and a page per subcommand (`ser-start.1`, ...), which the
+/// top-level page refers to.
+fn write_man_pages(cmd: &Command, dir: &Path, examples: bool) -> Result<()> {
+    let path = dir.join(man(cmd).get_filename());
+    std::fs::write(&path, render_man(cmd, examples)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    for sub in subcommands(cmd) {
+        write_man_pages(sub, dir, false)?;
+    }
+    Ok(())
+}
+
+fn render_markdown(cli: &Command) -> String {
+    let name = cli.get_name();
+    let mut out = format!("# {}\n\n{}\n\n## Options\n\n", name, about(cli));
+    for arg in global_arguments(cli) {
+        out.push_str(&format!("- `{}`: {}\n", usage(arg), help(arg)));
+    }
+    out.push_str("\n## Commands\n");
+    for sub in subcommands(cli) {
+        out.push_str(&format!(
+            "\n### `{} {}`\n\n{}\n\n```\n{}\n```\n",
+            name,
+            sub.get_name(),
+            about(sub),
+            synopsis(cli, sub)
+        ));
+        let documented: Vec<&Arg> = arguments(sub).collect();
+        if !documented.is_empty() {
+            out.push('\n');
+        }
+        for arg in documented {
+            out.push_str(&format!("- `{}`: {}\n", usage(arg), help(arg)));
+        }
+    }
+    out.push_str("\n## Examples\n");
+    for (description, example) in EXAMPLES {
+        out.push_str(&format!("\n{description}:\n\n```bash\n{example}\n```\n"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_every_subcommand() {
+        let mut cli = crate::Cli::command().disable_help_subcommand(true);
+        cli.build();
+        let man = render_man(&cli, true).unwrap();
+        let markdown = render_markdown(&cli);
+        for sub in subcommands(&cli) {
+            assert!(man.contains(&format!("ser\\-{}(1)", sub.get_name())));
+            assert!(markdown.contains(&format!("### `ser {}`", sub.get_name())));
+            let page = render_man(sub, false).unwrap();
+            assert!(
+                page.contains(&format!("ser\\-{}", sub.get_name())),
+                "{page}"
+            );
+        }
+        assert!(man.contains("\\-\\-verbose"));
+        assert!(man.contains(".SH EXAMPLES"));
+    }
+}
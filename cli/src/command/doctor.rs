@@ -0,0 +1,173 @@
+use anyhow::Result;
+use clap::Args;
+
+use serlib::platform;
+use serlib::platform::ListLevel;
+
+#[derive(Debug, Args)]
+pub struct Doctor {}
+
+/// The outcome of a single health check.
+enum Check {
+    Ok(String),
+    Warn(String),
+}
+
+impl Doctor {
+    pub fn run(&self) -> Result<()> {
+        let checks = run_checks()?;
+        let mut warnings = 0;
+        for check in &checks {
+            match check {
+                Check::Ok(msg) => println!("ok    {}", msg),
+                Check::Warn(msg) => {
+                    warnings += 1;
+                    println!("warn  {}", msg);
+                }
+            }
+        }
+        if warnings == 0 {
+            println!("\nNo problems found.");
+        } else {
+            println!("\n{} warning(s).", warnings);
+        }
+        Ok(())
+    }
+}
+
+fn run_checks() -> Result<Vec<Check>> {
+    // Only mutated by platform-specific checks.
+    #[allow(unused_mut)]
+    let mut checks = vec![
+        check_init_system(),
+        check_config(),
+        check_service_files(),
+        check_service_paths()?,
+    ];
+
+    #[cfg(target_os = "linux")]
+    checks.push(check_linger()?);
+    #[cfg(target_os = "linux")]
+    if platform::is_wsl() {
+        checks.push(check_wsl());
+    }
+
+    Ok(checks)
+}
+
+fn check_init_system() -> Check {
+    let host = platform::host_info();
+    match host.init_version {
+        Some(version) => Check::Ok(format!("{} found ({})", host.init_system, version)),
+        None => Check::Warn(format!(
+            "{} tools not found or not responding",
+            host.init_system
+        )),
+    }
+}
+
+fn check_config() -> Check {
+    match serlib::config::load() {
+        Ok(_) => Check::Ok("config file is valid (or absent)".to_string()),
+        Err(e) => Check::Warn(format!("{:#}", e)),
+    }
+}
+
+/// Unit files/plists that can't be read or parsed, which the init system
+/// likely can't load either and `ser list` leaves out, and unit files that
+/// don't look like unit files, which it lists anyway.
+fn check_service_files() -> Check {
+    let scan = platform::scan_services(ListLevel::System);
+    let problems: Vec<_> = scan.errors.iter().chain(&scan.warnings).collect();
+    if problems.is_empty() {
+        return Check::Ok("all service files parse".to_string());
+    }
+    Check::Warn(format!(
+        "{} service file(s) can't be parsed:\n{}",
+        problems.len(),
+        problems
+            .iter()
+            .map(|error| format!("      {error}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+/// A service whose program lives outside its `PATH` usually needs something
+/// next to it, e.g. a `#!/usr/bin/env node` script installed by Homebrew.
+fn check_service_paths() -> Result<Check> {
+    let mut outside = Vec::new();
+    for service in platform::list_services(ListLevel::System)? {
+        if !platform::is_managed(&service) {
+            continue;
+        }
+        let Ok(details) = platform::parse_service_file(std::path::Path::new(&service.path)) else {
+            continue;
+        };
+        let Some(dir) = std::path::Path::new(&details.program).parent() else {
+            continue;
+        };
+        let path = details.search_path();
+        if dir.is_absolute() && !std::env::split_paths(&path).any(|p| p == dir) {
+            outside.push(format!("{} ({})", details.name, dir.display()));
+        }
+    }
+    if outside.is_empty() {
+        return Ok(Check::Ok(
+            "managed services' programs are on their PATH".to_string(),
+        ));
+    }
+    Ok(Check::Warn(format!(
+        "these services' programs aren't on their PATH, so commands beside them won't be found: {} (fix: recreate with --capture-path)",
+        outside.join(", ")
+    )))
+}
+
+/// Without systemd under WSL, ser can only work with files.
+#[cfg(target_os = "linux")]
+fn check_wsl() -> Check {
+    if std::path::Path::new("/run/systemd/system").exists() {
+        return Check::Ok("WSL is running systemd".to_string());
+    }
+    Check::Warn(format!(
+        "services can't be started or stopped: {}",
+        platform::wsl_systemd_hint().unwrap_or("WSL isn't running systemd")
+    ))
+}
+
+/// User services that start at boot need lingering enabled for their owner.
+#[cfg(target_os = "linux")]
+fn check_linger() -> Result<Check> {
+    let Some(user) = platform::current_user() else {
+        return Ok(Check::Warn(
+            "could not determine the current user".to_string(),
+        ));
+    };
+    if platform::is_linger_enabled(&user) {
+        return Ok(Check::Ok(format!("linger is enabled for '{}'", user)));
+    }
+
+    let boot_user_services: Vec<String> = platform::list_services(ListLevel::User)?
+        .into_iter()
+        .filter(|s| platform::service_scope(s) == "user" && s.path.ends_with(".service"))
+        .filter(|s| {
+            platform::parse_service_file(std::path::Path::new(&s.path))
+                .map(|d| d.run_at_load)
+                .unwrap_or(false)
+        })
+        .map(|s| s.name)
+        .collect();
+    if boot_user_services.is_empty() {
+        Ok(Check::Ok(format!(
+            "linger is off for '{}', but no user services need to start at boot",
+            user
+        )))
+    } else {
+        Ok(Check::Warn(format!(
+            "linger is off for '{}', so these user services won't start at boot: {} (fix: loginctl enable-linger {})",
+            user,
+            boot_user_services.join(", "),
+            user
+        )))
+    }
+}
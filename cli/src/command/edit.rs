@@ -1,8 +1,14 @@
-use anyhow::Result;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
 use clap::Args;
 use std::process::Command;
 
+use serlib::guard::FileSnapshot;
 use serlib::platform;
+use serlib::plist::PlistFormat;
 
 #[derive(Debug, Args)]
 pub struct Edit {
@@ -10,11 +16,45 @@ pub struct Edit {
     pub name: String,
     #[arg(short, long, help = "Editor to use (default: $EDITOR or vim)")]
     pub editor: Option<String>,
+    #[arg(
+        long,
+        help = "Edit a vendor-owned file in place instead of creating an override"
+    )]
+    pub force: bool,
 }
 
 impl Edit {
     pub fn run(&self) -> Result<()> {
-        let service_path = platform::get_service(&self.name)?.path;
+        let service = platform::get_service(&self.name)?;
+        let mut service_path = service.path.clone();
+
+        // Changes to packaged files are lost on upgrade, so edit an override
+        // (a drop-in on Linux, a user-level copy on macOS) instead.
+        if platform::is_vendor_path(Path::new(&service.path)) {
+            if self.force && platform::is_protected(&service) && !platform::allows_protected() {
+                anyhow::bail!(
+                    "{} is an Apple system service and cannot be edited in place; drop --force to edit a user-level copy, or pass --allow-apple to try anyway",
+                    service.path
+                );
+            } else if self.force {
+                eprintln!(
+                    "Warning: {} is vendor-owned; edits may be lost on upgrade.",
+                    service.path
+                );
+            } else {
+                let override_path = platform::prepare_override(&service)?;
+                eprintln!(
+                    "{} is vendor-owned; editing override {} instead (use --force to edit the original).",
+                    service.path,
+                    override_path.display()
+                );
+                service_path = override_path.to_string_lossy().to_string();
+            }
+        }
+
+        if service_path == service.path {
+            crate::command::warn_if_package_owned(&service_path);
+        }
 
         let editor = self
             .editor
@@ -22,16 +62,61 @@ impl Edit {
             .or_else(|| std::env::var("EDITOR").ok())
             .unwrap_or_else(|| "vim".to_string());
 
-        let mut cmd = Command::new(&editor);
-        cmd.arg(&service_path);
-
-        let status = cmd.status()?;
+        // Keep other ser commands on this service (a second `ser edit`, `ser
+        // set`, a restart) from running meanwhile; the file lock also keeps
+        // out `ser fmt` and `ser lint --fix`, which work on paths.
+        let _lock = crate::command::lock_service(&service.name)?;
+        let _file_lock = crate::command::lock_service_file(&service_path)?;
 
-        if !status.success() {
-            return Err(anyhow::anyhow!("Editor exited with non-zero status"));
+        let path = Path::new(&service_path);
+        let snapshot = FileSnapshot::take(path);
+        let contents =
+            fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if path.extension().is_some_and(|ext| ext == "plist")
+            && PlistFormat::detect(&contents) == PlistFormat::Binary
+        {
+            edit_binary_plist(&editor, path, &contents, &snapshot)?;
+        } else {
+            run_editor(&editor, path)?;
         }
 
         println!("Service file edited: {service_path}");
         Ok(())
     }
 }
+
+fn run_editor(editor: &str, path: &Path) -> Result<()> {
+    let status = Command::new(editor).arg(path).status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("Editor exited with non-zero status"));
+    }
+    Ok(())
+}
+
+/// A binary plist can't be edited as text, so edit an XML copy and write it
+/// back as binary, asking first if something else changed the file while the
+/// editor was open.
+fn edit_binary_plist(
+    editor: &str,
+    path: &Path,
+    contents: &[u8],
+    snapshot: &FileSnapshot,
+) -> Result<()> {
+    let mut copy = tempfile::Builder::new()
+        .prefix("ser-edit-")
+        .suffix(".plist")
+        .tempfile()
+        .context("Failed to create a temporary file")?;
+    copy.write_all(&serlib::plist::convert_plist(contents, PlistFormat::Xml)?)?;
+    copy.flush()?;
+    run_editor(editor, copy.path())?;
+    // Editors often save by replacing the file, so read it back by path.
+    let xml = fs::read(copy.path())?;
+    let data = serlib::plist::convert_plist(&xml, PlistFormat::Binary)
+        .with_context(|| format!("Not saving {}; the edit left it invalid", path.display()))?;
+    if !crate::command::confirm_overwrite(snapshot)? {
+        anyhow::bail!("Not saving {}", path.display());
+    }
+    serlib::record_change("write", path);
+    serlib::state::write_atomic(path, data)
+}
@@ -1,22 +1,28 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use clap::Args;
 
-use serlib::platform;
-
 #[derive(Debug, Args)]
 pub struct Enable {
     #[arg(help = "Name of the service or timer to enable")]
     pub name: String,
+    /// Also start the service now (timers are always armed right away)
+    #[arg(long)]
+    pub now: bool,
 }
 
 impl Enable {
     pub fn run(&self) -> Result<()> {
-        let resolved_name = platform::resolve_service_name(&self.name)?;
+        let manager = crate::command::manager();
+        let resolved_name = manager.resolve(&self.name)?;
+        manager.require_backend(&format!("enable '{}'", self.name))?;
+        let _lock = crate::command::lock_service(&resolved_name)?;
 
-        // If the unit has a schedule, enabling it arms the timer; otherwise it
-        // enables the service. `start_service` routes to the timer when present.
-        let is_timer = platform::get_service_details(&resolved_name)
-            .map_err(|_| anyhow!("Service '{}' not found.", self.name))?
+        // If the unit has a schedule, enabling it arms the timer, now and at
+        // boot; starting a timer doesn't run the job. Otherwise the service
+        // starts at boot, and now only with --now.
+        let is_timer = manager
+            .get(&resolved_name)
+            .map_err(crate::command::not_found(&self.name))?
             .service
             .schedule
             .is_some();
@@ -26,9 +32,59 @@ impl Enable {
         } else {
             print!("Enabling service '{}'...", self.name);
         }
-        platform::start_service(&resolved_name)?;
+        manager.enable(&resolved_name)?;
+        if is_timer || self.now {
+            manager.start(&resolved_name)?;
+        }
         println!(" done.");
 
+        offer_linger(&resolved_name)?;
         Ok(())
     }
 }
+
+/// User services only start at boot when lingering is enabled for the user.
+/// If `name` is such a service and linger is off, offer to turn it on.
+#[cfg(target_os = "linux")]
+pub fn offer_linger(name: &str) -> Result<()> {
+    use crate::prompt::Confirm;
+    use dialoguer::theme::ColorfulTheme;
+    use serlib::platform;
+
+    let service_ref = platform::get_service(name)?;
+    if platform::service_scope(&service_ref) != "user" {
+        return Ok(());
+    }
+    let run_at_load = platform::get_service_details(name)
+        .map(|d| d.service.run_at_load)
+        .unwrap_or(false);
+    let Some(user) = platform::current_user() else {
+        return Ok(());
+    };
+    if !run_at_load || platform::is_linger_enabled(&user) {
+        return Ok(());
+    }
+
+    eprintln!(
+        "Note: '{}' is a user service, and lingering is off for '{}', so it won't start at boot.",
+        name, user
+    );
+    if atty::isnt(atty::Stream::Stdin) {
+        eprintln!("Run `loginctl enable-linger {}` to fix this.", user);
+        return Ok(());
+    }
+    let enable = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Enable lingering for '{}'?", user))
+        .default(true)
+        .interact()?;
+    if enable {
+        platform::enable_linger(&user)?;
+        println!("Lingering enabled for '{}'.", user);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn offer_linger(_name: &str) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use clap::Args;
+
+use serlib::platform;
+
+#[derive(Debug, Args)]
+pub struct Env {
+    #[arg(help = "Name of the service")]
+    pub name: String,
+    /// Print `export KEY='VALUE'` lines for `eval $(ser env app --export)`
+    #[arg(long)]
+    pub export: bool,
+}
+
+impl Env {
+    pub fn run(&self) -> Result<()> {
+        let resolved_name = platform::resolve_service_name(&self.name)?;
+        let details = platform::get_service_details(&resolved_name)?;
+        for (key, value) in details.service.effective_env()? {
+            if self.export {
+                println!("export {}={}", key, shell_quote(&value));
+            } else {
+                println!("{key}={value}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Single-quote a value for POSIX shells.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_for_shell() {
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
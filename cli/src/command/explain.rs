@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, ValueEnum};
+
+#[derive(Debug, Args)]
+pub struct Explain {
+    #[arg(value_enum)]
+    pub which: Which,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum Which {
+    /// The most recent ser invocation that ran a command or changed a file
+    Last,
+}
+
+impl Explain {
+    pub fn run(&self) -> Result<()> {
+        let path = trace_path().ok_or_else(|| anyhow!("No state directory available"))?;
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("Nothing recorded yet.");
+                return Ok(());
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        print!("{contents}");
+        Ok(())
+    }
+}
+
+fn trace_path() -> Option<PathBuf> {
+    serlib::config::state_dir().map(|dir| dir.join("last-explain.txt"))
+}
+
+/// Print what this invocation ran and changed (with `--explain`), and keep it
+/// for `ser explain last`. Invocations that did neither leave the previous
+/// record alone.
+pub fn finish(print: bool) {
+    let trace = serlib::take_trace();
+    if print {
+        eprintln!();
+        if trace.is_empty() {
+            eprintln!("No commands were run and no files were changed.");
+        } else {
+            eprintln!("Commands run and files changed:");
+            for line in &trace {
+                eprintln!("  {line}");
+            }
+        }
+    }
+    if trace.is_empty() {
+        return;
+    }
+    let Some(path) = trace_path() else {
+        return;
+    };
+    let invocation: Vec<String> = std::env::args().collect();
+    let record = format!(
+        "# {}\n{}\n",
+        serlib::redact_args(&invocation).join(" "),
+        trace.join("\n")
+    );
+    // Only a convenience, so a failure to save it isn't worth reporting
+    let _ = path
+        .parent()
+        .map(fs::create_dir_all)
+        .transpose()
+        .and_then(|_| write_private(&path, &record));
+}
+
+/// Write the record readable only by its owner: even redacted, the commands
+/// `ser` ran are nobody else's business.
+fn write_private(path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to a new file; tighten one left by an older ser.
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    std::io::Write::write_all(&mut options.open(path)?, contents.as_bytes())
+}
@@ -0,0 +1,78 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serlib::platform;
+use serlib::plist::PlistFormat;
+
+#[derive(Debug, Args)]
+pub struct Fmt {
+    /// Service names or paths to unit files/plists
+    #[arg(required = true)]
+    targets: Vec<String>,
+
+    /// Don't write anything; exit non-zero if any file isn't formatted
+    #[arg(long)]
+    check: bool,
+
+    /// Write plists in this format (default: the one each is in; OpenStep
+    /// plists become XML)
+    #[arg(long, value_enum)]
+    plist_format: Option<crate::command::PlistEncoding>,
+}
+
+impl Fmt {
+    pub fn run(&self) -> Result<()> {
+        let mut unformatted = Vec::new();
+        for target in &self.targets {
+            let path = resolve_path(target)?;
+            // Held from the read to the write, so a concurrent change isn't
+            // overwritten with this one's stale formatting.
+            let _lock = if self.check {
+                None
+            } else {
+                Some(crate::command::lock_service_file(&path.to_string_lossy())?)
+            };
+            let contents =
+                fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let formatted = self.format_contents(&path, &contents)?;
+            if formatted == contents {
+                continue;
+            }
+            if self.check {
+                println!("{}", path.display());
+                unformatted.push(path);
+            } else {
+                serlib::record_change("write", &path);
+                serlib::state::write_atomic(&path, formatted)?;
+                eprintln!("Formatted {}", path.display());
+            }
+        }
+        if !unformatted.is_empty() {
+            bail!("{} file(s) need formatting", unformatted.len());
+        }
+        Ok(())
+    }
+
+    fn format_contents(&self, path: &Path, contents: &[u8]) -> Result<Vec<u8>> {
+        if path.extension().is_some_and(|ext| ext == "plist") {
+            let format = match self.plist_format {
+                Some(encoding) => encoding.into(),
+                None => PlistFormat::detect(contents),
+            };
+            return serlib::plist::format_plist_as(contents, format);
+        }
+        let text = std::str::from_utf8(contents)
+            .with_context(|| format!("{} is not valid UTF-8", path.display()))?;
+        Ok(serlib::systemd::format_unit(text).into_bytes())
+    }
+}
+
+pub(crate) fn resolve_path(target: &str) -> Result<PathBuf> {
+    let path = Path::new(target);
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+    Ok(PathBuf::from(platform::get_service(target)?.path))
+}
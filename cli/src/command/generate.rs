@@ -1,7 +1,11 @@
 use anyhow::Result;
 use clap::{Args, ValueEnum};
 use dialoguer::theme::ColorfulTheme;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::interactive::ServiceKind;
+use serlib::platform;
+use serlib::ServiceDetails;
 
 #[derive(Debug, Clone, ValueEnum)]
 pub enum Format {
@@ -9,25 +13,100 @@ pub enum Format {
     Native,
     /// Generate systemd service file
     Systemd,
+    /// Generate a launchd plist, translating systemd specifiers like %h
+    Launchd,
 }
 
 #[derive(Debug, Args)]
 pub struct Generate {
     #[arg(long, default_value = "systemd", help = "Output format")]
     format: Format,
+    /// How to write a launchd plist
+    #[arg(long, value_enum, default_value = "xml")]
+    plist_format: crate::command::PlistEncoding,
+    /// Regenerate from an existing service (name or file path) instead of prompting
+    #[arg(long, value_name = "SERVICE", conflicts_with = "command")]
+    from: Option<String>,
+    /// The command to run. Given one, nothing is prompted for: anything
+    /// not set by flags takes its default
     command: Vec<String>,
+    #[command(flatten)]
+    flags: crate::interactive::ServiceFlags,
+    #[command(flatten)]
+    capture: crate::command::CaptureEnv,
 }
 
 impl Generate {
     pub fn run(&self) -> Result<()> {
-        let theme = ColorfulTheme::default();
-        let kind = crate::interactive::prompt_service_kind(&theme)?;
-        let details =
-            crate::interactive::collect_service_details(&theme, self.command.clone(), false, kind)?;
+        if self.flags.create_user {
+            anyhow::bail!(
+                "--create-user only applies when creating a service (ser new or ser add)"
+            );
+        }
+        let mut details = match &self.from {
+            Some(source) => load_existing(source)?,
+            None => {
+                let theme = ColorfulTheme::default();
+                let prompt = self.command.is_empty();
+                let kind = if prompt {
+                    crate::interactive::service_kind(&theme, &self.flags)?
+                } else if self.flags.schedule.is_some() {
+                    ServiceKind::Timer
+                } else {
+                    ServiceKind::Service
+                };
+                crate::interactive::collect_service_details(
+                    &theme,
+                    self.command.clone(),
+                    false,
+                    kind,
+                    &self.flags,
+                    prompt,
+                )?
+            }
+        };
+
+        self.capture.apply(&mut details);
+
+        let plist = matches!(self.format, Format::Launchd)
+            || matches!(self.format, Format::Native) && cfg!(target_os = "macos");
+        if !plist && matches!(self.plist_format, crate::command::PlistEncoding::Binary) {
+            anyhow::bail!("--plist-format only applies to launchd plists");
+        }
 
         let content = match self.format {
+            Format::Native if plist => {
+                let xml = serlib::platform::generate_file(&details)?;
+                crate::command::print_plist(&xml, self.plist_format)?;
+                eprintln!(
+                    "~/Library/LaunchAgents/{}.plist is the suggested file path.",
+                    details.name
+                );
+                return Ok(());
+            }
+            Format::Native if cfg!(windows) => {
+                println!("{}", serlib::platform::generate_file(&details)?);
+                eprintln!(
+                    "%ProgramData%\\ser\\services\\{}.json is the suggested file path.",
+                    details.name
+                );
+                return Ok(());
+            }
             Format::Native => serlib::platform::generate_file(&details)?,
             Format::Systemd => serlib::systemd::generate_file(&details)?,
+            Format::Launchd => {
+                let (translated, warnings) = serlib::specifiers::translate_for_launchd(&details);
+                for warning in warnings {
+                    eprintln!("Warning: {warning}");
+                }
+                let xml = serlib::plist::generate_file(&translated)?;
+                crate::command::print_plist(&xml, self.plist_format)?;
+                eprintln!(
+                    "~/Library/LaunchAgents/{}.plist is the suggested file path.",
+                    details.name
+                );
+                return Ok(());
+            }
         };
         println!("{content}");
 
@@ -53,3 +132,13 @@ impl Generate {
         Ok(())
     }
 }
+
+/// Parse an existing definition, given either as a file path or a service name.
+fn load_existing(source: &str) -> Result<ServiceDetails> {
+    let path = Path::new(source);
+    if path.is_file() {
+        return platform::parse_service_file(path);
+    }
+    let name = platform::resolve_service_name(source)?;
+    Ok(platform::get_service_details(&name)?.service)
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use clap::Args;
+
+use serlib::platform::{self, ListLevel};
+
+#[derive(Debug, Args)]
+pub struct Info {}
+
+impl Info {
+    pub fn run(&self) -> Result<()> {
+        let host = platform::host_info();
+        println!("ser: {}", crate::VERSION);
+        println!("Init system: {}", host.init_system);
+        println!(
+            "Init version: {}",
+            host.init_version.as_deref().unwrap_or("unknown")
+        );
+        if let Some(environment) = &host.environment {
+            println!("Environment: {environment}");
+        }
+        match platform::backend_unavailable() {
+            None => println!("Mode: managing services through {}", host.init_system),
+            Some(reason) => println!("Mode: files only; {reason}"),
+        }
+        for (facility, available) in &host.facilities {
+            println!(
+                "{}: {}",
+                facility,
+                if *available { "available" } else { "not found" }
+            );
+        }
+
+        match serlib::config::config_path() {
+            Some(path) => println!(
+                "Config file: {}{}",
+                path.display(),
+                if path.exists() { "" } else { " (not present)" }
+            ),
+            None => println!("Config file: none (no config directory)"),
+        }
+
+        match serlib::config::state_dir() {
+            Some(path) => println!("State directory: {}", path.display()),
+            None => println!("State directory: none (no home directory)"),
+        }
+
+        let dirs = platform::service_directories();
+        print_dirs("Default scope directories", &dirs.default_dirs);
+        print_dirs("User directories", &dirs.user_dirs);
+        print_dirs("System directories", &dirs.system_dirs);
+
+        let services = platform::list_services(ListLevel::System)?;
+        let managed = services.iter().filter(|s| platform::is_managed(s)).count();
+        println!(
+            "Services: {} total, {} managed by ser",
+            services.len(),
+            managed
+        );
+        Ok(())
+    }
+}
+
+fn print_dirs(label: &str, dirs: &[std::path::PathBuf]) {
+    println!("{}:", label);
+    for dir in dirs {
+        let note = if dir.exists() { "" } else { " (missing)" };
+        println!("  {}{}", dir.display(), note);
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serlib::platform;
+use serlib::spec::ServiceSpec;
+
+#[derive(Debug, Args)]
+pub struct Install {
+    /// Service definition (`.toml`) or a ready-made unit file/plist
+    file: PathBuf,
+
+    /// Start the service (or enable the timer) after installing
+    #[arg(long)]
+    start: bool,
+
+    /// Replace an existing service that ser doesn't manage
+    #[arg(long)]
+    force: bool,
+
+    /// Install a unit file/plist for the current user only
+    #[arg(long, conflicts_with = "system")]
+    user: bool,
+
+    /// Install a unit file/plist system-wide
+    #[arg(long)]
+    system: bool,
+}
+
+impl Install {
+    pub fn run(&self) -> Result<()> {
+        let name = self
+            .file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if !ServiceSpec::is_spec_path(&self.file) {
+            self.check_existing(&name)?;
+            let relocated = Relocated::new(&self.file)?;
+            let source = relocated.as_ref().map_or(self.file.as_path(), |r| &r.path);
+            let path = platform::install_service_file(source, self.user_scope(), self.start)?;
+            println!("Installed {}.", path.display());
+            if self.start {
+                println!("Started '{}'.", name);
+            }
+            return Ok(());
+        }
+
+        if self.user || self.system {
+            bail!("--user/--system only apply to unit files and plists");
+        }
+        let mut details = ServiceSpec::from_path(&self.file)?.into_details(&name)?;
+        details.program = crate::interactive::resolve_binary_path(&details.program)?;
+        self.check_existing(&details.name)?;
+
+        platform::create_service(&details)?;
+        println!("Installed '{}'.", details.name);
+
+        if self.start {
+            platform::start_service(&details.name)?;
+            println!("Started '{}'.", details.name);
+        }
+        Ok(())
+    }
+
+    /// Without a flag, use the scope `ser new` creates services in: system
+    /// units on Linux, LaunchAgents on macOS.
+    fn user_scope(&self) -> bool {
+        if self.user || self.system {
+            return self.user;
+        }
+        cfg!(target_os = "macos")
+    }
+
+    fn check_existing(&self, name: &str) -> Result<()> {
+        if let Ok(existing) = platform::get_service(name) {
+            if !platform::is_managed(&existing) && !self.force {
+                bail!(
+                    "'{}' already exists at {} and isn't managed by ser; use --force to replace it",
+                    name,
+                    existing.path
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A copy of a unit file or plist whose program doesn't exist on this
+/// machine, rewritten to where the program does live. Removed when dropped.
+struct Relocated {
+    _dir: tempfile::TempDir,
+    path: PathBuf,
+}
+
+impl Relocated {
+    /// `None` if the file's program exists, so it can be installed as-is.
+    fn new(file: &Path) -> Result<Option<Relocated>> {
+        let Ok(details) = platform::parse_service_file(file) else {
+            return Ok(None);
+        };
+        let program = details.program;
+        if program.is_empty() || Path::new(&program).exists() {
+            return Ok(None);
+        }
+        let found = crate::interactive::resolve_binary_path(&program)?;
+
+        let dir = tempfile::Builder::new()
+            .prefix("ser-install-")
+            .tempdir()
+            .context("Failed to create a temporary directory")?;
+        let path = dir.path().join(file.file_name().unwrap_or_default());
+        if file.extension().is_some_and(|ext| ext == "plist") {
+            let mut plist = plist::Value::from_file(file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            relocate_plist(&mut plist, &program, &found);
+            plist
+                .to_file_xml(&path)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        } else {
+            let contents = fs::read_to_string(file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            fs::write(&path, relocate_unit(&contents, &program, &found))
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(Some(Relocated { _dir: dir, path }))
+    }
+}
+
+/// `contents` with `program` swapped for `found` where an `ExecStart=` line
+/// runs it (after any `-@:+!` prefixes); the rest of the file is untouched.
+fn relocate_unit(contents: &str, program: &str, found: &str) -> String {
+    contents
+        .split_inclusive('\n')
+        .map(|line| {
+            let Some(value) = line.trim_start().strip_prefix("ExecStart=") else {
+                return line.to_string();
+            };
+            let command = value.trim_start_matches(['-', '@', ':', '+', '!']);
+            match command.strip_prefix(program) {
+                Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => {
+                    format!("{}{found}{rest}", &line[..line.len() - command.len()])
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Point a plist's `Program`, or the program in its `ProgramArguments` (past
+/// ser's `sh -c` wrapper, if it has one), at `found`.
+fn relocate_plist(plist: &mut plist::Value, program: &str, found: &str) {
+    let Some(dict) = plist.as_dictionary_mut() else {
+        return;
+    };
+    let relocate = |value: Option<&mut plist::Value>| {
+        if let Some(plist::Value::String(path)) = value {
+            if path == program {
+                *path = found.to_string();
+            }
+        }
+    };
+    relocate(dict.get_mut("Program"));
+    if let Some(args) = dict
+        .get_mut("ProgramArguments")
+        .and_then(plist::Value::as_array_mut)
+    {
+        let strings: Vec<String> = args
+            .iter()
+            .filter_map(|arg| arg.as_string().map(str::to_string))
+            .collect();
+        let index = if serlib::plist::unwrap_wrapper(&strings).is_some() {
+            3
+        } else {
+            0
+        };
+        relocate(args.get_mut(index));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocates_only_the_program() {
+        let unit = "[Unit]\nDescription=/opt/homebrew/bin/foo\n\n[Service]\nExecStart=-/opt/homebrew/bin/foo --config /opt/homebrew/bin/foo.toml\nExecStartPre=/opt/homebrew/bin/foo check\n";
+        assert_eq!(
+            relocate_unit(unit, "/opt/homebrew/bin/foo", "/usr/local/bin/foo"),
+            "[Unit]\nDescription=/opt/homebrew/bin/foo\n\n[Service]\nExecStart=-/usr/local/bin/foo --config /opt/homebrew/bin/foo.toml\nExecStartPre=/opt/homebrew/bin/foo check\n"
+        );
+    }
+}
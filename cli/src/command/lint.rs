@@ -0,0 +1,93 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serlib::lint::{self, Report};
+
+#[derive(Debug, Args)]
+pub struct Lint {
+    /// Service names or paths to unit files/plists
+    #[arg(required = true)]
+    targets: Vec<String>,
+
+    /// Apply safe fixes (the original is kept in ser's backups directory)
+    #[arg(long)]
+    fix: bool,
+}
+
+impl Lint {
+    pub fn run(&self) -> Result<()> {
+        let mut remaining = 0;
+        for target in &self.targets {
+            let path = crate::command::fmt::resolve_path(target)?;
+            // Held from the read to the write, so a fix computed from this
+            // read never lands over a change made meanwhile.
+            let _lock = if self.fix {
+                Some(crate::command::lock_service_file(&path.to_string_lossy())?)
+            } else {
+                None
+            };
+            let contents =
+                fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let report = lint_contents(&path, &contents)?;
+            let fixing = self.fix && report.findings.iter().any(|f| f.fixable);
+            for finding in &report.findings {
+                let location = match finding.line {
+                    Some(line) => format!("{}:{line}", path.display()),
+                    None => path.display().to_string(),
+                };
+                let note = if fixing && finding.fixable {
+                    " (fixed)"
+                } else if finding.fixable {
+                    " (fixable with --fix)"
+                } else {
+                    ""
+                };
+                println!("{location}: {}{note}", finding.message);
+                if !(fixing && finding.fixable) {
+                    remaining += 1;
+                }
+            }
+            if fixing {
+                let backup = save_original(&path)?;
+                serlib::record_change("write", &path);
+                serlib::state::write_atomic(&path, &report.fixed)?;
+                eprintln!(
+                    "Fixed {} (original saved to {})",
+                    path.display(),
+                    backup.display()
+                );
+            }
+        }
+        if remaining > 0 {
+            bail!("{remaining} problem(s) found");
+        }
+        Ok(())
+    }
+}
+
+/// Copy a file about to be fixed into ser's backups directory, named after
+/// its whole path and the time, and return where it went.
+fn save_original(path: &Path) -> Result<PathBuf> {
+    let dir = serlib::config::backup_dir().context("No state directory to keep the original in")?;
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let full = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let backup = dir.join(format!(
+        "{}.{}",
+        full.to_string_lossy().replace('/', "%"),
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    // fs::copy keeps the original's permissions, in case it holds secrets.
+    fs::copy(path, &backup).with_context(|| format!("Failed to back up {}", path.display()))?;
+    Ok(backup)
+}
+
+fn lint_contents(path: &Path, contents: &[u8]) -> Result<Report> {
+    if path.extension().is_some_and(|ext| ext == "plist") {
+        return lint::lint_plist(contents);
+    }
+    let text = std::str::from_utf8(contents)
+        .with_context(|| format!("{} is not valid UTF-8", path.display()))?;
+    Ok(lint::lint_unit(text))
+}
@@ -1,20 +1,74 @@
-use anyhow::Result;
-use clap::Args;
+use anyhow::{anyhow, Result};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
 use std::collections::HashSet;
 use tabled::{
-    settings::{Padding, Style},
+    settings::{location::ByColumnName, Padding, Remove, Style},
     Table, Tabled,
 };
 
-use serlib::{
-    platform::{self, ListLevel, ServiceRef},
-    systemd::MANAGED_BY_COMMENT,
-};
+use crate::command::DataFormat;
+use serlib::platform::{self, ListLevel, ServiceManager, ServiceRef, Source};
+use serlib::state::Registry;
 
 #[derive(Debug, Args)]
 pub struct List {
     #[arg(short, long, help = "Show all services (system and user)")]
     pub all: bool,
+    #[arg(
+        long,
+        requires = "all",
+        help = "With --all, also show Apple system services (macOS)"
+    )]
+    pub include_apple: bool,
+    #[arg(short, long, help = "Only show services with this tag")]
+    pub tag: Option<String>,
+    #[arg(
+        long,
+        value_name = "SOURCE",
+        num_args = 0..=1,
+        help = "Show where each service came from; with a value (ser, homebrew, vendor, apple, custom), only that source"
+    )]
+    pub source: Option<Option<String>>,
+    /// Also show services matching the `hidden` patterns in the config file
+    #[arg(long)]
+    pub no_hidden: bool,
+    /// Print only service names, one per line (for shell completion)
+    #[arg(long)]
+    pub names: bool,
+    /// Stable, tab-separated output for scripts (same as --format tsv)
+    #[arg(long, conflicts_with = "format")]
+    pub porcelain: bool,
+    #[arg(long, help = "Output format")]
+    pub format: Option<ListFormat>,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum ListFormat {
+    /// Aligned columns for reading; may change between versions
+    Table,
+    /// The porcelain format: one service per line with the tab-separated
+    /// columns name, type, status, enabled, schedule, source, path. Columns
+    /// are never added in the middle, truncated, or translated; `-` marks an
+    /// empty value.
+    Tsv,
+    /// A JSON array of services, for scripts
+    Json,
+    /// The same as YAML
+    Yaml,
+}
+
+/// A service in `--format json`/`yaml`.
+#[derive(Serialize)]
+struct ListEntry {
+    #[serde(flatten)]
+    service: ServiceRef,
+    #[serde(rename = "type")]
+    service_type: String,
+    running: bool,
+    /// The next run for a timer, when known.
+    schedule: Option<String>,
+    pinned: bool,
 }
 
 #[derive(Tabled)]
@@ -29,8 +83,20 @@ struct ServiceRow {
     enabled: String,
     #[tabled(rename = "Schedule")]
     schedule: String,
+    #[tabled(rename = "Source")]
+    source: String,
     #[tabled(rename = "Path")]
     path: String,
+    #[tabled(skip)]
+    pinned: bool,
+}
+
+impl ServiceRow {
+    /// A pinned service that should be running but isn't. Timers are only
+    /// running mid-run, so they never count.
+    fn needs_attention(&self) -> bool {
+        self.pinned && self.status != "running" && self.service_type != "timer"
+    }
 }
 
 impl List {
@@ -40,107 +106,294 @@ impl List {
         } else {
             ListLevel::Default
         };
-        let mut services = platform::list_services(level)?;
-        services.sort_by(|a, b| a.name.cmp(&b.name));
-        if services.is_empty() {
-            eprintln!("No services found.");
-            return Ok(());
+        let manager = crate::command::manager();
+        let mut services = listed_services(manager, level, self.include_apple, self.no_hidden);
+
+        if let Some(Some(wanted)) = &self.source {
+            let wanted =
+                Source::parse(wanted).ok_or_else(|| anyhow!("Unknown source '{}'", wanted))?;
+            services.retain(|s| s.source == wanted);
         }
 
-        if matches!(level, ListLevel::Default) {
-            services.retain(|s| {
-                if s.path.contains("systemd") {
-                    if let Ok(content) = std::fs::read_to_string(&s.path) {
-                        content.starts_with(MANAGED_BY_COMMENT)
-                    } else {
-                        false
-                    }
-                } else {
-                    true
-                }
-            });
+        if let Some(tag) = &self.tag {
+            services.retain(|s| platform::service_tags(s).iter().any(|t| t == tag));
         }
 
-        // Filter out .timer files that have a matching .service file
-        // to avoid duplicate display (we'll show the service with timer info instead)
-        let timer_base_names: HashSet<_> = services
-            .iter()
-            .filter(|s| s.name.ends_with(".timer"))
-            .map(|s| s.name.trim_end_matches(".timer").to_string())
-            .collect();
+        let output = if self.names {
+            Output::Names
+        } else if self.porcelain {
+            Output::Porcelain
+        } else {
+            match self.format {
+                Some(ListFormat::Table) => Output::Table,
+                Some(ListFormat::Tsv) => Output::Porcelain,
+                Some(ListFormat::Json) => Output::Data(DataFormat::Json),
+                Some(ListFormat::Yaml) => Output::Data(DataFormat::Yaml),
+                None => Output::Auto,
+            }
+        };
+        print_services(manager, services, output, self.source.is_some())
+    }
+}
 
-        let service_base_names: HashSet<_> = services
-            .iter()
-            .filter(|s| s.name.ends_with(".service"))
-            .map(|s| s.name.trim_end_matches(".service").to_string())
-            .collect();
+/// How [`print_services`] shows the services.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Output {
+    /// A table on a terminal, tab-separated lines without headers on a pipe
+    Auto,
+    /// Always the table
+    Table,
+    /// `--format tsv`
+    Porcelain,
+    /// `--format json`/`yaml`
+    Data(DataFormat),
+    /// Only the names, one per line
+    Names,
+}
 
-        services.retain(|s| {
-            if s.name.ends_with(".timer") {
-                // Keep timer only if there's no matching service
-                let base_name = s.name.trim_end_matches(".timer");
-                !service_base_names.contains(base_name)
-            } else {
-                true
-            }
-        });
+/// The services `ser list` shows at `level`, sorted by name: only the
+/// managed ones at the default level, and without Apple system services or
+/// ones the config file hides unless asked for.
+pub(crate) fn listed_services(
+    manager: &dyn ServiceManager,
+    level: ListLevel,
+    include_apple: bool,
+    include_hidden: bool,
+) -> Vec<ServiceRef> {
+    let scan = manager.scan(level);
+    if serlib::is_verbose() {
+        for error in &scan.errors {
+            eprintln!("Skipped {error}");
+        }
+        for warning in &scan.warnings {
+            eprintln!("Warning: {warning}");
+        }
+    }
+    let mut services = scan.services;
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if matches!(level, ListLevel::Default) {
+        services.retain(platform::is_managed);
+    }
+
+    if !include_apple {
+        services.retain(|s| !platform::is_protected(s));
+    }
+
+    if !include_hidden {
+        services.retain(|s| !serlib::config::is_hidden(&s.name));
+    }
+    services
+}
+
+/// Print `services` the way `ser list` does: pinned ones first, a timer
+/// folded into its service, and with their running state and schedule.
+pub(crate) fn print_services(
+    manager: &dyn ServiceManager,
+    mut services: Vec<ServiceRef>,
+    output: Output,
+    show_source: bool,
+) -> Result<()> {
+    if services.is_empty() && !matches!(output, Output::Data(_)) {
+        eprintln!("No services found.");
+        return Ok(());
+    }
+
+    // Filter out .timer files that have a matching .service file
+    // to avoid duplicate display (we'll show the service with timer info instead)
+    let timer_base_names: HashSet<_> = services
+        .iter()
+        .filter(|s| s.name.ends_with(".timer"))
+        .map(|s| s.name.trim_end_matches(".timer").to_string())
+        .collect();
 
-        let rows: Vec<ServiceRow> = services
+    let service_base_names: HashSet<_> = services
+        .iter()
+        .filter(|s| s.name.ends_with(".service"))
+        .map(|s| s.name.trim_end_matches(".service").to_string())
+        .collect();
+
+    services.retain(|s| {
+        if s.name.ends_with(".timer") {
+            // Keep timer only if there's no matching service
+            let base_name = s.name.trim_end_matches(".timer");
+            !service_base_names.contains(base_name)
+        } else {
+            true
+        }
+    });
+
+    // Pinned services come first, in every output format
+    let registry = Registry::load().unwrap_or_default();
+    services.sort_by_key(|s| !registry.is_pinned(&s.name));
+
+    if matches!(output, Output::Names) {
+        for service in &services {
+            println!("{}", service.name);
+        }
+        return Ok(());
+    }
+    let running = if manager.is_native() {
+        platform::resolve_enabled(&mut services);
+        platform::running_states(&services)
+    } else {
+        services
+            .iter()
+            .map(|s| manager.is_running(&s.name).unwrap_or(false))
+            .collect()
+    };
+
+    if let Output::Data(format) = output {
+        let entries: Vec<ListEntry> = services
             .into_iter()
-            .map(|service| {
-                let display_name = if service.name.starts_with("homebrew.mxcl.") {
-                    service
-                        .name
-                        .strip_prefix("homebrew.mxcl.")
-                        .unwrap_or(&service.name)
-                        .to_string()
-                } else {
-                    service.name.clone()
-                };
-
-                // Determine status based on running state
-                let is_running = platform::is_service_running(&service.name).unwrap_or(false);
-                let status = if is_running { "running" } else { "stopped" }.to_string();
-                let enabled = if service.enabled { "true" } else { "false" }.to_string();
-
-                // Determine type and schedule info
+            .zip(running)
+            .map(|(service, running)| {
                 let (service_type, schedule) =
-                    get_service_type_and_schedule(&service, &timer_base_names);
-
-                ServiceRow {
-                    name: display_name,
+                    get_service_type_and_schedule(manager, &service, &timer_base_names);
+                ListEntry {
+                    running,
+                    pinned: registry.is_pinned(&service.name),
+                    schedule: (schedule != "-").then_some(schedule),
                     service_type,
-                    status,
-                    enabled,
-                    schedule,
-                    path: service.path,
+                    service,
                 }
             })
             .collect();
+        return crate::command::print_data(&entries, format);
+    }
+
+    let porcelain = matches!(output, Output::Porcelain);
+    let rows: Vec<ServiceRow> = services
+        .into_iter()
+        .zip(running)
+        .map(|(service, is_running)| {
+            let display_name = if service.name.starts_with("homebrew.mxcl.") && !porcelain {
+                service
+                    .name
+                    .strip_prefix("homebrew.mxcl.")
+                    .unwrap_or(&service.name)
+                    .to_string()
+            } else {
+                service.name.clone()
+            };
+
+            let status = if is_running { "running" } else { "stopped" }.to_string();
+            let enabled = if service.enabled { "true" } else { "false" }.to_string();
 
-        // Check if output is piped (not a terminal)
-        if atty::isnt(atty::Stream::Stdout) {
-            // If piped, print without headers
-            for row in &rows {
+            // Determine type and schedule info
+            let (service_type, schedule) =
+                get_service_type_and_schedule(manager, &service, &timer_base_names);
+
+            ServiceRow {
+                pinned: registry.is_pinned(&service.name),
+                name: display_name,
+                service_type,
+                status,
+                enabled,
+                schedule,
+                source: service.source.as_str().to_string(),
+                path: service.path,
+            }
+        })
+        .collect();
+
+    if porcelain {
+        for row in &rows {
+            let fields = [
+                &row.name,
+                &row.service_type,
+                &row.status,
+                &row.enabled,
+                &row.schedule,
+                &row.source,
+                &row.path,
+            ];
+            let fields: Vec<String> = fields.iter().map(|f| porcelain_field(f)).collect();
+            println!("{}", fields.join("\t"));
+        }
+    } else if matches!(output, Output::Auto) && atty::isnt(atty::Stream::Stdout) {
+        // If piped, print without headers
+        for row in &rows {
+            if show_source {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    row.name,
+                    row.service_type,
+                    row.status,
+                    row.enabled,
+                    row.schedule,
+                    row.source,
+                    row.path
+                );
+            } else {
                 println!(
                     "{}\t{}\t{}\t{}\t{}\t{}",
                     row.name, row.service_type, row.status, row.enabled, row.schedule, row.path
                 );
             }
-        } else {
-            // If terminal, print table with headers but no borders
+        }
+    } else {
+        // If terminal, print table with headers but no borders, with
+        // pinned services in a section of their own
+        let (mut pinned, rest): (Vec<_>, Vec<_>) = rows.into_iter().partition(|r| r.pinned);
+        let stopped: Vec<String> = pinned
+            .iter_mut()
+            .filter(|row| row.needs_attention())
+            .map(|row| {
+                row.status = format!("{} (!)", row.status);
+                row.name.clone()
+            })
+            .collect();
+        let table = |rows: Vec<ServiceRow>| {
             let mut table = Table::new(rows);
             table.with(Style::blank()).with(Padding::zero());
-            println!("{table}");
+            if !show_source {
+                table.with(Remove::column(ByColumnName::new("Source")));
+            }
+            table
+        };
+        if !pinned.is_empty() {
+            println!("Pinned\n{}", table(pinned));
+            if !rest.is_empty() {
+                println!();
+            }
+        }
+        if !rest.is_empty() {
+            println!("{}", table(rest));
+        }
+        for name in stopped {
+            eprintln!("Warning: pinned service '{name}' is not running");
         }
-        Ok(())
     }
+    Ok(())
+}
+
+/// A porcelain column value: never empty, and never containing the tab or
+/// newline separators.
+fn porcelain_field(value: &str) -> String {
+    if value.is_empty() {
+        return "-".to_string();
+    }
+    value.replace(['\t', '\n', '\r'], " ")
 }
 
 fn get_service_type_and_schedule(
+    manager: &dyn ServiceManager,
     service: &ServiceRef,
     #[allow(unused_variables)] timer_base_names: &HashSet<String>,
 ) -> (String, String) {
+    // Another backend's files can only tell from their own schedule.
+    if !manager.is_native() {
+        return match manager
+            .get(&service.name)
+            .ok()
+            .and_then(|d| d.service.schedule)
+        {
+            Some(schedule) => ("timer".to_string(), schedule.display()),
+            None => ("service".to_string(), "-".to_string()),
+        };
+    }
+
     // Check if this is a timer or has an associated timer
     #[allow(unused_variables)]
     let base_name = service
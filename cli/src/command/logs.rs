@@ -1,12 +1,35 @@
 use anyhow::Result;
-use clap::Args;
+use clap::{Args, ValueEnum};
 
-use serlib::platform;
+use serlib::logs::TimestampStyle;
+use serlib::platform::{Invocation, LogOptions, LogPriority};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Priority {
+    Err,
+    Warning,
+    Info,
+    Debug,
+}
+
+impl From<Priority> for LogPriority {
+    fn from(priority: Priority) -> Self {
+        match priority {
+            Priority::Err => LogPriority::Err,
+            Priority::Warning => LogPriority::Warning,
+            Priority::Info => LogPriority::Info,
+            Priority::Debug => LogPriority::Debug,
+        }
+    }
+}
 
 #[derive(Debug, Args)]
 pub struct Logs {
-    #[arg(help = "Name of the service to show logs for")]
-    pub name: String,
+    #[arg(
+        required = true,
+        help = "Name of the service to show logs for (several are merged by time)"
+    )]
+    pub names: Vec<String>,
     #[arg(
         short = 'n',
         long,
@@ -16,12 +39,57 @@ pub struct Logs {
     pub lines: u32,
     #[arg(short, long, help = "Follow log output (like tail -f)")]
     pub follow: bool,
+    #[arg(
+        short,
+        long,
+        value_enum,
+        help = "Only show messages at this level or more severe"
+    )]
+    pub priority: Option<Priority>,
+    #[arg(long, help = "Show timestamps in UTC instead of local time")]
+    pub utc: bool,
+    #[arg(
+        long,
+        conflicts_with = "utc",
+        help = "Show timestamps relative to now, like \"3m ago\""
+    )]
+    pub relative: bool,
+    #[arg(short, long, help = "Only show messages from the current boot")]
+    pub boot: bool,
+    #[arg(
+        long,
+        value_name = "latest|N",
+        allow_negative_numbers = true,
+        help = "Only show one run of the service: latest, or an offset like -1 (systemd)"
+    )]
+    pub invocation: Option<Invocation>,
 }
 
 impl Logs {
     pub fn run(&self) -> Result<()> {
-        let service_ref = platform::get_service(&self.name)?;
-        platform::show_service_logs(&service_ref.name, self.lines, self.follow)?;
+        let manager = crate::command::manager();
+        let names = self
+            .names
+            .iter()
+            .map(|name| manager.resolve(name))
+            .collect::<Result<Vec<_>>>()?;
+        let timestamps = if self.utc {
+            TimestampStyle::Utc
+        } else if self.relative {
+            TimestampStyle::Relative
+        } else {
+            TimestampStyle::Local
+        };
+        let options = LogOptions {
+            lines: self.lines,
+            follow: self.follow,
+            priority: self.priority.map(LogPriority::from),
+            timestamps,
+            boot: self.boot,
+            invocation: self.invocation,
+            since: None,
+        };
+        manager.logs(&names, &options)?;
         Ok(())
     }
 }
@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::{Args, Subcommand};
+use dialoguer::theme::ColorfulTheme;
+
+use crate::prompt::Confirm;
+
+use serlib::{backup, migrate};
+
+#[derive(Debug, Args)]
+pub struct Migrate {
+    #[command(subcommand)]
+    command: MigrateCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum MigrateCommand {
+    #[command(about = "Bundle all managed services on this (old) machine")]
+    Export(crate::command::Backup),
+    #[command(about = "Convert and install a bundle's services on this (new) machine")]
+    Import(Import),
+}
+
+impl Migrate {
+    pub fn run(&self) -> Result<()> {
+        match &self.command {
+            MigrateCommand::Export(cmd) => cmd.run(),
+            MigrateCommand::Import(cmd) => cmd.run(),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct Import {
+    /// Bundle written by `ser migrate export` (or `ser backup`)
+    archive: PathBuf,
+    /// Show what would be installed, then stop
+    #[arg(long)]
+    dry_run: bool,
+    /// Install the services but don't start the ones that were running
+    #[arg(long)]
+    no_start: bool,
+    /// Don't ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+}
+
+impl Import {
+    pub fn run(&self) -> Result<()> {
+        let passphrase = if backup::is_encrypted(&self.archive)? {
+            Some(crate::command::backup::passphrase(false)?)
+        } else {
+            None
+        };
+        let unpacked = backup::unpack(&self.archive, passphrase.as_deref())?;
+        let manifest = &unpacked.manifest;
+        println!(
+            "Bundle from {} ({}), {} service(s)\n",
+            manifest.host,
+            manifest.os,
+            manifest.services.len()
+        );
+
+        let plans = migrate::plan(&unpacked);
+        for plan in &plans {
+            let status = if !plan.is_ready() {
+                "skipped"
+            } else if plan.converted {
+                "converted"
+            } else {
+                "as-is"
+            };
+            println!("{} ({status})", plan.entry.name);
+            for change in &plan.changes {
+                println!("  {change}");
+            }
+            for problem in &plan.problems {
+                println!("  ! {problem}");
+            }
+        }
+        let ready = plans.iter().filter(|p| p.is_ready()).count();
+        println!();
+        if self.dry_run {
+            println!("{ready} of {} service(s) can be installed", plans.len());
+            return Ok(());
+        }
+        if ready == 0 {
+            bail!("None of the services can be installed on this machine");
+        }
+        if !self.yes
+            && !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Install {ready} service(s)?"))
+                .default(true)
+                .interact()?
+        {
+            return Ok(());
+        }
+
+        let results = migrate::apply(&unpacked, &plans, !self.no_start)?;
+        let mut failures = 0;
+        for unit in &results {
+            match &unit.result {
+                Ok(()) => println!("  {} installed", unit.name),
+                Err(e) => {
+                    failures += 1;
+                    eprintln!("  {} failed: {e:#}", unit.name);
+                }
+            }
+        }
+        let skipped = plans.len() - ready;
+        if failures > 0 || skipped > 0 {
+            bail!("{failures} service(s) failed and {skipped} were skipped");
+        }
+        Ok(())
+    }
+}
@@ -1,25 +1,403 @@
+pub mod add;
+pub mod audit;
+pub mod backup;
+pub mod check;
+pub mod completions;
+pub mod deploy;
+pub mod diff;
 pub mod disable;
+pub mod docs;
+pub mod doctor;
 pub mod edit;
 pub mod enable;
+pub mod env;
+pub mod explain;
+pub mod fmt;
 pub mod generate;
+pub mod info;
+pub mod install;
+pub mod lint;
 pub mod list;
 pub mod logs;
+pub mod migrate;
 pub mod new;
+pub mod notify;
+pub mod path;
+pub mod pin;
+pub mod preset;
+pub mod raw;
+pub mod remove;
 pub mod restart;
+pub mod restore;
+pub mod sample;
+pub mod search;
+pub mod selftest;
+pub mod set;
 pub mod show;
 pub mod start;
+pub mod stats;
+pub mod status;
 pub mod stop;
 pub mod timer;
+pub mod tree;
+pub mod up;
+pub mod uptime;
+pub mod which;
 
+pub use add::Add;
+pub use audit::Audit;
+pub use backup::Backup;
+pub use check::Check;
+pub use completions::{CompleteNames, Completions};
+pub use deploy::Deploy;
+pub use diff::Diff;
 pub use disable::Disable;
+pub use docs::Docs;
+pub use doctor::Doctor;
 pub use edit::Edit;
 pub use enable::Enable;
+pub use env::Env;
+pub use explain::Explain;
+pub use fmt::Fmt;
 pub use generate::Generate;
+pub use info::Info;
+pub use install::Install;
+pub use lint::Lint;
 pub use list::List;
 pub use logs::Logs;
+pub use migrate::Migrate;
 pub use new::New;
+pub use notify::Notify;
+pub use path::Path;
+pub use pin::Pin;
+pub use preset::Preset;
+pub use raw::Raw;
+pub use remove::Remove;
 pub use restart::Restart;
+pub use restore::Restore;
+pub use sample::Sample;
+pub use search::Search;
+pub use selftest::Selftest;
+pub use set::Set;
 pub use show::Show;
 pub use start::Start;
+pub use stats::Stats;
+pub use status::Status;
 pub use stop::Stop;
 pub use timer::Timer;
+pub use tree::Tree;
+pub use up::Up;
+pub use uptime::Uptime;
+pub use which::Which;
+
+/// Warn that a file belongs to a package, so edits/removal will fight the
+/// package manager. Returns whether the file is package-owned.
+pub fn warn_if_package_owned(path: &str) -> bool {
+    let Some(package) = serlib::platform::owning_package(std::path::Path::new(path)) else {
+        return false;
+    };
+    eprintln!(
+        "Warning: {} is owned by package {}.",
+        path,
+        package.display()
+    );
+    if package.manager == "brew" {
+        eprintln!(
+            "Consider `brew services` to manage it; changes here are lost on `brew upgrade`."
+        );
+    } else {
+        eprintln!("Changes may be lost on upgrade; prefer an override (`ser edit` creates one).");
+    }
+    true
+}
+
+/// Serialize mutations of a service file across `ser` processes. Waits (with a
+/// note) if another invocation holds the lock.
+pub fn lock_service_file(path: &str) -> anyhow::Result<serlib::guard::FileLock> {
+    use serlib::guard::FileLock;
+
+    let path = std::path::Path::new(path);
+    if let Some(lock) = FileLock::try_acquire(path)? {
+        return Ok(lock);
+    }
+    if NO_WAIT.load(std::sync::atomic::Ordering::Relaxed) {
+        anyhow::bail!("Another ser process is working on {}", path.display());
+    }
+    eprintln!(
+        "Waiting for another ser process working on {}...",
+        path.display()
+    );
+    FileLock::acquire(path)
+}
+
+/// Exit with this status once the command is done, having already said why
+/// (`ser status --exit-code`, a failed `ser raw`). Returned as an error so
+/// `main` still finishes up, e.g. prints `--explain`, before exiting.
+#[derive(Debug)]
+pub struct ExitStatus(pub i32);
+
+impl std::fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exit status {}", self.0)
+    }
+}
+
+impl std::error::Error for ExitStatus {}
+
+static NO_WAIT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// With `--no-wait`, fail instead of waiting when another `ser` process holds
+/// a lock.
+pub fn set_no_wait(no_wait: bool) {
+    NO_WAIT.store(no_wait, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// `--backend` values: this machine's service manager, or definitions in
+/// another init system's format.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Backend {
+    Native,
+    Systemd,
+    Launchd,
+}
+
+static MANAGER: std::sync::OnceLock<Box<dyn serlib::platform::ServiceManager>> =
+    std::sync::OnceLock::new();
+
+/// Pick the service manager commands work through. `systemd` and `launchd`
+/// work on the files in the first `--dir`, whatever this machine runs.
+pub fn set_backend(backend: Backend, dirs: &[std::path::PathBuf]) -> anyhow::Result<()> {
+    use serlib::platform::{DefinitionFormat, DirectoryManager, NativeManager, ServiceManager};
+
+    let format = match backend {
+        Backend::Native => None,
+        Backend::Systemd => Some(DefinitionFormat::Systemd),
+        Backend::Launchd => Some(DefinitionFormat::Launchd),
+    };
+    let manager: Box<dyn ServiceManager> = match (format, dirs.first()) {
+        (None, _) => Box::new(NativeManager),
+        (Some(format), Some(dir)) => Box::new(DirectoryManager::new(format, dir)),
+        (Some(_), None) => anyhow::bail!(
+            "--backend {} needs a --dir to keep its files in",
+            format!("{backend:?}").to_lowercase()
+        ),
+    };
+    let _ = MANAGER.set(manager);
+    Ok(())
+}
+
+/// The service manager chosen with `--backend`.
+pub fn manager() -> &'static dyn serlib::platform::ServiceManager {
+    MANAGER
+        .get_or_init(|| Box::new(serlib::platform::NativeManager))
+        .as_ref()
+}
+
+/// Serialize operations on a service across `ser` processes, so e.g. a
+/// restart from CI and one from a shell don't interleave their stop and
+/// start. Waits (with a note) for another invocation unless `--no-wait`.
+pub fn lock_service(name: &str) -> anyhow::Result<serlib::guard::FileLock> {
+    use serlib::guard::{self, FileLock};
+
+    let target = guard::service_lock_target(name.trim_end_matches(".service"));
+    if let Some(lock) = FileLock::try_acquire(&target)? {
+        return Ok(lock);
+    }
+    if NO_WAIT.load(std::sync::atomic::Ordering::Relaxed) {
+        anyhow::bail!("Another ser process is working on '{name}'");
+    }
+    eprintln!("Waiting for another ser process working on '{name}'...");
+    FileLock::acquire(&target)
+}
+
+/// If the file changed since `snapshot` was taken, ask before overwriting it.
+/// Returns `false` if the user declines (or can't be asked).
+pub fn confirm_overwrite(snapshot: &serlib::guard::FileSnapshot) -> anyhow::Result<bool> {
+    use crate::prompt::Confirm;
+    use dialoguer::theme::ColorfulTheme;
+
+    if !snapshot.has_changed() {
+        return Ok(true);
+    }
+    eprintln!(
+        "Warning: {} was modified by someone else since ser read it.",
+        snapshot.path.display()
+    );
+    if atty::isnt(atty::Stream::Stdin) {
+        eprintln!("Refusing to overwrite it without confirmation.");
+        return Ok(false);
+    }
+    Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt("Overwrite it anyway?")
+        .default(false)
+        .interact()
+}
+
+/// For a failed service lookup: "not found", unless the backend timed out or
+/// `ser` was interrupted, which is what the user needs to hear instead.
+pub fn not_found(name: &str) -> impl FnOnce(anyhow::Error) -> anyhow::Error + '_ {
+    move |e| {
+        if serlib::interrupt::is_timeout_or_interrupt(&e) {
+            e
+        } else {
+            anyhow::anyhow!("Service '{name}' not found.")
+        }
+    }
+}
+
+/// Remember the definition a service was (re)started with, for
+/// `restart --if-changed`. Failing to record only costs a later extra restart,
+/// so it's a warning.
+pub fn record_loaded(name: &str, path: &std::path::Path) {
+    if let Err(e) = serlib::state::record_loaded(name, path) {
+        eprintln!("Warning: couldn't record the state of '{name}': {e:#}");
+    }
+}
+
+/// Open the firewall for the ports `name` declares (`--open-firewall`).
+pub fn open_firewall(name: &str) -> anyhow::Result<()> {
+    let details = serlib::platform::get_service_details(name)?;
+    for rule in serlib::firewall::open(name, &details)? {
+        println!("Opened the firewall for {rule}");
+    }
+    Ok(())
+}
+
+/// Close whatever firewall rules `ser` opened for a service about to be
+/// removed. Done first, so a failure leaves the service (and the record of
+/// its rules) in place to retry.
+pub fn close_firewall(name: &str) -> anyhow::Result<()> {
+    let rules = serlib::firewall::close(name).map_err(|e| {
+        e.context(format!(
+            "Not removing '{name}' with its firewall rules open"
+        ))
+    })?;
+    for rule in rules {
+        println!("Closed the firewall for {rule}");
+    }
+    Ok(())
+}
+
+/// Report the outcome of an operation as a desktop notification when it took
+/// longer than the configured threshold (and notifications are enabled).
+pub fn notify_if_slow<T>(started: std::time::Instant, what: &str, result: &anyhow::Result<T>) {
+    let config = &serlib::config::settings().notifications;
+    if !config.desktop || started.elapsed().as_secs() < config.after_secs {
+        return;
+    }
+    let body = match result {
+        Ok(_) => format!("{what}: done"),
+        Err(e) => format!("{what}: failed: {e}"),
+    };
+    if let Err(e) = serlib::platform::notify_desktop("ser", &body) {
+        eprintln!("Warning: couldn't show a desktop notification: {e:#}");
+    }
+}
+
+/// Run a client-side hook (see `serlib::hooks`). With `force`, a failing hook
+/// is reported as a warning and the operation continues.
+pub fn run_hook(
+    hooks: &serlib::hooks::Hooks,
+    point: serlib::hooks::HookPoint,
+    name: &str,
+    force: bool,
+) -> anyhow::Result<()> {
+    match hooks.run(point, name) {
+        Err(e) if force => {
+            eprintln!("Warning: {e:#}");
+            Ok(())
+        }
+        result => result.map_err(|e| e.context("Use --force to ignore hook failures")),
+    }
+}
+
+/// `--project` for commands that run hooks. Without it, only the config
+/// file's hooks run; see `serlib::hooks::hooks_for`.
+#[derive(Debug, Default, clap::Args)]
+pub struct ProjectHooks {
+    /// Run the hooks in this .ser.toml (it must be yours and writable only by you)
+    #[arg(long, value_name = "FILE")]
+    pub project: Option<std::path::PathBuf>,
+}
+
+impl ProjectHooks {
+    pub fn hooks_for(&self, name: &str) -> anyhow::Result<serlib::hooks::Hooks> {
+        match &self.project {
+            Some(path) => serlib::hooks::project_hooks(name, path),
+            None => Ok(serlib::hooks::hooks_for(name)),
+        }
+    }
+}
+
+/// `--capture-path`/`--capture-env` for commands that define a service.
+#[derive(Debug, Default, clap::Args)]
+pub struct CaptureEnv {
+    /// Record your current PATH in the service, instead of the init system's minimal one
+    #[arg(long)]
+    pub capture_path: bool,
+    /// Also record this variable from your environment (repeatable)
+    #[arg(long, value_name = "VAR")]
+    pub capture_env: Vec<String>,
+}
+
+impl CaptureEnv {
+    pub fn apply(&self, details: &mut serlib::ServiceDetails) {
+        let mut names: Vec<&str> = self.capture_env.iter().map(String::as_str).collect();
+        if self.capture_path {
+            names.insert(0, "PATH");
+        }
+        let captured = details.capture_env(&names);
+        if !captured.is_empty() {
+            eprintln!("Captured {} from your environment.", captured.join(", "));
+        }
+    }
+}
+
+/// `--plist-format` values: the plist formats ser can write.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum PlistEncoding {
+    Xml,
+    Binary,
+}
+
+impl From<PlistEncoding> for serlib::plist::PlistFormat {
+    fn from(encoding: PlistEncoding) -> Self {
+        match encoding {
+            PlistEncoding::Xml => serlib::plist::PlistFormat::Xml,
+            PlistEncoding::Binary => serlib::plist::PlistFormat::Binary,
+        }
+    }
+}
+
+/// Print a generated plist in `encoding`. Binary goes to stdout as-is, so
+/// only when it's redirected.
+pub fn print_plist(xml: &str, encoding: PlistEncoding) -> anyhow::Result<()> {
+    use std::io::Write;
+    match encoding {
+        PlistEncoding::Xml => println!("{xml}"),
+        PlistEncoding::Binary => {
+            if atty::is(atty::Stream::Stdout) {
+                anyhow::bail!("Not writing a binary plist to the terminal; redirect it to a file");
+            }
+            let data = serlib::plist::convert_plist(xml.as_bytes(), encoding.into())?;
+            std::io::stdout().write_all(&data)?;
+        }
+    }
+    Ok(())
+}
+
+/// `--format` values for printing data for scripts.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum DataFormat {
+    Json,
+    Yaml,
+}
+
+/// Print `value` as `format`.
+pub fn print_data<T: serde::Serialize>(value: &T, format: DataFormat) -> anyhow::Result<()> {
+    match format {
+        DataFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        DataFormat::Yaml => print!("{}", serlib::yaml::to_string(value)?),
+    }
+    Ok(())
+}
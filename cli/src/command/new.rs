@@ -1,32 +1,106 @@
 use anyhow::Result;
 use clap::Args;
-use dialoguer::{theme::ColorfulTheme, Confirm};
+use dialoguer::theme::ColorfulTheme;
 
-use serlib::platform;
+use crate::i18n::{t, tf};
+use crate::prompt::{Confirm, Select};
 use serlib::ServiceDetails;
 
 #[derive(Debug, Args)]
 pub struct New {
     command: Vec<String>,
+    #[command(flatten)]
+    flags: crate::interactive::ServiceFlags,
+    #[command(flatten)]
+    capture: crate::command::CaptureEnv,
+    /// Also ask which Linux capabilities it needs (e.g. to bind ports below
+    /// 1024 without root) and drop the rest
+    #[arg(long)]
+    hardened: bool,
 }
 
 impl New {
     pub fn run(&self) -> Result<()> {
+        let manager = crate::command::manager();
+        if !manager.is_native() && self.flags.create_user {
+            anyhow::bail!(
+                "--create-user changes this machine, not the {} files in --dir",
+                manager.name()
+            );
+        }
         let theme = ColorfulTheme::default();
-        let kind = crate::interactive::prompt_service_kind(&theme)?;
-        let details =
-            crate::interactive::collect_service_details(&theme, self.command.clone(), true, kind)?;
+        if self.command.is_empty() {
+            if let Some(mut details) = from_project(&theme)? {
+                self.capture.apply(&mut details);
+                if self.hardened {
+                    crate::interactive::collect_hardening(&theme, &mut details)?;
+                }
+                if let Some(user) = &self.flags.user {
+                    crate::interactive::check_user_name(&self.flags)?;
+                    details.user = Some(user.clone());
+                }
+                crate::interactive::create_run_as_user(&self.flags, &mut details)?;
+                return finish_create(&theme, details);
+            }
+        }
+        let kind = crate::interactive::service_kind(&theme, &self.flags)?;
+        let mut details = crate::interactive::collect_service_details(
+            &theme,
+            self.command.clone(),
+            true,
+            kind,
+            &self.flags,
+            true,
+        )?;
+        self.capture.apply(&mut details);
+        if self.hardened {
+            crate::interactive::collect_hardening(&theme, &mut details)?;
+        }
+        crate::interactive::create_run_as_user(&self.flags, &mut details)?;
         finish_create(&theme, details)
     }
 }
 
+/// Offer the service(s) from a `.ser.toml` in this project, so creating one is
+/// a confirmation rather than a round of prompts.
+fn from_project(theme: &ColorfulTheme) -> Result<Option<ServiceDetails>> {
+    let Some((path, mut services)) = crate::command::up::project_services()? else {
+        return Ok(None);
+    };
+    eprintln!("{}", tf("Found {}", &[&path.display()]));
+    let index = if services.len() == 1 {
+        0
+    } else {
+        let names: Vec<&str> = services.iter().map(|s| s.name.as_str()).collect();
+        Select::with_theme(theme)
+            .with_prompt(t("Which service?"))
+            .items(&names)
+            .default(0)
+            .interact()?
+    };
+    let details = services.swap_remove(index);
+    crate::command::up::print_summary(&details);
+    let accept = Confirm::with_theme(theme)
+        .with_prompt(t("Create this service?"))
+        .default(true)
+        .interact()?;
+    Ok(accept.then_some(details))
+}
+
 /// Create a service or timer from collected details, then offer to start/enable
 /// it. Shared by `ser new` and `ser timer create`.
 pub fn finish_create(theme: &ColorfulTheme, details: ServiceDetails) -> Result<()> {
     let is_scheduled = details.schedule.is_some();
 
     // Create the service (and timer on Linux if scheduled)
-    platform::create_service(&details)?;
+    let manager = crate::command::manager();
+    manager.create(&details)?;
+    if !manager.is_native() {
+        // Nothing here runs it, so there's nothing to start.
+        let path = manager.get(&details.name)?.path;
+        println!("{}", tf("Wrote {}", &[&path]));
+        return Ok(());
+    }
 
     if is_scheduled {
         let schedule_display = details
@@ -35,20 +109,32 @@ pub fn finish_create(theme: &ColorfulTheme, details: ServiceDetails) -> Result<(
             .map(|s| s.display())
             .unwrap_or_default();
         println!(
-            "Timer '{}' created successfully (schedule: {}).",
-            details.name, schedule_display
+            "{}",
+            tf(
+                "Timer '{}' created successfully (schedule: {}).",
+                &[&details.name, &schedule_display]
+            )
         );
         #[cfg(target_os = "linux")]
-        println!("Timer file: /etc/systemd/system/{}.timer", details.name);
+        println!(
+            "{}",
+            tf(
+                "Timer file: {}",
+                &[&format!("/etc/systemd/system/{}.timer", details.name)]
+            )
+        );
     } else {
-        println!("Service '{}' created successfully.", details.name);
+        println!(
+            "{}",
+            tf("Service '{}' created successfully.", &[&details.name])
+        );
     }
 
     // Ask if user wants to start/enable it now
     let prompt = if is_scheduled {
-        "Enable the timer now?"
+        t("Enable the timer now?")
     } else {
-        "Start the service now?"
+        t("Start the service now?")
     };
 
     let start_now = Confirm::with_theme(theme)
@@ -58,12 +144,13 @@ pub fn finish_create(theme: &ColorfulTheme, details: ServiceDetails) -> Result<(
 
     if start_now {
         if is_scheduled {
-            print!("Enabling timer '{}'...", details.name);
+            print!("{}", tf("Enabling timer '{}'...", &[&details.name]));
         } else {
-            print!("Starting service '{}'...", details.name);
+            print!("{}", tf("Starting service '{}'...", &[&details.name]));
         }
-        platform::start_service(&details.name)?;
-        println!(" done.");
+        manager.start(&details.name)?;
+        println!("{}", t(" done."));
+        crate::command::enable::offer_linger(&details.name)?;
     }
 
     Ok(())
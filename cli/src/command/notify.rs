@@ -0,0 +1,100 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+
+use serlib::notify::{self, Event};
+#[cfg(target_os = "linux")]
+use serlib::platform;
+
+#[derive(Debug, Args)]
+pub struct Notify {
+    #[command(subcommand)]
+    command: NotifyCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum NotifyCommand {
+    #[command(about = "Send a test alert to verify the configured channels")]
+    Test(Test),
+    #[command(about = "Send an alert for a service (e.g. from an OnFailure= unit)")]
+    Send(Send),
+    #[command(about = "Alert whenever a service fails (Linux: installs an OnFailure= hook)")]
+    OnFailure(OnFailure),
+}
+
+impl Notify {
+    pub fn run(&self) -> Result<()> {
+        match &self.command {
+            NotifyCommand::Test(cmd) => cmd.run(),
+            NotifyCommand::Send(cmd) => cmd.run(),
+            NotifyCommand::OnFailure(cmd) => cmd.run(),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct Test {}
+
+impl Test {
+    pub fn run(&self) -> Result<()> {
+        let event = Event {
+            service: "ser-test".to_string(),
+            state: "ok".to_string(),
+            status: None,
+            logs: vec!["This is a test alert from `ser notify test`.".to_string()],
+        };
+        notify::send_all(&event)?;
+        println!("Test alert delivered.");
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct Send {
+    #[arg(help = "Name of the service the alert is about")]
+    pub name: String,
+    #[arg(long, default_value = "failed", help = "State to report")]
+    pub state: String,
+}
+
+impl Send {
+    pub fn run(&self) -> Result<()> {
+        let name = serlib::platform::normalize_service_name(&self.name);
+        let event = Event::with_logs(name, &self.state, notify::log_lines());
+        notify::send_all(&event)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct OnFailure {
+    #[arg(help = "Name of the service to watch")]
+    pub name: String,
+    #[arg(long, help = "Stop alerting for this service")]
+    pub remove: bool,
+}
+
+impl OnFailure {
+    #[cfg(target_os = "linux")]
+    pub fn run(&self) -> Result<()> {
+        let resolved_name = platform::resolve_service_name(&self.name)?;
+        if self.remove {
+            platform::remove_failure_alert(&resolved_name)?;
+            println!("'{}' will no longer send failure alerts.", self.name);
+        } else {
+            let ser = std::env::current_exe()?;
+            platform::install_failure_alert(&resolved_name, &ser)?;
+            println!(
+                "'{}' will send an alert through `ser notify send` when it fails.",
+                self.name
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn run(&self) -> Result<()> {
+        anyhow::bail!(
+            "launchd has no OnFailure= equivalent; run `ser notify send {}` from your own monitoring",
+            self.name
+        )
+    }
+}
@@ -0,0 +1,30 @@
+use anyhow::{anyhow, Result};
+use clap::Args;
+
+use serlib::platform;
+
+#[derive(Debug, Args)]
+pub struct Path {
+    #[arg(help = "Name of the service")]
+    pub name: String,
+    /// Print the service's working directory instead of its definition file
+    #[arg(long)]
+    pub workdir: bool,
+}
+
+impl Path {
+    pub fn run(&self) -> Result<()> {
+        let resolved_name = platform::resolve_service_name(&self.name)?;
+        let details = platform::get_service_details(&resolved_name)?;
+        if self.workdir {
+            let dir = details
+                .service
+                .working_directory
+                .ok_or_else(|| anyhow!("'{}' has no working directory", self.name))?;
+            println!("{dir}");
+        } else {
+            println!("{}", details.path);
+        }
+        Ok(())
+    }
+}
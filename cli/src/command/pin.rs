@@ -0,0 +1,33 @@
+use anyhow::Result;
+use clap::Args;
+
+use serlib::platform;
+use serlib::state::{self, Registry};
+
+#[derive(Debug, Args)]
+pub struct Pin {
+    /// Service to pin; without one, lists the pinned services
+    pub name: Option<String>,
+    /// Unpin the service instead
+    #[arg(long, requires = "name")]
+    pub remove: bool,
+}
+
+impl Pin {
+    pub fn run(&self) -> Result<()> {
+        let Some(name) = &self.name else {
+            for name in Registry::load()?.pinned() {
+                println!("{name}");
+            }
+            return Ok(());
+        };
+        let resolved_name = platform::resolve_service_name(name)?;
+        state::set_pinned(&resolved_name, !self.remove)?;
+        if self.remove {
+            println!("Unpinned '{name}'.");
+        } else {
+            println!("Pinned '{name}'; it will be listed first.");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use clap::Args;
+
+#[cfg(target_os = "linux")]
+use serlib::platform;
+
+#[derive(Debug, Args)]
+pub struct Preset {
+    #[arg(help = "Name of the service or timer to reset")]
+    pub name: String,
+}
+
+impl Preset {
+    #[cfg(target_os = "linux")]
+    pub fn run(&self) -> Result<()> {
+        let resolved_name = platform::resolve_service_name(&self.name)?;
+        let _lock = crate::command::lock_service(&resolved_name)?;
+
+        print!("Resetting '{}' to its vendor preset...", self.name);
+        platform::preset_service(&resolved_name)?;
+        println!(" done.");
+        if let Some(state) = platform::unit_file_state(&resolved_name)? {
+            println!("'{}' is now {}.", self.name, state.state);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn run(&self) -> Result<()> {
+        anyhow::bail!(
+            "Can't reset '{}': vendor presets are a systemd feature; use `ser enable` or `ser disable`",
+            self.name
+        )
+    }
+}
@@ -0,0 +1,30 @@
+use anyhow::{Context, Result};
+use clap::Args;
+
+use serlib::platform;
+
+#[derive(Debug, Args)]
+pub struct Raw {
+    #[arg(help = "Name of the service")]
+    pub name: String,
+    /// Arguments for systemctl/launchctl; the service's full unit name or
+    /// launchd target is appended, or replaces a `{}` argument
+    #[arg(last = true, required = true)]
+    pub args: Vec<String>,
+}
+
+impl Raw {
+    pub fn run(&self) -> Result<()> {
+        let resolved_name = platform::resolve_service_name(&self.name)?;
+        let mut cmd = platform::native_command(&resolved_name, &self.args)?;
+        serlib::print_command(&cmd);
+        let program = cmd.get_program().to_string_lossy().into_owned();
+        let status = cmd
+            .status()
+            .with_context(|| format!("Failed to execute {program}"))?;
+        if !status.success() {
+            return Err(crate::command::ExitStatus(status.code().unwrap_or(1)).into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use clap::Args;
+use dialoguer::theme::ColorfulTheme;
+
+use crate::prompt::Confirm;
+
+use serlib::state;
+
+#[derive(Debug, Args)]
+pub struct Remove {
+    #[arg(help = "Name of the service to remove")]
+    pub name: String,
+    #[arg(short = 'y', long, help = "Skip the confirmation prompt")]
+    pub yes: bool,
+}
+
+impl Remove {
+    pub fn run(&self) -> Result<()> {
+        let manager = crate::command::manager();
+        let resolved_name = manager.resolve(&self.name)?;
+        let _lock = crate::command::lock_service(&resolved_name)?;
+        let path = manager.get(&resolved_name)?.path;
+
+        if !self.yes {
+            if atty::isnt(atty::Stream::Stdin) {
+                anyhow::bail!(
+                    "Not removing '{}' without confirmation; pass --yes",
+                    self.name
+                );
+            }
+            let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Stop '{}' and delete {path}?", self.name))
+                .default(false)
+                .interact()?;
+            if !confirmed {
+                println!("Aborted.");
+                return Ok(());
+            }
+        }
+
+        // Files for another machine have no firewall rules or state here.
+        if manager.is_native() {
+            crate::command::close_firewall(&resolved_name)?;
+        }
+        print!("Removing service '{}'...", self.name);
+        manager.delete(&resolved_name)?;
+        println!(" done.");
+        if !manager.is_native() {
+            return Ok(());
+        }
+        if let Err(e) = state::forget(&resolved_name) {
+            eprintln!(
+                "Warning: couldn't clear the state of '{}': {e:#}",
+                self.name
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,21 +1,84 @@
+use std::path::PathBuf;
+use std::time::Instant;
+
 use anyhow::Result;
 use clap::Args;
 
-use serlib::platform;
+use serlib::hooks::HookPoint;
+use serlib::platform::{Invocation, LogOptions};
+use serlib::state;
 
 #[derive(Debug, Args)]
 pub struct Restart {
     #[arg(help = "Name of the service to restart")]
     pub name: String,
+    /// Only restart if the definition changed since ser last started it
+    #[arg(long)]
+    pub if_changed: bool,
+    /// Continue even if a hook fails
+    #[arg(long)]
+    pub force: bool,
+    /// After restarting, follow the logs of the new run only
+    #[arg(short, long)]
+    pub follow: bool,
+    #[command(flatten)]
+    pub project: crate::command::ProjectHooks,
 }
 
 impl Restart {
     pub fn run(&self) -> Result<()> {
-        let resolved_name = platform::resolve_service_name(&self.name)?;
+        let manager = crate::command::manager();
+        let resolved_name = manager.resolve(&self.name)?;
+        manager.require_backend(&format!("restart '{}'", self.name))?;
+        let _lock = crate::command::lock_service(&resolved_name)?;
+        let path = PathBuf::from(manager.get(&resolved_name)?.path);
+
+        if self.if_changed && !state::definition_changed(&resolved_name, &path)? {
+            println!(
+                "Service '{}' is unchanged since it was last started; not restarting.",
+                self.name
+            );
+            return Ok(());
+        }
 
         print!("Restarting service '{}'...", self.name);
-        platform::restart_service(&resolved_name)?;
+        // A restart is a stop followed by a start, so it runs all four hooks,
+        // each where it would for a separate stop and start.
+        let hooks = self.project.hooks_for(&resolved_name)?;
+        crate::command::run_hook(&hooks, HookPoint::PreStop, &resolved_name, self.force)?;
+
+        let began = chrono::Utc::now();
+        let started = Instant::now();
+        let between = [HookPoint::PostStop, HookPoint::PreStart];
+        let result = if between.iter().any(|&point| hooks.command(point).is_some()) {
+            manager.stop(&resolved_name).and_then(|()| {
+                for point in between {
+                    crate::command::run_hook(&hooks, point, &resolved_name, self.force)?;
+                }
+                manager.start(&resolved_name)
+            })
+        } else {
+            manager.restart(&resolved_name)
+        };
+        crate::command::notify_if_slow(started, &format!("Restart {}", self.name), &result);
+        result?;
         println!(" done.");
+        crate::command::record_loaded(&resolved_name, &path);
+        crate::command::run_hook(&hooks, HookPoint::PostStart, &resolved_name, self.force)?;
+
+        if self.follow {
+            // systemd tags each run with an invocation ID, which excludes the
+            // old process's output exactly; launchd has no such thing, so
+            // fall back to output since the restart began.
+            let options = LogOptions {
+                lines: 50,
+                follow: true,
+                invocation: cfg!(target_os = "linux").then_some(Invocation::Latest),
+                since: Some(began),
+                ..Default::default()
+            };
+            manager.logs(&[resolved_name], &options)?;
+        }
 
         Ok(())
     }
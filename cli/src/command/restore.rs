@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Args;
+
+use serlib::backup;
+
+#[derive(Debug, Args)]
+pub struct Restore {
+    /// Archive written by `ser backup`
+    pub archive: PathBuf,
+    /// Install the services but don't start the ones that were running
+    #[arg(long)]
+    pub no_start: bool,
+}
+
+impl Restore {
+    pub fn run(&self) -> Result<()> {
+        let passphrase = if backup::is_encrypted(&self.archive)? {
+            Some(crate::command::backup::passphrase(false)?)
+        } else {
+            None
+        };
+        let unpacked = backup::unpack(&self.archive, passphrase.as_deref())?;
+        let manifest = &unpacked.manifest;
+        println!(
+            "Restoring {} service(s) backed up on {} ({}) at {}",
+            manifest.services.len(),
+            manifest.host,
+            manifest.os,
+            manifest.created
+        );
+        if manifest.os != std::env::consts::OS {
+            bail!(
+                "This backup is from {}; use `ser migrate` to convert it for {}",
+                manifest.os,
+                std::env::consts::OS
+            );
+        }
+
+        let results = backup::restore(&unpacked, !self.no_start)?;
+        let mut failures = 0;
+        for unit in &results {
+            match &unit.result {
+                Ok(()) => println!("  {} restored", unit.name),
+                Err(e) => {
+                    failures += 1;
+                    eprintln!("  {} failed: {e:#}", unit.name);
+                }
+            }
+        }
+        if failures > 0 {
+            bail!("{failures} service(s) failed to restore");
+        }
+        Ok(())
+    }
+}
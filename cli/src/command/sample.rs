@@ -0,0 +1,86 @@
+use anyhow::Result;
+use clap::Args;
+
+use serlib::history;
+use serlib::platform::{self, ListLevel};
+use serlib::{Schedule, ServiceDetails};
+
+/// Name of the job `ser sample --install` creates.
+const SAMPLER_NAME: &str = "ser-sampler";
+
+#[derive(Debug, Args)]
+pub struct Sample {
+    /// Install a job that samples every minute, instead of sampling once
+    #[arg(long)]
+    install: bool,
+}
+
+impl Sample {
+    pub fn run(&self) -> Result<()> {
+        if self.install {
+            return install_sampler();
+        }
+        let now = chrono::Utc::now().timestamp();
+        let services: Vec<_> = platform::list_services(ListLevel::Default)?
+            .into_iter()
+            .filter(|s| platform::is_managed(s) && s.name != SAMPLER_NAME)
+            .collect();
+        let running: Vec<bool> = services
+            .iter()
+            .map(|s| platform::is_service_running(&s.name).unwrap_or(false))
+            .collect();
+        let live: Vec<_> = services
+            .iter()
+            .zip(&running)
+            .filter(|(_, running)| **running)
+            .map(|(s, _)| s.clone())
+            .collect();
+        let mut usages = platform::resource_usage(&live).into_iter();
+        let samples: Vec<history::Sample> = services
+            .iter()
+            .zip(running)
+            .map(|(s, running)| {
+                let usage = if running {
+                    usages.next().flatten()
+                } else {
+                    None
+                };
+                history::Sample {
+                    time: now,
+                    service: platform::normalize_service_name(&s.name).to_string(),
+                    running,
+                    cpu: usage.map(|u| u.cpu_percent),
+                    memory: usage.map(|u| u.memory_bytes),
+                    pid: running.then(|| platform::main_pid(&s.name)).flatten(),
+                }
+            })
+            .collect();
+        history::record(&samples)?;
+        if serlib::is_verbose() {
+            eprintln!("Recorded {} sample(s)", samples.len());
+        }
+        Ok(())
+    }
+}
+
+/// Install the sampler as a system job. Run through `sudo`, it runs as the
+/// invoking user, so samples land in the state directory that user's `ser
+/// uptime` and `ser stats` read, not root's.
+fn install_sampler() -> Result<()> {
+    let user = std::env::var("SUDO_USER")
+        .ok()
+        .filter(|u| !u.is_empty() && u != "root");
+    let details = ServiceDetails {
+        name: SAMPLER_NAME.to_string(),
+        description: Some("Record service states for ser uptime and ser stats".to_string()),
+        program: std::env::current_exe()?.to_string_lossy().to_string(),
+        arguments: vec!["sample".to_string()],
+        schedule: Some(Schedule::Interval(60)),
+        user,
+        ..Default::default()
+    };
+    platform::create_service(&details)?;
+    platform::start_service(SAMPLER_NAME)?;
+    println!("Installed '{SAMPLER_NAME}'; it records service states every minute.");
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::Result;
+use clap::Args;
+use tabled::{
+    settings::{Padding, Style},
+    Table, Tabled,
+};
+
+use serlib::platform::{self, ListLevel};
+use serlib::search::match_service;
+
+#[derive(Debug, Args)]
+pub struct Search {
+    #[arg(help = "Text to look for in names, descriptions, commands, and environment")]
+    pub query: String,
+    #[arg(short, long, help = "Search all services (system and user)")]
+    pub all: bool,
+}
+
+#[derive(Tabled)]
+struct SearchRow {
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "Field")]
+    field: String,
+    #[tabled(rename = "Match")]
+    context: String,
+    #[tabled(rename = "Path")]
+    path: String,
+}
+
+impl Search {
+    pub fn run(&self) -> Result<()> {
+        let level = if self.all {
+            ListLevel::System
+        } else {
+            ListLevel::Default
+        };
+        let mut services = platform::list_services(level)?;
+        if matches!(level, ListLevel::Default) {
+            services.retain(platform::is_managed);
+        }
+
+        // (score, row) pairs; each service contributes its best match first.
+        let mut results: Vec<(u32, SearchRow)> = Vec::new();
+        let mut seen = HashSet::new();
+        for service in services {
+            // `/lib` is often a symlink to `/usr/lib`; only report each file once.
+            let canonical = std::fs::canonicalize(&service.path)
+                .unwrap_or_else(|_| service.path.clone().into());
+            if !seen.insert(canonical) {
+                continue;
+            }
+            let details = platform::parse_service_file(Path::new(&service.path)).ok();
+            let name = platform::normalize_service_name(&service.name);
+            let mut matches = match_service(name, details.as_ref(), &self.query);
+            matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+            let Some(best) = matches.first() else {
+                continue;
+            };
+            let total: u32 = matches.iter().map(|m| m.score).sum();
+            let fields: Vec<_> = matches.iter().map(|m| m.field).collect();
+            results.push((
+                total,
+                SearchRow {
+                    name: name.to_string(),
+                    field: fields.join(", "),
+                    context: best.context.clone(),
+                    path: service.path,
+                },
+            ));
+        }
+
+        if results.is_empty() {
+            eprintln!("No services match '{}'.", self.query);
+            return Ok(());
+        }
+        results.sort_by(|(a, ra), (b, rb)| b.cmp(a).then_with(|| ra.name.cmp(&rb.name)));
+        let rows: Vec<SearchRow> = results.into_iter().map(|(_, row)| row).collect();
+
+        if atty::isnt(atty::Stream::Stdout) {
+            for row in &rows {
+                println!("{}\t{}\t{}\t{}", row.name, row.field, row.context, row.path);
+            }
+        } else {
+            let mut table = Table::new(rows);
+            table.with(Style::blank()).with(Padding::zero());
+            println!("{table}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use clap::Args;
+
+use serlib::platform;
+use serlib::ServiceDetails;
+
+#[derive(Debug, Args)]
+pub struct Selftest {
+    #[arg(long, help = "Leave the scratch service installed for inspection")]
+    pub keep: bool,
+}
+
+impl Selftest {
+    pub fn run(&self) -> Result<()> {
+        let name = format!("ser-selftest-{}", std::process::id());
+        let details = ServiceDetails {
+            name: name.clone(),
+            description: Some("ser self-test (safe to remove)".to_string()),
+            program: "/bin/sleep".to_string(),
+            arguments: vec!["300".to_string()],
+            // `start` enables the unit on Linux, which needs an [Install] section.
+            run_at_load: true,
+            ..Default::default()
+        };
+
+        println!("Running self-test with scratch service '{}'...\n", name);
+        let mut failures = 0;
+        let mut step = |label: &str, result: Result<()>| -> bool {
+            match result {
+                Ok(()) => {
+                    println!("ok    {}", label);
+                    true
+                }
+                Err(e) => {
+                    failures += 1;
+                    println!("FAIL  {}: {:#}", label, e);
+                    false
+                }
+            }
+        };
+
+        let created = step("create service", platform::create_service(&details));
+        if created {
+            let resolved = platform::resolve_service_name(&name);
+            step(
+                "discover service",
+                resolved
+                    .as_ref()
+                    .map(|_| ())
+                    .map_err(|e| anyhow::anyhow!("{e:#}")),
+            );
+            let resolved = resolved.unwrap_or_else(|_| name.clone());
+
+            if step("start service", platform::start_service(&resolved)) {
+                step("verify running", wait_for_running(&resolved, true));
+                step(
+                    "read logs",
+                    platform::show_service_logs(
+                        std::slice::from_ref(&resolved),
+                        &platform::LogOptions {
+                            lines: 5,
+                            ..Default::default()
+                        },
+                    ),
+                );
+                step("stop service", platform::stop_service(&resolved));
+                step("verify stopped", wait_for_running(&resolved, false));
+            }
+            if !self.keep {
+                step("remove service", platform::delete_service(&resolved));
+            }
+        }
+
+        println!();
+        if failures == 0 {
+            println!("All checks passed.");
+            Ok(())
+        } else {
+            bail!("{} check(s) failed", failures)
+        }
+    }
+}
+
+/// Poll until the service reaches the wanted running state, up to 5 seconds.
+fn wait_for_running(name: &str, wanted: bool) -> Result<()> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        if platform::is_service_running(name)? == wanted {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            bail!(
+                "service did not become {} within 5s",
+                if wanted { "running" } else { "stopped" }
+            );
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
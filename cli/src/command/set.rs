@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use clap::Args;
+
+use serlib::guard::FileSnapshot;
+
+#[derive(Debug, Args)]
+pub struct Set {
+    #[arg(help = "Name of the service to update")]
+    pub name: String,
+    #[arg(
+        required = true,
+        help = "Settings to change as key=value (e.g. oom_score=500 oom_policy=stop)"
+    )]
+    pub settings: Vec<String>,
+}
+
+impl Set {
+    pub fn run(&self) -> Result<()> {
+        let manager = crate::command::manager();
+        let resolved_name = manager.resolve(&self.name)?;
+        // The service lock first, as every command takes it, then the
+        // file's, which `ser fmt` and `ser lint --fix` take on their own.
+        let _lock = crate::command::lock_service(&resolved_name)?;
+        let path = manager.get(&resolved_name)?.path;
+        let _file_lock = crate::command::lock_service_file(&path)?;
+        // Snapshot, then read what gets changed, both under the locks, so an
+        // edit made before them is in the details and one after is caught.
+        let snapshot = FileSnapshot::take(Path::new(&path));
+        let found = manager.get(&resolved_name)?;
+        let mut details = found.service.clone();
+
+        for setting in &self.settings {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected key=value, got '{}'", setting))?;
+            details.set_field(key.trim(), value)?;
+        }
+
+        #[cfg(target_os = "macos")]
+        if details.oom_score_adjust.is_some() || details.oom_policy.is_some() {
+            eprintln!(
+                "Warning: launchd has no OOM score or policy; these settings are ignored on macOS."
+            );
+        }
+        #[cfg(target_os = "macos")]
+        if !details.ambient_capabilities.is_empty() || details.capability_bounding_set.is_some() {
+            eprintln!(
+                "Warning: launchd has no capabilities; these settings are ignored on macOS \
+                 (since 10.14 any user can bind ports below 1024)."
+            );
+        }
+        #[cfg(target_os = "macos")]
+        if details.restricts_network() {
+            eprintln!(
+                "Warning: launchd has no network sandbox; these settings are ignored on macOS."
+            );
+        }
+
+        if !crate::command::confirm_overwrite(&snapshot)? {
+            bail!("Not updating '{}'", self.name);
+        }
+
+        manager.update(Path::new(&found.path), &found.service, &details)?;
+        println!("Updated service '{}'.", self.name);
+        if manager.is_native() {
+            println!("Run `ser restart {}` to apply the changes.", self.name);
+        }
+        Ok(())
+    }
+}
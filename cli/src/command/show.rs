@@ -1,21 +1,102 @@
 use anyhow::Result;
 use clap::Args;
+use serde::Serialize;
 
+use crate::command::timer;
+use crate::command::DataFormat;
 use serlib::platform;
+use serlib::Schedule;
 
 #[derive(Debug, Args)]
 pub struct Show {
     #[arg(help = "Name of the service to show")]
     pub name: String,
+    /// Also print every key/value in the service file, including ones ser doesn't model
+    #[arg(long)]
+    pub raw: bool,
+    /// Only read the files; don't ask systemd/launchd what it has loaded
+    #[arg(long)]
+    pub files_only: bool,
+    /// Print the service as JSON or YAML, for scripts
+    #[arg(long, value_enum)]
+    pub format: Option<DataFormat>,
+}
+
+/// `ser show --format json`/`yaml`: the parsed service, and with `--raw`
+/// every key in its file.
+#[derive(Serialize)]
+struct ShowOutput {
+    #[serde(flatten)]
+    details: serlib::FsServiceDetails,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw: Option<Vec<serlib::RawField>>,
 }
 
 impl Show {
     pub fn run(&self) -> Result<()> {
-        let resolved_name = platform::resolve_service_name(&self.name)?;
-        let details = platform::get_service_details(&resolved_name)?;
+        let manager = crate::command::manager();
+        let resolved_name = manager.resolve(&self.name)?;
+        let details = manager.get(&resolved_name)?;
+        // The files only hint at whether it's enabled; systemd knows.
+        #[cfg(target_os = "linux")]
+        let unit_state = (manager.is_native() && !self.files_only)
+            .then(|| platform::unit_file_state(&resolved_name).ok().flatten())
+            .flatten();
+        #[cfg(target_os = "linux")]
+        let details = serlib::FsServiceDetails {
+            enabled: unit_state
+                .as_ref()
+                .map_or(details.enabled, |state| state.is_enabled()),
+            ..details
+        };
+        if let Some(format) = self.format {
+            let raw = if self.raw {
+                Some(platform::raw_fields(std::path::Path::new(&details.path))?)
+            } else {
+                None
+            };
+            return crate::command::print_data(&ShowOutput { details, raw }, format);
+        }
 
         println!("Service: {}", details.service.name);
+        if let Some(ref trigger) = details.trigger {
+            println!("Unit: {} (activates {})", trigger.name, trigger.unit);
+            for (key, value) in &trigger.settings {
+                println!("  {key}={value}");
+            }
+        }
+        if let Some(ref description) = details.service.description {
+            println!("Description: {}", description);
+        }
+        if !details.service.tags.is_empty() {
+            println!("Tags: {}", details.service.tags.join(", "));
+        }
+        if let Some(ref owner) = details.service.owner {
+            println!("Owner: {}", owner);
+        }
         println!("Path: {}", details.path);
+        let unit_name = details
+            .trigger
+            .as_ref()
+            .map_or(resolved_name.as_str(), |t| t.name.as_str());
+        // Drop-ins, packages, and runtime state belong to this machine, not
+        // to the files of another backend.
+        let native = manager.is_native();
+        let drop_ins = if native {
+            platform::drop_in_paths(unit_name, std::path::Path::new(&details.path))
+        } else {
+            Vec::new()
+        };
+        if !drop_ins.is_empty() {
+            let paths: Vec<String> = drop_ins.iter().map(|p| p.display().to_string()).collect();
+            println!("Drop-ins: {}", paths.join(", "));
+        }
+        if let Some(package) = native
+            .then(|| platform::owning_package(std::path::Path::new(&details.path)))
+            .flatten()
+        {
+            println!("Package: {}", package.display());
+        }
         println!(
             "Status: {}",
             if details.running {
@@ -25,6 +106,42 @@ impl Show {
             }
         );
         println!("Enabled: {}", if details.enabled { "Yes" } else { "No" });
+        if native
+            && !self.files_only
+            && manager.require_backend("query the service manager").is_ok()
+        {
+            match platform::runtime_properties(unit_name) {
+                Ok(runtime) => print_runtime(&details, &runtime),
+                Err(e) => eprintln!("Warning: couldn't query the service manager: {e:#}"),
+            }
+        }
+
+        // User services need lingering to start at boot without a login session.
+        #[cfg(target_os = "linux")]
+        if let Some(service_ref) = native
+            .then(|| platform::get_service(&resolved_name).ok())
+            .flatten()
+        {
+            if platform::service_scope(&service_ref) == "user" {
+                if let Some(user) = platform::current_user() {
+                    let linger = platform::is_linger_enabled(&user);
+                    println!("Linger ({}): {}", user, if linger { "Yes" } else { "No" });
+                }
+            }
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(state) = &unit_state {
+            if let Some(preset) = &state.preset {
+                if state.drifted() {
+                    println!(
+                        "Vendor Preset: {preset} (now {}; `ser preset {}` restores it)",
+                        state.state, self.name
+                    );
+                } else {
+                    println!("Vendor Preset: {preset}");
+                }
+            }
+        }
 
         if !details.service.program.is_empty() {
             println!("Program: {}", details.service.program);
@@ -37,9 +154,42 @@ impl Show {
         if let Some(ref wd) = details.service.working_directory {
             println!("Working Directory: {}", wd);
         }
+        if let Some(ref user) = details.service.user {
+            println!("User: {}", user);
+        }
+        if cfg!(target_os = "linux") {
+            print_expanded(&details.service, &details.path);
+        }
 
         if let Some(ref schedule) = details.service.schedule {
             println!("Schedule: {}", schedule.display());
+            if let Some(jitter) = details.service.jitter {
+                println!("Jitter: up to {}", serlib::humanize_secs(jitter));
+            }
+            if let Some(catch_up) = details.service.catch_up {
+                println!("Catch Up: {}", if catch_up { "Yes" } else { "No" });
+            }
+            if let Some(accuracy) = details.service.accuracy {
+                println!("Accuracy: {}", serlib::humanize_secs(accuracy));
+            }
+            match schedule {
+                Schedule::Calendar(_) => {
+                    let runs: Vec<String> = timer::upcoming_runs(schedule, 3)
+                        .into_iter()
+                        .map(timer::format_dt)
+                        .collect();
+                    if !runs.is_empty() {
+                        println!("Next Runs: {}", runs.join(", "));
+                    }
+                }
+                Schedule::Interval(_) => {
+                    let now = chrono::Local::now().naive_local();
+                    println!(
+                        "Next Run: {}",
+                        timer::next_run(&resolved_name, schedule, now)
+                    );
+                }
+            }
         }
 
         println!(
@@ -59,6 +209,175 @@ impl Show {
             }
         );
 
+        if !details.service.conditions.is_empty() {
+            println!("Conditions:");
+            for condition in &details.service.conditions {
+                println!("  {}", condition.to_systemd_line());
+            }
+        }
+        if !details.service.directories.is_empty() {
+            println!("Directories:");
+            for dir in &details.service.directories {
+                println!("  {}={}", dir.kind.directive(), dir.name);
+            }
+        }
+        if let Some(score) = details.service.oom_score_adjust {
+            println!("OOM Score Adjust: {}", score);
+        }
+        if let Some(policy) = details.service.oom_policy {
+            println!("OOM Policy: {}", policy.as_str());
+        }
+        if !details.service.ambient_capabilities.is_empty() {
+            println!(
+                "Ambient Capabilities: {}",
+                details.service.ambient_capabilities.join(" ")
+            );
+        }
+        if let Some(set) = &details.service.capability_bounding_set {
+            println!(
+                "Capability Bounding Set: {}",
+                if set.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    set.join(" ")
+                }
+            );
+        }
+        if details.service.private_network {
+            println!("Private Network: yes");
+        }
+        for (label, list) in [
+            ("IP Address Allow", &details.service.ip_address_allow),
+            ("IP Address Deny", &details.service.ip_address_deny),
+            (
+                "Address Families",
+                &details.service.restrict_address_families,
+            ),
+        ] {
+            if !list.is_empty() {
+                println!("{label}: {}", list.join(" "));
+            }
+        }
+
+        if !drop_ins.is_empty() {
+            print_overrides(std::path::Path::new(&details.path), &drop_ins)?;
+        }
+
+        if self.raw {
+            print_raw(std::path::Path::new(&details.path), &drop_ins)?;
+        }
+
         Ok(())
     }
 }
+
+/// Runtime state only the service manager knows, and anywhere its view of
+/// the unit differs from the files.
+fn print_runtime(details: &serlib::FsServiceDetails, runtime: &platform::RuntimeProperties) {
+    if let Some(ref state) = runtime.state {
+        println!("State: {}", state);
+    }
+    if let Some(pid) = runtime.main_pid {
+        println!("Main PID: {}", pid);
+    }
+    if let Some(restarts) = runtime.restarts {
+        println!("Restarts: {}", restarts);
+    }
+    for discrepancy in platform::discrepancies(details, runtime) {
+        println!("Warning: {}", discrepancy);
+    }
+}
+
+/// The settings drop-ins change, and which drop-in each comes from.
+fn print_overrides(path: &std::path::Path, drop_ins: &[std::path::PathBuf]) -> Result<()> {
+    println!("From drop-ins:");
+    for effective in platform::effective_fields(path, drop_ins)? {
+        if effective.source != path {
+            let field = &effective.field;
+            println!(
+                "  {}={}  ({})",
+                field.key,
+                field.value,
+                effective.source.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Every key/value in the file, grouped under its section as in the file.
+/// With drop-ins, these are the effective values, marked where a drop-in
+/// set them.
+fn print_raw(path: &std::path::Path, drop_ins: &[std::path::PathBuf]) -> Result<()> {
+    let fields = if drop_ins.is_empty() {
+        platform::raw_fields(path)?
+            .into_iter()
+            .map(|field| (field, None))
+            .collect()
+    } else {
+        let mut fields: Vec<_> = platform::effective_fields(path, drop_ins)?
+            .into_iter()
+            .map(|e| {
+                let source = (e.source != path).then_some(e.source);
+                (e.field, source)
+            })
+            .collect();
+        // Group by section, keeping file order within each.
+        let mut order: Vec<String> = Vec::new();
+        for (field, _) in &fields {
+            if !order.contains(&field.section) {
+                order.push(field.section.clone());
+            }
+        }
+        fields.sort_by_key(|(field, _)| order.iter().position(|s| *s == field.section));
+        fields
+    };
+    println!();
+    println!("Raw ({}):", path.display());
+    let mut section = None;
+    for (field, source) in &fields {
+        if section != Some(&field.section) {
+            section = Some(&field.section);
+            if !field.section.is_empty() {
+                println!("  [{}]", field.section);
+            }
+        }
+        let indent = if field.section.is_empty() {
+            "  "
+        } else {
+            "    "
+        };
+        match source {
+            Some(source) => println!(
+                "{indent}{}={}  ({})",
+                field.key,
+                field.value,
+                source.display()
+            ),
+            None => println!("{indent}{}={}", field.key, field.value),
+        }
+    }
+    Ok(())
+}
+
+/// The command as systemd will run it, if specifiers (`%h`) or variables
+/// (`${PORT}`) make it differ from what's written.
+fn print_expanded(service: &serlib::ServiceDetails, path: &str) {
+    use serlib::specifiers::{self, Specifiers};
+
+    let user = specifiers::is_user_unit(std::path::Path::new(path));
+    let specs = Specifiers::systemd(&service.name, user);
+    let env = service.effective_env().unwrap_or_default();
+    let command = std::iter::once(&service.program)
+        .chain(&service.arguments)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let expanded = specifiers::expand(&command, &specs, &env);
+    if expanded.value != command {
+        println!("Expanded Command: {}", expanded.value);
+    }
+    if !expanded.unresolved.is_empty() {
+        println!("Unresolved: {}", expanded.unresolved.join(", "));
+    }
+}
@@ -1,21 +1,58 @@
-use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
 use clap::Args;
+use dialoguer::theme::ColorfulTheme;
+
+use crate::prompt::Confirm;
 
+use serlib::hooks::HookPoint;
 use serlib::platform;
+use serlib::ports;
+use serlib::readiness::{self, Probe};
 
 #[derive(Debug, Args)]
 pub struct Start {
     #[arg(help = "Name of the service to start")]
     pub name: String,
+    #[arg(long, help = "Start stopped dependencies (from After=) without asking")]
+    pub with_deps: bool,
+    #[arg(long, help = "Continue even if a pre_start/post_start hook fails")]
+    pub force: bool,
+    /// Wait until the service is ready: port:PORT accepts connections,
+    /// url:URL answers 2xx, or log:REGEX matches a new log line (repeatable)
+    #[arg(long, value_name = "PROBE")]
+    pub wait_for: Vec<Probe>,
+    /// Seconds to wait for --wait-for before failing
+    #[arg(long, default_value_t = 30, requires = "wait_for")]
+    pub timeout: u64,
+    /// Start even if a port it declares is already in use
+    #[arg(long)]
+    pub ignore_port_conflicts: bool,
+    /// Open the ports it declares in the firewall (ufw or firewalld; the
+    /// application firewall on macOS), closed again when it's removed
+    #[arg(long)]
+    pub open_firewall: bool,
+    #[command(flatten)]
+    pub project: crate::command::ProjectHooks,
 }
 
 impl Start {
     pub fn run(&self) -> Result<()> {
-        let resolved_name = platform::resolve_service_name(&self.name)?;
+        let manager = crate::command::manager();
+        let resolved_name = manager.resolve(&self.name)?;
+        manager.require_backend(&format!("start '{}'", self.name))?;
+        let _lock = crate::command::lock_service(&resolved_name)?;
 
         // Check if service exists, and whether it's a scheduled (timer) unit.
-        let details = platform::get_service_details(&resolved_name)
-            .map_err(|_| anyhow!("Service '{}' not found.", self.name))?;
+        let details = manager
+            .get(&resolved_name)
+            .map_err(crate::command::not_found(&self.name))?;
+
+        let mut visited = HashSet::from([resolved_name.clone()]);
+        self.start_dependencies(&details.service.after, &mut visited)?;
 
         // For a timer, `start` runs the job once now rather than arming the
         // schedule — use `ser enable` to turn the schedule on.
@@ -28,13 +65,137 @@ impl Start {
 
         if details.running {
             println!("Service '{}' is already running.", self.name);
-            return Ok(());
+            return self.wait_until_ready(&resolved_name, &Default::default());
         }
 
+        if !self.ignore_port_conflicts {
+            self.check_ports(&details)?;
+        }
+
+        if self.open_firewall {
+            crate::command::open_firewall(&resolved_name)?;
+        }
+
+        let hooks = self.project.hooks_for(&resolved_name)?;
+        crate::command::run_hook(&hooks, HookPoint::PreStart, &resolved_name, self.force)?;
+
+        let baseline = if self.wait_for.iter().any(|p| matches!(p, Probe::Log(_))) {
+            readiness::log_baseline(&resolved_name)
+        } else {
+            Default::default()
+        };
         print!("Starting service '{}'...", self.name);
-        platform::start_service(&resolved_name)?;
+        let started = Instant::now();
+        let result = manager.start(&resolved_name);
+        crate::command::notify_if_slow(started, &format!("Start {}", self.name), &result);
+        result?;
         println!(" done.");
+        crate::command::record_loaded(&resolved_name, Path::new(&details.path));
+        crate::command::run_hook(&hooks, HookPoint::PostStart, &resolved_name, self.force)?;
+
+        self.wait_until_ready(&resolved_name, &baseline)
+    }
+
+    /// Fail before starting if something else listens on a port the service
+    /// declares, since it would only crash-loop on EADDRINUSE.
+    fn check_ports(&self, details: &serlib::FsServiceDetails) -> Result<()> {
+        let urls: Vec<&str> = self
+            .wait_for
+            .iter()
+            .filter_map(|probe| match probe {
+                Probe::Url(url) => Some(url.as_str()),
+                _ => None,
+            })
+            .collect();
+        let mut ports = ports::declared_ports(details, &urls);
+        for probe in &self.wait_for {
+            let Probe::Port(address) = probe else {
+                continue;
+            };
+            if let Some(port) = address.rsplit_once(':').and_then(|(_, p)| p.parse().ok()) {
+                if !ports.contains(&port) {
+                    ports.push(port);
+                }
+            }
+        }
+        let conflicts: Vec<_> = ports.into_iter().filter_map(ports::listener).collect();
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+        for listener in &conflicts {
+            eprintln!("Port {} is already in use by {listener}", listener.port);
+        }
+        bail!(
+            "Not starting '{}': it would fail to bind (use --ignore-port-conflicts to start anyway)",
+            self.name
+        )
+    }
 
+    fn wait_until_ready(&self, name: &str, baseline: &HashSet<String>) -> Result<()> {
+        if self.wait_for.is_empty() {
+            return Ok(());
+        }
+        print!("Waiting for '{}' to be ready...", self.name);
+        std::io::Write::flush(&mut std::io::stdout())?;
+        let started = Instant::now();
+        readiness::wait_for(
+            name,
+            &self.wait_for,
+            Duration::from_secs(self.timeout),
+            baseline,
+        )
+        .inspect_err(|_| println!(" failed."))?;
+        println!(" ready after {:.1}s.", started.elapsed().as_secs_f64());
+        Ok(())
+    }
+
+    /// Start any `After=` dependencies that are known services but not running,
+    /// depth-first so a dependency's own dependencies come up before it.
+    /// Targets (`network.target`, ...) are left to the init system.
+    fn start_dependencies(&self, after: &[String], visited: &mut HashSet<String>) -> Result<()> {
+        for dep in after {
+            if dep.ends_with(".target") {
+                continue;
+            }
+            let manager = crate::command::manager();
+            let Ok(dep_name) = manager.resolve(dep) else {
+                continue;
+            };
+            if !visited.insert(dep_name.clone()) {
+                continue;
+            }
+            let Ok(dep_details) = manager.get(&dep_name) else {
+                continue;
+            };
+            self.start_dependencies(&dep_details.service.after, visited)?;
+            if dep_details.running {
+                continue;
+            }
+
+            if !self.with_deps {
+                if atty::isnt(atty::Stream::Stdin) {
+                    eprintln!(
+                        "Warning: dependency '{}' is not running (use --with-deps to start it).",
+                        dep
+                    );
+                    continue;
+                }
+                let start_dep = Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!(
+                        "Dependency '{}' is not running. Start it first?",
+                        dep
+                    ))
+                    .default(true)
+                    .interact()?;
+                if !start_dep {
+                    continue;
+                }
+            }
+
+            print!("Starting dependency '{}'...", dep);
+            manager.start(&dep_name)?;
+            println!(" done.");
+        }
         Ok(())
     }
 }
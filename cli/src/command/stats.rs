@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Result};
+use clap::Args;
+
+use serlib::history::{self, Summary};
+use serlib::platform;
+
+/// Width of the sparklines, in terminal columns.
+const SPARKLINE_WIDTH: usize = 48;
+
+#[derive(Debug, Args)]
+pub struct Stats {
+    #[arg(help = "Name of the service")]
+    pub name: String,
+    /// How far back to look, e.g. 24h, 7d, 30d
+    #[arg(long, default_value = "24h")]
+    pub last: String,
+}
+
+impl Stats {
+    pub fn run(&self) -> Result<()> {
+        let window = history::parse_window(&self.last)
+            .ok_or_else(|| anyhow!("Invalid window '{}'; use e.g. 24h, 7d, 30d", self.last))?;
+        let name = platform::normalize_service_name(&self.name);
+        let since = chrono::Utc::now().timestamp() - window;
+        let samples = history::load(name, since)?;
+        let cpu: Vec<f64> = samples.iter().filter_map(|s| s.cpu).collect();
+        let memory: Vec<f64> = samples
+            .iter()
+            .filter_map(|s| s.memory)
+            .map(|m| m as f64)
+            .collect();
+        if cpu.is_empty() && memory.is_empty() {
+            println!(
+                "No usage samples for '{}' in the last {}. Run `ser sample --install` to start recording.",
+                self.name, self.last
+            );
+            return Ok(());
+        }
+
+        println!("Service: {}", name);
+        println!("Window: last {} ({} samples)", self.last, samples.len());
+        print_series("CPU", &cpu, |v| format!("{v:.1}%"));
+        print_series("Memory", &memory, format_bytes);
+        Ok(())
+    }
+}
+
+fn print_series(label: &str, values: &[f64], format: impl Fn(f64) -> String) {
+    let Some(Summary { min, p50, p95, max }) = history::summarize(values) else {
+        return;
+    };
+    println!();
+    println!("{label}: {}", history::sparkline(values, SPARKLINE_WIDTH));
+    println!(
+        "  min {}  p50 {}  p95 {}  max {}",
+        format(min),
+        format(p50),
+        format(p95),
+        format(max)
+    );
+}
+
+fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
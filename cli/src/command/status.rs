@@ -0,0 +1,131 @@
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::command::list;
+use serlib::platform::{self, ListLevel};
+
+#[derive(Debug, Args)]
+pub struct Status {
+    /// Services to check; `@tag` selects every managed service with that tag.
+    /// Without any, lists services like `ser list`
+    targets: Vec<String>,
+
+    /// Check every service ser manages
+    #[arg(long, conflicts_with = "targets")]
+    all_managed: bool,
+
+    /// Exit 0 only if every service is running, 1 if any failed, or else 3
+    /// if any are stopped (the code `systemctl status` uses). A scheduled job
+    /// waiting for its next run, after a run that didn't fail, counts as
+    /// running
+    #[arg(long)]
+    exit_code: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Health {
+    Running,
+    /// A scheduled job between runs, whose last run didn't fail.
+    Idle,
+    Stopped,
+    Failed,
+}
+
+impl Health {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Health::Running => "running",
+            Health::Idle => "idle",
+            Health::Stopped => "stopped",
+            Health::Failed => "failed",
+        }
+    }
+}
+
+impl Status {
+    pub fn run(&self) -> Result<()> {
+        if self.targets.is_empty() && !self.all_managed {
+            let manager = crate::command::manager();
+            let services = list::listed_services(manager, ListLevel::Default, false, false);
+            return list::print_services(manager, services, list::Output::Auto, false);
+        }
+
+        let names = self.resolve_names()?;
+        if names.is_empty() {
+            bail!("No services matched");
+        }
+        let width = names.iter().map(String::len).max().unwrap_or(0);
+        let mut counts = [0; 4];
+        for name in &names {
+            let health = health(name);
+            counts[health as usize] += 1;
+            println!("{name:width$}  {}", health.as_str());
+        }
+
+        let [running, idle, stopped, failed] = counts;
+        let mut summary = vec![format!("{running} running")];
+        for (count, label) in [(idle, "idle"), (stopped, "stopped"), (failed, "failed")] {
+            if count > 0 {
+                summary.push(format!("{count} {label}"));
+            }
+        }
+        println!("{} service(s): {}", names.len(), summary.join(", "));
+
+        if self.exit_code {
+            // Idle timers are deliberately healthy: they run when scheduled.
+            let code = if failed > 0 {
+                1
+            } else if stopped > 0 {
+                3
+            } else {
+                0
+            };
+            if code != 0 {
+                return Err(crate::command::ExitStatus(code).into());
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_names(&self) -> Result<Vec<String>> {
+        let managed = || -> Result<Vec<platform::ServiceRef>> {
+            Ok(platform::list_services(ListLevel::Default)?
+                .into_iter()
+                .filter(platform::is_managed)
+                .collect())
+        };
+        let name = |s: &platform::ServiceRef| platform::normalize_service_name(&s.name).to_string();
+        if self.all_managed {
+            return Ok(managed()?.iter().map(name).collect());
+        }
+        let mut names = Vec::new();
+        for target in &self.targets {
+            let matched = match target.strip_prefix('@') {
+                Some(tag) => managed()?
+                    .iter()
+                    .filter(|s| platform::service_tags(s).iter().any(|t| t == tag))
+                    .map(name)
+                    .collect(),
+                None => vec![platform::resolve_service_name(target)?],
+            };
+            for name in matched {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        Ok(names)
+    }
+}
+
+fn health(name: &str) -> Health {
+    if platform::is_service_failed(name) {
+        Health::Failed
+    } else if platform::is_service_running(name).unwrap_or(false) {
+        Health::Running
+    } else if platform::has_timer(name) {
+        Health::Idle
+    } else {
+        Health::Stopped
+    }
+}
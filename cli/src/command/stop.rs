@@ -1,35 +1,53 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use clap::Args;
 
-use serlib::platform;
+use serlib::hooks::HookPoint;
 
 #[derive(Debug, Args)]
 pub struct Stop {
     #[arg(help = "Name of the service to stop")]
     pub name: String,
+    #[arg(long, help = "Continue even if a pre_stop/post_stop hook fails")]
+    pub force: bool,
+    #[command(flatten)]
+    pub project: crate::command::ProjectHooks,
 }
 
 impl Stop {
     pub fn run(&self) -> Result<()> {
-        let resolved_name = platform::resolve_service_name(&self.name)?;
+        let manager = crate::command::manager();
+        let resolved_name = manager.resolve(&self.name)?;
+        manager.require_backend(&format!("stop '{}'", self.name))?;
+        let _lock = crate::command::lock_service(&resolved_name)?;
 
         // Check if service exists and is running
-        match platform::get_service_details(&resolved_name) {
+        match manager.get(&resolved_name) {
             Ok(details) => {
-                if !details.running {
+                // A timer-backed service is idle between runs, but its timer
+                // still needs stopping.
+                #[cfg(target_os = "linux")]
+                let armed = details.trigger.is_none()
+                    && serlib::platform::has_timer(&resolved_name)
+                    && serlib::platform::is_timer_enabled(&resolved_name);
+                #[cfg(not(target_os = "linux"))]
+                let armed = false;
+                if !details.running && !armed {
                     println!("Service '{}' is already stopped.", self.name);
                     return Ok(());
                 }
             }
-            Err(_) => {
-                return Err(anyhow!("Service '{}' not found.", self.name));
-            }
+            Err(e) => return Err(crate::command::not_found(&self.name)(e)),
         }
 
+        let hooks = self.project.hooks_for(&resolved_name)?;
+        crate::command::run_hook(&hooks, HookPoint::PreStop, &resolved_name, self.force)?;
+
         print!("Stopping service '{}'...", self.name);
-        platform::stop_service(&resolved_name)?;
+        manager.stop(&resolved_name)?;
         println!(" done.");
 
+        crate::command::run_hook(&hooks, HookPoint::PostStop, &resolved_name, self.force)?;
+
         Ok(())
     }
 }
@@ -4,13 +4,15 @@ use anyhow::{anyhow, Result};
 use chrono::NaiveDateTime;
 use clap::{Args, Subcommand};
 use dialoguer::theme::ColorfulTheme;
-use dialoguer::Confirm;
+
+use crate::prompt::Confirm;
 use tabled::{
     settings::{Padding, Style},
     Table, Tabled,
 };
 
 use crate::interactive::ServiceKind;
+use serlib::guard::FileSnapshot;
 use serlib::platform::{self, ListLevel};
 use serlib::Schedule;
 
@@ -55,22 +57,34 @@ impl Timer {
 #[derive(Debug, Args)]
 pub struct Create {
     command: Vec<String>,
+    /// Delay each run by a random amount up to this span, e.g. 5m
+    #[arg(long, value_parser = parse_span)]
+    jitter: Option<u64>,
+    /// Whether a run missed while the machine was off runs later (default true)
+    #[arg(long)]
+    catch_up: Option<bool>,
 }
 
 impl Create {
     pub fn run(&self) -> Result<()> {
-        println!("Creating a new timer...\n");
+        println!("{}\n", crate::i18n::t("Creating a new timer..."));
         let theme = ColorfulTheme::default();
-        let details = crate::interactive::collect_service_details(
+        let mut details = crate::interactive::collect_service_details(
             &theme,
             self.command.clone(),
             true,
             ServiceKind::Timer,
+            &Default::default(),
+            true,
         )?;
+        details.jitter = self.jitter;
+        details.catch_up = self.catch_up;
         crate::command::new::finish_create(&theme, details)
     }
 }
 
+/// All scheduled jobs with when they last ran, how that went, and when they
+/// run next, like `systemctl list-timers` but covering launchd jobs too.
 #[derive(Debug, Args)]
 pub struct List {
     #[arg(short, long, help = "Include system timers, not just managed ones")]
@@ -83,6 +97,10 @@ struct TimerRow {
     name: String,
     #[tabled(rename = "Schedule")]
     schedule: String,
+    #[tabled(rename = "Last run")]
+    last: String,
+    #[tabled(rename = "Result")]
+    result: String,
     #[tabled(rename = "Next run")]
     next: String,
     #[tabled(rename = "Enabled")]
@@ -102,20 +120,25 @@ impl List {
         let now = chrono::Local::now().naive_local();
         let rows: Vec<TimerRow> = timers
             .iter()
-            .map(|t| TimerRow {
-                name: t.display_name.clone(),
-                schedule: t.schedule.display(),
-                next: format_next(t.schedule.next_fire_after(now)),
-                enabled: if t.enabled { "true" } else { "false" }.to_string(),
-                status: if t.running { "running" } else { "stopped" }.to_string(),
+            .map(|t| {
+                let last = platform::last_run(&t.display_name);
+                TimerRow {
+                    name: t.display_name.clone(),
+                    schedule: t.schedule.display(),
+                    last: last.time.unwrap_or_else(|| "-".to_string()),
+                    result: last.result.unwrap_or_else(|| "-".to_string()),
+                    next: next_run(&t.display_name, &t.schedule, now),
+                    enabled: if t.enabled { "true" } else { "false" }.to_string(),
+                    status: if t.running { "running" } else { "stopped" }.to_string(),
+                }
             })
             .collect();
 
         if atty::isnt(atty::Stream::Stdout) {
             for row in &rows {
                 println!(
-                    "{}\t{}\t{}\t{}\t{}",
-                    row.name, row.schedule, row.next, row.enabled, row.status
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    row.name, row.schedule, row.last, row.result, row.next, row.enabled, row.status
                 );
             }
         } else {
@@ -137,27 +160,32 @@ impl Show {
     pub fn run(&self) -> Result<()> {
         let resolved = platform::resolve_service_name(&self.name)?;
         let details = platform::get_service_details(&resolved)?;
-        let schedule = details
-            .service
-            .schedule
-            .ok_or_else(|| anyhow!("'{}' is not a timer (no schedule configured)", self.name))?;
+        let schedule =
+            details.service.schedule.clone().ok_or_else(|| {
+                anyhow!("'{}' is not a timer (no schedule configured)", self.name)
+            })?;
 
-        // macOS plist parsing leaves the name blank, so prefer the resolved
-        // unit/label name and only fall back to the parsed Description.
+        // Prefer the parsed unit/label name, falling back to the resolved one.
         let display_name = if details.service.name.is_empty() {
             platform::normalize_service_name(&resolved).to_string()
         } else {
             details.service.name.clone()
         };
         println!("Timer: {}", display_name);
+        if let Some(ref description) = details.service.description {
+            println!("Description: {}", description);
+        }
         println!("Path: {}", details.path);
-        println!("Schedule: {}", schedule.display());
+        println!("Schedule: {}", describe(&schedule, &details.service));
         match &schedule {
             Schedule::Calendar(c) => println!("OnCalendar: {}", c.to_systemd_oncalendar()),
             Schedule::Interval(secs) => {
                 println!("Interval: {}", Schedule::interval_to_systemd(*secs))
             }
         }
+        if let Some(catch_up) = details.service.catch_up {
+            println!("Catch Up: {}", if catch_up { "Yes" } else { "No" });
+        }
         println!("Enabled: {}", if details.enabled { "Yes" } else { "No" });
 
         if !details.service.program.is_empty() {
@@ -171,12 +199,10 @@ impl Show {
 
         match &schedule {
             // Interval timers fire relative to activation, so wall-clock times
-            // aren't computable here.
-            Schedule::Interval(secs) => {
-                println!(
-                    "Upcoming: runs every {}",
-                    Schedule::interval_to_systemd(*secs)
-                );
+            // aren't computable here; systemd can still say when it's due.
+            Schedule::Interval(_) => {
+                let now = chrono::Local::now().naive_local();
+                println!("Upcoming: {}", next_run(&resolved, &schedule, now));
             }
             Schedule::Calendar(_) => {
                 let upcoming = upcoming_runs(&schedule, 5);
@@ -258,12 +284,24 @@ impl Next {
 pub struct Edit {
     #[arg(help = "Name of the timer to edit")]
     name: String,
+    /// Delay each run by a random amount up to this span, e.g. 5m (0 to remove)
+    #[arg(long, value_parser = parse_span)]
+    jitter: Option<u64>,
+    /// How closely runs must hit their time, e.g. 1m; systemd only (0 to remove)
+    #[arg(long, value_parser = parse_span)]
+    accuracy: Option<u64>,
+    /// Whether a run missed while the machine was off runs later
+    #[arg(long)]
+    catch_up: Option<bool>,
 }
 
 impl Edit {
     pub fn run(&self) -> Result<()> {
         let theme = ColorfulTheme::default();
         let resolved = platform::resolve_service_name(&self.name)?;
+        let path = platform::get_service(&resolved)?.path;
+        let _lock = crate::command::lock_service_file(&path)?;
+        let snapshot = FileSnapshot::take(std::path::Path::new(&path));
         let mut details = platform::get_service_details(&resolved)?.service;
 
         let current = details
@@ -272,13 +310,33 @@ impl Edit {
             .ok_or_else(|| anyhow!("'{}' is not a timer (no schedule configured)", self.name))?;
         println!("Current schedule: {}\n", current.display());
 
-        let new_schedule = crate::interactive::collect_schedule(&theme)?
-            .ok_or_else(|| anyhow!("A timer requires a schedule"))?;
-        details.schedule = Some(new_schedule.clone());
+        // With --jitter/--accuracy/--catch-up, only those change; otherwise
+        // ask for a new schedule.
+        if self.jitter.is_some() || self.accuracy.is_some() || self.catch_up.is_some() {
+            if self.catch_up.is_some() {
+                details.catch_up = self.catch_up;
+            }
+            if let Some(jitter) = self.jitter {
+                details.jitter = (jitter > 0).then_some(jitter);
+            }
+            if let Some(accuracy) = self.accuracy {
+                details.accuracy = (accuracy > 0).then_some(accuracy);
+            }
+        } else {
+            details.schedule = Some(
+                crate::interactive::collect_schedule(&theme)?
+                    .ok_or_else(|| anyhow!("A timer requires a schedule"))?,
+            );
+        }
+        let new_schedule = details.schedule.clone().expect("checked above");
+
+        if !crate::command::confirm_overwrite(&snapshot)? {
+            return Err(anyhow!("Not updating '{}'", self.name));
+        }
 
         // Regenerate the unit/plist with the new schedule, preserving everything else.
         platform::create_service(&details)?;
-        println!("\nUpdated schedule: {}", new_schedule.display());
+        println!("\nUpdated schedule: {}", describe(&new_schedule, &details));
 
         let apply = Confirm::with_theme(&theme)
             .with_prompt("Apply now (restart the timer)?")
@@ -305,6 +363,7 @@ pub struct Rm {
 impl Rm {
     pub fn run(&self) -> Result<()> {
         let resolved = platform::resolve_service_name(&self.name)?;
+        crate::command::warn_if_package_owned(&platform::get_service(&resolved)?.path);
 
         if !self.yes {
             let confirmed = Confirm::with_theme(&ColorfulTheme::default())
@@ -317,23 +376,24 @@ impl Rm {
             }
         }
 
-        platform::remove_service(&resolved)?;
+        crate::command::close_firewall(&resolved)?;
+        platform::delete_service(&resolved)?;
         println!("Removed timer '{}'.", self.name);
         Ok(())
     }
 }
 
-struct TimerEntry {
-    display_name: String,
-    schedule: Schedule,
-    enabled: bool,
-    running: bool,
+pub(crate) struct TimerEntry {
+    pub display_name: String,
+    pub schedule: Schedule,
+    pub enabled: bool,
+    pub running: bool,
 }
 
 /// Gather every scheduled unit on the system as a `TimerEntry`. The schedule is
 /// read uniformly via `get_service_details`, which on Linux pairs the `.service`
 /// with its `.timer` unit.
-fn collect_timers(all: bool) -> Result<Vec<TimerEntry>> {
+pub(crate) fn collect_timers(all: bool) -> Result<Vec<TimerEntry>> {
     let level = if all {
         ListLevel::System
     } else {
@@ -369,7 +429,7 @@ fn collect_timers(all: bool) -> Result<Vec<TimerEntry>> {
 }
 
 /// The next `count` fire times of a schedule, starting from now.
-fn upcoming_runs(schedule: &Schedule, count: usize) -> Vec<NaiveDateTime> {
+pub(crate) fn upcoming_runs(schedule: &Schedule, count: usize) -> Vec<NaiveDateTime> {
     let mut runs = Vec::new();
     let mut cursor = chrono::Local::now().naive_local();
     for _ in 0..count {
@@ -384,10 +444,45 @@ fn upcoming_runs(schedule: &Schedule, count: usize) -> Vec<NaiveDateTime> {
     runs
 }
 
+/// When a schedule next fires, for display. Calendar schedules are computed;
+/// interval ones depend on the last run, so they come from systemd where it
+/// can tell us, and are otherwise described as "every ...".
+pub(crate) fn next_run(name: &str, schedule: &Schedule, now: NaiveDateTime) -> String {
+    match schedule {
+        Schedule::Calendar(_) => format_next(schedule.next_fire_after(now)),
+        Schedule::Interval(_) => {
+            #[cfg(target_os = "linux")]
+            if let Ok(Some(next)) = platform::get_timer_next_trigger(name) {
+                return next;
+            }
+            #[cfg(not(target_os = "linux"))]
+            let _ = name;
+            schedule.display()
+        }
+    }
+}
+
+/// A schedule with its jitter, e.g. `daily at 03:00 (+ up to 5m jitter)`.
+fn describe(schedule: &Schedule, details: &serlib::ServiceDetails) -> String {
+    match details.jitter {
+        Some(jitter) => format!(
+            "{} (+ up to {} jitter)",
+            schedule.display(),
+            serlib::humanize_secs(jitter)
+        ),
+        None => schedule.display(),
+    }
+}
+
+fn parse_span(span: &str) -> Result<u64, String> {
+    Schedule::parse_interval_secs(span)
+        .ok_or_else(|| format!("expected a time span like 30s, 5m, or 1h, got '{span}'"))
+}
+
 fn format_next(dt: Option<NaiveDateTime>) -> String {
     dt.map(format_dt).unwrap_or_else(|| "-".to_string())
 }
 
-fn format_dt(dt: NaiveDateTime) -> String {
+pub(crate) fn format_dt(dt: NaiveDateTime) -> String {
     dt.format("%a %Y-%m-%d %H:%M").to_string()
 }
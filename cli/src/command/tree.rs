@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+
+use serlib::platform::{self, ListLevel, ServiceRef};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum GroupBy {
+    /// User vs. system services
+    Scope,
+    /// Where the definition came from (homebrew, ser, vendor, custom)
+    Source,
+    /// The target/trigger that starts the service (multi-user, timers, ...)
+    Target,
+    /// Service tags
+    Tag,
+}
+
+#[derive(Debug, Args)]
+pub struct Tree {
+    #[arg(
+        short,
+        long,
+        value_enum,
+        default_value = "scope",
+        help = "How to group services"
+    )]
+    pub by: GroupBy,
+    #[arg(short, long, help = "Show all services (system and user)")]
+    pub all: bool,
+}
+
+impl Tree {
+    pub fn run(&self) -> Result<()> {
+        let level = if self.all {
+            ListLevel::System
+        } else {
+            ListLevel::Default
+        };
+        let mut services = platform::list_services(level)?;
+        if matches!(level, ListLevel::Default) {
+            services.retain(platform::is_managed);
+        }
+        if services.is_empty() {
+            eprintln!("No services found.");
+            return Ok(());
+        }
+
+        let mut groups: BTreeMap<String, Vec<&ServiceRef>> = BTreeMap::new();
+        for service in &services {
+            for group in self.groups_for(service) {
+                groups.entry(group).or_default().push(service);
+            }
+        }
+
+        for (group, mut members) in groups {
+            members.sort_by(|a, b| a.name.cmp(&b.name));
+            println!("{} ({})", group, members.len());
+            let last = members.len() - 1;
+            for (i, service) in members.iter().enumerate() {
+                let branch = if i == last { "└──" } else { "├──" };
+                let enabled = if service.enabled { "" } else { "  [disabled]" };
+                println!("{} {}{}", branch, service.name, enabled);
+            }
+        }
+        Ok(())
+    }
+
+    fn groups_for(&self, service: &ServiceRef) -> Vec<String> {
+        match self.by {
+            GroupBy::Scope => vec![platform::service_scope(service).to_string()],
+            GroupBy::Source => vec![service.source.as_str().to_string()],
+            GroupBy::Target => service_targets(service),
+            GroupBy::Tag => {
+                let tags = platform::parse_service_file(Path::new(&service.path))
+                    .map(|d| d.tags)
+                    .unwrap_or_default();
+                if tags.is_empty() {
+                    vec!["(untagged)".to_string()]
+                } else {
+                    tags
+                }
+            }
+        }
+    }
+}
+
+/// The targets a unit is installed into (`WantedBy=`/`RequiredBy=`); timers
+/// group under `timers.target`. On macOS, jobs group by how launchd starts them.
+fn service_targets(service: &ServiceRef) -> Vec<String> {
+    if service.name.ends_with(".timer") {
+        return vec!["timers.target".to_string()];
+    }
+    if service.path.ends_with(".plist") {
+        let group = match platform::parse_service_file(Path::new(&service.path)) {
+            Ok(d) if d.schedule.is_some() => "scheduled",
+            Ok(d) if d.run_at_load => "at load",
+            Ok(_) => "on demand",
+            Err(_) => "(unparseable)",
+        };
+        return vec![group.to_string()];
+    }
+
+    let contents = std::fs::read_to_string(&service.path).unwrap_or_default();
+    let targets: Vec<String> = contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            line.strip_prefix("WantedBy=")
+                .or_else(|| line.strip_prefix("RequiredBy="))
+        })
+        .flat_map(|value| value.split_whitespace().map(|s| s.to_string()))
+        .collect();
+    if targets.is_empty() {
+        vec!["(static)".to_string()]
+    } else {
+        targets
+    }
+}
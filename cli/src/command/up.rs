@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+use clap::Args;
+use dialoguer::theme::ColorfulTheme;
+
+use crate::prompt::Confirm;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::progress::Progress;
+use serlib::platform;
+use serlib::spec;
+use serlib::ServiceDetails;
+
+#[derive(Debug, Args)]
+pub struct Up {
+    /// Don't ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+}
+
+impl Up {
+    pub fn run(&self) -> Result<()> {
+        let (path, services) = project_services()?.ok_or_else(|| {
+            anyhow!(
+                "No {} found in this directory or its parents",
+                spec::PROJECT_FILE
+            )
+        })?;
+        eprintln!("Using {}", path.display());
+        for details in &services {
+            print_summary(details);
+        }
+        if !self.yes
+            && !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Install and start {} service(s)?", services.len()))
+                .default(true)
+                .interact()?
+        {
+            return Ok(());
+        }
+
+        let was_running: Vec<bool> = services
+            .iter()
+            .map(|d| platform::is_service_running(&d.name).unwrap_or(false))
+            .collect();
+
+        let started = Instant::now();
+        let mut progress = Progress::new(services.len());
+        let results = platform::create_services(&services);
+        for (result, was_running) in results.into_iter().zip(was_running) {
+            let action = if was_running {
+                "restarting"
+            } else {
+                "starting"
+            };
+            progress.step(&result.name, action, || {
+                result.result?;
+                let done = if was_running {
+                    platform::restart_service(&result.name)?;
+                    "updated and restarted"
+                } else {
+                    platform::start_service(&result.name)?;
+                    "started"
+                };
+                if let Ok(service) = platform::get_service(&result.name) {
+                    crate::command::record_loaded(&result.name, Path::new(&service.path));
+                }
+                Ok(done.to_string())
+            });
+        }
+        let failed = progress.finish();
+        let result = if failed > 0 {
+            Err(anyhow!(
+                "{} of {} service(s) failed",
+                failed,
+                services.len()
+            ))
+        } else {
+            Ok(())
+        };
+        crate::command::notify_if_slow(started, "ser up", &result);
+        result?;
+        Ok(())
+    }
+}
+
+/// The services in the nearest `.ser.toml`, with bare program names resolved
+/// on PATH. `None` if there's no project file.
+pub fn project_services() -> Result<Option<(PathBuf, Vec<ServiceDetails>)>> {
+    let Some(path) = spec::find_project_file(&std::env::current_dir()?) else {
+        return Ok(None);
+    };
+    let mut services = spec::load_project(&path)?;
+    for details in &mut services {
+        details.program = crate::interactive::resolve_binary_path(&details.program)?;
+    }
+    Ok(Some((path, services)))
+}
+
+pub fn print_summary(details: &ServiceDetails) {
+    println!("{}", details.name);
+    println!(
+        "  Command: {} {}",
+        details.program,
+        details.arguments.join(" ")
+    );
+    if let Some(dir) = &details.working_directory {
+        println!("  Working directory: {dir}");
+    }
+    for file in &details.env_files {
+        println!("  Env file: {}", file.to_systemd_value());
+    }
+    if let Some(schedule) = &details.schedule {
+        println!("  Schedule: {}", schedule.display());
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::{anyhow, Result};
+use chrono::{Local, TimeZone};
+use clap::Args;
+
+use serlib::history;
+use serlib::platform;
+
+#[derive(Debug, Args)]
+pub struct Uptime {
+    #[arg(help = "Name of the service")]
+    pub name: String,
+    /// How far back to look, e.g. 24h, 7d, 30d
+    #[arg(long, default_value = "24h")]
+    pub last: String,
+}
+
+impl Uptime {
+    pub fn run(&self) -> Result<()> {
+        let window = history::parse_window(&self.last)
+            .ok_or_else(|| anyhow!("Invalid window '{}'; use e.g. 24h, 7d, 30d", self.last))?;
+        let name = platform::normalize_service_name(&self.name);
+        let since = chrono::Utc::now().timestamp() - window;
+        let samples = history::load(name, since)?;
+        let Some(report) = history::availability(&samples) else {
+            println!(
+                "No samples for '{}' in the last {}. Run `ser sample --install` to start recording.",
+                self.name, self.last
+            );
+            return Ok(());
+        };
+
+        println!("Service: {}", name);
+        println!("Window: last {} ({} samples)", self.last, report.samples);
+        println!("Availability: {:.2}%", report.percent);
+        println!("Incidents: {}", report.incidents.len());
+        for incident in &report.incidents {
+            match incident.end {
+                Some(end) => println!(
+                    "  {} - {} ({})",
+                    format_time(incident.start),
+                    format_time(end),
+                    format_duration(end - incident.start)
+                ),
+                None => println!("  {} - ongoing", format_time(incident.start)),
+            }
+        }
+        let restarts = history::restarts(&samples);
+        println!("Restarts: {}", restarts.len());
+        for time in restarts {
+            println!("  {}", format_time(time));
+        }
+        Ok(())
+    }
+}
+
+fn format_time(ts: i64) -> String {
+    Local
+        .timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| ts.to_string())
+}
+
+fn format_duration(secs: i64) -> String {
+    match secs {
+        s if s >= 3600 => format!("{}h{}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
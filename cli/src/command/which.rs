@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use clap::Args;
+
+use serlib::platform::{self, ListLevel};
+
+#[derive(Debug, Args)]
+pub struct Which {
+    #[arg(long, help = "Find the service running this process")]
+    pub pid: Option<u32>,
+    #[arg(long, help = "Find the service listening on this TCP port")]
+    pub port: Option<u16>,
+    #[arg(long, help = "Find services whose definition references this path")]
+    pub path: Option<String>,
+}
+
+impl Which {
+    pub fn run(&self) -> Result<()> {
+        if let Some(pid) = self.pid {
+            print_pid_owner(pid)?;
+        } else if let Some(port) = self.port {
+            let pids = platform::pids_listening_on(port)?;
+            if pids.is_empty() {
+                bail!("Nothing is listening on port {}", port);
+            }
+            for pid in pids {
+                print_pid_owner(pid)?;
+            }
+        } else if let Some(path) = &self.path {
+            find_path_references(path)?;
+        } else {
+            bail!("Specify one of --pid, --port, or --path");
+        }
+        Ok(())
+    }
+}
+
+fn print_pid_owner(pid: u32) -> Result<()> {
+    match platform::service_for_pid(pid)? {
+        Some(service) => println!("{}\t{}", pid, service),
+        None => println!("{}\t(not owned by a service)", pid),
+    }
+    Ok(())
+}
+
+fn find_path_references(path: &str) -> Result<()> {
+    let services = platform::list_services(ListLevel::System)?;
+    let mut found = false;
+    for service in services {
+        let Ok(details) = platform::parse_service_file(Path::new(&service.path)) else {
+            continue;
+        };
+        let field = if details.program == path {
+            Some("program")
+        } else if details.arguments.iter().any(|a| a.contains(path)) {
+            Some("argument")
+        } else if details.working_directory.as_deref() == Some(path) {
+            Some("working directory")
+        } else if details.env_files.iter().any(|f| f.path == path) {
+            Some("env file")
+        } else {
+            // Catch references we don't model (WatchPaths, Condition*, ...).
+            std::fs::read_to_string(&service.path)
+                .ok()
+                .filter(|raw| raw.contains(path))
+                .map(|_| "file")
+        };
+        if let Some(field) = field {
+            found = true;
+            println!("{}\t{}\t{}", service.name, field, service.path);
+        }
+    }
+    if !found {
+        bail!("No service references '{}'", path);
+    }
+    Ok(())
+}
@@ -0,0 +1,324 @@
+//! Translations of the interactive flows' prompts and messages.
+//!
+//! Messages are looked up by their English text, gettext-style, so a missing
+//! translation shows the English. The locale comes from `locale` in the
+//! config file, else `LC_ALL`, `LC_MESSAGES`, or `LANG`.
+
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    Es,
+}
+
+impl Locale {
+    /// The locale for a tag like `es`, `es_MX.UTF-8`, or `C`; `None` for a
+    /// language without a catalog.
+    fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['_', '-', '.', '@']).next().unwrap_or("");
+        match language.to_ascii_lowercase().as_str() {
+            "en" | "c" | "posix" => Some(Locale::En),
+            "es" => Some(Locale::Es),
+            _ => None,
+        }
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => &[],
+            Locale::Es => ES,
+        }
+    }
+}
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+pub fn locale() -> Locale {
+    *LOCALE.get_or_init(|| {
+        let configured = serlib::config::settings().locale.clone();
+        let from_env = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty());
+        configured
+            .or(from_env)
+            .and_then(|tag| Locale::from_tag(&tag))
+            .unwrap_or(Locale::En)
+    })
+}
+
+/// `message` in the user's language.
+pub fn t(message: &'static str) -> &'static str {
+    lookup(locale(), message)
+}
+
+/// [`t`] with each `{}` replaced by the next of `args`.
+pub fn tf(message: &'static str, args: &[&dyn Display]) -> String {
+    fill(t(message), args)
+}
+
+fn lookup(locale: Locale, message: &'static str) -> &'static str {
+    locale
+        .catalog()
+        .iter()
+        .find(|(english, _)| *english == message)
+        .map_or(message, |(_, translated)| translated)
+}
+
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            filled.push_str(&arg.to_string());
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+const ES: &[(&str, &str)] = &[
+    ("What do you want to create?", "¿Qué quieres crear?"),
+    (
+        "Service (runs continuously, optionally on boot)",
+        "Servicio (se ejecuta continuamente, opcionalmente al arrancar)",
+    ),
+    (
+        "Timer (runs on a schedule)",
+        "Temporizador (se ejecuta según una programación)",
+    ),
+    (
+        "Creating service configuration...",
+        "Creando la configuración del servicio...",
+    ),
+    (
+        "Creating a new timer...",
+        "Creando un nuevo temporizador...",
+    ),
+    ("No command given", "No se indicó ningún comando"),
+    ("Command to execute", "Comando a ejecutar"),
+    ("Command cannot be empty", "El comando no puede estar vacío"),
+    (
+        "Service name (e.g., com.example.myservice)",
+        "Nombre del servicio (p. ej., com.example.myservice)",
+    ),
+    (
+        "Service name cannot be empty",
+        "El nombre del servicio no puede estar vacío",
+    ),
+    (
+        "Service name cannot contain spaces",
+        "El nombre del servicio no puede contener espacios",
+    ),
+    (
+        "Invalid service name '{}': it must be non-empty without spaces",
+        "Nombre de servicio no válido '{}': no puede estar vacío ni contener espacios",
+    ),
+    ("Working directory path", "Ruta del directorio de trabajo"),
+    (
+        "Environment file paths (comma-separated, -path if optional)",
+        "Rutas de archivos de entorno (separadas por comas, -ruta si es opcional)",
+    ),
+    (
+        "Environment variable key (or leave empty to finish)",
+        "Variable de entorno (o déjalo vacío para terminar)",
+    ),
+    (
+        "Format is 'KEY=VALUE'. Please try again.",
+        "El formato es 'CLAVE=VALOR'. Inténtalo de nuevo.",
+    ),
+    ("Networked service?", "¿Servicio de red?"),
+    (
+        "Start automatically when system boots?",
+        "¿Iniciar automáticamente al arrancar el sistema?",
+    ),
+    (
+        "Restart automatically if it crashes?",
+        "¿Reiniciar automáticamente si falla?",
+    ),
+    (
+        "A timer requires a schedule",
+        "Un temporizador requiere una programación",
+    ),
+    (
+        "Schedule configuration:",
+        "Configuración de la programación:",
+    ),
+    ("Schedule type", "Tipo de programación"),
+    ("Daily at specific time", "Diariamente a una hora concreta"),
+    ("Weekly on specific day", "Semanalmente un día concreto"),
+    ("Monthly on specific day", "Mensualmente un día concreto"),
+    (
+        "Every N minutes/hours (interval)",
+        "Cada N minutos/horas (intervalo)",
+    ),
+    ("Custom schedule", "Programación personalizada"),
+    (
+        "Leave fields empty for 'any' (like * in cron)",
+        "Deja los campos vacíos para 'cualquiera' (como * en cron)",
+    ),
+    ("Month (1-12)", "Mes (1-12)"),
+    ("Day of month (1-31)", "Día del mes (1-31)"),
+    ("Hour (0-23)", "Hora (0-23)"),
+    ("Minute (0-59)", "Minuto (0-59)"),
+    ("{} (or empty for any)", "{} (o vacío para cualquiera)"),
+    ("Invalid number", "Número no válido"),
+    (
+        "Value must be between {} and {}",
+        "El valor debe estar entre {} y {}",
+    ),
+    ("Interval unit", "Unidad del intervalo"),
+    ("Minutes", "Minutos"),
+    ("Hours", "Horas"),
+    ("Seconds", "Segundos"),
+    ("Run every", "Ejecutar cada"),
+    (
+        "Interval must be at least 1",
+        "El intervalo debe ser al menos 1",
+    ),
+    ("Hour must be 0-23", "La hora debe estar entre 0 y 23"),
+    ("Minute must be 0-59", "El minuto debe estar entre 0 y 59"),
+    ("Day must be 1-31", "El día debe estar entre 1 y 31"),
+    ("Day of week", "Día de la semana"),
+    ("Any day", "Cualquier día"),
+    ("Sunday", "Domingo"),
+    ("Monday", "Lunes"),
+    ("Tuesday", "Martes"),
+    ("Wednesday", "Miércoles"),
+    ("Thursday", "Jueves"),
+    ("Friday", "Viernes"),
+    ("Saturday", "Sábado"),
+    (
+        "'{}' does not exist here. Use {} instead?",
+        "'{}' no existe aquí. ¿Usar {} en su lugar?",
+    ),
+    ("Found {}", "Encontrado {}"),
+    ("Which service?", "¿Qué servicio?"),
+    ("Create this service?", "¿Crear este servicio?"),
+    (
+        "Timer '{}' created successfully (schedule: {}).",
+        "Temporizador '{}' creado correctamente (programación: {}).",
+    ),
+    ("Timer file: {}", "Archivo del temporizador: {}"),
+    ("Wrote {}", "Escrito {}"),
+    (
+        "Service '{}' created successfully.",
+        "Servicio '{}' creado correctamente.",
+    ),
+    ("Enable the timer now?", "¿Activar el temporizador ahora?"),
+    ("Start the service now?", "¿Iniciar el servicio ahora?"),
+    (
+        "Enabling timer '{}'...",
+        "Activando el temporizador '{}'...",
+    ),
+    ("Starting service '{}'...", "Iniciando el servicio '{}'..."),
+    (" done.", " hecho."),
+    ("Created '{}' running {}", "Creado '{}', que ejecuta {}"),
+    ("Started '{}'", "Iniciado '{}'"),
+    (
+        "No answer given (end of input)",
+        "No se dio ninguna respuesta (fin de la entrada)",
+    ),
+    ("Please answer y or n.", "Responde y (sí) o n (no)."),
+    ("Number", "Número"),
+    (
+        "Please enter a number from 1 to {}.",
+        "Escribe un número del 1 al {}.",
+    ),
+    ("Hardening:", "Endurecimiento:"),
+    (
+        "Invalid user name '{}': use lowercase letters, digits, _ and -",
+        "Nombre de usuario no válido '{}': usa minúsculas, dígitos, _ y -",
+    ),
+    (
+        "User '{}' already exists (home {}).",
+        "El usuario '{}' ya existe (directorio personal {}).",
+    ),
+    (
+        "Created system user '{}' (home {}).",
+        "Usuario del sistema '{}' creado (directorio personal {}).",
+    ),
+    (
+        "launchd has no capabilities to restrict. Since macOS 10.14 any user can bind ports below 1024, so nothing needs granting.",
+        "launchd no tiene capacidades que restringir. Desde macOS 10.14 cualquier usuario puede usar puertos por debajo de 1024, así que no hace falta conceder nada.",
+    ),
+    (
+        "Only root can bind ports below 1024, unless the service is granted CAP_NET_BIND_SERVICE (AmbientCapabilities=).",
+        "Solo root puede usar puertos por debajo de 1024, salvo que el servicio reciba CAP_NET_BIND_SERVICE (AmbientCapabilities=).",
+    ),
+    (
+        "Does it listen on a port below 1024?",
+        "¿Escucha en un puerto por debajo de 1024?",
+    ),
+    (
+        "A bounding set (CapabilityBoundingSet=) caps what it can ever gain, even as root.",
+        "Un conjunto límite (CapabilityBoundingSet=) acota lo que puede llegar a obtener, incluso como root.",
+    ),
+    (
+        "Drop every capability it wasn't granted?",
+        "¿Quitar todas las capacidades que no se le concedieron?",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_with_english_fallback() {
+        assert_eq!(Locale::from_tag("es_MX.UTF-8"), Some(Locale::Es));
+        assert_eq!(Locale::from_tag("C"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("fr_FR"), None);
+        assert_eq!(lookup(Locale::Es, "Schedule type"), "Tipo de programación");
+        assert_eq!(lookup(Locale::En, "Schedule type"), "Schedule type");
+        assert_eq!(
+            lookup(Locale::Es, "Not in the catalog"),
+            "Not in the catalog"
+        );
+        assert_eq!(
+            fill(
+                lookup(Locale::Es, "Value must be between {} and {}"),
+                &[&1, &12]
+            ),
+            "El valor debe estar entre 1 y 12"
+        );
+    }
+
+    /// Every message the interactive flows translate has a Spanish entry
+    /// with the same placeholders.
+    #[test]
+    fn spanish_catalog_is_complete() {
+        let sources = [
+            include_str!("interactive.rs"),
+            include_str!("command/new.rs"),
+            include_str!("command/add.rs"),
+            include_str!("command/timer.rs"),
+            include_str!("prompt.rs"),
+        ];
+        for source in sources {
+            for call in ["t(\"", "tf(\""] {
+                for (index, _) in source.match_indices(call) {
+                    let before = source[..index].chars().last().unwrap_or(' ');
+                    if before.is_alphanumeric() || before == '_' {
+                        continue;
+                    }
+                    let rest = &source[index + call.len()..];
+                    let message = &rest[..rest.find('"').unwrap()];
+                    let translated = ES
+                        .iter()
+                        .find(|(english, _)| *english == message)
+                        .map(|(_, es)| *es)
+                        .unwrap_or_else(|| panic!("no Spanish for {message:?}"));
+                    assert_eq!(
+                        message.matches("{}").count(),
+                        translated.matches("{}").count(),
+                        "{message:?}"
+                    );
+                }
+            }
+        }
+    }
+}
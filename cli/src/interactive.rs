@@ -1,6 +1,7 @@
+use crate::i18n::{t, tf};
+use crate::prompt::{Confirm, Input, Select};
 use anyhow::Context;
 use dialoguer::theme::ColorfulTheme;
-use dialoguer::{Confirm, Input, Select};
 use serlib::{CalendarSchedule, Schedule, ServiceDetails};
 use std::process::Command;
 
@@ -14,11 +15,11 @@ pub enum ServiceKind {
 /// Ask the user whether they want to create a service or a timer.
 pub fn prompt_service_kind(theme: &ColorfulTheme) -> anyhow::Result<ServiceKind> {
     let choices = vec![
-        "Service (runs continuously, optionally on boot)",
-        "Timer (runs on a schedule)",
+        t("Service (runs continuously, optionally on boot)"),
+        t("Timer (runs on a schedule)"),
     ];
     let selection = Select::with_theme(theme)
-        .with_prompt("What do you want to create?")
+        .with_prompt(t("What do you want to create?"))
         .items(&choices)
         .default(0)
         .interact()?;
@@ -29,26 +30,100 @@ pub fn prompt_service_kind(theme: &ColorfulTheme) -> anyhow::Result<ServiceKind>
     })
 }
 
+/// Answers to `collect_service_details` given as flags; whatever is set here
+/// isn't asked for.
+#[derive(Debug, Default, Clone, clap::Args)]
+pub struct ServiceFlags {
+    /// Service name [default: the program's file name]
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Directory to run the service in
+    #[arg(long, value_name = "DIR")]
+    pub working_directory: Option<String>,
+    /// Environment file to load, `-path` if optional (repeatable)
+    #[arg(long, value_name = "PATH")]
+    pub env_file: Vec<String>,
+    /// Environment variable to set (repeatable)
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_env_var)]
+    pub env: Vec<(String, String)>,
+    /// Whether it needs the network (start after network-online.target)
+    #[arg(long, value_name = "BOOL")]
+    pub networked: Option<bool>,
+    /// Whether it starts when the system boots
+    #[arg(long, value_name = "BOOL")]
+    pub run_at_load: Option<bool>,
+    /// Whether it's restarted when it crashes
+    #[arg(long, value_name = "BOOL")]
+    pub keep_alive: Option<bool>,
+    /// Make it a timer: an interval like `15m` or an OnCalendar expression
+    /// like `Mon *-*-* 09:00:00`
+    #[arg(long, value_parser = parse_schedule)]
+    pub schedule: Option<Schedule>,
+    /// Linux capability to grant without running as root, e.g.
+    /// `CAP_NET_BIND_SERVICE` to bind ports below 1024 (repeatable)
+    #[arg(long, value_name = "CAP")]
+    pub capability: Vec<String>,
+    /// Account to run the service as, instead of root or your user
+    #[arg(long, value_name = "NAME")]
+    pub user: Option<String>,
+    /// Create the --user account as a system user (no login shell, a home
+    /// directory it owns) if it doesn't exist
+    #[arg(long, requires = "user")]
+    pub create_user: bool,
+}
+
+fn parse_env_var(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{value}'"))?;
+    Ok((key.trim().to_string(), value.trim().to_string()))
+}
+
+fn parse_schedule(value: &str) -> Result<Schedule, String> {
+    Schedule::parse_interval_secs(value)
+        .map(Schedule::Interval)
+        .or_else(|| CalendarSchedule::from_systemd_oncalendar(value).map(Schedule::Calendar))
+        .ok_or_else(|| format!("'{value}' isn't an interval or an OnCalendar expression"))
+}
+
+/// The service kind the flags imply, or a prompt for it.
+pub fn service_kind(theme: &ColorfulTheme, flags: &ServiceFlags) -> anyhow::Result<ServiceKind> {
+    if flags.schedule.is_some() {
+        Ok(ServiceKind::Timer)
+    } else {
+        prompt_service_kind(theme)
+    }
+}
+
+/// Build service details from `command` and `flags`, prompting for anything
+/// they leave out. Without `prompt`, those fields take their defaults instead.
 pub fn collect_service_details(
     theme: &ColorfulTheme,
     mut command: Vec<String>,
     validate: bool,
     kind: ServiceKind,
+    flags: &ServiceFlags,
+    prompt: bool,
 ) -> anyhow::Result<ServiceDetails> {
-    println!("Creating service configuration...\n");
+    if prompt {
+        println!("{}\n", t("Creating service configuration..."));
+    }
 
     if command.is_empty() {
+        if !prompt {
+            anyhow::bail!(t("No command given"));
+        }
         let c = Input::with_theme(theme)
-            .with_prompt("Command to execute")
+            .with_prompt(t("Command to execute"))
             .validate_with(|input: &String| -> anyhow::Result<(), &str> {
                 if input.trim().is_empty() {
-                    Err("Command cannot be empty")
+                    Err(t("Command cannot be empty"))
                 } else {
                     Ok(())
                 }
             })
             .interact_text()?;
-        command = c.split_whitespace().map(String::from).collect();
+        command = serlib::systemd::split_words(&c);
     }
 
     let program = command.remove(0);
@@ -63,23 +138,35 @@ pub fn collect_service_details(
 
     let default_basename = bin_path.rsplit('/').next().unwrap().to_string();
     // Service name
-    let name: String = Input::with_theme(theme)
-        .with_prompt("Service name (e.g., com.example.myservice)")
-        .default(default_basename)
-        .validate_with(|input: &String| -> anyhow::Result<(), &str> {
-            if input.trim().is_empty() {
-                Err("Service name cannot be empty")
-            } else if input.contains(' ') {
-                Err("Service name cannot contain spaces")
-            } else {
-                Ok(())
-            }
-        })
-        .interact_text()?;
+    let name: String = match &flags.name {
+        Some(name) => name.clone(),
+        None if !prompt => default_basename,
+        None => Input::with_theme(theme)
+            .with_prompt(t("Service name (e.g., com.example.myservice)"))
+            .default(default_basename)
+            .validate_with(|input: &String| -> anyhow::Result<(), &str> {
+                if input.trim().is_empty() {
+                    Err(t("Service name cannot be empty"))
+                } else if input.contains(' ') {
+                    Err(t("Service name cannot contain spaces"))
+                } else {
+                    Ok(())
+                }
+            })
+            .interact_text()?,
+    };
+    if name.trim().is_empty() || name.contains(' ') {
+        anyhow::bail!(tf(
+            "Invalid service name '{}': it must be non-empty without spaces",
+            &[&name]
+        ));
+    }
 
-    let working_directory = {
+    let working_directory = if flags.working_directory.is_some() || !prompt {
+        flags.working_directory.clone()
+    } else {
         let input: String = Input::with_theme(theme)
-            .with_prompt("Working directory path")
+            .with_prompt(t("Working directory path"))
             .allow_empty(true)
             .interact_text()?;
         if input.trim().is_empty() {
@@ -89,30 +176,40 @@ pub fn collect_service_details(
         }
     };
 
-    let env_file = {
+    let env_files = if !flags.env_file.is_empty() || !prompt {
+        flags
+            .env_file
+            .iter()
+            .map(|f| serlib::EnvFile::parse(f))
+            .collect()
+    } else {
         let input: String = Input::with_theme(theme)
-            .with_prompt("Environment file path")
+            .with_prompt(t(
+                "Environment file paths (comma-separated, -path if optional)",
+            ))
             .allow_empty(true)
             .interact_text()?;
-        if input.trim().is_empty() {
-            None
-        } else {
-            Some(input.trim().to_string())
-        }
+        input
+            .split(',')
+            .filter(|f| !f.trim().is_empty())
+            .map(serlib::EnvFile::parse)
+            .collect()
     };
 
-    let env_vars = {
+    let env_vars = if !flags.env.is_empty() || !prompt {
+        flags.env.clone()
+    } else {
         let mut vars = Vec::new();
         loop {
             let kv: String = Input::with_theme(theme)
-                .with_prompt("Environment variable key (or leave empty to finish)")
+                .with_prompt(t("Environment variable key (or leave empty to finish)"))
                 .allow_empty(true)
                 .interact_text()?;
             if kv.trim().is_empty() {
                 break;
             }
             let Some((k, v)) = kv.split_once('=') else {
-                eprintln!("Format is 'KEY=VALUE'. Please try again.");
+                eprintln!("{}", t("Format is 'KEY=VALUE'. Please try again."));
                 continue;
             };
             let key = k.trim().to_string();
@@ -121,30 +218,50 @@ pub fn collect_service_details(
         }
         vars
     };
-    let after = collect_after(theme)?;
+    let networked = answer(theme, flags.networked, prompt, t("Networked service?"))?;
+    let after = if networked {
+        vec![
+            "network.target".to_string(),
+            "network-online.target".to_string(),
+        ]
+    } else {
+        Vec::new()
+    };
 
     // Service-only options (run at load / keep alive) vs. timer-only (schedule).
     // Scheduled units deliberately don't use RunAtLoad/KeepAlive, so we only ask
     // the questions that apply to the chosen kind.
     let (run_at_load, keep_alive, schedule) = match kind {
         ServiceKind::Service => {
-            let run_at_load = Confirm::with_theme(theme)
-                .with_prompt("Start automatically when system boots?")
-                .default(true)
-                .interact()?;
-            let keep_alive = Confirm::with_theme(theme)
-                .with_prompt("Restart automatically if it crashes?")
-                .default(true)
-                .interact()?;
+            let run_at_load = answer(
+                theme,
+                flags.run_at_load,
+                prompt,
+                t("Start automatically when system boots?"),
+            )?;
+            let keep_alive = answer(
+                theme,
+                flags.keep_alive,
+                prompt,
+                t("Restart automatically if it crashes?"),
+            )?;
             (run_at_load, keep_alive, None)
         }
         ServiceKind::Timer => {
-            let schedule = collect_schedule(theme)?
-                .ok_or_else(|| anyhow::anyhow!("A timer requires a schedule"))?;
+            let schedule = match &flags.schedule {
+                Some(schedule) => Some(schedule.clone()),
+                None if !prompt => None,
+                None => collect_schedule(theme)?,
+            };
+            let schedule =
+                schedule.ok_or_else(|| anyhow::anyhow!(t("A timer requires a schedule")))?;
             (false, false, Some(schedule))
         }
     };
 
+    let ambient_capabilities = serlib::parse_capabilities(&flags.capability.join(" "))?;
+    check_user_name(flags)?;
+
     Ok(ServiceDetails {
         name,
         program: bin_path,
@@ -152,41 +269,150 @@ pub fn collect_service_details(
         working_directory,
         run_at_load,
         keep_alive,
-        env_file,
+        env_files,
         env_vars,
         after,
         schedule,
+        ambient_capabilities,
+        user: flags.user.clone(),
+        ..Default::default()
     })
 }
 
-fn collect_after(theme: &ColorfulTheme) -> anyhow::Result<Vec<String>> {
-    let networked = Confirm::with_theme(theme)
-        .with_prompt("Networked service?")
+/// Refuse a `--user` that isn't a plain account name.
+pub fn check_user_name(flags: &ServiceFlags) -> anyhow::Result<()> {
+    if let Some(user) = flags
+        .user
+        .as_deref()
+        .filter(|u| !serlib::is_valid_user_name(u))
+    {
+        anyhow::bail!(tf(
+            "Invalid user name '{}': use lowercase letters, digits, _ and -",
+            &[&user]
+        ));
+    }
+    Ok(())
+}
+
+/// `--create-user`: make the account the service runs as before the service
+/// is created, so the first start doesn't fail on an unknown user. The
+/// service starts in the account's home unless it has a working directory.
+pub fn create_run_as_user(
+    flags: &ServiceFlags,
+    details: &mut ServiceDetails,
+) -> anyhow::Result<()> {
+    let Some(user) = flags.user.as_deref().filter(|_| flags.create_user) else {
+        return Ok(());
+    };
+    let existed = serlib::platform::user_exists(user);
+    let home = serlib::platform::create_system_user(user)?;
+    if details.working_directory.is_none() && home.is_dir() {
+        details.working_directory = Some(home.to_string_lossy().into_owned());
+    }
+    if existed {
+        println!(
+            "{}",
+            tf(
+                "User '{}' already exists (home {}).",
+                &[&user, &home.display()]
+            )
+        );
+    } else {
+        println!(
+            "{}",
+            tf(
+                "Created system user '{}' (home {}).",
+                &[&user, &home.display()]
+            )
+        );
+    }
+    Ok(())
+}
+
+const NET_BIND_SERVICE: &str = "CAP_NET_BIND_SERVICE";
+
+/// `ser new --hardened`: explain Linux capabilities and ask which the service
+/// needs, so one that binds a low port doesn't have to run as root, and drop
+/// the rest.
+pub fn collect_hardening(
+    theme: &ColorfulTheme,
+    details: &mut ServiceDetails,
+) -> anyhow::Result<()> {
+    println!("\n{}", t("Hardening:"));
+    if cfg!(target_os = "macos") {
+        println!(
+            "{}",
+            t("launchd has no capabilities to restrict. Since macOS 10.14 any user can bind ports below 1024, so nothing needs granting.")
+        );
+        return Ok(());
+    }
+    println!(
+        "{}",
+        t("Only root can bind ports below 1024, unless the service is granted CAP_NET_BIND_SERVICE (AmbientCapabilities=).")
+    );
+    let binds_low_port = details
+        .ambient_capabilities
+        .iter()
+        .any(|c| c == NET_BIND_SERVICE)
+        || Confirm::with_theme(theme)
+            .with_prompt(t("Does it listen on a port below 1024?"))
+            .default(false)
+            .interact()?;
+    if binds_low_port
+        && !details
+            .ambient_capabilities
+            .iter()
+            .any(|c| c == NET_BIND_SERVICE)
+    {
+        details
+            .ambient_capabilities
+            .push(NET_BIND_SERVICE.to_string());
+    }
+    println!(
+        "{}",
+        t("A bounding set (CapabilityBoundingSet=) caps what it can ever gain, even as root.")
+    );
+    let restrict = Confirm::with_theme(theme)
+        .with_prompt(t("Drop every capability it wasn't granted?"))
         .default(true)
         .interact()?;
-    Ok(if networked {
-        vec![
-            "network.target".to_string(),
-            "network-online.target".to_string(),
-        ]
-    } else {
-        Vec::new()
-    })
+    if restrict {
+        details.capability_bounding_set = Some(details.ambient_capabilities.clone());
+    }
+    Ok(())
+}
+
+/// A yes/no answer: the flag if given, else a prompt defaulting to yes (or
+/// just yes, without `prompt`).
+fn answer(
+    theme: &ColorfulTheme,
+    flag: Option<bool>,
+    prompt: bool,
+    question: &str,
+) -> anyhow::Result<bool> {
+    match flag {
+        Some(value) => Ok(value),
+        None if !prompt => Ok(true),
+        None => Ok(Confirm::with_theme(theme)
+            .with_prompt(question)
+            .default(true)
+            .interact()?),
+    }
 }
 
 pub fn collect_schedule(theme: &ColorfulTheme) -> anyhow::Result<Option<Schedule>> {
-    println!("\nSchedule configuration:");
+    println!("\n{}", t("Schedule configuration:"));
 
     let choices = vec![
-        "Daily at specific time",
-        "Weekly on specific day",
-        "Monthly on specific day",
-        "Every N minutes/hours (interval)",
-        "Custom schedule",
+        t("Daily at specific time"),
+        t("Weekly on specific day"),
+        t("Monthly on specific day"),
+        t("Every N minutes/hours (interval)"),
+        t("Custom schedule"),
     ];
 
     let selection = Select::with_theme(theme)
-        .with_prompt("Schedule type")
+        .with_prompt(t("Schedule type"))
         .items(&choices)
         .default(0)
         .interact()?;
@@ -236,12 +462,12 @@ pub fn collect_schedule(theme: &ColorfulTheme) -> anyhow::Result<Option<Schedule
         }
         4 => {
             // Custom
-            println!("Leave fields empty for 'any' (like * in cron)\n");
-            let month = collect_optional_number(theme, "Month (1-12)", 1, 12)?;
-            let day = collect_optional_number(theme, "Day of month (1-31)", 1, 31)?;
+            println!("{}\n", t("Leave fields empty for 'any' (like * in cron)"));
+            let month = collect_optional_number(theme, t("Month (1-12)"), 1, 12)?;
+            let day = collect_optional_number(theme, t("Day of month (1-31)"), 1, 31)?;
             let weekday = collect_optional_weekday(theme)?;
-            let hour = collect_optional_number(theme, "Hour (0-23)", 0, 23)?;
-            let minute = collect_optional_number(theme, "Minute (0-59)", 0, 59)?;
+            let hour = collect_optional_number(theme, t("Hour (0-23)"), 0, 23)?;
+            let minute = collect_optional_number(theme, t("Minute (0-59)"), 0, 59)?;
             Schedule::Calendar(CalendarSchedule {
                 month,
                 day,
@@ -256,9 +482,9 @@ pub fn collect_schedule(theme: &ColorfulTheme) -> anyhow::Result<Option<Schedule
 }
 
 fn collect_interval_secs(theme: &ColorfulTheme) -> anyhow::Result<u64> {
-    let units = vec!["Minutes", "Hours", "Seconds"];
+    let units = vec![t("Minutes"), t("Hours"), t("Seconds")];
     let unit = Select::with_theme(theme)
-        .with_prompt("Interval unit")
+        .with_prompt(t("Interval unit"))
         .items(&units)
         .default(0)
         .interact()?;
@@ -268,10 +494,10 @@ fn collect_interval_secs(theme: &ColorfulTheme) -> anyhow::Result<u64> {
         _ => 1,
     };
     let amount: u64 = Input::with_theme(theme)
-        .with_prompt("Run every")
+        .with_prompt(t("Run every"))
         .validate_with(|input: &u64| {
             if *input == 0 {
-                Err("Interval must be at least 1")
+                Err(t("Interval must be at least 1"))
             } else {
                 Ok(())
             }
@@ -282,11 +508,11 @@ fn collect_interval_secs(theme: &ColorfulTheme) -> anyhow::Result<u64> {
 
 fn collect_hour(theme: &ColorfulTheme) -> anyhow::Result<u8> {
     let hour: u8 = Input::with_theme(theme)
-        .with_prompt("Hour (0-23)")
+        .with_prompt(t("Hour (0-23)"))
         .default(0)
         .validate_with(|input: &u8| {
             if *input > 23 {
-                Err("Hour must be 0-23")
+                Err(t("Hour must be 0-23"))
             } else {
                 Ok(())
             }
@@ -297,11 +523,11 @@ fn collect_hour(theme: &ColorfulTheme) -> anyhow::Result<u8> {
 
 fn collect_minute(theme: &ColorfulTheme) -> anyhow::Result<u8> {
     let minute: u8 = Input::with_theme(theme)
-        .with_prompt("Minute (0-59)")
+        .with_prompt(t("Minute (0-59)"))
         .default(0)
         .validate_with(|input: &u8| {
             if *input > 59 {
-                Err("Minute must be 0-59")
+                Err(t("Minute must be 0-59"))
             } else {
                 Ok(())
             }
@@ -312,16 +538,16 @@ fn collect_minute(theme: &ColorfulTheme) -> anyhow::Result<u8> {
 
 fn collect_weekday(theme: &ColorfulTheme) -> anyhow::Result<u8> {
     let days = vec![
-        "Sunday",
-        "Monday",
-        "Tuesday",
-        "Wednesday",
-        "Thursday",
-        "Friday",
-        "Saturday",
+        t("Sunday"),
+        t("Monday"),
+        t("Tuesday"),
+        t("Wednesday"),
+        t("Thursday"),
+        t("Friday"),
+        t("Saturday"),
     ];
     let selection = Select::with_theme(theme)
-        .with_prompt("Day of week")
+        .with_prompt(t("Day of week"))
         .items(&days)
         .default(1) // Monday
         .interact()?;
@@ -330,11 +556,11 @@ fn collect_weekday(theme: &ColorfulTheme) -> anyhow::Result<u8> {
 
 fn collect_day_of_month(theme: &ColorfulTheme) -> anyhow::Result<u8> {
     let day: u8 = Input::with_theme(theme)
-        .with_prompt("Day of month (1-31)")
+        .with_prompt(t("Day of month (1-31)"))
         .default(1u8)
         .validate_with(|input: &u8| {
             if *input < 1 || *input > 31 {
-                Err("Day must be 1-31")
+                Err(t("Day must be 1-31"))
             } else {
                 Ok(())
             }
@@ -350,7 +576,7 @@ fn collect_optional_number(
     max: u8,
 ) -> anyhow::Result<Option<u8>> {
     let input: String = Input::with_theme(theme)
-        .with_prompt(format!("{} (or empty for any)", prompt))
+        .with_prompt(tf("{} (or empty for any)", &[&prompt]))
         .allow_empty(true)
         .interact_text()?;
 
@@ -360,9 +586,9 @@ fn collect_optional_number(
         let value: u8 = input
             .trim()
             .parse()
-            .map_err(|_| anyhow::anyhow!("Invalid number"))?;
+            .map_err(|_| anyhow::anyhow!(t("Invalid number")))?;
         if value < min || value > max {
-            anyhow::bail!("Value must be between {} and {}", min, max);
+            anyhow::bail!(tf("Value must be between {} and {}", &[&min, &max]));
         }
         Ok(Some(value))
     }
@@ -370,17 +596,17 @@ fn collect_optional_number(
 
 fn collect_optional_weekday(theme: &ColorfulTheme) -> anyhow::Result<Option<u8>> {
     let days = vec![
-        "Any day",
-        "Sunday",
-        "Monday",
-        "Tuesday",
-        "Wednesday",
-        "Thursday",
-        "Friday",
-        "Saturday",
+        t("Any day"),
+        t("Sunday"),
+        t("Monday"),
+        t("Tuesday"),
+        t("Wednesday"),
+        t("Thursday"),
+        t("Friday"),
+        t("Saturday"),
     ];
     let selection = Select::with_theme(theme)
-        .with_prompt("Day of week")
+        .with_prompt(t("Day of week"))
         .items(&days)
         .default(0)
         .interact()?;
@@ -392,13 +618,13 @@ fn collect_optional_weekday(theme: &ColorfulTheme) -> anyhow::Result<Option<u8>>
     }
 }
 
-fn resolve_binary_path(binary: &str) -> anyhow::Result<String> {
+pub fn resolve_binary_path(binary: &str) -> anyhow::Result<String> {
     // If it's already an absolute path, validate it exists and return as-is
     if binary.starts_with('/') {
         return if std::path::Path::new(binary).exists() {
             Ok(binary.to_string())
         } else {
-            Err(anyhow::anyhow!("Binary '{}' does not exist", binary))
+            offer_relocation(binary)
         };
     }
 
@@ -443,6 +669,31 @@ fn resolve_binary_path(binary: &str) -> anyhow::Result<String> {
     Err(anyhow::anyhow!("Binary '{}' not found in PATH", binary))
 }
 
+/// An absolute program path that doesn't exist here, typically from a file
+/// written on another machine: offer the same program found elsewhere
+/// (accepted automatically when there's no terminal to ask on).
+fn offer_relocation(binary: &str) -> anyhow::Result<String> {
+    let Some(found) = serlib::migrate::relocate_program(binary) else {
+        anyhow::bail!("Binary '{}' does not exist", binary);
+    };
+    let found = found.to_string_lossy().into_owned();
+    if atty::isnt(atty::Stream::Stdin) {
+        eprintln!("'{binary}' does not exist; using {found}");
+        return Ok(found);
+    }
+    let accept = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(tf(
+            "'{}' does not exist here. Use {} instead?",
+            &[&binary, &found],
+        ))
+        .default(true)
+        .interact()?;
+    if !accept {
+        anyhow::bail!("Binary '{}' does not exist", binary);
+    }
+    Ok(found)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -472,4 +723,36 @@ mod tests {
             .to_string()
             .contains("not found in PATH"));
     }
+
+    #[test]
+    fn collects_from_flags_without_prompting() {
+        let flags = ServiceFlags {
+            env: vec![("PORT".into(), "80".into())],
+            keep_alive: Some(false),
+            schedule: Some(parse_schedule("15m").unwrap()),
+            ..Default::default()
+        };
+        let command = vec!["nginx".into(), "-g".into(), "daemon off;".into()];
+        let theme = ColorfulTheme::default();
+        let service = collect_service_details(
+            &theme,
+            command.clone(),
+            false,
+            ServiceKind::Service,
+            &flags,
+            false,
+        )
+        .unwrap();
+        assert_eq!(service.name, "nginx");
+        assert_eq!(service.arguments, ["-g", "daemon off;"]);
+        assert_eq!(service.env_vars, [("PORT".to_string(), "80".to_string())]);
+        assert!(service.run_at_load && !service.keep_alive);
+        assert_eq!(service.after.len(), 2);
+
+        let timer =
+            collect_service_details(&theme, command, false, ServiceKind::Timer, &flags, false)
+                .unwrap();
+        assert!(matches!(timer.schedule, Some(Schedule::Interval(900))));
+        assert!(parse_schedule("whenever").is_err());
+    }
 }
@@ -1,10 +1,13 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 
 mod command;
+mod i18n;
 mod interactive;
+mod progress;
+mod prompt;
 
-const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), " (", env!("GIT_HASH"), ")");
+pub const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), " (", env!("GIT_HASH"), ")");
 
 #[derive(Parser)]
 #[command(name = "ser")]
@@ -15,6 +18,43 @@ struct Cli {
     #[arg(short = 'v', long = "verbose", global = true)]
     verbose: bool,
 
+    /// Don't show progress for multi-service operations
+    #[arg(short = 'q', long = "quiet", global = true)]
+    quiet: bool,
+
+    /// Fail instead of waiting when another ser process is working on the same service
+    #[arg(long, global = true)]
+    no_wait: bool,
+
+    /// Work from files only: never run systemctl/launchctl or read runtime
+    /// state, and with --dir, only look in those directories
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Afterwards, list the commands ser ran and the files it changed (also
+    /// kept for `ser explain last`)
+    #[arg(long, global = true)]
+    explain: bool,
+
+    /// Ask questions as plain lines on stdin instead of interactive widgets
+    /// (for screen readers; the default when TERM=dumb)
+    #[arg(long, global = true)]
+    plain_prompts: bool,
+
+    /// Let start, stop, rm, and edit act on Apple system services in
+    /// /System/Library (macOS), which are read-only by default
+    #[arg(long, global = true)]
+    allow_apple: bool,
+
+    /// Extra directory to scan for services (repeatable)
+    #[arg(long = "dir", global = true, value_name = "DIR")]
+    dirs: Vec<std::path::PathBuf>,
+
+    /// Work on this machine's services, or on systemd units or launchd
+    /// plists in the first --dir (e.g. to write units for Linux on a Mac)
+    #[arg(long, global = true, value_enum, default_value = "native")]
+    backend: command::Backend,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -22,9 +62,12 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     #[command(about = "List background services")]
-    #[command(alias = "status")]
     #[command(alias = "ls")]
     List(command::List),
+    #[command(about = "Summarize whether services are up (--exit-code for scripts)")]
+    Status(command::Status),
+    #[command(about = "List services grouped by scope, source, target, or tag")]
+    Tree(command::Tree),
     #[command(about = "Show detailed information about a service")]
     Show(command::Show),
     #[command(about = "Start a service")]
@@ -33,39 +76,214 @@ enum Commands {
     Stop(command::Stop),
     #[command(about = "Restart a service")]
     Restart(command::Restart),
-    #[command(about = "Enable a service or timer (start it, and arm a timer's schedule)")]
+    #[command(about = "Start a service or timer at boot, without starting it now")]
     Enable(command::Enable),
-    #[command(about = "Disable a service or timer (stop it, and disarm a timer's schedule)")]
+    #[command(about = "Stop a service or timer from starting at boot, without stopping it now")]
     Disable(command::Disable),
+    #[command(
+        about = "Reset whether a service starts at boot to its vendor preset (systemd only)"
+    )]
+    Preset(command::Preset),
+    #[command(about = "Stop and disable a service, then delete its file")]
+    #[command(alias = "delete", alias = "rm")]
+    Remove(command::Remove),
+    #[command(about = "Create and start a service from a command, with no prompts")]
+    Add(command::Add),
     #[command(about = "Create a new service interactively")]
     #[command(alias = "create")]
     New(command::New),
+    #[command(about = "Install a service from a .toml definition, unit file, or plist")]
+    Install(command::Install),
+    #[command(about = "Install and start the services in this project's .ser.toml")]
+    Up(command::Up),
+    #[command(
+        about = "Deploy a new binary blue/green: a temporary copy serves while the service restarts"
+    )]
+    Deploy(command::Deploy),
     #[command(about = "Generate service file content to stdout")]
     Generate(command::Generate),
+    #[command(about = "Rewrite unit files/plists in canonical form (--check for CI)")]
+    Fmt(command::Fmt),
+    #[command(
+        about = "Compare two service definitions field by field, or one service on two hosts"
+    )]
+    Diff(command::Diff),
+    #[command(about = "Find problems in unit files/plists (--fix to correct the safe ones)")]
+    Lint(command::Lint),
+    #[command(
+        about = "Validate the definitions in a directory without touching the system (for CI)"
+    )]
+    Check(command::Check),
+    #[command(about = "Search services by name, description, command, or environment")]
+    Search(command::Search),
+    #[command(about = "Find the service that owns a PID, port, or path")]
+    Which(command::Which),
+    #[command(about = "Print a service's file path, or its working directory with --workdir")]
+    Path(command::Path),
+    #[command(about = "Pin a service to the top of `ser list` (--remove to unpin)")]
+    Pin(command::Pin),
+    #[command(about = "Run systemctl/launchctl with extra arguments on a service's resolved name")]
+    Raw(command::Raw),
+    #[command(about = "Print a service's environment (--export for eval in a shell)")]
+    Env(command::Env),
+    #[command(about = "Show the commands and file changes of the last ser invocation")]
+    Explain(command::Explain),
+    #[command(about = "Change settings of a service (e.g. oom_score=500)")]
+    Set(command::Set),
     #[command(about = "Edit a service file")]
     Edit(command::Edit),
     #[command(about = "Show logs for a service")]
     Logs(command::Logs),
     #[command(about = "Manage scheduled timers")]
+    #[command(alias = "schedule")]
     Timer(command::Timer),
+    #[command(
+        about = "List scheduled jobs with their last run, result, and next run (same as `ser timer list`)"
+    )]
+    Timers(command::timer::List),
+    #[command(about = "Show availability and downtime incidents for a service")]
+    Uptime(command::Uptime),
+    #[command(about = "Show CPU and memory history for a service")]
+    Stats(command::Stats),
+    #[command(
+        about = "Record the state and usage of managed services (--install to do it every minute)"
+    )]
+    Sample(command::Sample),
+    #[command(about = "Send alerts through the configured webhook or email")]
+    Notify(command::Notify),
+    #[command(about = "Archive all managed services, their env files, and state")]
+    Backup(command::Backup),
+    #[command(about = "Restore services from a `ser backup` archive")]
+    Restore(command::Restore),
+    #[command(about = "Move services to another machine, converting them for its init system")]
+    Migrate(command::Migrate),
+    #[command(about = "Show what ser detected about this host")]
+    Info(command::Info),
+    #[command(about = "Check for common configuration problems")]
+    Doctor(command::Doctor),
+    #[command(
+        about = "Flag services enabled against their vendor preset or networked without a sandbox"
+    )]
+    Audit(command::Audit),
+    #[command(about = "Generate a man page or markdown reference")]
+    Docs(command::Docs),
+    #[command(about = "Print shell completions, or `install` them for your shell")]
+    Completions(command::Completions),
+    #[command(name = "__complete-names", hide = true)]
+    CompleteNames(command::CompleteNames),
+    #[command(about = "Exercise create/start/logs/stop/remove with a scratch service")]
+    Selftest(command::Selftest),
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(subcommand) = matches.subcommand_name() {
+        serlib::plist::set_command(subcommand);
+    }
+    serlib::interrupt::install_handlers();
     serlib::set_verbose(cli.verbose);
-    match cli.command {
+    progress::set_quiet(cli.quiet);
+    serlib::config::init()?;
+    if !matches!(cli.backend, command::Backend::Native) && !cli.command.honors_backend() {
+        anyhow::bail!(
+            "--backend only applies to list, show, logs, start, stop, restart, enable, disable, \
+             remove, add, new, and set"
+        );
+    }
+    command::set_backend(cli.backend, &cli.dirs)?;
+    serlib::config::set_extra_dirs(cli.dirs);
+    serlib::platform::set_offline(cli.offline);
+    serlib::platform::set_allow_protected(cli.allow_apple);
+    command::set_no_wait(cli.no_wait);
+    prompt::set_plain(cli.plain_prompts);
+    let explaining = !matches!(cli.command, Commands::Explain(_));
+    let result = run(cli.command);
+    if explaining {
+        command::explain::finish(cli.explain);
+    }
+    // The failure is only the stopped child's; the interrupt is the news.
+    if result.is_err() && serlib::interrupt::interrupted() {
+        eprintln!("Interrupted.");
+        std::process::exit(130);
+    }
+    if let Some(command::ExitStatus(code)) = result.as_ref().err().and_then(|e| e.downcast_ref()) {
+        std::process::exit(*code);
+    }
+    result
+}
+
+impl Commands {
+    /// Whether the command works through [`command::manager`], so it can
+    /// act on another backend's files instead of this machine.
+    fn honors_backend(&self) -> bool {
+        matches!(
+            self,
+            Commands::List(_)
+                | Commands::Show(_)
+                | Commands::Logs(_)
+                | Commands::Start(_)
+                | Commands::Stop(_)
+                | Commands::Restart(_)
+                | Commands::Enable(_)
+                | Commands::Disable(_)
+                | Commands::Remove(_)
+                | Commands::Add(_)
+                | Commands::New(_)
+                | Commands::Set(_)
+        )
+    }
+}
+
+fn run(command: Commands) -> Result<()> {
+    match command {
         Commands::List(list_cmd) => list_cmd.run()?,
+        Commands::Status(status_cmd) => status_cmd.run()?,
+        Commands::Tree(tree_cmd) => tree_cmd.run()?,
         Commands::Show(show_cmd) => show_cmd.run()?,
         Commands::Start(start_cmd) => start_cmd.run()?,
         Commands::Stop(stop_cmd) => stop_cmd.run()?,
         Commands::Restart(restart_cmd) => restart_cmd.run()?,
         Commands::Enable(enable_cmd) => enable_cmd.run()?,
         Commands::Disable(disable_cmd) => disable_cmd.run()?,
+        Commands::Preset(preset_cmd) => preset_cmd.run()?,
+        Commands::Remove(remove_cmd) => remove_cmd.run()?,
+        Commands::Add(add_cmd) => add_cmd.run()?,
         Commands::New(new_cmd) => new_cmd.run()?,
+        Commands::Install(install_cmd) => install_cmd.run()?,
+        Commands::Up(up_cmd) => up_cmd.run()?,
+        Commands::Deploy(deploy_cmd) => deploy_cmd.run()?,
         Commands::Generate(generate_cmd) => generate_cmd.run()?,
+        Commands::Fmt(fmt_cmd) => fmt_cmd.run()?,
+        Commands::Diff(diff_cmd) => diff_cmd.run()?,
+        Commands::Lint(lint_cmd) => lint_cmd.run()?,
+        Commands::Check(check_cmd) => check_cmd.run()?,
+        Commands::Search(search_cmd) => search_cmd.run()?,
+        Commands::Which(which_cmd) => which_cmd.run()?,
+        Commands::Path(path_cmd) => path_cmd.run()?,
+        Commands::Pin(pin_cmd) => pin_cmd.run()?,
+        Commands::Raw(raw_cmd) => raw_cmd.run()?,
+        Commands::Env(env_cmd) => env_cmd.run()?,
+        Commands::Explain(explain_cmd) => explain_cmd.run()?,
+        Commands::Set(set_cmd) => set_cmd.run()?,
         Commands::Edit(edit_cmd) => edit_cmd.run()?,
         Commands::Logs(logs_cmd) => logs_cmd.run()?,
         Commands::Timer(timer_cmd) => timer_cmd.run()?,
+        Commands::Timers(timers_cmd) => timers_cmd.run()?,
+        Commands::Uptime(uptime_cmd) => uptime_cmd.run()?,
+        Commands::Stats(stats_cmd) => stats_cmd.run()?,
+        Commands::Sample(sample_cmd) => sample_cmd.run()?,
+        Commands::Notify(notify_cmd) => notify_cmd.run()?,
+        Commands::Backup(backup_cmd) => backup_cmd.run()?,
+        Commands::Restore(restore_cmd) => restore_cmd.run()?,
+        Commands::Migrate(migrate_cmd) => migrate_cmd.run()?,
+        Commands::Info(info_cmd) => info_cmd.run()?,
+        Commands::Doctor(doctor_cmd) => doctor_cmd.run()?,
+        Commands::Audit(audit_cmd) => audit_cmd.run()?,
+        Commands::Selftest(selftest_cmd) => selftest_cmd.run()?,
+        Commands::Docs(docs_cmd) => docs_cmd.run()?,
+        Commands::Completions(completions_cmd) => completions_cmd.run()?,
+        Commands::CompleteNames(names_cmd) => names_cmd.run()?,
     }
     Ok(())
 }
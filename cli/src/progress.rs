@@ -0,0 +1,102 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tabled::{
+    settings::{Padding, Style},
+    Table, Tabled,
+};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppress progress output (the final summary is still printed).
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::SeqCst);
+}
+
+const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Per-service progress for operations over several services, followed by a
+/// summary table. Animates a spinner on a terminal; on a pipe, with `--quiet`,
+/// or with `--verbose` (whose command echo would garble it) it prints plain
+/// lines or nothing.
+pub struct Progress {
+    total: usize,
+    done: usize,
+    rows: Vec<Row>,
+}
+
+#[derive(Tabled)]
+struct Row {
+    #[tabled(rename = "Service")]
+    name: String,
+    #[tabled(rename = "Result")]
+    result: String,
+    #[tabled(skip)]
+    failed: bool,
+}
+
+impl Progress {
+    pub fn new(total: usize) -> Progress {
+        Progress {
+            total,
+            done: 0,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Run `f` for `name`, showing `action` while it runs, and record how it went.
+    /// `f` returns a short description of what it did on success.
+    pub fn step(&mut self, name: &str, action: &str, f: impl FnOnce() -> anyhow::Result<String>) {
+        self.done += 1;
+        let label = format!("[{}/{}] {}: {}", self.done, self.total, name, action);
+        let quiet = QUIET.load(Ordering::SeqCst);
+        let animate = !quiet && atty::is(atty::Stream::Stderr) && !serlib::is_verbose();
+
+        let result = if animate {
+            let finished = AtomicBool::new(false);
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    let mut frame = 0;
+                    while !finished.load(Ordering::SeqCst) {
+                        eprint!("\r{} {}", SPINNER[frame % SPINNER.len()], label);
+                        let _ = std::io::stderr().flush();
+                        frame += 1;
+                        std::thread::sleep(Duration::from_millis(100));
+                    }
+                });
+                let result = f();
+                finished.store(true, Ordering::SeqCst);
+                result
+            })
+        } else {
+            if !quiet {
+                eprintln!("{label}");
+            }
+            f()
+        };
+
+        let (mark, text) = match &result {
+            Ok(text) => ('✓', text.clone()),
+            Err(e) => ('✗', format!("failed: {e:#}")),
+        };
+        if animate {
+            // Clear the spinner line before writing the final one.
+            eprintln!("\r\x1b[2K{} [{}/{}] {}", mark, self.done, self.total, name);
+        }
+        self.rows.push(Row {
+            name: name.to_string(),
+            result: text,
+            failed: result.is_err(),
+        });
+    }
+
+    /// Print the summary table and return how many steps failed.
+    pub fn finish(self) -> usize {
+        let failed = self.rows.iter().filter(|r| r.failed).count();
+        let mut table = Table::new(self.rows);
+        table.with(Style::blank()).with(Padding::zero());
+        println!("{table}");
+        failed
+    }
+}
@@ -0,0 +1,270 @@
+//! The prompts of the interactive flows, with dialoguer's builder API. They
+//! render as dialoguer's themed widgets, or with `--plain-prompts` (or
+//! `TERM=dumb`) as plain questions answered with a line on stdin, which screen
+//! readers can follow. Both accept the same answers and defaults.
+
+use std::fmt::Display;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{bail, Result};
+use dialoguer::theme::Theme;
+
+use crate::i18n::{t, tf};
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Use plain prompts when asked to, or when the terminal can't do better.
+pub fn set_plain(plain: bool) {
+    let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+    PLAIN.store(plain || dumb, Ordering::SeqCst);
+}
+
+fn is_plain() -> bool {
+    PLAIN.load(Ordering::SeqCst)
+}
+
+/// Ask `question` on stderr and read one line of answer, without its newline.
+fn ask(question: &str) -> Result<String> {
+    eprint!("{question} ");
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line)? == 0 {
+        bail!(t("No answer given (end of input)"));
+    }
+    Ok(line.trim_end_matches(['\n', '\r']).to_string())
+}
+
+pub struct Confirm<'a> {
+    theme: &'a dyn Theme,
+    prompt: String,
+    default: bool,
+}
+
+impl<'a> Confirm<'a> {
+    pub fn with_theme(theme: &'a dyn Theme) -> Self {
+        Confirm {
+            theme,
+            prompt: String::new(),
+            default: true,
+        }
+    }
+
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    pub fn default(mut self, default: bool) -> Self {
+        self.default = default;
+        self
+    }
+
+    pub fn interact(self) -> Result<bool> {
+        if !is_plain() {
+            return Ok(dialoguer::Confirm::with_theme(self.theme)
+                .with_prompt(self.prompt)
+                .default(self.default)
+                .interact()?);
+        }
+        let hint = if self.default { "[Y/n]" } else { "[y/N]" };
+        loop {
+            match parse_yes_no(&ask(&format!("{} {hint}", self.prompt))?) {
+                Some(answer) => return Ok(answer.unwrap_or(self.default)),
+                None => eprintln!("{}", t("Please answer y or n.")),
+            }
+        }
+    }
+}
+
+/// `Some(None)` for an empty answer (take the default), `None` if it isn't
+/// yes or no.
+fn parse_yes_no(answer: &str) -> Option<Option<bool>> {
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "" => Some(None),
+        "y" | "yes" => Some(Some(true)),
+        "n" | "no" => Some(Some(false)),
+        _ => None,
+    }
+}
+
+pub struct Select<'a> {
+    theme: &'a dyn Theme,
+    prompt: String,
+    items: Vec<String>,
+    default: usize,
+}
+
+impl<'a> Select<'a> {
+    pub fn with_theme(theme: &'a dyn Theme) -> Self {
+        Select {
+            theme,
+            prompt: String::new(),
+            items: Vec::new(),
+            default: 0,
+        }
+    }
+
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    pub fn items<T: ToString>(mut self, items: impl IntoIterator<Item = T>) -> Self {
+        self.items
+            .extend(items.into_iter().map(|item| item.to_string()));
+        self
+    }
+
+    pub fn default(mut self, default: usize) -> Self {
+        self.default = default;
+        self
+    }
+
+    /// The index of the chosen item.
+    pub fn interact(self) -> Result<usize> {
+        if !is_plain() {
+            return Ok(dialoguer::Select::with_theme(self.theme)
+                .with_prompt(self.prompt)
+                .items(&self.items)
+                .default(self.default)
+                .interact()?);
+        }
+        eprintln!("{}", self.prompt);
+        for (index, item) in self.items.iter().enumerate() {
+            eprintln!("  {}) {item}", index + 1);
+        }
+        loop {
+            let question = format!("{} [{}]:", t("Number"), self.default + 1);
+            match parse_choice(&ask(&question)?, self.items.len()) {
+                Some(choice) => return Ok(choice.unwrap_or(self.default)),
+                None => eprintln!(
+                    "{}",
+                    tf("Please enter a number from 1 to {}.", &[&self.items.len()])
+                ),
+            }
+        }
+    }
+}
+
+/// A 1-based answer as an index: `Some(None)` for an empty answer, `None` if
+/// it's out of range.
+fn parse_choice(answer: &str, count: usize) -> Option<Option<usize>> {
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Some(None);
+    }
+    match answer.parse::<usize>() {
+        Ok(n) if (1..=count).contains(&n) => Some(Some(n - 1)),
+        _ => None,
+    }
+}
+
+type Validator<'a, T> = Box<dyn FnMut(&T) -> Result<(), String> + 'a>;
+
+pub struct Input<'a, T> {
+    theme: &'a dyn Theme,
+    prompt: String,
+    default: Option<T>,
+    allow_empty: bool,
+    validator: Option<Validator<'a, T>>,
+}
+
+impl<'a, T> Input<'a, T>
+where
+    T: Clone + Display + FromStr + 'a,
+    T::Err: Display,
+{
+    pub fn with_theme(theme: &'a dyn Theme) -> Self {
+        Input {
+            theme,
+            prompt: String::new(),
+            default: None,
+            allow_empty: false,
+            validator: None,
+        }
+    }
+
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    pub fn default(mut self, default: T) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn allow_empty(mut self, allow_empty: bool) -> Self {
+        self.allow_empty = allow_empty;
+        self
+    }
+
+    pub fn validate_with<E: ToString>(
+        mut self,
+        mut validator: impl FnMut(&T) -> Result<(), E> + 'a,
+    ) -> Self {
+        self.validator = Some(Box::new(move |value| {
+            validator(value).map_err(|e| e.to_string())
+        }));
+        self
+    }
+
+    pub fn interact_text(self) -> Result<T> {
+        if !is_plain() {
+            let mut input = dialoguer::Input::<T>::with_theme(self.theme)
+                .with_prompt(self.prompt)
+                .allow_empty(self.allow_empty);
+            if let Some(default) = self.default {
+                input = input.default(default);
+            }
+            if let Some(validator) = self.validator {
+                input = input.validate_with(validator);
+            }
+            return Ok(input.interact_text()?);
+        }
+        let question = match &self.default {
+            Some(default) => format!("{} [{default}]:", self.prompt),
+            None => format!("{}:", self.prompt),
+        };
+        let mut validator = self.validator;
+        loop {
+            let answer = ask(&question)?;
+            let value = match (answer.trim().is_empty(), &self.default) {
+                (true, Some(default)) => default.clone(),
+                (true, None) if !self.allow_empty => continue,
+                _ => match answer.trim().parse::<T>() {
+                    Ok(value) => value,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        continue;
+                    }
+                },
+            };
+            if let Some(Err(message)) = validator.as_mut().map(|v| v(&value)) {
+                eprintln!("{message}");
+                continue;
+            }
+            return Ok(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_answers() {
+        assert_eq!(parse_yes_no(" Y "), Some(Some(true)));
+        assert_eq!(parse_yes_no("no"), Some(Some(false)));
+        assert_eq!(parse_yes_no(""), Some(None));
+        assert_eq!(parse_yes_no("maybe"), None);
+        assert_eq!(parse_choice("2", 3), Some(Some(1)));
+        assert_eq!(parse_choice("", 3), Some(None));
+        assert_eq!(parse_choice("4", 3), None);
+        assert_eq!(parse_choice("0", 3), None);
+        assert_eq!(parse_choice("two", 3), None);
+    }
+}
@@ -0,0 +1,56 @@
+/*
+ * C bindings for serlib: manage launchd/systemd services without shelling
+ * out to the `ser` CLI.
+ *
+ * Every operation returns a SER_* status code. On failure, ser_last_error()
+ * describes what went wrong. JSON results are returned through `out`
+ * pointers; the caller owns them and frees them with ser_string_free().
+ */
+#ifndef SER_H
+#define SER_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SER_OK 0
+/* A null pointer, invalid UTF-8, malformed JSON, or an unknown list level. */
+#define SER_ERR_INVALID_ARGUMENT 1
+/* The operation itself failed, e.g. no such service or systemctl failed. */
+#define SER_ERR_FAILED 2
+/* A bug in ser; the message has the panic text. */
+#define SER_ERR_PANIC 3
+
+#define SER_LIST_DEFAULT 0
+#define SER_LIST_USER 1
+#define SER_LIST_SYSTEM 2
+
+/* A JSON array of {"name", "path", "enabled", "source"}. */
+int ser_list(int level, char **out_json);
+
+/* A JSON object of {"service", "path", "enabled", "running"}. */
+int ser_show(const char *name, char **out_json);
+
+int ser_start(const char *name);
+
+int ser_stop(const char *name);
+
+/* Create or update a service from a JSON object with the fields of
+ * ServiceDetails; only "name" and "program" are required. */
+int ser_create(const char *details_json);
+
+/* The last failure on this thread, or NULL after a success. Valid until the
+ * next call on this thread; don't free it. */
+const char *ser_last_error(void);
+
+/* Free a string returned through an `out` pointer. NULL is ignored. */
+void ser_string_free(char *s);
+
+/* The library version, e.g. "0.1.8". Static; don't free it. */
+const char *ser_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SER_H */
@@ -0,0 +1,235 @@
+//! A C ABI over serlib, so GUI apps (Swift, Electron) and other languages can
+//! manage services without shelling out to `ser`. The declarations are in
+//! `include/ser.h`.
+//!
+//! Every operation returns one of the `SER_*` status codes. On failure,
+//! `ser_last_error` describes what went wrong. Structured results come back
+//! as JSON strings through `out` pointers; the caller owns them and frees
+//! them with `ser_string_free`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use serlib::platform::{self, ListLevel};
+use serlib::ServiceDetails;
+
+pub const SER_OK: c_int = 0;
+/// A null pointer, invalid UTF-8, malformed JSON, or an unknown list level.
+pub const SER_ERR_INVALID_ARGUMENT: c_int = 1;
+/// The operation itself failed, e.g. no such service or systemctl failed.
+pub const SER_ERR_FAILED: c_int = 2;
+/// A bug in ser; the message has the panic text.
+pub const SER_ERR_PANIC: c_int = 3;
+
+pub const SER_LIST_DEFAULT: c_int = 0;
+pub const SER_LIST_USER: c_int = 1;
+pub const SER_LIST_SYSTEM: c_int = 2;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+struct Failure {
+    code: c_int,
+    message: String,
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(e: anyhow::Error) -> Failure {
+        Failure {
+            code: SER_ERR_FAILED,
+            message: format!("{e:#}"),
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> Failure {
+    Failure {
+        code: SER_ERR_INVALID_ARGUMENT,
+        message: message.into(),
+    }
+}
+
+fn set_last_error(message: Option<String>) {
+    let message = message.map(|m| CString::new(m.replace('\0', " ")).unwrap_or_default());
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// Run `f`, turning its error (or a panic, which must not unwind into C)
+/// into a status code and the thread's last error.
+fn call(f: impl FnOnce() -> Result<(), Failure>) -> c_int {
+    let failure = match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => {
+            set_last_error(None);
+            return SER_OK;
+        }
+        Ok(Err(failure)) => failure,
+        Err(panic) => Failure {
+            code: SER_ERR_PANIC,
+            message: panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "panic".to_string()),
+        },
+    };
+    set_last_error(Some(failure.message));
+    failure.code
+}
+
+/// # Safety
+/// `ptr` must be null or a valid NUL-terminated string.
+unsafe fn arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if ptr.is_null() {
+        return Err(invalid(format!("{name} is null")));
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| invalid(format!("{name} is not valid UTF-8")))
+}
+
+/// Serialize `value` into a caller-owned string at `out`.
+///
+/// # Safety
+/// `out` must be null or valid for a pointer write.
+unsafe fn write_json(out: *mut *mut c_char, value: &impl serde::Serialize) -> Result<(), Failure> {
+    if out.is_null() {
+        return Err(invalid("out is null"));
+    }
+    let json = serde_json::to_string(value).map_err(anyhow::Error::from)?;
+    let json = CString::new(json).map_err(anyhow::Error::from)?;
+    *out = json.into_raw();
+    Ok(())
+}
+
+/// List services as a JSON array of `{name, path, enabled, source}`.
+///
+/// # Safety
+/// `out_json` must be valid for a pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn ser_list(level: c_int, out_json: *mut *mut c_char) -> c_int {
+    call(|| {
+        let level = match level {
+            SER_LIST_DEFAULT => ListLevel::Default,
+            SER_LIST_USER => ListLevel::User,
+            SER_LIST_SYSTEM => ListLevel::System,
+            _ => return Err(invalid(format!("unknown list level {level}"))),
+        };
+        write_json(out_json, &platform::list_services(level)?)
+    })
+}
+
+/// A service's definition and state as a JSON object of
+/// `{service, path, enabled, running}`.
+///
+/// # Safety
+/// `name` must be a NUL-terminated string and `out_json` valid for a pointer
+/// write.
+#[no_mangle]
+pub unsafe extern "C" fn ser_show(name: *const c_char, out_json: *mut *mut c_char) -> c_int {
+    call(|| {
+        let name = arg(name, "name")?;
+        write_json(out_json, &platform::get_service_details(name)?)
+    })
+}
+
+/// # Safety
+/// `name` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ser_start(name: *const c_char) -> c_int {
+    call(|| Ok(platform::start_service(arg(name, "name")?)?))
+}
+
+/// # Safety
+/// `name` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ser_stop(name: *const c_char) -> c_int {
+    call(|| Ok(platform::stop_service(arg(name, "name")?)?))
+}
+
+/// Create or update a service from a JSON object with the fields of
+/// `ServiceDetails`; only `name` and `program` are required.
+///
+/// # Safety
+/// `details_json` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ser_create(details_json: *const c_char) -> c_int {
+    call(|| {
+        let json = arg(details_json, "details_json")?;
+        let details: ServiceDetails = serde_json::from_str(json)
+            .map_err(|e| invalid(format!("invalid service details: {e}")))?;
+        if details.name.is_empty() || details.program.is_empty() {
+            return Err(invalid("service details need a name and a program"));
+        }
+        Ok(platform::create_service(&details)?)
+    })
+}
+
+/// The message for the last failed call on this thread, or null if it
+/// succeeded. Valid until the next call on this thread; don't free it.
+#[no_mangle]
+pub extern "C" fn ser_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// Free a string returned through an `out` pointer. Null is ignored.
+///
+/// # Safety
+/// `s` must be null or a string from this library, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn ser_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// The library version, e.g. `0.1.8`. Static; don't free it.
+#[no_mangle]
+pub extern "C" fn ser_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(ser_last_error()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn reports_invalid_arguments() {
+        unsafe {
+            assert_eq!(ser_start(ptr::null()), SER_ERR_INVALID_ARGUMENT);
+            assert_eq!(last_error(), "name is null");
+
+            let json = CString::new(r#"{"name": "web"}"#).unwrap();
+            assert_eq!(ser_create(json.as_ptr()), SER_ERR_INVALID_ARGUMENT);
+            assert_eq!(last_error(), "service details need a name and a program");
+
+            let mut out = ptr::null_mut();
+            assert_eq!(ser_list(7, &mut out), SER_ERR_INVALID_ARGUMENT);
+            assert!(out.is_null());
+        }
+    }
+
+    #[test]
+    fn returns_json_the_caller_frees() {
+        unsafe {
+            let name = CString::new("ser-no-such-service").unwrap();
+            let mut out = ptr::null_mut();
+            assert_eq!(ser_show(name.as_ptr(), &mut out), SER_ERR_FAILED);
+            assert!(!last_error().is_empty());
+
+            assert_eq!(ser_list(SER_LIST_DEFAULT, &mut out), SER_OK);
+            assert!(ser_last_error().is_null());
+            let json = CStr::from_ptr(out).to_str().unwrap();
+            assert!(json.starts_with('['));
+            ser_string_free(out);
+        }
+    }
+}
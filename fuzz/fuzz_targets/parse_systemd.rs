@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    if let Err(e) = serlib::roundtrip::check_systemd(input) {
+        panic!("{e}");
+    }
+});
@@ -0,0 +1,462 @@
+//! `ser backup` / `ser restore`: every managed service's definition, its env
+//! file, and the state registry in one tarball, optionally encrypted, that
+//! can be restored onto a fresh machine.
+//!
+//! Archives are built with the system `tar`, and encrypted with `openssl enc`
+//! (AES-256-CBC, PBKDF2) using a passphrase passed through the environment.
+//! tar's output is piped straight into openssl (and back), so the plaintext
+//! archive never reaches the disk.
+
+use crate::platform::{self, ListLevel, UnitResult};
+use crate::state::Registry;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// The file inside an archive describing its contents.
+pub const MANIFEST: &str = "manifest.toml";
+/// Environment variable the passphrase is handed to `openssl` through, so it
+/// never appears in a process listing.
+pub const PASSPHRASE_ENV: &str = "SER_BACKUP_PASSPHRASE";
+/// `openssl enc -salt` output starts with this.
+const OPENSSL_MAGIC: &[u8] = b"Salted__";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    pub created: String,
+    pub host: String,
+    /// `std::env::consts::OS` of the machine the backup was taken on.
+    pub os: String,
+    /// Home directory of the user who took the backup, so paths under it can
+    /// be mapped onto this machine's home by `ser migrate`.
+    pub home: Option<String>,
+    /// Whether the archive includes `state.toml`.
+    pub state: bool,
+    pub services: Vec<BackupEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupEntry {
+    pub name: String,
+    /// Definition files (unit + timer, or plist), relative to the archive root.
+    pub files: Vec<String>,
+    /// Where the definition was installed.
+    pub original_path: String,
+    /// Installed for a user rather than system-wide.
+    pub user: bool,
+    pub running: bool,
+    pub enabled: bool,
+    /// Original paths of the env files that existed, archived in order as
+    /// `env/<name>.<index>.env`.
+    pub env_files: Vec<String>,
+}
+
+impl BackupEntry {
+    pub fn archived_env_file(&self, index: usize) -> String {
+        format!("env/{}.{index}.env", self.name)
+    }
+
+    /// Whether the service should be started once restored. A timer-backed
+    /// job is "running" only mid-run; what matters is that its timer was
+    /// enabled.
+    pub fn was_active(&self) -> bool {
+        let scheduled = self.files.len() > 1 || self.original_path.ends_with(".plist");
+        self.running || (scheduled && self.enabled)
+    }
+}
+
+/// `ser-backup-<host>-<date>.tar.gz`, with `.enc` appended when encrypted.
+pub fn default_archive_name(encrypted: bool) -> PathBuf {
+    let date = chrono::Local::now().format("%Y%m%d");
+    let suffix = if encrypted { ".enc" } else { "" };
+    PathBuf::from(format!(
+        "ser-backup-{}-{date}.tar.gz{suffix}",
+        crate::specifiers::hostname()
+    ))
+}
+
+/// A scratch directory with a random name, readable only by its owner
+/// (0700), and removed when dropped. It holds env files, which may be
+/// secret.
+struct Staging(tempfile::TempDir);
+
+impl Staging {
+    fn new(purpose: &str) -> Result<Staging> {
+        let prefix = format!("ser-{purpose}-");
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(&prefix);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(fs::Permissions::from_mode(0o700));
+        }
+        builder
+            .tempdir()
+            .map(Staging)
+            .context("Failed to create a scratch directory")
+    }
+
+    fn path(&self) -> &Path {
+        self.0.path()
+    }
+}
+
+/// Archive all managed services to `archive`, encrypting it if a passphrase
+/// is given. Returns what was archived.
+pub fn create(archive: &Path, passphrase: Option<&str>) -> Result<Manifest> {
+    let archive = &std::path::absolute(archive)?;
+    let staging = Staging::new("backup")?;
+    let root = staging.path();
+    fs::create_dir_all(root.join("services"))?;
+    fs::create_dir_all(root.join("env"))?;
+
+    let home = dirs::home_dir();
+    let mut manifest = Manifest {
+        created: chrono::Local::now().to_rfc3339(),
+        host: crate::specifiers::hostname(),
+        os: std::env::consts::OS.to_string(),
+        home: home.as_ref().map(|h| h.to_string_lossy().into_owned()),
+        ..Default::default()
+    };
+
+    let mut services = platform::list_services(ListLevel::System)?;
+    services.retain(|s| platform::is_managed(s) && !s.name.ends_with(".timer"));
+    for service in services {
+        let name = platform::normalize_service_name(&service.name).to_string();
+        let details = platform::get_service_details(&service.name)
+            .with_context(|| format!("Failed to read '{name}'"))?;
+        let path = PathBuf::from(&service.path);
+
+        let mut files = Vec::new();
+        let timer = path.with_extension("timer");
+        for file in [
+            Some(path.as_path()),
+            timer.exists().then_some(timer.as_path()),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+            let archived = format!("services/{file_name}");
+            fs::copy(file, root.join(&archived))
+                .with_context(|| format!("Failed to copy {}", file.display()))?;
+            files.push(archived);
+        }
+
+        let mut entry = BackupEntry {
+            name,
+            files,
+            original_path: service.path.clone(),
+            user: home.as_ref().is_some_and(|home| path.starts_with(home)),
+            running: details.running,
+            enabled: details.enabled,
+            env_files: Vec::new(),
+        };
+        for env_file in &details.service.env_files {
+            if Path::new(&env_file.path).exists() {
+                let archived = entry.archived_env_file(entry.env_files.len());
+                fs::copy(&env_file.path, root.join(archived))
+                    .with_context(|| format!("Failed to copy {}", env_file.path))?;
+                entry.env_files.push(env_file.path.clone());
+            }
+        }
+        manifest.services.push(entry);
+    }
+
+    if let Some(state) = Registry::path().filter(|p| p.exists()) {
+        fs::copy(&state, root.join("state.toml"))
+            .with_context(|| format!("Failed to copy {}", state.display()))?;
+        manifest.state = true;
+    }
+    fs::write(root.join(MANIFEST), toml::to_string(&manifest)?)?;
+
+    match passphrase {
+        None => tar(&["-czf"], archive, root)?,
+        Some(passphrase) => seal(root, archive, passphrase)?,
+    }
+    Ok(manifest)
+}
+
+/// Whether `archive` was encrypted by [`create`].
+pub fn is_encrypted(archive: &Path) -> Result<bool> {
+    let mut magic = [0u8; 8];
+    let mut file =
+        fs::File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+    use std::io::Read;
+    Ok(file.read_exact(&mut magic).is_ok() && magic == OPENSSL_MAGIC)
+}
+
+/// An archive unpacked into a scratch directory, removed when dropped.
+pub struct Unpacked {
+    staging: Staging,
+    pub manifest: Manifest,
+}
+
+impl Unpacked {
+    pub fn root(&self) -> &Path {
+        self.staging.path()
+    }
+}
+
+/// Decrypt (if needed) and extract an archive made by [`create`].
+pub fn unpack(archive: &Path, passphrase: Option<&str>) -> Result<Unpacked> {
+    let archive = &std::path::absolute(archive)?;
+    let staging = Staging::new("restore")?;
+    let root = staging.path().to_path_buf();
+    if is_encrypted(archive)? {
+        let passphrase = passphrase.ok_or_else(|| {
+            anyhow!(
+                "{} is encrypted; provide its passphrase (or set {PASSPHRASE_ENV})",
+                archive.display()
+            )
+        })?;
+        open(archive, &root, passphrase)?;
+    } else {
+        tar(&["-xzf"], archive, &root)?;
+    }
+    let contents = fs::read_to_string(root.join(MANIFEST))
+        .with_context(|| format!("{} is not a ser backup (no {MANIFEST})", archive.display()))?;
+    let manifest = toml::from_str(&contents).context("Invalid backup manifest")?;
+    Ok(Unpacked { staging, manifest })
+}
+
+/// Restore everything in an unpacked archive: env files go back to their
+/// original paths (unless one is already there), definitions are installed (user or system, as before),
+/// state is merged into this machine's registry, and services that were
+/// running are started when `start` is set. Returns a result per service.
+pub fn restore(unpacked: &Unpacked, start: bool) -> Result<Vec<UnitResult>> {
+    restore_state(unpacked)?;
+    Ok(unpacked
+        .manifest
+        .services
+        .iter()
+        .map(|entry| UnitResult {
+            name: entry.name.clone(),
+            result: restore_entry(unpacked, entry, start),
+        })
+        .collect())
+}
+
+/// Merge the archived state registry into this machine's, keeping any
+/// state already recorded here.
+pub fn restore_state(unpacked: &Unpacked) -> Result<()> {
+    if !unpacked.manifest.state {
+        return Ok(());
+    }
+    let contents = fs::read_to_string(unpacked.root().join("state.toml"))?;
+    let backed_up: Registry = toml::from_str(&contents).context("Invalid state.toml")?;
+    Registry::update(|registry| {
+        for (name, state) in backed_up.services {
+            registry.services.entry(name).or_insert(state);
+        }
+    })
+}
+
+/// Copy an entry's `index`th archived env file to `target`, creating its
+/// directory. An env file already at `target` is kept rather than replaced,
+/// since it may hold secrets set up on this machine; a warning says so when
+/// it differs from the archived one.
+pub fn restore_env_file(
+    unpacked: &Unpacked,
+    entry: &BackupEntry,
+    index: usize,
+    target: &Path,
+) -> Result<()> {
+    let source = unpacked.root().join(entry.archived_env_file(index));
+    if target.exists() {
+        let archived =
+            fs::read(&source).with_context(|| format!("Failed to read {}", source.display()))?;
+        let existing =
+            fs::read(target).with_context(|| format!("Failed to read {}", target.display()))?;
+        if archived != existing {
+            eprintln!(
+                "Warning: keeping the existing {}; the backed-up copy differs and was not restored.",
+                target.display()
+            );
+        }
+        return Ok(());
+    }
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    crate::record_change("write", target);
+    fs::copy(&source, target).with_context(|| format!("Failed to restore {}", target.display()))?;
+    Ok(())
+}
+
+fn restore_entry(unpacked: &Unpacked, entry: &BackupEntry, start: bool) -> Result<()> {
+    for (index, env_file) in entry.env_files.iter().enumerate() {
+        restore_env_file(unpacked, entry, index, Path::new(env_file))?;
+    }
+    for file in &entry.files {
+        platform::install_service_file(&unpacked.root().join(file), entry.user, false)?;
+    }
+    if start && entry.was_active() {
+        platform::start_service(&entry.name)?;
+    }
+    Ok(())
+}
+
+fn tar(mode: &[&str], archive: &Path, dir: &Path) -> Result<()> {
+    let mut cmd = Command::new("tar");
+    cmd.args(mode).arg(archive).arg("-C").arg(dir);
+    if mode.contains(&"-czf") {
+        cmd.arg(".");
+    }
+    crate::print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute tar")?;
+    if !output.status.success() {
+        bail!(
+            "tar failed for {}: {}",
+            archive.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn openssl(decrypt: bool, passphrase: &str) -> Command {
+    let mut cmd = Command::new("openssl");
+    cmd.args(["enc", "-aes-256-cbc", "-pbkdf2", "-salt"]);
+    if decrypt {
+        cmd.arg("-d");
+    }
+    cmd.arg("-pass")
+        .arg(format!("env:{PASSPHRASE_ENV}"))
+        .env(PASSPHRASE_ENV, passphrase);
+    cmd
+}
+
+/// Archive `dir` and encrypt it to `archive` in one pipeline.
+fn seal(dir: &Path, archive: &Path, passphrase: &str) -> Result<()> {
+    let mut tar = Command::new("tar");
+    tar.args(["-czf", "-", "-C"]).arg(dir).arg(".");
+    let mut openssl = openssl(false, passphrase);
+    openssl.arg("-out").arg(archive);
+    let (tar, openssl) = pipe(tar, openssl)?;
+    for (what, output) in [("tar", tar), ("openssl", openssl)] {
+        if !output.status.success() {
+            let _ = fs::remove_file(archive);
+            bail!(
+                "{what} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Decrypt `archive` and extract it into `dir` in one pipeline.
+fn open(archive: &Path, dir: &Path, passphrase: &str) -> Result<()> {
+    let mut openssl = openssl(true, passphrase);
+    openssl.arg("-in").arg(archive);
+    let mut tar = Command::new("tar");
+    tar.args(["-xzf", "-", "-C"]).arg(dir);
+    let (openssl, tar) = pipe(openssl, tar)?;
+    if !openssl.status.success() {
+        bail!("Failed to decrypt {}; wrong passphrase?", archive.display());
+    }
+    if !tar.status.success() {
+        bail!(
+            "tar failed for {}: {}",
+            archive.display(),
+            String::from_utf8_lossy(&tar.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Run `producer | consumer`, returning both outcomes. Both are stopped if
+/// `ser` is interrupted meanwhile.
+fn pipe(mut producer: Command, mut consumer: Command) -> Result<(Output, Output)> {
+    crate::print_command(&producer);
+    crate::print_command(&consumer);
+    let program = |cmd: &Command| cmd.get_program().to_string_lossy().into_owned();
+    let mut first = producer
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to execute {}", program(&producer)))?;
+    let stdout = first.stdout.take().expect("stdout is piped");
+    let second = consumer
+        .stdin(stdout)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to execute {}", program(&consumer)));
+    let second = match second {
+        Ok(second) => second,
+        Err(e) => {
+            let _ = first.kill();
+            let _ = first.wait();
+            return Err(e);
+        }
+    };
+    let tracked = (
+        crate::interrupt::StopOnInterrupt::track(&first),
+        crate::interrupt::StopOnInterrupt::track(&second),
+    );
+    let second = second.wait_with_output()?;
+    let first = first.wait_with_output()?;
+    tracked.0.finish()?;
+    tracked.1.finish()?;
+    Ok((first, second))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypts_and_detects_archives() {
+        let staging = Staging::new("backup-test").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(staging.path()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        let plain = staging.path().join("plain");
+        let sealed = staging.path().join("sealed");
+        let opened = staging.path().join("opened");
+        fs::create_dir(&plain).unwrap();
+        fs::create_dir(&opened).unwrap();
+        fs::write(plain.join("unit"), "hello").unwrap();
+        if seal(&plain, &sealed, "secret").is_err() {
+            // No openssl on this machine; nothing to check.
+            return;
+        }
+        assert!(is_encrypted(&sealed).unwrap());
+        assert!(!is_encrypted(&plain.join("unit")).unwrap());
+        assert!(open(&sealed, &opened, "wrong").is_err());
+        open(&sealed, &opened, "secret").unwrap();
+        assert_eq!(fs::read_to_string(opened.join("unit")).unwrap(), "hello");
+    }
+
+    #[test]
+    fn restore_keeps_existing_env_files() {
+        let unpacked = Unpacked {
+            staging: Staging::new("restore-test").unwrap(),
+            manifest: Manifest::default(),
+        };
+        let entry = BackupEntry {
+            name: "web".into(),
+            ..Default::default()
+        };
+        fs::create_dir(unpacked.root().join("env")).unwrap();
+        fs::write(unpacked.root().join(entry.archived_env_file(0)), "A=old").unwrap();
+
+        let missing = unpacked.root().join("etc/missing.env");
+        restore_env_file(&unpacked, &entry, 0, &missing).unwrap();
+        assert_eq!(fs::read_to_string(&missing).unwrap(), "A=old");
+
+        let existing = unpacked.root().join("existing.env");
+        fs::write(&existing, "A=new").unwrap();
+        restore_env_file(&unpacked, &entry, 0, &existing).unwrap();
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "A=new");
+    }
+}
@@ -0,0 +1,228 @@
+//! `ser check`: validate the service definitions kept in a directory (unit
+//! files, plists, and `ser` TOML specs) without touching the running system,
+//! for gating changes to them in CI.
+
+use crate::spec::{self, ServiceSpec};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// The definition can't be installed.
+    Error,
+    /// It installs, but probably won't behave as intended (see `ser lint`).
+    Warning,
+}
+
+/// One problem in one file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub level: Level,
+    pub rule: &'static str,
+    pub message: String,
+}
+
+/// Check every definition under `dir`, skipping hidden directories. Files
+/// are visited in path order so output is stable between runs.
+pub fn check_dir(dir: &Path) -> Result<Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+    for path in definition_files(dir)? {
+        diagnostics.extend(check_file(&path)?);
+    }
+    Ok(diagnostics)
+}
+
+/// Check one file. TOML files that aren't `ser` specs (say, a `Cargo.toml`)
+/// have nothing to report.
+pub fn check_file(path: &Path) -> Result<Vec<Diagnostic>> {
+    let contents = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let diagnostic = |level, rule, line, message: String| Diagnostic {
+        path: path.to_path_buf(),
+        line,
+        level,
+        rule,
+        message,
+    };
+    let invalid =
+        |e: anyhow::Error| vec![diagnostic(Level::Error, "invalid", None, format!("{e:#}"))];
+    let lint = |report: crate::lint::Report| {
+        report
+            .findings
+            .into_iter()
+            .map(|f| diagnostic(Level::Warning, f.rule, f.line, f.message))
+            .collect::<Vec<_>>()
+    };
+
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    Ok(match extension {
+        "plist" => match crate::lint::lint_plist(&contents) {
+            Ok(report) => match crate::plist::parse_plist(&contents) {
+                Ok(_) => lint(report),
+                Err(e) => invalid(e),
+            },
+            Err(e) => invalid(e),
+        },
+        "service" => {
+            let Ok(text) = std::str::from_utf8(&contents) else {
+                return Ok(invalid(anyhow::anyhow!("not valid UTF-8")));
+            };
+            match crate::systemd::parse_systemd(text) {
+                Ok(_) => lint(crate::lint::lint_unit(text)),
+                Err(e) => invalid(e),
+            }
+        }
+        "toml" if path.file_name().is_some_and(|n| n == spec::PROJECT_FILE) => {
+            match spec::load_project(path) {
+                Ok(_) => Vec::new(),
+                Err(e) => invalid(e),
+            }
+        }
+        "toml" if is_service_spec(&contents) => {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            match ServiceSpec::from_path(path).and_then(|spec| spec.into_details(&name)) {
+                Ok(_) => Vec::new(),
+                Err(e) => invalid(e),
+            }
+        }
+        _ => Vec::new(),
+    })
+}
+
+/// A TOML file that defines a service, as opposed to any other config file.
+fn is_service_spec(contents: &[u8]) -> bool {
+    let Ok(table) = std::str::from_utf8(contents)
+        .unwrap_or_default()
+        .parse::<toml::Table>()
+    else {
+        return false;
+    };
+    table.contains_key("command") || table.contains_key("program")
+}
+
+fn definition_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries =
+            fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if path.is_dir() {
+                if !name.starts_with('.') {
+                    pending.push(path);
+                }
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext == "service" || ext == "plist" || ext == "toml")
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The diagnostics as a JSON array.
+pub fn to_json(diagnostics: &[Diagnostic]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(diagnostics)?)
+}
+
+/// The diagnostics as a SARIF 2.1.0 log, the format code scanning tools
+/// (e.g. GitHub's) ingest.
+pub fn to_sarif(diagnostics: &[Diagnostic]) -> Result<String> {
+    let mut rules: Vec<&str> = diagnostics.iter().map(|d| d.rule).collect();
+    rules.sort();
+    rules.dedup();
+    let results: Vec<_> = diagnostics
+        .iter()
+        .map(|d| {
+            let mut location = json!({
+                "physicalLocation": {
+                    "artifactLocation": { "uri": d.path.to_string_lossy() },
+                }
+            });
+            if let Some(line) = d.line {
+                location["physicalLocation"]["region"] = json!({ "startLine": line });
+            }
+            json!({
+                "ruleId": d.rule,
+                "level": match d.level {
+                    Level::Error => "error",
+                    Level::Warning => "warning",
+                },
+                "message": { "text": d.message },
+                "locations": [location],
+            })
+        })
+        .collect();
+    let log = json!({
+        "version": "2.1.0",
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "ser",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/kurtbuilds/ser",
+                    "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }],
+    });
+    Ok(serde_json::to_string_pretty(&log)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_definitions_in_a_directory() {
+        // Everything the check looks at, including the program the unit
+        // runs, is in the fixture directory, not on the host.
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path();
+        fs::create_dir_all(dir.join("deploy")).unwrap();
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        fs::write(dir.join("bin/web"), "").unwrap();
+        fs::write(
+            dir.join("deploy/web.service"),
+            format!(
+                "[Service]\nExecStart={}\nType=simple\nType=exec\n",
+                dir.join("bin/web").display()
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("deploy/bad.service"), "[Service]\nType=simple\n").unwrap();
+        fs::write(dir.join("worker.toml"), "command = \"\"\n").unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+
+        let diagnostics = check_dir(dir).unwrap();
+        let summary: Vec<_> = diagnostics
+            .iter()
+            .map(|d| {
+                let name = d.path.file_name().unwrap().to_string_lossy().into_owned();
+                (name, d.level, d.rule)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("bad.service".to_string(), Level::Error, "invalid"),
+                ("web.service".to_string(), Level::Warning, "duplicate-key"),
+                ("worker.toml".to_string(), Level::Error, "invalid"),
+            ]
+        );
+        let sarif = to_sarif(&diagnostics).unwrap();
+        assert!(sarif.contains("\"startLine\": 3"));
+    }
+}
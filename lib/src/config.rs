@@ -0,0 +1,259 @@
+use crate::hooks::Hooks;
+use crate::notify::{Email, Webhook};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+static EXTRA_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// User settings read from `config.toml`. Every field is optional.
+///
+/// ```toml
+/// # Extra directories scanned for services, alongside the built-in ones.
+/// dirs = ["/opt/myorg/services", "~/dotfiles/units"]
+///
+/// # Services left out of `ser list` unless `--no-hidden` is given.
+/// hidden = ["com.apple.*", "snap-*"]
+///
+/// # Language for prompts (`en` or `es`); defaults to LC_ALL/LC_MESSAGES/LANG.
+/// locale = "es"
+///
+/// [hooks.web]
+/// pre_start = "./bin/migrate"
+///
+/// [notifications]
+/// # Desktop notification when start/restart/up take longer than `after_secs`.
+/// desktop = true
+/// after_secs = 2
+///
+/// [backend]
+/// # Give up on a read-only systemctl/launchctl query after this long
+/// # (0: wait forever), retrying queries that time out or can't reach the
+/// # init system. Starts, stops, and hooks always run to the end.
+/// timeout_secs = 120
+/// retries = 2
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub dirs: Vec<PathBuf>,
+    /// Glob patterns (`*` and `?`) for services `ser list` leaves out.
+    pub hidden: Vec<String>,
+    /// Language for the interactive prompts, overriding the environment.
+    pub locale: Option<String>,
+    pub notifications: Notifications,
+    /// Client-side hooks per service name; see [`crate::hooks::Hooks`].
+    pub hooks: BTreeMap<String, Hooks>,
+    pub backend: Backend,
+}
+
+/// Limits on the init system's tools, for when D-Bus or launchd stops
+/// answering; see [`crate::interrupt::query`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Backend {
+    pub timeout_secs: u64,
+    pub retries: u32,
+}
+
+impl Backend {
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        (self.timeout_secs > 0).then(|| std::time::Duration::from_secs(self.timeout_secs))
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend {
+            timeout_secs: 120,
+            retries: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Notifications {
+    pub desktop: bool,
+    pub after_secs: u64,
+    /// Where `ser notify` delivers alerts; see [`crate::notify::Webhook`].
+    pub webhook: Option<Webhook>,
+    /// Email alerts; see [`crate::notify::Email`].
+    pub email: Option<Email>,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Notifications {
+            desktop: false,
+            after_secs: 2,
+            webhook: None,
+            email: None,
+        }
+    }
+}
+
+/// Location of the user's `ser` configuration file: `$SER_CONFIG`, else
+/// `$XDG_CONFIG_HOME/ser/config.toml` or the platform equivalent.
+///
+/// Returns `None` when there is no home directory to anchor it, e.g. a system
+/// account in a minimal container; `ser` then runs with default settings.
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("SER_CONFIG").filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    xdg_dir("XDG_CONFIG_HOME", dirs::config_dir).map(|dir| dir.join("ser").join("config.toml"))
+}
+
+/// Directory for `ser`'s own persistent state (`$XDG_STATE_HOME/ser`, falling
+/// back to the platform's local data directory). `None` without a home.
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", || {
+        dirs::state_dir().or_else(dirs::data_local_dir)
+    })
+    .map(|dir| dir.join("ser"))
+}
+
+/// Where `ser` keeps backups of files it overwrites or exports.
+pub fn backup_dir() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("backups"))
+}
+
+/// Honor an explicit, absolute `$XDG_*` variable on every platform (the XDG
+/// spec says relative values must be ignored), else use the platform default.
+fn xdg_dir(var: &str, fallback: impl FnOnce() -> Option<PathBuf>) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(fallback)
+}
+
+/// Read the config file, if there is one. A missing file yields the defaults;
+/// a malformed one is an error so typos don't go unnoticed.
+pub fn load() -> Result<Settings> {
+    let Some(path) = config_path() else {
+        return Ok(Settings::default());
+    };
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let mut settings: Settings = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+    settings.dirs = settings.dirs.iter().map(|d| expand_tilde(d)).collect();
+    Ok(settings)
+}
+
+/// Load the config file once for the lifetime of the process.
+pub fn init() -> Result<()> {
+    let settings = load()?;
+    let _ = SETTINGS.set(settings);
+    Ok(())
+}
+
+/// The loaded settings, or the defaults if [`init`] was never called.
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+/// Add directories to scan for this invocation (e.g. from `--dir` flags).
+pub fn set_extra_dirs(dirs: Vec<PathBuf>) {
+    *EXTRA_DIRS.lock().unwrap() = dirs;
+}
+
+/// All user-supplied scan directories: `--dir` flags first, then the config file.
+pub fn extra_dirs() -> Vec<PathBuf> {
+    let mut dirs = EXTRA_DIRS.lock().unwrap().clone();
+    for dir in &settings().dirs {
+        if !dirs.contains(dir) {
+            dirs.push(dir.clone());
+        }
+    }
+    dirs
+}
+
+/// Whether `name` matches a `hidden` pattern, with or without its
+/// `.service` suffix. Hiding only affects listings; the service can still be
+/// named directly.
+pub fn is_hidden(name: &str) -> bool {
+    let base = name.strip_suffix(".service").unwrap_or(name);
+    settings()
+        .hidden
+        .iter()
+        .any(|pattern| glob_match(pattern, name) || glob_match(pattern, base))
+}
+
+/// Match a shell-style glob where `*` is any run of characters and `?` is
+/// any one character.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the last `*`: its position and the text it covers up to.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn expand_tilde(path: &std::path::Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dirs() {
+        let settings: Settings = toml::from_str(r#"dirs = ["/opt/myorg/services"]"#).unwrap();
+        assert_eq!(settings.dirs, vec![PathBuf::from("/opt/myorg/services")]);
+
+        let empty: Settings = toml::from_str("").unwrap();
+        assert!(empty.dirs.is_empty());
+        assert!(!empty.notifications.desktop);
+        assert_eq!(empty.notifications.after_secs, 2);
+        assert_eq!(empty.backend.timeout_secs, 120);
+
+        let settings: Settings = toml::from_str("[backend]\ntimeout_secs = 0").unwrap();
+        assert_eq!(settings.backend.timeout(), None);
+        assert_eq!(settings.backend.retries, 0);
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_match("com.apple.*", "com.apple.Finder"));
+        assert!(glob_match("snap-*", "snap-core-1234.mount"));
+        assert!(glob_match("*-daily", "apt-daily"));
+        assert!(glob_match("web?", "web2"));
+        assert!(glob_match("a*b*c", "axxbyybc"));
+        assert!(!glob_match("snap-*", "snapd"));
+        assert!(!glob_match("web?", "web"));
+        assert!(!glob_match("com.apple.*", "com.example.apple"));
+    }
+}
@@ -0,0 +1,377 @@
+//! Field-level comparison of two service definitions for `ser diff`: two
+//! files (in either format), or the same service on two hosts.
+
+use crate::ServiceDetails;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Whether a difference changes what runs, or only how it's labeled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Impact {
+    /// The command, environment, restart policy, schedule, and so on.
+    Runtime,
+    /// Description, tags, and owner.
+    Cosmetic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    /// The field in unit file terms, e.g. `ExecStart` or `Environment PORT`.
+    pub field: String,
+    /// `None` when the field is unset on that side.
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub impact: Impact,
+}
+
+/// Every field that differs between two definitions, runtime differences
+/// first. Names aren't compared, since diffing two names is the usual point.
+pub fn diff(left: &ServiceDetails, right: &ServiceDetails) -> Vec<FieldDiff> {
+    let mut diffs = Vec::new();
+    let mut compare = |field: &str, impact, left: Option<String>, right: Option<String>| {
+        if left != right {
+            diffs.push(FieldDiff {
+                field: field.to_string(),
+                left,
+                right,
+                impact,
+            });
+        }
+    };
+    let non_empty = |values: Vec<String>| (!values.is_empty()).then(|| values.join(" "));
+    let command = |s: &ServiceDetails| {
+        non_empty(
+            std::iter::once(&s.program)
+                .chain(&s.arguments)
+                .filter(|part| !part.is_empty())
+                .cloned()
+                .collect(),
+        )
+    };
+
+    use Impact::*;
+    compare("ExecStart", Runtime, command(left), command(right));
+    compare(
+        "WorkingDirectory",
+        Runtime,
+        left.working_directory.clone(),
+        right.working_directory.clone(),
+    );
+    compare("User", Runtime, left.user.clone(), right.user.clone());
+    let mut keys: Vec<&String> = left.env_vars.iter().map(|(k, _)| k).collect();
+    for (key, _) in &right.env_vars {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    let env = |s: &ServiceDetails, key: &str| {
+        s.env_vars
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    };
+    for key in keys {
+        compare(
+            &format!("Environment {key}"),
+            Runtime,
+            env(left, key),
+            env(right, key),
+        );
+    }
+    let env_files =
+        |s: &ServiceDetails| non_empty(s.env_files.iter().map(|f| f.to_systemd_value()).collect());
+    compare(
+        "EnvironmentFile",
+        Runtime,
+        env_files(left),
+        env_files(right),
+    );
+    let yes_no = |value: bool| Some(if value { "yes" } else { "no" }.to_string());
+    compare(
+        "Restart (keep alive)",
+        Runtime,
+        yes_no(left.keep_alive),
+        yes_no(right.keep_alive),
+    );
+    compare(
+        "Start at boot",
+        Runtime,
+        yes_no(left.run_at_load),
+        yes_no(right.run_at_load),
+    );
+    compare(
+        "Schedule",
+        Runtime,
+        left.schedule.as_ref().map(|s| s.display()),
+        right.schedule.as_ref().map(|s| s.display()),
+    );
+    let span = |secs: Option<u64>| secs.map(crate::humanize_secs);
+    compare("Jitter", Runtime, span(left.jitter), span(right.jitter));
+    compare(
+        "Accuracy",
+        Runtime,
+        span(left.accuracy),
+        span(right.accuracy),
+    );
+    compare(
+        "Catch up",
+        Runtime,
+        left.catch_up.and_then(yes_no),
+        right.catch_up.and_then(yes_no),
+    );
+    compare(
+        "After",
+        Runtime,
+        non_empty(left.after.clone()),
+        non_empty(right.after.clone()),
+    );
+    let conditions =
+        |s: &ServiceDetails| non_empty(s.conditions.iter().map(|c| c.to_systemd_line()).collect());
+    compare("Conditions", Runtime, conditions(left), conditions(right));
+    let directories = |s: &ServiceDetails| {
+        non_empty(
+            s.directories
+                .iter()
+                .map(|d| format!("{}={}", d.kind.directive(), d.name))
+                .collect(),
+        )
+    };
+    compare(
+        "Directories",
+        Runtime,
+        directories(left),
+        directories(right),
+    );
+    compare(
+        "OOMScoreAdjust",
+        Runtime,
+        left.oom_score_adjust.map(|s| s.to_string()),
+        right.oom_score_adjust.map(|s| s.to_string()),
+    );
+    compare(
+        "OOMPolicy",
+        Runtime,
+        left.oom_policy.map(|p| p.as_str().to_string()),
+        right.oom_policy.map(|p| p.as_str().to_string()),
+    );
+    compare(
+        "AmbientCapabilities",
+        Runtime,
+        non_empty(left.ambient_capabilities.clone()),
+        non_empty(right.ambient_capabilities.clone()),
+    );
+    compare(
+        "CapabilityBoundingSet",
+        Runtime,
+        left.capability_bounding_set
+            .as_ref()
+            .map(|set| set.join(" ")),
+        right
+            .capability_bounding_set
+            .as_ref()
+            .map(|set| set.join(" ")),
+    );
+    compare(
+        "PrivateNetwork",
+        Runtime,
+        yes_no(left.private_network),
+        yes_no(right.private_network),
+    );
+    compare(
+        "IPAddressAllow",
+        Runtime,
+        non_empty(left.ip_address_allow.clone()),
+        non_empty(right.ip_address_allow.clone()),
+    );
+    compare(
+        "IPAddressDeny",
+        Runtime,
+        non_empty(left.ip_address_deny.clone()),
+        non_empty(right.ip_address_deny.clone()),
+    );
+    compare(
+        "RestrictAddressFamilies",
+        Runtime,
+        non_empty(left.restrict_address_families.clone()),
+        non_empty(right.restrict_address_families.clone()),
+    );
+
+    compare(
+        "Description",
+        Cosmetic,
+        left.description.clone(),
+        right.description.clone(),
+    );
+    compare(
+        "Tags",
+        Cosmetic,
+        non_empty(left.tags.clone()),
+        non_empty(right.tags.clone()),
+    );
+    compare("Owner", Cosmetic, left.owner.clone(), right.owner.clone());
+    diffs
+}
+
+/// Parse a unit file or plist by its extension, regardless of which init
+/// system this machine runs. A unit's paired `.timer` supplies its schedule.
+pub fn load_file(path: &Path) -> Result<ServiceDetails> {
+    let contents =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut service = if path.extension().is_some_and(|ext| ext == "plist") {
+        crate::plist::parse_plist(&contents)?
+    } else {
+        let text = String::from_utf8(contents)
+            .with_context(|| format!("{} is not valid UTF-8", path.display()))?;
+        let mut service = crate::systemd::parse_systemd(&text)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if let Ok(timer) = std::fs::read_to_string(path.with_extension("timer")) {
+            crate::systemd::parse_timer_into(&mut service, &timer);
+        }
+        service
+    };
+    if service.name.is_empty() {
+        service.name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+    Ok(service)
+}
+
+/// Fetch a service's definition from another host over ssh: `systemctl cat`
+/// of the unit and its `.timer`, if any (so drop-ins are applied and the
+/// schedule comes along) on Linux, or the plist from the usual launchd
+/// directories on macOS.
+pub fn fetch_remote(host: &str, name: &str) -> Result<ServiceDetails> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "@._:-".contains(c))
+    {
+        bail!("Invalid service name '{name}'");
+    }
+    let stem = name.trim_end_matches(".service");
+    let script = format!(
+        "systemctl cat -- {name} 2>/dev/null && {{ systemctl cat -- {stem}.timer 2>/dev/null; true; }} || \
+         for f in ~/Library/LaunchAgents/{name}.plist /Library/LaunchAgents/{name}.plist /Library/LaunchDaemons/{name}.plist; do \
+         [ -f \"$f\" ] && exec plutil -convert xml1 -o - \"$f\"; done; exit 1"
+    );
+    let mut cmd = Command::new("ssh");
+    cmd.args(["-o", "BatchMode=yes", host, &script]);
+    crate::print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute ssh")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "Couldn't read '{name}' on {host}: {}",
+            match stderr.trim() {
+                "" => "no such service",
+                message => message,
+            }
+        );
+    }
+    let mut service = if output.stdout.starts_with(b"<?xml") {
+        crate::plist::parse_plist(&output.stdout)?
+    } else {
+        parse_systemctl_cat(&String::from_utf8_lossy(&output.stdout))
+            .with_context(|| format!("Failed to parse '{name}' from {host}"))?
+    };
+    service.name = stem.to_string();
+    Ok(service)
+}
+
+/// `systemctl cat` prints the unit file and each drop-in, each after a
+/// `# /path` header line; merge them as systemd would. Files of a `.timer`
+/// (the unit or its `.timer.d` drop-ins) are merged apart and give the
+/// schedule.
+fn parse_systemctl_cat(output: &str) -> Result<ServiceDetails> {
+    let mut files: Vec<(PathBuf, String)> = Vec::new();
+    for line in output.lines() {
+        match line.strip_prefix("# /") {
+            Some(path) if !path.contains(char::is_whitespace) => {
+                files.push((PathBuf::from(format!("/{path}")), String::new()));
+            }
+            _ => {
+                if let Some((_, contents)) = files.last_mut() {
+                    contents.push_str(line);
+                    contents.push('\n');
+                }
+            }
+        }
+    }
+    let (timer, unit): (Vec<_>, Vec<_>) = files.into_iter().partition(|(path, _)| {
+        path.extension().is_some_and(|ext| ext == "timer")
+            || path
+                .parent()
+                .is_some_and(|dir| dir.to_string_lossy().ends_with(".timer.d"))
+    });
+    let merged = crate::systemd::merge_unit_files(&unit);
+    let mut service = crate::systemd::parse_systemd(&crate::systemd::render_fields(&merged))?;
+    if !timer.is_empty() {
+        let merged = crate::systemd::merge_unit_files(&timer);
+        crate::systemd::parse_timer_into(&mut service, &crate::systemd::render_fields(&merged));
+    }
+    Ok(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separates_runtime_from_cosmetic_differences() {
+        let left = ServiceDetails {
+            name: "web".to_string(),
+            description: Some("Web".to_string()),
+            program: "/usr/bin/web".to_string(),
+            arguments: vec!["--port".to_string(), "80".to_string()],
+            env_vars: vec![("PORT".to_string(), "80".to_string())],
+            keep_alive: true,
+            ..Default::default()
+        };
+        let right = ServiceDetails {
+            name: "web-staging".to_string(),
+            description: Some("Web (staging)".to_string()),
+            env_vars: vec![
+                ("PORT".to_string(), "8080".to_string()),
+                ("DEBUG".to_string(), "1".to_string()),
+            ],
+            ..left.clone()
+        };
+        let diffs = diff(&left, &right);
+        let fields: Vec<(&str, Impact)> =
+            diffs.iter().map(|d| (d.field.as_str(), d.impact)).collect();
+        assert_eq!(
+            fields,
+            [
+                ("Environment PORT", Impact::Runtime),
+                ("Environment DEBUG", Impact::Runtime),
+                ("Description", Impact::Cosmetic),
+            ]
+        );
+        assert_eq!(diffs[1].left, None);
+        assert!(diff(&left, &left).is_empty());
+    }
+
+    #[test]
+    fn merges_systemctl_cat_output() {
+        let output = "# /lib/systemd/system/web.service\n[Service]\nExecStart=/usr/bin/web\nRestart=always\n\n# /etc/systemd/system/web.service.d/override.conf\n[Service]\nExecStart=\nExecStart=/usr/bin/web --port 9090\n";
+        let service = parse_systemctl_cat(output).unwrap();
+        assert_eq!(service.arguments, ["--port", "9090"]);
+        assert!(service.keep_alive);
+        assert!(service.schedule.is_none());
+
+        let output = "# /etc/systemd/system/backup.service\n[Service]\nType=oneshot\nExecStart=/usr/bin/backup\n\n# /etc/systemd/system/backup.timer\n[Timer]\nOnCalendar=daily\n\n# /etc/systemd/system/backup.timer.d/night.conf\n[Timer]\nOnCalendar=\nOnCalendar=*-*-* 03:00:00\n";
+        let service = parse_systemctl_cat(output).unwrap();
+        assert_eq!(service.program, "/usr/bin/backup");
+        let mut expected = ServiceDetails::default();
+        crate::systemd::parse_timer_into(&mut expected, "OnCalendar=*-*-* 03:00:00");
+        assert!(expected.schedule.is_some());
+        assert_eq!(
+            format!("{:?}", service.schedule),
+            format!("{:?}", expected.schedule)
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Opening a service's ports in the local firewall (`--open-firewall`), and
+//! closing them again when it's removed. Every rule ser adds is recorded in
+//! the state registry, so removal only undoes what ser did; a rule that was
+//! already there isn't recorded, and so is left in place.
+//!
+//! On Linux the rules are ufw or firewalld port rules for the ports the
+//! service declares. The macOS application firewall works per program
+//! rather than per port, so there the service's program is allowed instead.
+
+use crate::state::Registry;
+use crate::FsServiceDetails;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+const SOCKETFILTERFW: &str = "/usr/libexec/ApplicationFirewall/socketfilterfw";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Ufw,
+    Firewalld,
+    /// The macOS application firewall (`socketfilterfw`).
+    Macos,
+}
+
+/// One rule ser added: a port like `8080/tcp`, or a program path for the
+/// macOS application firewall.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    pub backend: Backend,
+    pub target: String,
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let backend = match self.backend {
+            Backend::Ufw => "ufw",
+            Backend::Firewalld => "firewalld",
+            Backend::Macos => "application firewall",
+        };
+        write!(f, "{} ({backend})", self.target)
+    }
+}
+
+/// The firewall in use here, if any: an active ufw or a running firewalld on
+/// Linux, or the application firewall on macOS. Fails when ufw is installed
+/// but won't say whether it's active, which it only tells root.
+pub fn detect() -> Result<Option<Backend>> {
+    if cfg!(target_os = "macos") {
+        return Ok(Path::new(SOCKETFILTERFW).exists().then_some(Backend::Macos));
+    }
+    if let Ok(output) = Command::new("ufw").arg("status").output() {
+        let text =
+            String::from_utf8_lossy(&output.stdout) + String::from_utf8_lossy(&output.stderr);
+        if output.status.success() && text.contains("Status: active") {
+            return Ok(Some(Backend::Ufw));
+        }
+        if text.contains("need to be root") {
+            bail!("ufw only reports its status to root; re-run with sudo to open the firewall");
+        }
+    }
+    let running = Command::new("firewall-cmd")
+        .arg("--state")
+        .output()
+        .is_ok_and(|o| {
+            o.status.success() && String::from_utf8_lossy(&o.stdout).contains("running")
+        });
+    Ok(running.then_some(Backend::Firewalld))
+}
+
+/// The rules that would let `details` through `backend`.
+pub fn rules_for(backend: Backend, details: &FsServiceDetails) -> Vec<Rule> {
+    match backend {
+        Backend::Macos => vec![Rule {
+            backend,
+            target: details.service.program.clone(),
+        }],
+        Backend::Ufw | Backend::Firewalld => crate::ports::declared_ports(details, &[])
+            .into_iter()
+            .map(|port| Rule {
+                backend,
+                target: format!("{port}/tcp"),
+            })
+            .collect(),
+    }
+}
+
+/// Open the firewall for service `name` and record the rules. Rules already
+/// recorded for it are left alone, and rules the firewall already has are
+/// neither added nor recorded, so removing the service won't close them.
+/// Returns the rules added.
+pub fn open(name: &str, details: &FsServiceDetails) -> Result<Vec<Rule>> {
+    let Some(backend) = detect()? else {
+        bail!("No active firewall found (ufw, firewalld, or the macOS application firewall)");
+    };
+    let recorded = Registry::load()?
+        .service(name)
+        .map(|s| s.firewall.clone())
+        .unwrap_or_default();
+    let wanted = rules_for(backend, details);
+    if wanted.is_empty() && recorded.is_empty() {
+        bail!("'{name}' declares no port to open (a --port argument, PORT, or a socket's ListenStream=)");
+    }
+    let mut rules = Vec::new();
+    for rule in wanted.into_iter().filter(|rule| !recorded.contains(rule)) {
+        if exists(&rule)? {
+            eprintln!("{rule} is already open; leaving it to whoever opened it.");
+            continue;
+        }
+        apply(&rule, true)?;
+        // Record as we go, so a later failure doesn't orphan this rule.
+        Registry::update(|registry| {
+            registry
+                .services
+                .entry(name.to_string())
+                .or_default()
+                .firewall
+                .push(rule.clone());
+        })?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// Whether the firewall already has `rule`, however it got there.
+fn exists(rule: &Rule) -> Result<bool> {
+    let (program, args): (&str, Vec<String>) = match rule.backend {
+        Backend::Ufw => ("ufw", vec!["show".to_string(), "added".to_string()]),
+        Backend::Firewalld => (
+            "firewall-cmd",
+            vec![
+                "--permanent".to_string(),
+                format!("--query-port={}", rule.target),
+            ],
+        ),
+        Backend::Macos => (SOCKETFILTERFW, vec!["--listapps".to_string()]),
+    };
+    let mut cmd = Command::new(program);
+    cmd.args(&args);
+    crate::print_command(&cmd);
+    let output = crate::interrupt::query(&mut cmd)
+        .with_context(|| format!("Failed to execute {program}"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(match rule.backend {
+        Backend::Ufw => ufw_has_rule(&stdout, &rule.target),
+        // Exits 0 for "yes" and 1 for "no".
+        Backend::Firewalld => output.status.success(),
+        Backend::Macos => stdout
+            .lines()
+            .any(|line| line.trim_end().ends_with(&rule.target)),
+    })
+}
+
+/// Whether `ufw show added` lists an `allow` for `target`.
+fn ufw_has_rule(added: &str, target: &str) -> bool {
+    added
+        .lines()
+        .any(|line| line.split_whitespace().eq(["ufw", "allow", target]))
+}
+
+/// Remove every rule recorded for service `name`. Returns the rules removed.
+pub fn close(name: &str) -> Result<Vec<Rule>> {
+    let recorded = Registry::load()?
+        .service(name)
+        .map(|s| s.firewall.clone())
+        .unwrap_or_default();
+    for rule in &recorded {
+        apply(rule, false)?;
+        Registry::update(|registry| {
+            if let Some(state) = registry.services.get_mut(name) {
+                state.firewall.retain(|r| r != rule);
+            }
+        })?;
+    }
+    Ok(recorded)
+}
+
+fn apply(rule: &Rule, open: bool) -> Result<()> {
+    let commands: Vec<Vec<String>> = match rule.backend {
+        Backend::Ufw => {
+            let mut args = vec!["ufw".to_string()];
+            if !open {
+                args.push("delete".to_string());
+            }
+            args.extend(["allow".to_string(), rule.target.clone()]);
+            vec![args]
+        }
+        Backend::Firewalld => {
+            let flag = if open { "--add-port" } else { "--remove-port" };
+            let flag = format!("{flag}={}", rule.target);
+            // Runtime and permanent configuration are separate.
+            vec![
+                vec!["firewall-cmd".to_string(), flag.clone()],
+                vec!["firewall-cmd".to_string(), "--permanent".to_string(), flag],
+            ]
+        }
+        Backend::Macos if open => vec![
+            vec![
+                SOCKETFILTERFW.to_string(),
+                "--add".to_string(),
+                rule.target.clone(),
+            ],
+            vec![
+                SOCKETFILTERFW.to_string(),
+                "--unblockapp".to_string(),
+                rule.target.clone(),
+            ],
+        ],
+        Backend::Macos => vec![vec![
+            SOCKETFILTERFW.to_string(),
+            "--remove".to_string(),
+            rule.target.clone(),
+        ]],
+    };
+    for args in commands {
+        let mut cmd = Command::new(&args[0]);
+        cmd.args(&args[1..]);
+        crate::print_command(&cmd);
+        let output = crate::interrupt::output(&mut cmd)
+            .with_context(|| format!("Failed to execute {}", args[0]))?;
+        if !output.status.success() {
+            bail!(
+                "Failed to {} {rule}: {} (changing the firewall needs root; try sudo)",
+                if open { "open" } else { "close" },
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ServiceDetails;
+
+    #[test]
+    fn rules_follow_the_backend() {
+        let details = FsServiceDetails {
+            service: ServiceDetails {
+                program: "/usr/local/bin/web".to_string(),
+                arguments: vec!["--port".to_string(), "8080".to_string()],
+                ..Default::default()
+            },
+            path: String::new(),
+            enabled: false,
+            running: false,
+            pid: None,
+            trigger: None,
+        };
+        let ufw = rules_for(Backend::Ufw, &details);
+        assert_eq!(ufw.len(), 1);
+        assert_eq!(ufw[0].to_string(), "8080/tcp (ufw)");
+        assert_eq!(
+            rules_for(Backend::Macos, &details)[0].target,
+            "/usr/local/bin/web"
+        );
+
+        let added = "Added user rules (see 'ufw status' for running firewall):\nufw allow 22/tcp\nufw allow 8080/tcp\nufw deny 9090/tcp\n";
+        assert!(ufw_has_rule(added, "8080/tcp"));
+        assert!(!ufw_has_rule(added, "9090/tcp"));
+        assert!(!ufw_has_rule(added, "80/tcp"));
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// An advisory, process-wide lock on a service file, held until dropped.
+///
+/// The lock lives in a separate file in a directory every user shares (not
+/// on the unit file itself) so editors that replace files atomically don't
+/// lose it, and so a CI user and root working on the same service wait for
+/// each other. It only coordinates `ser` invocations; other tools don't
+/// honor it.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Block until the lock for `target` is available.
+    pub fn acquire(target: &Path) -> Result<FileLock> {
+        let file = open_lock_file(target)?;
+        file.lock()
+            .with_context(|| format!("Failed to lock {}", target.display()))?;
+        Ok(FileLock { _file: file })
+    }
+
+    /// Take the lock for `target` if it's free; `None` if another process holds it.
+    pub fn try_acquire(target: &Path) -> Result<Option<FileLock>> {
+        let file = open_lock_file(target)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(FileLock { _file: file })),
+            Err(fs::TryLockError::WouldBlock) => Ok(None),
+            Err(fs::TryLockError::Error(e)) => {
+                Err(e).with_context(|| format!("Failed to lock {}", target.display()))
+            }
+        }
+    }
+}
+
+/// The lock target serializing operations on the service `name` (start,
+/// stop, restart, ...), as opposed to edits of its file.
+pub fn service_lock_target(name: &str) -> PathBuf {
+    PathBuf::from(format!("service:{name}"))
+}
+
+/// Where lock files live: `/run/lock/ser` (`/tmp/ser-locks` without
+/// `/run/lock`), shared by every user like `/run/lock` itself.
+#[cfg(unix)]
+fn lock_dir() -> PathBuf {
+    if Path::new("/run/lock").is_dir() {
+        PathBuf::from("/run/lock/ser")
+    } else {
+        PathBuf::from("/tmp/ser-locks")
+    }
+}
+
+#[cfg(not(unix))]
+fn lock_dir() -> PathBuf {
+    std::env::temp_dir().join("ser-locks")
+}
+
+fn open_lock_file(target: &Path) -> Result<File> {
+    let dir = lock_dir();
+    if !dir.is_dir() {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create lock directory: {}", dir.display()))?;
+        // World-writable and sticky, so other users can add their own lock
+        // files but not remove anyone else's.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(&dir, fs::Permissions::from_mode(0o1777));
+        }
+    }
+    let path = dir.join(lock_file_name(target));
+    // Never follow a link planted in the shared directory.
+    let options = |create: bool| {
+        let mut options = OpenOptions::new();
+        options
+            .read(true)
+            .write(create)
+            .create(create)
+            .truncate(false);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o644).custom_flags(libc::O_NOFOLLOW);
+        }
+        options
+    };
+    // Reading is enough to lock, so a lock file another user created can
+    // still be used.
+    match options(true).open(&path) {
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => options(false).open(&path),
+        result => result,
+    }
+    .with_context(|| format!("Failed to open lock file: {}", path.display()))
+}
+
+/// Flatten a path into a single file name, e.g. `/etc/a.service` -> `%etc%a.service.lock`.
+fn lock_file_name(target: &Path) -> String {
+    format!("{}.lock", target.to_string_lossy().replace('/', "%"))
+}
+
+/// What a file looked like when it was read, to detect changes made by someone
+/// else before it gets overwritten.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSnapshot {
+    pub path: PathBuf,
+    pub checksum: Option<u64>,
+}
+
+impl FileSnapshot {
+    /// Record the current state of `path`. A missing file is a valid snapshot.
+    pub fn take(path: &Path) -> FileSnapshot {
+        FileSnapshot {
+            path: path.to_path_buf(),
+            checksum: fs::read(path).ok().map(|bytes| checksum(&bytes)),
+        }
+    }
+
+    /// Whether the file's contents differ from when the snapshot was taken.
+    /// Only the checksum decides: a touch without a content change is
+    /// ignored, and a change within the mtime's granularity isn't missed.
+    pub fn has_changed(&self) -> bool {
+        FileSnapshot::take(&self.path).checksum != self.checksum
+    }
+}
+
+/// A fast, non-cryptographic content checksum for change detection.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_detects_content_changes() {
+        let path = std::env::temp_dir().join(format!("ser-guard-test-{}", std::process::id()));
+        fs::write(&path, "one").unwrap();
+        let snapshot = FileSnapshot::take(&path);
+        assert!(!snapshot.has_changed());
+
+        // Right away, so the mtime may well be the same.
+        fs::write(&path, "two").unwrap();
+        assert!(snapshot.has_changed());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn lock_is_exclusive() {
+        let target = Path::new("/nonexistent/ser-guard-lock-test.service");
+        let held = FileLock::try_acquire(target).unwrap();
+        assert!(held.is_some());
+        // A second handle in the same process is refused while the first is held.
+        assert!(FileLock::try_acquire(target).unwrap().is_none());
+        drop(held);
+        assert!(FileLock::try_acquire(target).unwrap().is_some());
+    }
+}
@@ -0,0 +1,328 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Samples older than this are dropped when the store is next written.
+const RETENTION_SECS: i64 = 32 * 24 * 3600;
+
+/// One observation of a service, as recorded by `ser sample`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// Unix timestamp, in seconds.
+    pub time: i64,
+    pub service: String,
+    pub running: bool,
+    /// CPU use, where 100.0 is one full core.
+    pub cpu: Option<f64>,
+    /// Memory in use, in bytes.
+    pub memory: Option<u64>,
+    /// The main process while running; a new one means it restarted.
+    pub pid: Option<u32>,
+}
+
+impl Sample {
+    fn to_line(&self) -> String {
+        let state = if self.running { "running" } else { "stopped" };
+        let cpu = self.cpu.map_or("-".to_string(), |c| c.to_string());
+        let memory = self.memory.map_or("-".to_string(), |m| m.to_string());
+        let pid = self.pid.map_or("-".to_string(), |p| p.to_string());
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.time, self.service, state, cpu, memory, pid
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Sample> {
+        let mut fields = line.split('\t');
+        let time = fields.next()?.parse().ok()?;
+        let service = fields.next()?.to_string();
+        let running = fields.next()? == "running";
+        // Usage and pid columns were added later; older lines simply lack them.
+        let cpu = fields.next().and_then(|f| f.parse().ok());
+        let memory = fields.next().and_then(|f| f.parse().ok());
+        let pid = fields.next().and_then(|f| f.parse().ok());
+        Some(Sample {
+            time,
+            service,
+            running,
+            cpu,
+            memory,
+            pid,
+        })
+    }
+}
+
+/// The sample store: a TSV file (`time  service  state  cpu  memory  pid`)
+/// under the state directory, appended to by `ser sample`.
+pub fn store_path() -> Option<PathBuf> {
+    crate::config::state_dir().map(|dir| dir.join("samples.tsv"))
+}
+
+/// Append samples, pruning ones past the retention window first if needed.
+pub fn record(samples: &[Sample]) -> Result<()> {
+    let path = store_path().ok_or_else(|| anyhow!("No state directory available"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let _lock = crate::guard::FileLock::acquire(&path)?;
+    let now = chrono::Utc::now().timestamp();
+    prune(&path, now - RETENTION_SECS)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    for sample in samples {
+        writeln!(file, "{}", sample.to_line())?;
+    }
+    Ok(())
+}
+
+/// Rewrite the store without samples older than `cutoff`, if it has any.
+fn prune(path: &std::path::Path, cutoff: i64) -> Result<()> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Ok(());
+    };
+    let oldest = contents.lines().next().and_then(Sample::from_line);
+    if oldest.is_none_or(|s| s.time >= cutoff) {
+        return Ok(());
+    }
+    let kept: String = contents
+        .lines()
+        .filter(|line| Sample::from_line(line).is_some_and(|s| s.time >= cutoff))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    fs::write(path, kept).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Samples for `service` taken at or after `since`, oldest first.
+pub fn load(service: &str, since: i64) -> Result<Vec<Sample>> {
+    let Some(path) = store_path() else {
+        return Ok(Vec::new());
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    Ok(contents
+        .lines()
+        .filter_map(Sample::from_line)
+        .filter(|s| s.service == service && s.time >= since)
+        .collect())
+}
+
+/// Parse a look-back window like `24h`, `7d`, or `4w` into seconds.
+pub fn parse_window(window: &str) -> Option<i64> {
+    let window = window.trim();
+    let split = window.find(|c: char| !c.is_ascii_digit())?;
+    let (digits, unit) = window.split_at(split);
+    let value: i64 = digits.parse().ok()?;
+    let multiplier = match unit {
+        "m" | "min" => 60,
+        "h" => 3600,
+        "d" => 24 * 3600,
+        "w" => 7 * 24 * 3600,
+        _ => return None,
+    };
+    Some(value * multiplier)
+}
+
+/// A stretch of time a service was seen stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    pub start: i64,
+    /// When it was next seen running; `None` if it still isn't.
+    pub end: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Availability {
+    /// Share of samples in which the service was running, 0.0 to 100.0.
+    pub percent: f64,
+    pub samples: usize,
+    pub incidents: Vec<Incident>,
+}
+
+/// Availability over a series of samples (oldest first). `None` without samples.
+pub fn availability(samples: &[Sample]) -> Option<Availability> {
+    if samples.is_empty() {
+        return None;
+    }
+    let running = samples.iter().filter(|s| s.running).count();
+    let mut incidents: Vec<Incident> = Vec::new();
+    for sample in samples {
+        match incidents.last_mut() {
+            Some(open) if open.end.is_none() && sample.running => {
+                open.end = Some(sample.time);
+            }
+            Some(open) if open.end.is_none() => {}
+            _ if !sample.running => incidents.push(Incident {
+                start: sample.time,
+                end: None,
+            }),
+            _ => {}
+        }
+    }
+    Some(Availability {
+        percent: running as f64 * 100.0 / samples.len() as f64,
+        samples: samples.len(),
+        incidents,
+    })
+}
+
+/// When a service restarted: the samples (oldest first) where it was running
+/// under a different main process than the last time it was seen running.
+/// Restarts between two samples of the same process can't be seen, and
+/// samples without a pid are skipped.
+pub fn restarts(samples: &[Sample]) -> Vec<i64> {
+    let mut last_pid = None;
+    let mut times = Vec::new();
+    for sample in samples.iter().filter(|s| s.running) {
+        let Some(pid) = sample.pid else { continue };
+        if last_pid.is_some_and(|last| last != pid) {
+            times.push(sample.time);
+        }
+        last_pid = Some(pid);
+    }
+    times
+}
+
+/// Distribution of a series of values, for `ser stats`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub min: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+/// Min, median, 95th percentile, and max of `values` (nearest-rank).
+pub fn summarize(values: &[f64]) -> Option<Summary> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let rank =
+        |p: f64| sorted[((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1];
+    Some(Summary {
+        min: sorted[0],
+        p50: rank(0.5),
+        p95: rank(0.95),
+        max: sorted[sorted.len() - 1],
+    })
+}
+
+/// Render `values` as a sparkline at most `width` characters wide, averaging
+/// neighbouring values into buckets when there are more values than columns.
+pub fn sparkline(values: &[f64], width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    if values.is_empty() || width == 0 {
+        return String::new();
+    }
+    let per_bucket = values.len().div_ceil(width);
+    let buckets: Vec<f64> = values
+        .chunks(per_bucket)
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect();
+    let min = buckets.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = buckets.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    buckets
+        .iter()
+        .map(|v| {
+            if range <= 0.0 {
+                BARS[0]
+            } else {
+                BARS[(((v - min) / range) * 7.0).round() as usize]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: i64, running: bool) -> Sample {
+        Sample {
+            time,
+            service: "web".to_string(),
+            running,
+            cpu: None,
+            memory: None,
+            pid: running.then_some(100),
+        }
+    }
+
+    #[test]
+    fn computes_availability_and_incidents() {
+        let samples = vec![
+            sample(0, true),
+            sample(60, false),
+            sample(120, false),
+            sample(180, true),
+            sample(240, false),
+        ];
+        let report = availability(&samples).unwrap();
+        assert_eq!(report.percent, 40.0);
+        assert_eq!(
+            report.incidents,
+            vec![
+                Incident {
+                    start: 60,
+                    end: Some(180)
+                },
+                Incident {
+                    start: 240,
+                    end: None
+                },
+            ]
+        );
+        assert_eq!(
+            Sample::from_line(&samples[0].to_line()),
+            Some(samples[0].clone())
+        );
+    }
+
+    #[test]
+    fn finds_restarts_by_pid() {
+        let mut samples = vec![
+            sample(0, true),
+            sample(60, true),
+            sample(120, false),
+            sample(180, true),
+            sample(240, true),
+        ];
+        samples[1].pid = Some(101);
+        samples[3].pid = Some(102);
+        samples[4].pid = Some(102);
+        assert_eq!(restarts(&samples), vec![60, 180]);
+        let old = Sample::from_line("10\tweb\trunning\t2.5\t1024").unwrap();
+        assert_eq!(old.pid, None);
+    }
+
+    #[test]
+    fn summarizes_usage() {
+        let values: Vec<f64> = (1..=100).map(f64::from).collect();
+        let summary = summarize(&values).unwrap();
+        assert_eq!(
+            (summary.min, summary.p50, summary.p95, summary.max),
+            (1.0, 50.0, 95.0, 100.0)
+        );
+        assert_eq!(sparkline(&[0.0, 7.0, 3.5], 10), "▁█▅");
+        assert_eq!(sparkline(&values, 4).chars().count(), 4);
+        let line = Sample::from_line("10\tweb\trunning\t2.5\t1024").unwrap();
+        assert_eq!((line.cpu, line.memory), (Some(2.5), Some(1024)));
+    }
+
+    #[test]
+    fn parses_windows() {
+        assert_eq!(parse_window("24h"), Some(86400));
+        assert_eq!(parse_window("7d"), Some(604800));
+        assert_eq!(parse_window("day"), None);
+    }
+}
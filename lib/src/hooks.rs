@@ -0,0 +1,200 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Shell commands the CLI runs around lifecycle operations, e.g. database
+/// migrations before starting an app. They run locally, as the invoking user,
+/// not under the init system.
+///
+/// ```toml
+/// # config.toml
+/// [hooks.web]
+/// pre_start = "./bin/migrate"
+/// post_stop = "curl -X POST https://chat.example.com/hooks/web-stopped"
+/// # `ser deploy`: point the proxy at $SER_PORT
+/// switch = "sed -i \"s/127.0.0.1:[0-9]*/127.0.0.1:$SER_PORT/\" /etc/caddy/web && systemctl reload caddy"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    pub pre_start: Option<String>,
+    pub post_start: Option<String>,
+    pub pre_stop: Option<String>,
+    pub post_stop: Option<String>,
+    /// Repoint a reverse proxy at the instance on `SER_PORT`, for `ser deploy`.
+    pub switch: Option<String>,
+    /// Where to run the commands; the project directory for `.ser.toml` hooks.
+    #[serde(skip)]
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookPoint {
+    PreStart,
+    PostStart,
+    PreStop,
+    PostStop,
+    Switch,
+}
+
+impl HookPoint {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookPoint::PreStart => "pre_start",
+            HookPoint::PostStart => "post_start",
+            HookPoint::PreStop => "pre_stop",
+            HookPoint::PostStop => "post_stop",
+            HookPoint::Switch => "switch",
+        }
+    }
+}
+
+impl Hooks {
+    pub fn command(&self, point: HookPoint) -> Option<&str> {
+        match point {
+            HookPoint::PreStart => self.pre_start.as_deref(),
+            HookPoint::PostStart => self.post_start.as_deref(),
+            HookPoint::PreStop => self.pre_stop.as_deref(),
+            HookPoint::PostStop => self.post_stop.as_deref(),
+            HookPoint::Switch => self.switch.as_deref(),
+        }
+        .filter(|command| !command.trim().is_empty())
+    }
+
+    /// Run the hook for `point`, if any, via `sh -c` with `SER_SERVICE` and
+    /// `SER_HOOK` set. Output is captured and included in the error if the
+    /// command fails.
+    pub fn run(&self, point: HookPoint, service: &str) -> Result<()> {
+        self.run_with_env(point, service, &[])
+    }
+
+    /// [`Hooks::run`] with extra environment variables, e.g. `SER_PORT` for
+    /// the switch hook.
+    pub fn run_with_env(
+        &self,
+        point: HookPoint,
+        service: &str,
+        env: &[(&str, &str)],
+    ) -> Result<()> {
+        let Some(command) = self.command(point) else {
+            return Ok(());
+        };
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command])
+            .env("SER_SERVICE", service)
+            .env("SER_HOOK", point.as_str())
+            .envs(env.iter().copied());
+        if let Some(dir) = &self.dir {
+            cmd.current_dir(dir);
+        }
+        crate::print_command(&cmd);
+        let output = crate::interrupt::output(&mut cmd)
+            .with_context(|| format!("Failed to run {} hook: {command}", point.as_str()))?;
+        if !output.status.success() {
+            let mut captured = String::from_utf8_lossy(&output.stdout).into_owned();
+            captured.push_str(&String::from_utf8_lossy(&output.stderr));
+            bail!(
+                "{} hook for '{}' failed ({}): {}\n{}",
+                point.as_str(),
+                service,
+                output.status,
+                command,
+                captured.trim_end()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Hooks for a service from the config file. A `.ser.toml` is never picked
+/// up from the current directory: anyone can leave one in /tmp or a cloned
+/// repo, and its hooks would run as whoever runs ser, root under sudo. Name
+/// one explicitly with [`project_hooks`].
+pub fn hooks_for(service: &str) -> Hooks {
+    let name = crate::platform::normalize_service_name(service);
+    crate::config::settings()
+        .hooks
+        .get(name)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Hooks for a service from a `.ser.toml` named on the command line, or from
+/// the config file if it has none for the service. The file must belong to
+/// the invoking user and be writable by no one else.
+pub fn project_hooks(service: &str, path: &Path) -> Result<Hooks> {
+    check_trusted(path)?;
+    let name = crate::platform::normalize_service_name(service);
+    Ok(crate::spec::load_project_hooks(path)?
+        .remove(name)
+        .unwrap_or_else(|| hooks_for(service)))
+}
+
+#[cfg(unix)]
+fn check_trusted(path: &Path) -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let meta =
+        std::fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    // SAFETY: geteuid has no preconditions and can't fail.
+    let uid = unsafe { libc::geteuid() };
+    if meta.uid() != uid {
+        bail!(
+            "Not running hooks from {}: it belongs to uid {}, not the user running ser (uid {uid})",
+            path.display(),
+            meta.uid()
+        );
+    }
+    if meta.mode() & 0o022 != 0 {
+        bail!(
+            "Not running hooks from {}: others can write to it (fix: chmod go-w {})",
+            path.display(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_trusted(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failing_hook_reports_output() {
+        let hooks = Hooks {
+            pre_start: Some("echo migrating; exit 3".to_string()),
+            post_start: Some("true".to_string()),
+            ..Default::default()
+        };
+        assert!(hooks.run(HookPoint::PostStart, "web").is_ok());
+        assert!(hooks.run(HookPoint::PreStop, "web").is_ok());
+        let err = hooks.run(HookPoint::PreStart, "web").unwrap_err();
+        assert!(err.to_string().contains("migrating"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn project_hooks_must_be_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("ser-hooks-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".ser.toml");
+        std::fs::write(
+            &path,
+            "[services.web]\ncommand = \"/bin/web\"\n[services.web.hooks]\npre_start = \"true\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o666)).unwrap();
+        assert!(project_hooks("web", &path).is_err());
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let hooks = project_hooks("web.service", &path).unwrap();
+        assert_eq!(hooks.pre_start.as_deref(), Some("true"));
+        assert_eq!(hooks.dir.as_deref(), Some(&*dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,403 @@
+//! Stopping the tools `ser` runs (systemctl, launchctl, journalctl, ...) when
+//! `ser` itself is interrupted, so a Ctrl-C, `kill`, or closed terminal
+//! doesn't leave them running on their own.
+//!
+//! Children started through [`output`] and [`status`] are tracked while they
+//! run. Once [`install_handlers`] has been called (the `ser` binary does; a
+//! program embedding the library keeps its own signal handling unless it
+//! opts in), a SIGINT, SIGTERM, or SIGHUP stops them and makes each call that
+//! was running fail as interrupted, so the operation unwinds (releasing its
+//! locks) instead of carrying on to its next step. Later calls run as usual.
+//! With no child running, the signal is handled as it would be without
+//! `ser`'s handler.
+//!
+//! Read-only questions for the init system go through [`query`] instead,
+//! which also gives up after `[backend] timeout_secs`, so a hung systemctl
+//! (D-Bus trouble, say) fails with the command that stalled instead of
+//! hanging `ser`; `retries` retries those and calls that couldn't reach the
+//! init system. Everything else (starts that wait for the service, hooks,
+//! `ssh`, `chown -R`) runs for as long as it takes: killing it halfway, or
+//! running it twice, would leave things worse than waiting.
+//!
+//! Signals are a Unix matter: on Windows, Ctrl-C already reaches the whole
+//! console, children included, so only the timeouts apply there.
+
+use std::io::{self, Read};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+#[cfg(unix)]
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+#[cfg(unix)]
+use std::sync::Once;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How many signals have stopped tracked children; each call compares it
+/// with the count when it started.
+static INTERRUPTS: AtomicU32 = AtomicU32::new(0);
+/// Pids of the children to stop; 0 marks a free slot.
+static CHILDREN: [AtomicI32; 16] = [const { AtomicI32::new(0) }; 16];
+/// The handler each of [`SIGNALS`] had before `ser`'s, to fall back on.
+#[cfg(unix)]
+static PREVIOUS: [AtomicUsize; 3] = [const { AtomicUsize::new(0) }; 3];
+#[cfg(unix)]
+static INSTALL: Once = Once::new();
+
+#[cfg(unix)]
+const SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+/// Whether `ser` has been interrupted while a tracked child was running.
+pub fn interrupted() -> bool {
+    INTERRUPTS.load(Ordering::SeqCst) > 0
+}
+
+/// Whether `error` came from a call that timed out or was interrupted, as
+/// opposed to the tool's own answer, so callers don't reword it as e.g.
+/// "not found".
+pub fn is_timeout_or_interrupt(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<io::Error>().is_some_and(|e| {
+            matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+            )
+        })
+    })
+}
+
+/// Messages from systemctl and launchctl that mean the init system couldn't
+/// be reached this time, rather than that the request was refused.
+const TRANSIENT: &[&str] = &[
+    "Failed to connect to bus",
+    "Connection timed out",
+    "Transport endpoint is not connected",
+    "Resource temporarily unavailable",
+    "Operation now in progress",
+];
+
+/// [`Command::output`], stopping the child if `ser` is interrupted meanwhile.
+pub fn output(cmd: &mut Command) -> io::Result<Output> {
+    output_within(cmd, None)
+}
+
+/// [`Command::status`], stopping the child if `ser` is interrupted meanwhile.
+pub fn status(cmd: &mut Command) -> io::Result<ExitStatus> {
+    let mut child = cmd.spawn()?;
+    let tracked = StopOnInterrupt::track(&child);
+    let status = wait(&mut child, cmd, None);
+    tracked.finish()?;
+    status
+}
+
+/// [`output`] for a read-only question to the init system (`systemctl
+/// show`, `launchctl list`, `sc query`), which is safe to give up on and ask
+/// again: within the configured timeout, retried while it times out or
+/// can't reach the init system.
+pub fn query(cmd: &mut Command) -> io::Result<Output> {
+    let backend = &crate::config::settings().backend;
+    let mut retries = backend.retries;
+    loop {
+        let result = output_within(cmd, backend.timeout());
+        let transient = match &result {
+            Err(e) => e.kind() == io::ErrorKind::TimedOut,
+            Ok(output) => is_transient_failure(output),
+        };
+        if !transient || retries == 0 {
+            return result;
+        }
+        retries -= 1;
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn is_transient_failure(output: &Output) -> bool {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    !output.status.success() && TRANSIENT.iter().any(|m| stderr.contains(m))
+}
+
+fn output_within(cmd: &mut Command, timeout: Option<Duration>) -> io::Result<Output> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = cmd.spawn()?;
+    let tracked = StopOnInterrupt::track(&child);
+    // Drain both pipes while waiting, so a chatty child can't block on a
+    // full pipe.
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        })
+    };
+    let stdout = read(child.stdout.take().map(|p| Box::new(p) as _));
+    let stderr = read(child.stderr.take().map(|p| Box::new(p) as _));
+    let status = wait(&mut child, cmd, timeout);
+    tracked.finish()?;
+    Ok(Output {
+        status: status?,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Wait for `child`, killing it once `timeout` passes.
+fn wait(child: &mut Child, cmd: &Command, timeout: Option<Duration>) -> io::Result<ExitStatus> {
+    let Some(timeout) = timeout else {
+        return child.wait();
+    };
+    let deadline = Instant::now() + timeout;
+    // Most calls finish in milliseconds; back off for the slow ones.
+    let mut pause = Duration::from_millis(1);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "backend timed out after {}s: {} (raise [backend] timeout_secs in the config \
+                     if it's just slow)",
+                    timeout.as_secs(),
+                    crate::command_line(cmd)
+                ),
+            ));
+        }
+        thread::sleep(pause);
+        pause = (pause * 2).min(Duration::from_millis(50));
+    }
+}
+
+/// Tracks a child for as long as it's alive: an interrupt stops it rather
+/// than killing `ser`. [`StopOnInterrupt::new`] also stops it once stdout is
+/// closed (`ser logs -f | head`), for children that run until stopped.
+pub struct StopOnInterrupt {
+    slot: Option<usize>,
+    /// [`INTERRUPTS`] when tracking began.
+    seen: u32,
+    /// The stdout watcher of [`StopOnInterrupt::new`], ended when dropped.
+    watcher: Option<Watcher>,
+}
+
+impl StopOnInterrupt {
+    pub fn new(child: &Child) -> StopOnInterrupt {
+        let mut tracked = StopOnInterrupt::track(child);
+        tracked.watcher = watch_stdout();
+        tracked
+    }
+
+    /// Track `child` without watching stdout, for children that exit on
+    /// their own.
+    pub(crate) fn track(child: &Child) -> StopOnInterrupt {
+        let seen = INTERRUPTS.load(Ordering::SeqCst);
+        let pid = child.id() as i32;
+        let slot = CHILDREN.iter().position(|slot| {
+            slot.compare_exchange(0, pid, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        });
+        StopOnInterrupt {
+            slot,
+            seen,
+            watcher: None,
+        }
+    }
+
+    /// Whether a signal stopped the child since it was tracked.
+    pub fn interrupted(&self) -> bool {
+        INTERRUPTS.load(Ordering::SeqCst) != self.seen
+    }
+
+    /// Stop tracking, failing as interrupted if a signal stopped the child.
+    pub(crate) fn finish(self) -> io::Result<()> {
+        if self.interrupted() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StopOnInterrupt {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot {
+            CHILDREN[slot].store(0, Ordering::SeqCst);
+        }
+        if let Some(watcher) = self.watcher.take() {
+            watcher.stop();
+        }
+    }
+}
+
+/// Install the SIGINT, SIGTERM, and SIGHUP handlers that stop tracked
+/// children instead of leaving them behind. Idempotent.
+#[cfg(unix)]
+pub fn install_handlers() {
+    INSTALL.call_once(|| {
+        let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        for (signal, previous) in SIGNALS.into_iter().zip(&PREVIOUS) {
+            // A handler rather than SIG_IGN: children must not inherit an
+            // ignored SIGINT, so Ctrl-C reaches them too.
+            let old = unsafe { libc::signal(signal, handler) };
+            if old == libc::SIG_IGN {
+                // Ignored on purpose (nohup, a background job): keep it so.
+                unsafe { libc::signal(signal, libc::SIG_IGN) };
+            }
+            previous.store(old, Ordering::SeqCst);
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn install_handlers() {}
+
+/// Only async-signal-safe calls: this runs in a signal handler.
+#[cfg(unix)]
+extern "C" fn on_signal(signal: libc::c_int) {
+    let mut stopped = false;
+    for slot in &CHILDREN {
+        let pid = slot.load(Ordering::SeqCst);
+        if pid > 0 {
+            unsafe { libc::kill(pid, libc::SIGTERM) };
+            stopped = true;
+        }
+    }
+    if stopped {
+        INTERRUPTS.fetch_add(1, Ordering::SeqCst);
+        return;
+    }
+    let index = SIGNALS.iter().position(|&s| s == signal).unwrap_or(0);
+    let previous = PREVIOUS[index].load(Ordering::SeqCst);
+    if previous == libc::SIG_DFL || previous == libc::SIG_ERR {
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    } else if previous != libc::SIG_IGN {
+        let previous: extern "C" fn(libc::c_int) = unsafe { std::mem::transmute(previous) };
+        previous(signal);
+    }
+}
+
+/// A thread waiting for stdout to close, and the pipe that wakes it to end.
+#[cfg(unix)]
+struct Watcher {
+    wake: io::PipeWriter,
+    thread: thread::JoinHandle<()>,
+}
+
+#[cfg(unix)]
+impl Watcher {
+    /// End the thread and wait for it.
+    fn stop(self) {
+        // Closing the pipe wakes the thread's poll.
+        drop(self.wake);
+        let _ = self.thread.join();
+    }
+}
+
+#[cfg(not(unix))]
+struct Watcher;
+
+#[cfg(not(unix))]
+impl Watcher {
+    fn stop(self) {}
+}
+
+/// Stop the tracked children once whatever reads our stdout goes away.
+/// Otherwise a quiet log would keep both processes around until its next
+/// line failed to write. `None` if the wake-up pipe can't be made.
+#[cfg(unix)]
+fn watch_stdout() -> Option<Watcher> {
+    use std::os::fd::AsRawFd;
+
+    let (woken, wake) = io::pipe().ok()?;
+    let thread = thread::spawn(move || {
+        // With no events asked for, stdout only wakes poll for POLLERR or
+        // POLLHUP, which a pipe reports once its reader has exited. The wake
+        // pipe reads as at end of file once its writer is closed.
+        let mut fds = [
+            libc::pollfd {
+                fd: libc::STDOUT_FILENO,
+                events: 0,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: woken.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        let ready = loop {
+            let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+            if ready >= 0 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                break ready;
+            }
+        };
+        if ready > 0 && fds[0].revents & (libc::POLLERR | libc::POLLHUP) != 0 {
+            for slot in &CHILDREN {
+                let pid = slot.load(Ordering::SeqCst);
+                if pid > 0 {
+                    unsafe { libc::kill(pid, libc::SIGTERM) };
+                }
+            }
+        }
+    });
+    Some(Watcher { wake, thread })
+}
+
+#[cfg(not(unix))]
+fn watch_stdout() -> Option<Watcher> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Raising a signal reaches every tracked child in the process, so the
+    /// test runs alone in a copy of the test binary.
+    #[test]
+    #[cfg(unix)]
+    fn signal_stops_the_child() {
+        const ALONE: &str = "SER_INTERRUPT_TEST";
+        if std::env::var_os(ALONE).is_none() {
+            let output = Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", "interrupt::tests::signal_stops_the_child"])
+                .env(ALONE, "1")
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{output:?}");
+            return;
+        }
+        install_handlers();
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let tracked = StopOnInterrupt::track(&child);
+        unsafe { libc::raise(libc::SIGTERM) };
+        assert!(interrupted());
+        assert!(!child.wait().unwrap().success());
+        assert_eq!(
+            tracked.finish().unwrap_err().kind(),
+            io::ErrorKind::Interrupted
+        );
+        // Only the call that was running fails.
+        assert!(status(&mut Command::new("true")).unwrap().success());
+    }
+
+    #[test]
+    fn hung_command_times_out() {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("5");
+        let started = Instant::now();
+        let error = output_within(&mut cmd, Some(Duration::from_millis(100))).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(error.to_string().contains("sleep 5"), "{error}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let done = output_within(Command::new("echo").arg("hi"), Some(Duration::from_secs(5)));
+        assert_eq!(done.unwrap().stdout, b"hi\n");
+    }
+}
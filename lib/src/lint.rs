@@ -0,0 +1,350 @@
+//! `ser lint`: problems in unit files and plists that systemd/launchd accept
+//! silently but that make a service misbehave, with safe automatic fixes
+//! for the ones that have an unambiguous correction.
+
+use crate::systemd::{quote_env_assignment, split_words};
+use anyhow::{Context, Result};
+use plist::Value;
+
+/// One problem found in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Short identifier for the kind of problem, e.g. `duplicate-key`.
+    pub rule: &'static str,
+    /// 1-based line in a unit file; `None` for plists.
+    pub line: Option<usize>,
+    pub message: String,
+    /// Whether the fixed contents correct it.
+    pub fixable: bool,
+}
+
+/// The findings for a file, and its contents with every fixable one fixed.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+    pub fixed: String,
+}
+
+/// Keys systemd reads once, so a repeat silently overrides the earlier line.
+const SINGLE_VALUED_KEYS: &[&str] = &[
+    "Description",
+    "Type",
+    "Restart",
+    "RestartSec",
+    "WorkingDirectory",
+    "User",
+    "Group",
+    "KillMode",
+    "TimeoutStartSec",
+    "TimeoutStopSec",
+    "StandardOutput",
+    "StandardError",
+    "OOMScoreAdjust",
+    "OOMPolicy",
+    "PrivateNetwork",
+    "Persistent",
+    "AccuracySec",
+    "RandomizedDelaySec",
+];
+
+/// launchd keys whose value must be a boolean; a string `"true"` or an
+/// integer is ignored, not coerced.
+const BOOLEAN_PLIST_KEYS: &[&str] = &[
+    "RunAtLoad",
+    "KeepAlive",
+    "Disabled",
+    "AbandonProcessGroup",
+    "EnableGlobbing",
+    "EnableTransactions",
+    "LowPriorityIO",
+    "StartOnMount",
+    "Debug",
+    "WaitForDebugger",
+];
+
+/// Lint a unit file.
+pub fn lint_unit(contents: &str) -> Report {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut out: Vec<Option<String>> = lines.iter().map(|l| Some(l.to_string())).collect();
+    let mut findings = Vec::new();
+
+    // (section, key, line index) of every assignment.
+    let mut entries = Vec::new();
+    let mut section = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.to_string();
+        } else if let Some((key, _)) = line.split_once('=') {
+            if !line.starts_with('#') && !line.starts_with(';') {
+                entries.push((section.clone(), key.trim().to_string(), i));
+            }
+        }
+    }
+
+    for (n, (section, key, i)) in entries.iter().enumerate() {
+        let duplicated = SINGLE_VALUED_KEYS.contains(&key.as_str())
+            && entries[n + 1..]
+                .iter()
+                .any(|(s, k, _)| s == section && k == key);
+        if duplicated {
+            findings.push(Finding {
+                rule: "duplicate-key",
+                line: Some(i + 1),
+                message: format!("{key}= is set again later in [{section}], which overrides it"),
+                fixable: true,
+            });
+            out[*i] = None;
+            continue;
+        }
+        let value = lines[*i].split_once('=').map_or("", |(_, v)| v.trim());
+        match key.as_str() {
+            "ExecStart" => {
+                if let Some(fixed) = lint_exec_start(value, *i, &mut findings) {
+                    out[*i] = Some(format!("ExecStart={fixed}"));
+                }
+            }
+            "Environment" => {
+                if let Some(fixed) = lint_environment(value, *i, &mut findings) {
+                    out[*i] = Some(format!("Environment={fixed}"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let restarts = entries.iter().any(|(s, k, i)| {
+        s == "Service" && k == "Restart" && out[*i].is_some() && {
+            let value = lines[*i].split_once('=').map_or("", |(_, v)| v.trim());
+            value != "no"
+        }
+    });
+    let installed = entries
+        .iter()
+        .any(|(s, k, _)| s == "Install" && k == "WantedBy");
+    let mut fixed: Vec<String> = out.into_iter().flatten().collect();
+    if restarts && !installed {
+        findings.push(Finding {
+            rule: "missing-install",
+            line: None,
+            message: "Restart= makes this a daemon, but without WantedBy= it won't start at boot"
+                .to_string(),
+            fixable: true,
+        });
+        match fixed.iter().position(|l| l.trim() == "[Install]") {
+            Some(header) => fixed.insert(header + 1, "WantedBy=default.target".to_string()),
+            None => {
+                if fixed.last().is_some_and(|l| !l.trim().is_empty()) {
+                    fixed.push(String::new());
+                }
+                fixed.push("[Install]".to_string());
+                fixed.push("WantedBy=default.target".to_string());
+            }
+        }
+    }
+
+    let mut fixed = fixed.join("\n");
+    if contents.ends_with('\n') || !fixed.is_empty() {
+        fixed.push('\n');
+    }
+    Report { findings, fixed }
+}
+
+/// A relative or missing program, and a bare `;` (which systemd reads as a
+/// command separator). Returns the corrected value if anything was fixable.
+fn lint_exec_start(value: &str, i: usize, findings: &mut Vec<Finding>) -> Option<String> {
+    // Leading `-`, `@`, `+`, `!` prefixes modify how the command runs.
+    let prefix_len = value
+        .find(|c: char| !matches!(c, '-' | '@' | '+' | '!' | ':'))
+        .unwrap_or(value.len());
+    let (prefix, command) = value.split_at(prefix_len);
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let program = *words.first()?;
+    // Rewritten word by word so quoting and spacing elsewhere are kept.
+    let mut fixed = command.to_string();
+    let mut changed = false;
+
+    if !program.starts_with('/') || !std::path::Path::new(program).exists() {
+        let resolved = if program.starts_with('/') {
+            crate::migrate::relocate_program(program)
+        } else {
+            crate::migrate::find_in_path(program)
+        };
+        let problem = if program.starts_with('/') {
+            "does not exist"
+        } else {
+            "is not an absolute path"
+        };
+        findings.push(Finding {
+            rule: "program-path",
+            line: Some(i + 1),
+            message: match &resolved {
+                Some(found) => format!("program {program} {problem}; use {}", found.display()),
+                None => format!("program {program} {problem}"),
+            },
+            fixable: resolved.is_some(),
+        });
+        if let Some(found) = resolved {
+            fixed = fixed.replacen(program, &found.to_string_lossy(), 1);
+            changed = true;
+        }
+    }
+    if words[1..].contains(&";") {
+        findings.push(Finding {
+            rule: "exec-separator",
+            line: Some(i + 1),
+            message: "a bare ; in ExecStart= separates commands; escape it as \\;".to_string(),
+            fixable: true,
+        });
+        let escaped: Vec<&str> = fixed
+            .split(' ')
+            .map(|word| if word == ";" { "\\;" } else { word })
+            .collect();
+        fixed = escaped.join(" ");
+        changed = true;
+    }
+    changed.then(|| format!("{prefix}{fixed}"))
+}
+
+/// Unquoted values with spaces (`Environment=GREETING=hello world`) are split
+/// into separate, invalid assignments. Returns the quoted value if needed.
+fn lint_environment(value: &str, i: usize, findings: &mut Vec<Finding>) -> Option<String> {
+    let mut assignments: Vec<String> = Vec::new();
+    let mut merged = false;
+    for token in split_words(value) {
+        match assignments.last_mut() {
+            Some(last) if !token.contains('=') => {
+                last.push(' ');
+                last.push_str(&token);
+                merged = true;
+            }
+            _ => assignments.push(token),
+        }
+    }
+    if !merged {
+        return None;
+    }
+    findings.push(Finding {
+        rule: "environment-quoting",
+        line: Some(i + 1),
+        message: "Environment= value with spaces must be quoted".to_string(),
+        fixable: true,
+    });
+    Some(
+        assignments
+            .iter()
+            .map(|a| quote_env_assignment(a))
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Lint a plist (XML or binary). The fixed contents are XML.
+pub fn lint_plist(contents: &[u8]) -> Result<Report> {
+    let mut value: Value = plist::from_bytes(contents).context("Failed to parse plist")?;
+    let dict = value
+        .as_dictionary_mut()
+        .context("Invalid plist format: not a dictionary")?;
+    let mut findings = Vec::new();
+
+    for key in BOOLEAN_PLIST_KEYS {
+        let Some(current) = dict.get_mut(key) else {
+            continue;
+        };
+        let coerced = match &*current {
+            Value::String(s) => match s.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "1" => Some(true),
+                "false" | "no" | "0" => Some(false),
+                _ => None,
+            },
+            Value::Integer(n) => n.as_signed().map(|n| n != 0),
+            _ => continue,
+        };
+        findings.push(Finding {
+            rule: "plist-boolean",
+            line: None,
+            message: format!("{key} must be <true/> or <false/>; launchd ignores other types"),
+            fixable: coerced.is_some(),
+        });
+        if let Some(b) = coerced {
+            *current = Value::Boolean(b);
+        }
+    }
+
+    let program = match (dict.get("Program"), dict.get("ProgramArguments")) {
+        (Some(Value::String(p)), _) => Some(("Program", p.clone())),
+        (None, Some(Value::Array(args))) => args
+            .first()
+            .and_then(|v| v.as_string())
+            .map(|p| ("ProgramArguments", p.to_string())),
+        _ => None,
+    };
+    if let Some((key, program)) = program {
+        if !program.starts_with('/') || !std::path::Path::new(&program).exists() {
+            let resolved = if program.starts_with('/') {
+                crate::migrate::relocate_program(&program)
+            } else {
+                crate::migrate::find_in_path(&program)
+            };
+            findings.push(Finding {
+                rule: "program-path",
+                line: None,
+                message: match &resolved {
+                    Some(found) => format!("program {program} not found; use {}", found.display()),
+                    None => format!("program {program} not found"),
+                },
+                fixable: resolved.is_some(),
+            });
+            if let Some(found) = resolved {
+                let found = Value::String(found.to_string_lossy().into_owned());
+                match dict.get_mut(key) {
+                    Some(Value::Array(args)) => args[0] = found,
+                    Some(slot) => *slot = found,
+                    None => {}
+                }
+            }
+        }
+    }
+
+    let mut data = Vec::new();
+    plist::to_writer_xml(&mut data, &value).context("Failed to serialize plist")?;
+    let mut fixed = String::from_utf8(data)?;
+    if !fixed.ends_with('\n') {
+        fixed.push('\n');
+    }
+    Ok(Report { findings, fixed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixes_unit_files() {
+        let unit = "[Unit]\nDescription=old\nDescription=web\n\n[Service]\nExecStart=sh -c true ; x\nEnvironment=GREETING=hello world A=1\nRestart=always\n";
+        let report = lint_unit(unit);
+        assert_eq!(report.findings.len(), 5);
+        assert!(report.findings.iter().all(|f| f.fixable));
+        let fixed = report.fixed;
+        assert!(!fixed.contains("Description=old"));
+        assert!(fixed.contains("ExecStart=/") && fixed.contains("/sh -c true \\; x\n"));
+        assert!(fixed.contains("Environment=\"GREETING=hello world\" \"A=1\"\n"));
+        assert!(fixed.ends_with("\n[Install]\nWantedBy=default.target\n"));
+        assert!(lint_unit(&fixed).findings.is_empty());
+    }
+
+    #[test]
+    fn fixes_plist_booleans() {
+        let xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict>
+<key>Label</key><string>web</string>
+<key>Program</key><string>/bin/sh</string>
+<key>RunAtLoad</key><string>YES</string>
+<key>KeepAlive</key><integer>1</integer>
+</dict></plist>"#;
+        let report = lint_plist(xml).unwrap();
+        assert_eq!(report.findings.len(), 2);
+        let fixed = lint_plist(report.fixed.as_bytes()).unwrap();
+        assert!(fixed.findings.is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+//! Backend-independent log output: timestamps from journald and the unified
+//! log are parsed into one representation, so `ser logs` can display them the
+//! same way on every platform and sort merged output strictly by time.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone, Utc};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+
+/// How `ser logs` displays timestamps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampStyle {
+    /// Local time, e.g. `2026-10-16 14:03:27`.
+    #[default]
+    Local,
+    /// UTC, e.g. `2026-10-16T12:03:27Z`.
+    Utc,
+    /// Age relative to now, e.g. `3m ago`.
+    Relative,
+}
+
+/// One log line, with its timestamp (if it had one) split off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub time: Option<DateTime<Utc>>,
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn parse(line: &str) -> LogEntry {
+        match parse_timestamp(line) {
+            Some((time, message)) => LogEntry {
+                time: Some(time),
+                message: message.to_string(),
+            },
+            None => LogEntry {
+                time: None,
+                message: line.to_string(),
+            },
+        }
+    }
+
+    pub fn format(&self, style: TimestampStyle, now: DateTime<Utc>) -> String {
+        match self.time {
+            Some(time) => format!("{} {}", format_timestamp(time, style, now), self.message),
+            None => self.message.clone(),
+        }
+    }
+}
+
+/// Split a leading timestamp off a log line. Understands the formats the
+/// backends produce: Unix seconds (`journalctl -o short-unix`), ISO 8601 with
+/// an offset (`log show --style syslog`, RFC 3339), and classic syslog
+/// (`Oct 16 14:03:27`, assumed local time in the current year).
+pub fn parse_timestamp(line: &str) -> Option<(DateTime<Utc>, &str)> {
+    let line = line.trim_start();
+    let (first, rest) = split_token(line);
+
+    if first.contains('.') && first.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        let secs: f64 = first.parse().ok()?;
+        let time = Utc
+            .timestamp_micros((secs * 1_000_000.0).round() as i64)
+            .single()?;
+        return Some((time, rest));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(first) {
+        return Some((time.with_timezone(&Utc), rest));
+    }
+
+    let (second, after_two) = split_token(rest);
+    let two = format!("{first} {second}");
+    if let Ok(time) = DateTime::parse_from_str(&two, "%Y-%m-%d %H:%M:%S%.f%z") {
+        return Some((time.with_timezone(&Utc), after_two));
+    }
+
+    let (third, after_three) = split_token(after_two);
+    let year = Local::now().year();
+    let syslog = format!("{year} {first} {second} {third}");
+    let naive = NaiveDateTime::parse_from_str(&syslog, "%Y %b %d %H:%M:%S").ok()?;
+    let time = Local.from_local_datetime(&naive).earliest()?;
+    Some((time.with_timezone(&Utc), after_three))
+}
+
+fn split_token(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    match s.find(char::is_whitespace) {
+        Some(i) => (&s[..i], s[i..].trim_start()),
+        None => (s, ""),
+    }
+}
+
+pub fn format_timestamp(time: DateTime<Utc>, style: TimestampStyle, now: DateTime<Utc>) -> String {
+    match style {
+        TimestampStyle::Local => time
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string(),
+        TimestampStyle::Utc => time.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        TimestampStyle::Relative => {
+            let secs = (now - time).num_seconds().max(0);
+            let age = match secs {
+                s if s < 60 => format!("{s}s"),
+                s if s < 3600 => format!("{}m", s / 60),
+                s if s < 86400 => format!("{}h", s / 3600),
+                s => format!("{}d", s / 86400),
+            };
+            format!("{:>8}", format!("{age} ago"))
+        }
+    }
+}
+
+/// Order entries strictly by time. Lines without a timestamp (continuations
+/// of multi-line messages) stay attached to the entry before them; any before
+/// the first timestamped line are tool headers and are dropped.
+pub fn sort_entries(entries: Vec<LogEntry>) -> Vec<LogEntry> {
+    let mut groups: Vec<(DateTime<Utc>, Vec<LogEntry>)> = Vec::new();
+    for entry in entries {
+        match (entry.time, groups.last_mut()) {
+            (Some(time), _) => groups.push((time, vec![entry])),
+            (None, Some((_, group))) => group.push(entry),
+            (None, None) => {}
+        }
+    }
+    // A stable sort keeps same-instant lines in the order the backend gave.
+    groups.sort_by_key(|(time, _)| *time);
+    groups.into_iter().flat_map(|(_, group)| group).collect()
+}
+
+/// Print a backend's log output with normalized timestamps. When `follow`,
+/// lines are printed as they arrive; otherwise they are sorted by time and
+/// the last `lines` are printed. Stops quietly if stdout is closed.
+pub fn print_logs(
+    output: impl Read,
+    style: TimestampStyle,
+    follow: bool,
+    lines: u32,
+) -> Result<()> {
+    if follow {
+        return print_as_they_come(output, style).map(|_| ());
+    }
+    let reader = BufReader::new(output);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+
+    let entries: Vec<LogEntry> = reader
+        .lines()
+        .map_while(|line| line.ok())
+        .map(|line| LogEntry::parse(&line))
+        .collect();
+    let entries = sort_entries(entries);
+    let start = entries.len().saturating_sub(lines as usize);
+    let now = Utc::now();
+    for entry in &entries[start..] {
+        if writeln!(out, "{}", entry.format(style, now)).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Print lines as they arrive until `output` ends (true) or stdout is closed
+/// (false).
+fn print_as_they_come(output: impl Read, style: TimestampStyle) -> Result<bool> {
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for line in BufReader::new(output).lines() {
+        let line = line.context("Failed to read log output")?;
+        let entry = LogEntry::parse(&line);
+        if writeln!(out, "{}", entry.format(style, Utc::now())).is_err() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Run a following log command (`journalctl -f`, `log stream`) and print its
+/// lines as they arrive, until it exits, stdout is closed, or `ser` is
+/// interrupted. It's stopped in the last two cases, which aren't errors;
+/// exiting non-zero on its own is.
+pub fn follow(mut cmd: Command, style: TimestampStyle) -> Result<()> {
+    cmd.stdout(Stdio::piped());
+    crate::print_command(&cmd);
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to execute {program}"))?;
+    let guard = crate::interrupt::StopOnInterrupt::new(&child);
+    let ended = match child.stdout.take() {
+        Some(stdout) => print_as_they_come(stdout, style),
+        None => Ok(true),
+    };
+    if !matches!(ended, Ok(true)) {
+        let _ = child.kill();
+    }
+    let status = child.wait();
+    let interrupted = guard.interrupted();
+    drop(guard);
+    if interrupted || !ended? {
+        return Ok(());
+    }
+    let status = status.with_context(|| format!("Failed to wait for {program}"))?;
+    // No exit code means a signal stopped it, e.g. the stdout watcher's.
+    if status.code().is_some_and(|code| code != 0) {
+        bail!("{program} exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_backend_timestamps() {
+        let (time, rest) = parse_timestamp("1760623407.500000 host web[1]: hi").unwrap();
+        assert_eq!(time.timestamp(), 1760623407);
+        assert_eq!(rest, "host web[1]: hi");
+
+        let (macos, rest) =
+            parse_timestamp("2025-10-16 07:03:27.500000-0700  localhost web[1]: hi").unwrap();
+        assert_eq!(macos, time);
+        assert_eq!(rest, "localhost web[1]: hi");
+
+        let (rfc, _) = parse_timestamp("2025-10-16T14:03:27.5Z web: hi").unwrap();
+        assert_eq!(rfc, time);
+        assert!(parse_timestamp("Timestamp (process)[PID]").is_none());
+    }
+
+    #[test]
+    fn sorts_and_formats_entries() {
+        let entries = vec![
+            LogEntry::parse("Filtering the log data"),
+            LogEntry::parse("20.0 b"),
+            LogEntry::parse("10.0 a"),
+            LogEntry::parse("  continued"),
+        ];
+        let sorted = sort_entries(entries);
+        let messages: Vec<_> = sorted.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["a", "  continued", "b"]);
+
+        let now = Utc.timestamp_opt(200, 0).unwrap();
+        assert_eq!(
+            sorted[0].format(TimestampStyle::Utc, now),
+            "1970-01-01T00:00:10Z a"
+        );
+        assert_eq!(
+            sorted[0].format(TimestampStyle::Relative, now),
+            "  3m ago a"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn follow_reports_the_commands_failure() {
+        let sh = |script: &str| {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", script]);
+            cmd
+        };
+        follow(sh("exit 0"), TimestampStyle::default()).unwrap();
+        let error = follow(sh("exit 3"), TimestampStyle::default()).unwrap_err();
+        assert!(error.to_string().contains("exit status: 3"), "{error}");
+    }
+}
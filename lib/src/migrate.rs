@@ -0,0 +1,326 @@
+//! `ser migrate`: move services to another machine, possibly one with a
+//! different init system. The portable bundle is a `ser backup` archive; on
+//! import each definition is read in its original format, converted to this
+//! machine's, and checked for paths that don't exist here, so only services
+//! that can actually run get installed.
+
+use crate::backup::{BackupEntry, Unpacked};
+use crate::platform::{self, UnitResult};
+use crate::ServiceDetails;
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Install prefixes that hold the same programs on different machines:
+/// Homebrew on Apple Silicon, Intel Macs, and Linux, and the usual merged or
+/// split `/usr` layouts.
+const EQUIVALENT_PREFIXES: &[&[&str]] = &[
+    &[
+        "/opt/homebrew/",
+        "/usr/local/",
+        "/home/linuxbrew/.linuxbrew/",
+    ],
+    &["/usr/local/bin/", "/usr/bin/", "/bin/"],
+    &["/usr/local/sbin/", "/usr/sbin/", "/sbin/"],
+];
+
+/// What importing one archived service will do.
+#[derive(Debug)]
+pub struct Plan {
+    pub entry: BackupEntry,
+    /// The definition, converted for this machine.
+    pub details: ServiceDetails,
+    /// The archived definition can't be installed as-is (it's for another
+    /// init system, or paths in it were rewritten), so a new one is generated
+    /// from `details`.
+    pub converted: bool,
+    /// Where each of the entry's archived env files goes on this machine.
+    pub env_targets: Vec<String>,
+    /// Adjustments made, e.g. a program found at a different path.
+    pub changes: Vec<String>,
+    /// Reasons the service can't be installed here.
+    pub problems: Vec<String>,
+}
+
+impl Plan {
+    pub fn is_ready(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Work out how each service in an unpacked archive maps onto this machine.
+pub fn plan(unpacked: &Unpacked) -> Vec<Plan> {
+    let manifest = &unpacked.manifest;
+    let foreign = manifest.os != std::env::consts::OS;
+    let old_home = manifest.home.as_deref();
+    let new_home = dirs::home_dir().map(|h| h.to_string_lossy().into_owned());
+
+    manifest
+        .services
+        .iter()
+        .map(|entry| {
+            let mut plan = Plan {
+                entry: entry.clone(),
+                details: ServiceDetails::default(),
+                converted: foreign,
+                env_targets: entry.env_files.clone(),
+                changes: Vec::new(),
+                problems: Vec::new(),
+            };
+            match read_definition(unpacked.root(), entry) {
+                Ok(details) => plan.details = details,
+                Err(e) => {
+                    plan.problems.push(format!("{e:#}"));
+                    return plan;
+                }
+            }
+            if let (Some(old), Some(new)) = (old_home, new_home.as_deref()) {
+                for target in &mut plan.env_targets {
+                    if let Some(moved) = moved(target, old, new) {
+                        *target = moved;
+                    }
+                }
+                if old != new && rewrite_home(&mut plan.details, old, new) {
+                    plan.converted = true;
+                    plan.changes
+                        .push(format!("paths under {old} moved to {new}"));
+                }
+            }
+            check_paths(&mut plan);
+            if foreign {
+                plan.changes.extend(dropped_settings(&plan.details));
+                if cfg!(target_os = "macos") {
+                    let (translated, warnings) =
+                        crate::specifiers::translate_for_launchd(&plan.details);
+                    plan.details = translated;
+                    plan.changes.extend(warnings);
+                }
+            }
+            plan
+        })
+        .collect()
+}
+
+/// Install every service whose plan is ready, after merging the archived
+/// state registry. Services that were running are started when `start` is
+/// set. Returns a result per installed service.
+pub fn apply(unpacked: &Unpacked, plans: &[Plan], start: bool) -> Result<Vec<UnitResult>> {
+    crate::backup::restore_state(unpacked)?;
+    Ok(plans
+        .iter()
+        .filter(|plan| plan.is_ready())
+        .map(|plan| UnitResult {
+            name: plan.details.name.clone(),
+            result: apply_plan(unpacked, plan, start),
+        })
+        .collect())
+}
+
+fn apply_plan(unpacked: &Unpacked, plan: &Plan, start: bool) -> Result<()> {
+    for (index, target) in plan.env_targets.iter().enumerate() {
+        crate::backup::restore_env_file(unpacked, &plan.entry, index, Path::new(target))?;
+    }
+    if plan.converted {
+        platform::create_service(&plan.details)?;
+    } else {
+        for file in &plan.entry.files {
+            platform::install_service_file(&unpacked.root().join(file), plan.entry.user, false)?;
+        }
+    }
+    if start && plan.entry.was_active() {
+        platform::start_service(&plan.details.name)?;
+    }
+    Ok(())
+}
+
+/// Read an archived definition in whichever format it was saved in.
+fn read_definition(root: &Path, entry: &BackupEntry) -> Result<ServiceDetails> {
+    let path_of = |ext: &str| {
+        entry
+            .files
+            .iter()
+            .find(|f| f.ends_with(ext))
+            .map(|f| root.join(f))
+    };
+    if let Some(path) = path_of(".plist") {
+        let contents = fs::read(&path)?;
+        return crate::plist::parse_plist(&contents)
+            .with_context(|| format!("Failed to convert {}", entry.original_path));
+    }
+    let path = path_of(".service")
+        .ok_or_else(|| anyhow!("No unit file or plist archived for '{}'", entry.name))?;
+    let contents = fs::read_to_string(&path)?;
+    let mut details = crate::systemd::parse_systemd(&contents)
+        .with_context(|| format!("Failed to convert {}", entry.original_path))?;
+    details.name = entry.name.clone();
+    if let Some(timer) = path_of(".timer") {
+        crate::systemd::parse_timer_into(&mut details, &fs::read_to_string(timer)?);
+    }
+    Ok(details)
+}
+
+/// Move paths under the old home directory to the new one. Returns whether
+/// anything changed.
+fn rewrite_home(details: &mut ServiceDetails, old: &str, new: &str) -> bool {
+    let mut changed = false;
+    let mut rewrite = |value: &mut String| {
+        if let Some(moved) = moved(value, old, new) {
+            *value = moved;
+            changed = true;
+        }
+    };
+    rewrite(&mut details.program);
+    details.arguments.iter_mut().for_each(&mut rewrite);
+    details.working_directory.iter_mut().for_each(&mut rewrite);
+    details
+        .env_files
+        .iter_mut()
+        .for_each(|file| rewrite(&mut file.path));
+    details
+        .env_vars
+        .iter_mut()
+        .for_each(|(_, value)| rewrite(value));
+    changed
+}
+
+/// `path` with a leading `old` directory replaced by `new`.
+fn moved(path: &str, old: &str, new: &str) -> Option<String> {
+    let rest = path.strip_prefix(old)?;
+    (rest.is_empty() || rest.starts_with('/')).then(|| format!("{new}{rest}"))
+}
+
+/// Re-resolve the program if it isn't where the old machine had it, and
+/// flag a missing working directory.
+fn check_paths(plan: &mut Plan) {
+    let program = plan.details.program.clone();
+    if !Path::new(&program).is_file() {
+        match relocate_program(&program) {
+            Some(found) => {
+                let found = found.to_string_lossy().into_owned();
+                plan.changes.push(format!("program {program} -> {found}"));
+                plan.details.program = found;
+                plan.converted = true;
+            }
+            None => plan
+                .problems
+                .push(format!("program {program} not found on this machine")),
+        }
+    }
+    if let Some(dir) = &plan.details.working_directory {
+        if !Path::new(dir).is_dir() {
+            plan.problems
+                .push(format!("working directory {dir} does not exist"));
+        }
+    }
+}
+
+/// Settings this machine's init system has no equivalent for.
+fn dropped_settings(details: &ServiceDetails) -> Vec<String> {
+    if !cfg!(target_os = "macos") {
+        return Vec::new();
+    }
+    let mut dropped = Vec::new();
+    if !details.after.is_empty() {
+        dropped.push("After=");
+    }
+    if details.oom_score_adjust.is_some() || details.oom_policy.is_some() {
+        dropped.push("OOM settings");
+    }
+    if details.accuracy.is_some() {
+        dropped.push("AccuracySec=");
+    }
+    if details.conditions.iter().any(|c| c.kind != "PathExists") {
+        dropped.push("conditions other than PathExists");
+    }
+    dropped
+        .into_iter()
+        .map(|setting| format!("{setting} dropped (launchd has no equivalent)"))
+        .collect()
+}
+
+/// Find a program that isn't at `program` on this machine, as written on
+/// another one: first under equivalent install prefixes (keeping the rest of
+/// the path, so `/opt/homebrew/opt/x/bin/x` maps to `/usr/local/opt/x/bin/x`)
+/// and the current user's home, then by name on `$PATH`.
+pub fn relocate_program(program: &str) -> Option<PathBuf> {
+    let mut candidates: Vec<PathBuf> = EQUIVALENT_PREFIXES
+        .iter()
+        .flat_map(|group| {
+            group.iter().filter_map(move |prefix| {
+                let rest = program.strip_prefix(prefix)?;
+                Some(
+                    group
+                        .iter()
+                        .map(move |other| PathBuf::from(format!("{other}{rest}"))),
+                )
+            })
+        })
+        .flatten()
+        .collect();
+    let in_other_home = ["/Users/", "/home/"]
+        .iter()
+        .find_map(|prefix| program.strip_prefix(prefix)?.split_once('/'));
+    if let (Some((_, rest)), Some(home)) = (in_other_home, dirs::home_dir()) {
+        candidates.push(home.join(rest));
+    }
+    let found = candidates.into_iter().find(|c| is_executable(c));
+    found.or_else(|| {
+        let name = Path::new(program).file_name()?.to_str()?;
+        find_in_path(name)
+    })
+}
+
+/// The first executable file named `name` in `$PATH`.
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    if name.is_empty() {
+        return None;
+    }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+/// Windows has no executable bit; the name (`sc.exe`) says what runs.
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_paths_under_old_home() {
+        let mut details = ServiceDetails {
+            program: "/Users/kurt/bin/web".to_string(),
+            arguments: vec!["--config".into(), "/Users/kurt/web.toml".into()],
+            working_directory: Some("/Users/kurtis".to_string()),
+            ..Default::default()
+        };
+        assert!(rewrite_home(&mut details, "/Users/kurt", "/home/kurt"));
+        assert_eq!(details.program, "/home/kurt/bin/web");
+        assert_eq!(details.arguments[1], "/home/kurt/web.toml");
+        // Only whole path components are matched.
+        assert_eq!(details.working_directory.as_deref(), Some("/Users/kurtis"));
+    }
+
+    #[test]
+    fn finds_programs_on_path() {
+        assert!(find_in_path("sh").is_some());
+        assert!(find_in_path("ser-no-such-program").is_none());
+        assert!(find_in_path("").is_none());
+
+        let sh = relocate_program("/opt/homebrew/bin/sh").unwrap();
+        assert!(sh.ends_with("sh") && sh.is_file());
+        assert!(relocate_program("/opt/homebrew/bin/ser-no-such-program").is_none());
+    }
+}
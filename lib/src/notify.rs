@@ -0,0 +1,382 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Deliver alerts to a webhook (Slack, Discord, or anything accepting a JSON
+/// POST), configured in `config.toml`:
+///
+/// ```toml
+/// [notifications.webhook]
+/// url = "https://hooks.slack.com/services/..."
+/// # Optional; {service}, {state}, {host}, and {logs} are JSON-escaped.
+/// template = '{"text": "{service} is {state} on {host}\n{logs}"}'
+/// retries = 3
+/// log_lines = 10
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Webhook {
+    pub url: String,
+    pub template: Option<String>,
+    /// Extra attempts after the first, with exponential backoff from 1s.
+    pub retries: u32,
+    /// How many recent log lines to include as `{logs}`.
+    pub log_lines: u32,
+}
+
+impl Default for Webhook {
+    fn default() -> Self {
+        Webhook {
+            url: String::new(),
+            template: None,
+            retries: 3,
+            log_lines: 10,
+        }
+    }
+}
+
+/// Email alerts through the local `sendmail` or, with `smtp_url`, an SMTP
+/// server (sent with `curl`):
+///
+/// ```toml
+/// [notifications.email]
+/// to = ["ops@example.com"]
+/// from = "ser@web01.example.com"
+/// # Optional; without it, `sendmail -t` is used.
+/// smtp_url = "smtps://smtp.example.com:465"
+/// smtp_user = "alerts@example.com"
+/// # Read from this environment variable rather than stored in the file.
+/// smtp_password_env = "SER_SMTP_PASSWORD"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Email {
+    pub to: Vec<String>,
+    pub from: Option<String>,
+    pub smtp_url: Option<String>,
+    pub smtp_user: Option<String>,
+    pub smtp_password_env: Option<String>,
+    pub log_lines: u32,
+}
+
+impl Default for Email {
+    fn default() -> Self {
+        Email {
+            to: Vec::new(),
+            from: None,
+            smtp_url: None,
+            smtp_user: None,
+            smtp_password_env: None,
+            log_lines: 20,
+        }
+    }
+}
+
+impl Email {
+    /// An RFC 5322 message with the service state, status, and recent logs.
+    pub fn render(&self, event: &Event) -> String {
+        let host = crate::specifiers::hostname();
+        let from = self.from.clone().unwrap_or_else(|| format!("ser@{host}"));
+        let mut message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: [ser] {} is {} on {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+            from,
+            self.to.join(", "),
+            event.service,
+            event.state,
+            host
+        );
+        message.push_str(&format!(
+            "{} is {} on {}.\n",
+            event.service, event.state, host
+        ));
+        if let Some(status) = &event.status {
+            message.push_str(&format!("\n{status}\n"));
+        }
+        if !event.logs.is_empty() {
+            message.push_str("\nRecent logs:\n");
+            for line in &event.logs {
+                message.push_str(line);
+                message.push('\n');
+            }
+        }
+        message
+    }
+
+    pub fn send(&self, event: &Event) -> Result<()> {
+        if self.to.is_empty() {
+            bail!("notifications.email.to is empty");
+        }
+        let message = self.render(event);
+        // Held until curl exits; it names the credentials file.
+        let mut credentials = None;
+        let mut cmd = match &self.smtp_url {
+            Some(url) => {
+                let mut cmd = Command::new("curl");
+                cmd.args(["--silent", "--show-error", "--ssl-reqd", "--url", url]);
+                if let Some(from) = &self.from {
+                    cmd.args(["--mail-from", from]);
+                }
+                for to in &self.to {
+                    cmd.args(["--mail-rcpt", to]);
+                }
+                if let Some(user) = &self.smtp_user {
+                    let password = match &self.smtp_password_env {
+                        Some(var) => {
+                            std::env::var(var).with_context(|| format!("{var} is not set"))?
+                        }
+                        None => String::new(),
+                    };
+                    // In a private config file rather than on argv, where
+                    // any local user could read it; stdin carries the message.
+                    let mut file = tempfile::NamedTempFile::new()
+                        .context("Failed to create a curl config file")?;
+                    writeln!(file, "user = {}", curl_quote(&format!("{user}:{password}")))
+                        .context("Failed to write the curl config file")?;
+                    cmd.arg("--config").arg(file.path());
+                    credentials = Some(file);
+                }
+                cmd.args(["--upload-file", "-"]);
+                cmd
+            }
+            None => {
+                let mut cmd = Command::new("sendmail");
+                cmd.arg("-t");
+                cmd
+            }
+        };
+        let result = pipe_to(&mut cmd, &message);
+        drop(credentials);
+        result
+    }
+}
+
+/// Slack-compatible; most chat webhooks accept a `text` field.
+const DEFAULT_TEMPLATE: &str = r#"{"text": "ser: {service} is {state} on {host}\n{logs}"}"#;
+
+/// Something worth telling a human about.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub service: String,
+    pub state: String,
+    /// Init-system status output, when available.
+    pub status: Option<String>,
+    pub logs: Vec<String>,
+}
+
+impl Event {
+    /// An event for `service` with its status and recent logs attached
+    /// (best effort).
+    pub fn with_logs(service: &str, state: &str, lines: u32) -> Event {
+        Event {
+            service: service.to_string(),
+            state: state.to_string(),
+            status: crate::platform::status_report(service).ok(),
+            logs: crate::platform::recent_logs(service, lines).unwrap_or_default(),
+        }
+    }
+}
+
+impl Webhook {
+    pub fn render(&self, event: &Event) -> String {
+        let template = self.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+        template
+            .replace("{service}", &json_escape(&event.service))
+            .replace("{state}", &json_escape(&event.state))
+            .replace("{host}", &json_escape(&crate::specifiers::hostname()))
+            .replace("{logs}", &json_escape(&event.logs.join("\n")))
+    }
+
+    /// POST the rendered event with `curl`, retrying with backoff.
+    pub fn send(&self, event: &Event) -> Result<()> {
+        if self.url.is_empty() {
+            bail!("notifications.webhook.url is not set");
+        }
+        let payload = self.render(event);
+        let mut delay = Duration::from_secs(1);
+        let mut attempt = 0;
+        loop {
+            match post_json(&self.url, &payload) {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.retries => {
+                    return Err(e.context(format!(
+                        "Webhook delivery failed after {} attempt(s)",
+                        attempt + 1
+                    )))
+                }
+                Err(e) => {
+                    if crate::is_verbose() {
+                        eprintln!("Webhook attempt {} failed: {e:#}", attempt + 1);
+                    }
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// The URL usually carries a token, so it goes to curl on stdin, as a config
+/// file, with the payload, rather than on argv where any local user (and
+/// `--explain`) could read it.
+fn post_json(url: &str, payload: &str) -> Result<()> {
+    let mut cmd = Command::new("curl");
+    cmd.args([
+        "--silent",
+        "--show-error",
+        "--fail",
+        "--max-time",
+        "10",
+        "-X",
+        "POST",
+        "-H",
+        "Content-Type: application/json",
+        "--config",
+        "-",
+    ]);
+    let config = format!(
+        "url = {}\ndata-raw = {}\n",
+        curl_quote(url),
+        curl_quote(payload)
+    );
+    pipe_to(&mut cmd, &config)
+}
+
+/// `value` as a double-quoted curl config file string.
+fn curl_quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+    format!("\"{escaped}\"")
+}
+
+/// Run `cmd` with `input` on stdin, failing with its stderr if it fails.
+fn pipe_to(cmd: &mut Command, input: &str) -> Result<()> {
+    cmd.stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    crate::print_command(cmd);
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to execute {program}"))?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(input.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("{program} failed: {}", stderr.trim());
+    }
+    Ok(())
+}
+
+/// Send `event` through every configured channel. Every channel is tried
+/// even if one fails; the error lists the failures.
+pub fn send_all(event: &Event) -> Result<()> {
+    let config = &crate::config::settings().notifications;
+    let mut failures = Vec::new();
+    let mut channels = 0;
+    if let Some(webhook) = &config.webhook {
+        channels += 1;
+        if let Err(e) = webhook.send(event) {
+            failures.push(format!("webhook: {e:#}"));
+        }
+    }
+    if let Some(email) = &config.email {
+        channels += 1;
+        if let Err(e) = email.send(event) {
+            failures.push(format!("email: {e:#}"));
+        }
+    }
+    if channels == 0 {
+        bail!("No alert channels configured; add [notifications.webhook] or [notifications.email] to config.toml");
+    }
+    if !failures.is_empty() {
+        bail!("{}", failures.join("\n"));
+    }
+    Ok(())
+}
+
+/// The most log lines any configured channel wants.
+pub fn log_lines() -> u32 {
+    let config = &crate::config::settings().notifications;
+    let webhook = config.webhook.as_ref().map_or(0, |w| w.log_lines);
+    let email = config.email.as_ref().map_or(0, |e| e.log_lines);
+    webhook.max(email)
+}
+
+/// Escape text for inclusion inside a JSON string literal.
+fn json_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_escaped_payload() {
+        let webhook = Webhook {
+            url: "https://example.com".to_string(),
+            template: Some(r#"{"s": "{service}", "st": "{state}", "l": "{logs}"}"#.to_string()),
+            ..Default::default()
+        };
+        let event = Event {
+            service: "web".to_string(),
+            state: "failed".to_string(),
+            status: None,
+            logs: vec!["say \"hi\"".to_string(), "bye".to_string()],
+        };
+        assert_eq!(
+            webhook.render(&event),
+            r#"{"s": "web", "st": "failed", "l": "say \"hi\"\nbye"}"#
+        );
+    }
+
+    #[test]
+    fn quotes_curl_config_values() {
+        assert_eq!(
+            curl_quote("https://hooks.example.com/T0/\"x\"\n{\"a\": \"b\\nc\"}"),
+            r#""https://hooks.example.com/T0/\"x\"\n{\"a\": \"b\\nc\"}""#
+        );
+    }
+
+    #[test]
+    fn renders_email_message() {
+        let email = Email {
+            to: vec!["ops@example.com".to_string()],
+            from: Some("ser@example.com".to_string()),
+            ..Default::default()
+        };
+        let event = Event {
+            service: "web".to_string(),
+            state: "failed".to_string(),
+            status: Some("Active: failed".to_string()),
+            logs: vec!["boom".to_string()],
+        };
+        let message = email.render(&event);
+        assert!(message.starts_with("From: ser@example.com\r\nTo: ops@example.com\r\n"));
+        assert!(message.contains("Subject: [ser] web is failed on "));
+        assert!(message.ends_with("Active: failed\n\nRecent logs:\nboom\n"));
+    }
+}
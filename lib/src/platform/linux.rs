@@ -1,21 +1,31 @@
-use super::{list_services, Config, ServiceRef};
+use super::{
+    list_services, write_service_file, Config, HostInfo, Invocation, LastRun, LogOptions,
+    PackageInfo, RuntimeProperties, ServiceRef, Source, UnitResult,
+};
 pub use crate::systemd::generate_file;
 use crate::systemd::parse_systemd;
-use crate::{print_command, FsServiceDetails, ServiceDetails};
+use crate::{print_command, FsServiceDetails, ServiceDetails, TriggerKind, TriggerUnit};
 use anyhow::{anyhow, bail, Context, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::OnceLock;
+
+/// The `PATH` systemd gives a service that doesn't set one.
+pub const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
 
 pub(super) fn get_service_directories() -> Config {
     let mut user_dirs = Vec::new();
     let mut system_dirs = Vec::new();
     let mut default_dirs = Vec::new();
 
-    // User-specific systemd directory
-    if let Some(home) = std::env::var_os("HOME") {
-        let user_systemd = PathBuf::from(home).join(".config/systemd/user");
-        user_dirs.push(user_systemd);
+    // User-specific systemd directories ($XDG_CONFIG_HOME and $XDG_DATA_HOME).
+    // Without a home directory we simply fall back to system scope.
+    if let Some(config) = dirs::config_dir() {
+        user_dirs.push(config.join("systemd/user"));
+    }
+    if let Some(data) = dirs::data_dir() {
+        user_dirs.push(data.join("systemd/user"));
     }
 
     // User unit directories (global user services)
@@ -38,45 +48,34 @@ pub(super) fn get_service_directories() -> Config {
     }
 }
 
-pub(super) fn scan_directory(dir: &Path) -> Result<Vec<ServiceRef>> {
-    let mut services = Vec::new();
-
-    if !dir.exists() {
-        return Ok(services);
-    }
-
-    let entries = fs::read_dir(dir)?;
-
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
-
-        if let Some(extension) = path.extension().and_then(|s| s.to_str()) {
-            if matches!(
-                extension,
-                "service"
-                    | "socket"
-                    | "timer"
-                    | "target"
-                    | "mount"
-                    | "automount"
-                    | "swap"
-                    | "path"
-                    | "slice"
-                    | "scope"
-            ) {
-                if let Ok(service) = parse_unit_file(&path) {
-                    services.push(service);
-                }
-            }
-        }
-    }
-
-    Ok(services)
+/// Whether `path` is a unit file, by its extension.
+pub(super) fn is_definition(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|s| s.to_str()),
+        Some(
+            "service"
+                | "socket"
+                | "timer"
+                | "target"
+                | "mount"
+                | "automount"
+                | "swap"
+                | "path"
+                | "slice"
+                | "scope"
+        )
+    )
 }
 
-fn parse_unit_file(path: &Path) -> Result<ServiceRef> {
-    // let _contents = fs::read_to_string(path)?;
+/// Read a unit file for listing, failing only if it can't be read. A unit
+/// that isn't shaped like a unit file is still listed, with the problem as a
+/// warning: systemd may load what ser's check doesn't understand.
+pub(super) fn parse_service_ref(
+    path: &Path,
+    root: &Path,
+) -> Result<(ServiceRef, Option<anyhow::Error>)> {
+    let contents = fs::read_to_string(path)?;
+    let warning = crate::systemd::check_syntax(&contents).err();
 
     let name = path
         .file_name()
@@ -84,24 +83,35 @@ fn parse_unit_file(path: &Path) -> Result<ServiceRef> {
         .unwrap_or("unknown")
         .to_string();
 
-    // Simple heuristic: if the file exists and is readable, consider it "enabled"
-    // In reality, we'd need to check symlinks in /etc/systemd/system/*.wants/ directories
-    // or parse the unit file more thoroughly
+    // A guess from the files; `ser list` asks systemd about the units it
+    // shows with `resolve_enabled`.
     let enabled = is_service_enabled(path, &name);
+    let managed = contents.starts_with(crate::systemd::MANAGED_BY_COMMENT);
+    let source = Source::classify(&name, root, managed);
 
-    Ok(ServiceRef {
+    let service = ServiceRef {
         name,
         path: path.to_string_lossy().to_string(),
         enabled,
-    })
+        source,
+    };
+    Ok((service, warning))
 }
 
+/// Whether a unit is linked into a common target's `.wants` directory: the
+/// fallback when systemctl can't be asked, which misses user units, other
+/// targets, and aliases.
 fn is_service_enabled(_path: &Path, name: &str) -> bool {
-    // Check common systemd target directories for symlinks
+    if let Some(roots) = super::offline_roots() {
+        return is_wanted_in(&roots, name);
+    }
     let wants_dirs = [
         "/etc/systemd/system/multi-user.target.wants",
         "/etc/systemd/system/graphical.target.wants",
         "/etc/systemd/system/default.target.wants",
+        "/etc/systemd/system/timers.target.wants",
+        "/etc/systemd/system/sockets.target.wants",
+        "/etc/systemd/system/paths.target.wants",
     ];
 
     for wants_dir in &wants_dirs {
@@ -121,47 +131,282 @@ fn is_service_enabled(_path: &Path, name: &str) -> bool {
     false
 }
 
+/// Whether `name` is linked into a `.wants` directory of any target under
+/// one of `roots`, or aliased in one of them.
+fn is_wanted_in(roots: &[PathBuf], name: &str) -> bool {
+    roots.iter().any(|root| {
+        root.join(name).is_symlink()
+            || fs::read_dir(root).is_ok_and(|entries| {
+                entries.flatten().any(|entry| {
+                    entry.file_name().to_string_lossy().ends_with(".wants")
+                        && entry.path().join(name).exists()
+                })
+            })
+    })
+}
+
+/// Set `enabled` on `services` from systemd's unit file states, which know
+/// every target, alias, and `Also=`: one `list-unit-files` call for the
+/// system units and one with `--user` for the user units. Units systemd
+/// doesn't know (e.g. in a `--dir` that isn't installed), and all of them
+/// where systemd can't be asked, keep the guesses from the files.
+pub(super) fn resolve_enabled(services: &mut [ServiceRef]) {
+    if backend_unavailable().is_some() {
+        return;
+    }
+    for user in [false, true] {
+        let in_scope = |s: &ServiceRef| s.path.contains("/systemd/user/") == user;
+        let mut names: Vec<&str> = services
+            .iter()
+            .filter(|s| in_scope(s))
+            .map(|s| s.name.as_str())
+            .collect();
+        if names.is_empty() {
+            continue;
+        }
+        names.sort();
+        names.dedup();
+        let Ok(states) = list_unit_files(user, &names) else {
+            continue;
+        };
+        let states: std::collections::HashMap<String, String> = states
+            .into_iter()
+            .map(|state| (state.unit, state.state))
+            .collect();
+        for service in services.iter_mut().filter(|s| in_scope(s)) {
+            if let Some(state) = states.get(&service.name) {
+                service.enabled = is_enabled_state(state);
+            }
+        }
+    }
+}
+
+/// [`super::running_states`]: one `systemctl is-active` per scope, which
+/// prints a state per unit in order.
+pub(super) fn running_states_at_once(services: &[ServiceRef]) -> Vec<bool> {
+    let mut running = vec![false; services.len()];
+    if backend_unavailable().is_some() {
+        return running;
+    }
+    for user in [false, true] {
+        let (indexes, names): (Vec<usize>, Vec<&str>) = services
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.path.contains("/systemd/user/") == user)
+            .map(|(i, s)| (i, s.name.as_str()))
+            .unzip();
+        if names.is_empty() {
+            continue;
+        }
+        let mut cmd = Command::new("systemctl");
+        if user {
+            cmd.arg("--user");
+        }
+        cmd.arg("is-active").args(&names);
+        print_command(&cmd);
+        // Exits non-zero when any unit is inactive; the states are still there.
+        let Ok(output) = crate::interrupt::query(&mut cmd) else {
+            continue;
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for (i, state) in indexes.into_iter().zip(stdout.lines()) {
+            running[i] = matches!(state.trim(), "active" | "reloading");
+        }
+    }
+    running
+}
+
+/// Whether a unit file state means the unit starts without being asked:
+/// linked into a target, through an alias or `Also=`, or by a generator.
+fn is_enabled_state(state: &str) -> bool {
+    matches!(
+        state,
+        "enabled" | "enabled-runtime" | "alias" | "indirect" | "generated"
+    )
+}
+
 pub fn get_service_details(name: &str) -> Result<FsServiceDetails> {
     // Find the service first
     let service_ref = super::get_service(name)?;
 
-    // Parse the unit file for detailed information
-    let contents = fs::read_to_string(&service_ref.path)
-        .with_context(|| format!("Failed to read service file: {}", service_ref.path))?;
-
-    let mut service = parse_systemd(&contents)?;
-    // The schedule lives in the paired `.timer` unit, not the `.service` file,
-    // so read it back here to populate `service.schedule`.
-    service.schedule = read_timer_schedule(&service_ref.path);
+    // Parse the unit file for detailed information. For a timer, socket, or
+    // path unit, that's the service it activates.
+    let trigger = read_trigger(&service_ref)?;
+    let service = match &trigger {
+        Some(trigger) => activated_service(trigger, Path::new(&service_ref.path))?,
+        None => parse_effective(&service_ref.name, Path::new(&service_ref.path))?,
+    };
     let running = is_service_running(name)?;
 
     Ok(FsServiceDetails {
         running,
+        pid: running.then(|| main_pid(name)).flatten(),
         service,
         enabled: service_ref.enabled,
         path: service_ref.path,
+        trigger,
     })
 }
 
-/// Read the schedule from the `.timer` unit paired with the given `.service`
-/// file path. Handles both `OnCalendar=` (calendar) and `OnUnitActiveSec=`
-/// (interval) timers. Returns `None` when there is no timer or its expression
-/// cannot be represented as a [`Schedule`].
-fn read_timer_schedule(service_path: &str) -> Option<crate::Schedule> {
-    use crate::{CalendarSchedule, Schedule};
+/// The `[Timer]`/`[Socket]`/`[Path]` side of a trigger unit; `None` for a
+/// service or any other kind of unit.
+fn read_trigger(service_ref: &ServiceRef) -> Result<Option<TriggerUnit>> {
+    if TriggerKind::from_unit_name(&service_ref.name).is_none() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&service_ref.path)
+        .with_context(|| format!("Failed to read unit file: {}", service_ref.path))?;
+    Ok(crate::systemd::parse_trigger_unit(
+        &service_ref.name,
+        &contents,
+    ))
+}
 
-    let timer_path = Path::new(service_path).with_extension("timer");
-    let contents = fs::read_to_string(&timer_path).ok()?;
-    for line in contents.lines() {
-        let line = line.trim();
-        if let Some(expr) = line.strip_prefix("OnCalendar=") {
-            return CalendarSchedule::from_systemd_oncalendar(expr).map(Schedule::Calendar);
+/// The definition of the unit a trigger activates. A timer's schedule comes
+/// from the timer itself. If the unit has no file (say, an instance of a
+/// template), only its name is filled in.
+fn activated_service(trigger: &TriggerUnit, trigger_path: &Path) -> Result<ServiceDetails> {
+    let mut service = match super::get_service(&trigger.unit) {
+        Ok(unit) if unit.name == trigger.unit => {
+            parse_effective(&unit.name, Path::new(&unit.path))?
         }
-        if let Some(span) = line.strip_prefix("OnUnitActiveSec=") {
-            return Schedule::parse_interval_secs(span).map(Schedule::Interval);
+        _ => ServiceDetails {
+            name: trigger.unit.trim_end_matches(".service").to_string(),
+            ..Default::default()
+        },
+    };
+    if trigger.kind == TriggerKind::Timer {
+        service.schedule = None;
+        if let Ok(contents) = fs::read_to_string(trigger_path) {
+            crate::systemd::parse_timer_into(&mut service, &contents);
         }
     }
-    None
+    Ok(service)
+}
+
+/// The unit that start/stop/restart act on: a timer, socket, or path unit
+/// as named, and otherwise the service's timer if it has one, since starting
+/// the service itself would just run it once.
+fn target_unit(name: &str) -> String {
+    if TriggerKind::from_unit_name(name).is_some() {
+        return name.to_string();
+    }
+    let timer_name = format!("{}.timer", name.trim_end_matches(".service"));
+    if unit_file_exists(&timer_name) {
+        timer_name
+    } else {
+        name.to_string()
+    }
+}
+
+/// Whether a system unit file with this name exists in any unit directory.
+fn unit_file_exists(unit: &str) -> bool {
+    super::service_directories()
+        .system_dirs
+        .iter()
+        .any(|dir| dir.join(unit).exists())
+}
+
+/// Parse a unit file on disk without querying systemd for runtime state.
+pub fn parse_service_file(path: &Path) -> Result<ServiceDetails> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read service file: {}", path.display()))?;
+
+    let mut service = parse_systemd(&contents)
+        .with_context(|| format!("Failed to parse service file: {}", path.display()))?;
+    service.name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string();
+    // The schedule lives in the paired `.timer` unit, not the `.service` file,
+    // so read it back here to populate `service.schedule`.
+    read_timer(&mut service, &path.to_string_lossy());
+    Ok(service)
+}
+
+/// Parse a unit as systemd loads it: the unit file with its drop-ins
+/// applied.
+fn parse_effective(name: &str, path: &Path) -> Result<ServiceDetails> {
+    let drop_ins = drop_in_paths(name, path);
+    if drop_ins.is_empty() {
+        return parse_service_file(path);
+    }
+    let mut service = parse_systemd(&crate::systemd::render_fields(&super::effective_fields(
+        path, &drop_ins,
+    )?))
+    .with_context(|| format!("Failed to parse service file: {}", path.display()))?;
+    service.name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string();
+    read_timer(&mut service, &path.to_string_lossy());
+    Ok(service)
+}
+
+/// The drop-ins (`<unit>.d/*.conf`) that apply to the unit at `path`, in the
+/// order systemd applies them: sorted by file name, where a file in a
+/// higher-priority directory (`/etc` over `/run` over `/usr/lib`) hides one
+/// of the same name in a lower one. An instance like `web@blue.service` also
+/// gets the drop-ins of its template, `web@.service`.
+pub fn drop_in_paths(name: &str, path: &Path) -> Vec<PathBuf> {
+    let user = path.to_string_lossy().contains("/systemd/user");
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if user {
+        dirs.extend(dirs::config_dir().map(|c| c.join("systemd/user")));
+        dirs.extend(["/etc/systemd/user", "/run/systemd/user"].map(PathBuf::from));
+        dirs.extend(dirs::data_dir().map(|d| d.join("systemd/user")));
+        dirs.extend(["/usr/local/lib/systemd/user", "/usr/lib/systemd/user"].map(PathBuf::from));
+    } else {
+        dirs.extend(
+            [
+                "/etc/systemd/system",
+                "/run/systemd/system",
+                "/usr/local/lib/systemd/system",
+                "/usr/lib/systemd/system",
+                "/lib/systemd/system",
+            ]
+            .map(PathBuf::from),
+        );
+    }
+    if let Some(roots) = super::offline_roots() {
+        dirs = roots;
+    }
+    let mut unit_names = vec![name.to_string()];
+    if let Some((template, rest)) = name.split_once('@') {
+        if let Some((instance, suffix)) = rest.rsplit_once('.') {
+            if !instance.is_empty() {
+                unit_names.push(format!("{template}@.{suffix}"));
+            }
+        }
+    }
+
+    let mut found: Vec<(String, PathBuf)> = Vec::new();
+    for dir in &dirs {
+        for unit in &unit_names {
+            let Ok(entries) = fs::read_dir(dir.join(format!("{unit}.d"))) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let file_name = entry.file_name().to_string_lossy().into_owned();
+                if file_name.ends_with(".conf") && !found.iter().any(|(f, _)| *f == file_name) {
+                    found.push((file_name, entry.path()));
+                }
+            }
+        }
+    }
+    found.sort();
+    found.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Read the schedule from the `.timer` unit paired with the given `.service`
+/// file path, if there is one.
+fn read_timer(service: &mut ServiceDetails, service_path: &str) {
+    let timer_path = Path::new(service_path).with_extension("timer");
+    if let Ok(contents) = fs::read_to_string(&timer_path) {
+        crate::systemd::parse_timer_into(service, &contents);
+    }
 }
 
 pub fn get_service_file_path(name: &str) -> Result<String> {
@@ -173,103 +418,352 @@ pub fn get_service_file_path(name: &str) -> Result<String> {
     Ok(service.path.clone())
 }
 
-pub fn start_service(name: &str) -> Result<()> {
-    // Reload systemd daemon to pick up any configuration changes
-    refresh_daemon()?;
+/// Why systemd can't be used here, if it can't: `systemctl` is missing, or
+/// the system wasn't booted with systemd (a chroot or most containers). Unit
+/// files can still be read and written.
+pub fn backend_unavailable() -> Option<&'static str> {
+    if super::is_offline() {
+        return Some("ser is running with --offline");
+    }
+    static REASON: OnceLock<Option<&'static str>> = OnceLock::new();
+    *REASON.get_or_init(|| {
+        if crate::migrate::find_in_path("systemctl").is_none() {
+            Some("systemctl isn't installed")
+        } else if !Path::new("/run/systemd/system").exists() {
+            Some(
+                wsl_systemd_hint()
+                    .unwrap_or("systemd isn't running (this looks like a chroot or container)"),
+            )
+        } else {
+            None
+        }
+    })
+}
 
-    // Check if this is a timer-based service
-    let base_name = name.trim_end_matches(".service").trim_end_matches(".timer");
-    let timer_name = format!("{}.timer", base_name);
-    let timer_path = PathBuf::from("/etc/systemd/system").join(&timer_name);
+/// Under WSL, why systemd isn't running and what to do about it: WSL 2 only
+/// boots the distro with systemd when /etc/wsl.conf asks for it, and WSL 1
+/// has no real Linux kernel to run it on at all. `None` elsewhere.
+pub fn wsl_systemd_hint() -> Option<&'static str> {
+    Some(match wsl_version()? {
+        1 => "this is WSL 1, which can't run systemd (move the distro to WSL 2 with `wsl --set-version <distro> 2` from Windows)",
+        _ => "systemd isn't enabled in this WSL distro (add `systemd=true` under `[boot]` in /etc/wsl.conf, then run `wsl --shutdown` from Windows)",
+    })
+}
+
+/// Whether this is Windows Subsystem for Linux.
+pub fn is_wsl() -> bool {
+    wsl_version().is_some()
+}
 
-    let unit_to_start = if timer_path.exists() {
-        // Start and enable the timer, not the service
-        &timer_name
+/// 1 or 2 under Windows Subsystem for Linux. WSL 2 runs a real kernel whose
+/// release names it (`5.15.153.1-microsoft-standard-WSL2`); WSL 1 translates
+/// syscalls and reports a Windows build (`4.4.0-19041-Microsoft`).
+fn wsl_version() -> Option<u8> {
+    let release = fs::read_to_string("/proc/sys/kernel/osrelease")
+        .unwrap_or_default()
+        .to_lowercase();
+    wsl_version_from(&release, std::env::var_os("WSL_DISTRO_NAME").is_some())
+}
+
+fn wsl_version_from(release: &str, distro_set: bool) -> Option<u8> {
+    if release.contains("wsl2") || release.contains("microsoft-standard") {
+        Some(2)
+    } else if release.contains("microsoft") {
+        Some(1)
+    } else if distro_set {
+        // A custom WSL 2 kernel may not say so.
+        Some(2)
     } else {
-        name
-    };
+        None
+    }
+}
+
+pub fn start_service(name: &str) -> Result<()> {
+    super::require_backend(&format!("start '{name}'"))?;
+    // Reload systemd if the unit changed, to pick up the new configuration
+    reload_if_changed(name)?;
+
+    // For a timer-based service, start and enable the timer, not the service
+    let unit_to_start = &target_unit(name);
 
     let mut cmd = Command::new("systemctl");
     cmd.args(["enable", "--now"]).arg(unit_to_start);
     print_command(&cmd);
-    let output = cmd.output().context("Failed to execute systemctl")?;
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute systemctl")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(anyhow!("Failed to start '{}': {}", unit_to_start, stderr));
     }
 
+    log_event("start", unit_to_start);
     Ok(())
 }
 
+/// `systemctl` with `args` and the service's full unit name, for `ser raw`.
+/// User units get `--user`.
+pub fn native_command(name: &str, args: &[String]) -> Result<Command> {
+    super::require_backend(&format!("run systemctl for '{name}'"))?;
+    let service = super::get_service(name)?;
+    let mut cmd = Command::new("systemctl");
+    if service.path.contains("/systemd/user/") {
+        cmd.arg("--user");
+    }
+    cmd.args(super::with_native_target(args, &service.name));
+    Ok(cmd)
+}
+
 /// Run the underlying service unit once, immediately, regardless of whether it
 /// is timer-backed. Unlike `start_service`, this never touches the `.timer`
 /// (which only arms the schedule) — it invokes the `.service` directly so the
 /// job executes right now.
 pub fn run_service_now(name: &str) -> Result<()> {
-    refresh_daemon()?;
+    super::require_backend(&format!("run '{name}'"))?;
+    reload_if_changed(name)?;
 
-    let base_name = name.trim_end_matches(".service").trim_end_matches(".timer");
-    let service_name = format!("{}.service", base_name);
+    let service_name = match super::get_service(name).ok().map(|s| read_trigger(&s)) {
+        Some(Ok(Some(trigger))) => trigger.unit,
+        _ => format!("{}.service", name.trim_end_matches(".service")),
+    };
 
     let mut cmd = Command::new("systemctl");
     cmd.arg("start").arg(&service_name);
     print_command(&cmd);
-    let output = cmd.output().context("Failed to execute systemctl")?;
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute systemctl")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(anyhow!("Failed to run '{}': {}", service_name, stderr));
     }
 
+    log_event("run", &service_name);
     Ok(())
 }
 
 pub fn stop_service(name: &str) -> Result<()> {
-    // Check if this is a timer-based service
-    let base_name = name.trim_end_matches(".service").trim_end_matches(".timer");
-    let timer_name = format!("{}.timer", base_name);
-    let timer_path = PathBuf::from("/etc/systemd/system").join(&timer_name);
-
-    let unit_to_stop = if timer_path.exists() {
-        // Stop and disable the timer
-        &timer_name
-    } else {
-        name
-    };
+    super::require_backend(&format!("stop '{name}'"))?;
+    // For a timer-based service, stop and disable the timer
+    let unit_to_stop = &target_unit(name);
 
     let mut cmd = Command::new("systemctl");
     cmd.args(["disable", "--now"]).arg(unit_to_stop);
     print_command(&cmd);
-    let output = cmd.output().context("Failed to execute systemctl")?;
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute systemctl")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(anyhow!("Failed to stop '{}': {}", unit_to_stop, stderr));
     }
 
+    log_event("stop", unit_to_stop);
+    Ok(())
+}
+
+/// Enable a service to start at boot without starting it now. For a
+/// timer-backed service this enables the timer.
+pub fn enable_service(name: &str) -> Result<()> {
+    super::require_backend(&format!("enable '{name}'"))?;
+    reload_if_changed(name)?;
+    let unit = target_unit(name);
+    enable_at_boot(&unit)?;
+    log_event("enable", &unit);
+    Ok(())
+}
+
+/// Keep a service from starting at boot, leaving it running if it is.
+pub fn disable_service(name: &str) -> Result<()> {
+    super::require_backend(&format!("disable '{name}'"))?;
+    let unit = target_unit(name);
+
+    let mut cmd = Command::new("systemctl");
+    cmd.arg("disable").arg(&unit);
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute systemctl")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to disable '{}': {}", unit, stderr));
+    }
+
+    log_event("disable", &unit);
+    Ok(())
+}
+
+/// A unit file's enablement next to its vendor preset, from
+/// `systemctl list-unit-files`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitFileState {
+    pub unit: String,
+    /// `enabled`, `disabled`, `static`, `masked`, ...
+    pub state: String,
+    /// `enabled` or `disabled`; `None` when no preset applies, or systemd
+    /// predates the column (v245).
+    pub preset: Option<String>,
+    /// Whether a preset rule names this unit, rather than only a catch-all
+    /// `enable *`/`disable *` (or systemd's default of enabling everything
+    /// else, which Debian and Ubuntu rely on).
+    pub explicit_preset: bool,
+}
+
+impl UnitFileState {
+    /// Whether the unit starts without being asked, however it's linked.
+    pub fn is_enabled(&self) -> bool {
+        is_enabled_state(&self.state)
+    }
+
+    /// Whether the unit was enabled or disabled against its preset, e.g. a
+    /// distro service someone turned off and forgot about.
+    pub fn drifted(&self) -> bool {
+        let toggled = |s: &str| matches!(s, "enabled" | "disabled");
+        match self.preset.as_deref() {
+            Some(preset) => toggled(preset) && toggled(&self.state) && self.state != preset,
+            None => false,
+        }
+    }
+}
+
+/// The enablement and preset of every unit file in the system scope, or the
+/// user scope with `user`. Empty where systemd can't be used (see
+/// [`backend_unavailable`]).
+pub fn unit_file_states(user: bool) -> Result<Vec<UnitFileState>> {
+    list_unit_files(user, &[])
+}
+
+/// The enablement and preset of one unit (its timer, for a timer-backed
+/// service, since that's what enabling it enables).
+pub fn unit_file_state(name: &str) -> Result<Option<UnitFileState>> {
+    let user = super::get_service(name)?.path.contains("/systemd/user/");
+    let unit = target_unit(name);
+    Ok(list_unit_files(user, &[&unit])?.into_iter().next())
+}
+
+fn list_unit_files(user: bool, units: &[&str]) -> Result<Vec<UnitFileState>> {
+    if backend_unavailable().is_some() {
+        return Ok(Vec::new());
+    }
+    let mut cmd = Command::new("systemctl");
+    if user {
+        cmd.arg("--user");
+    }
+    cmd.args(["list-unit-files", "--no-legend", "--no-pager"])
+        .args(units);
+    print_command(&cmd);
+    let output = crate::interrupt::query(&mut cmd).context("Failed to execute systemctl")?;
+    // No matching units is exit code 1 with no output; that's just empty.
+    let mut states = parse_unit_files(&String::from_utf8_lossy(&output.stdout));
+    let patterns = preset_patterns(user);
+    for state in &mut states {
+        state.explicit_preset = names_explicitly(&patterns, &state.unit);
+    }
+    Ok(states)
+}
+
+/// The unit patterns of every `enable`/`disable` rule in the scope's
+/// `*.preset` files, in the order systemd applies them: files sorted by name,
+/// a file in an earlier directory hiding one of the same name in a later one.
+fn preset_patterns(user: bool) -> Vec<String> {
+    let scope = if user { "user-preset" } else { "system-preset" };
+    let mut files: Vec<(std::ffi::OsString, PathBuf)> = Vec::new();
+    for dir in [
+        "/etc/systemd",
+        "/run/systemd",
+        "/usr/local/lib/systemd",
+        "/usr/lib/systemd",
+        "/lib/systemd",
+    ] {
+        let Ok(entries) = fs::read_dir(Path::new(dir).join(scope)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if name.to_string_lossy().ends_with(".preset") && !files.iter().any(|(n, _)| *n == name)
+            {
+                files.push((name, entry.path()));
+            }
+        }
+    }
+    files.sort();
+    files
+        .iter()
+        .filter_map(|(_, path)| fs::read_to_string(path).ok())
+        .flat_map(|contents| parse_preset_patterns(&contents))
+        .collect()
+}
+
+fn parse_preset_patterns(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            match words.next()? {
+                "enable" | "disable" | "ignore" => words.next().map(str::to_string),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Whether the first rule matching `unit` names it by more than `*`.
+fn names_explicitly(patterns: &[String], unit: &str) -> bool {
+    patterns
+        .iter()
+        .find(|pattern| crate::config::glob_match(pattern, unit))
+        .is_some_and(|pattern| pattern != "*")
+}
+
+fn parse_unit_files(output: &str) -> Vec<UnitFileState> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let unit = columns.next()?.to_string();
+            let state = columns.next()?.to_string();
+            let preset = columns.next().filter(|p| *p != "-").map(str::to_string);
+            Some(UnitFileState {
+                unit,
+                state,
+                preset,
+                explicit_preset: false,
+            })
+        })
+        .collect()
+}
+
+/// Reset a service's enablement to its vendor preset (`systemctl preset`).
+/// For a timer-backed service this is the timer's.
+pub fn preset_service(name: &str) -> Result<()> {
+    super::require_backend(&format!("reset '{name}' to its preset"))?;
+    let user = super::get_service(name)?.path.contains("/systemd/user/");
+    let unit = target_unit(name);
+
+    let mut cmd = Command::new("systemctl");
+    if user {
+        cmd.arg("--user");
+    }
+    cmd.arg("preset").arg(&unit);
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute systemctl")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("Failed to reset '{}' to its preset: {}", unit, stderr);
+    }
+
+    log_event("preset", &unit);
     Ok(())
 }
 
 pub fn restart_service(name: &str) -> Result<()> {
-    refresh_daemon()?;
+    super::require_backend(&format!("restart '{name}'"))?;
+    reload_if_changed(name)?;
 
     // For timer-backed units, restart the timer so a changed schedule is picked
     // up; restarting the .service would just run it once.
-    let base_name = name.trim_end_matches(".service").trim_end_matches(".timer");
-    let timer_name = format!("{}.timer", base_name);
-    let timer_path = PathBuf::from("/etc/systemd/system").join(&timer_name);
-
-    let unit_to_restart = if timer_path.exists() {
-        &timer_name
-    } else {
-        name
-    };
+    let unit_to_restart = &target_unit(name);
 
     let mut cmd = Command::new("systemctl");
     cmd.args(["restart"]).arg(unit_to_restart);
     print_command(&cmd);
-    let output = cmd.output().context("Failed to execute systemctl")?;
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute systemctl")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -280,35 +774,134 @@ pub fn restart_service(name: &str) -> Result<()> {
         ));
     }
 
+    log_event("restart", unit_to_restart);
     Ok(())
 }
 
-pub fn remove_service(name: &str) -> Result<()> {
-    // Best-effort stop/disable (also handles the paired timer) before deleting.
-    let _ = stop_service(name);
-
-    let base_name = name.trim_end_matches(".service").trim_end_matches(".timer");
-    let dir = PathBuf::from("/etc/systemd/system");
-    let service_path = dir.join(format!("{}.service", base_name));
-    let timer_path = dir.join(format!("{}.timer", base_name));
+/// Uninstall a service: stop and disable it, delete its unit file along with
+/// a paired `.timer` and its drop-ins, and reload systemd. The files are
+/// looked up where the unit actually lives, so a user unit is stopped with
+/// `systemctl --user` and its own drop-ins are removed. Units shipped by the
+/// distribution are refused.
+pub fn delete_service(name: &str) -> Result<()> {
+    let base_name = name.trim_end_matches(".timer");
+    let path = PathBuf::from(super::get_service(base_name)?.path);
+    if is_vendor_path(&path) {
+        bail!(
+            "{} belongs to the distribution; turn it off with `ser disable {base_name}` instead",
+            path.display()
+        );
+    }
+    let user = path.to_string_lossy().contains("/systemd/user/");
+    let systemctl = || {
+        let mut cmd = Command::new("systemctl");
+        if user {
+            cmd.arg("--user");
+        }
+        cmd
+    };
+    let file_name = path
+        .file_name()
+        .map(|f| f.to_string_lossy().into_owned())
+        .unwrap_or_else(|| format!("{base_name}.service"));
+    let timer_path = path.with_extension("timer");
+    let timer_name = format!("{}.timer", file_name.trim_end_matches(".service"));
+
+    if backend_unavailable().is_none() {
+        // Stop and disable the timer too, or the schedule outlives the unit.
+        let mut units = vec![file_name.clone()];
+        if timer_path.exists() {
+            units.push(timer_name);
+        }
+        let mut cmd = systemctl();
+        cmd.args(["disable", "--now"]).args(&units);
+        print_command(&cmd);
+        let output = crate::interrupt::output(&mut cmd).context("Failed to execute systemctl")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("Failed to stop '{}': {}", file_name, stderr.trim());
+        }
+    }
 
-    let mut removed = false;
-    for path in [&service_path, &timer_path] {
-        if path.exists() {
-            fs::remove_file(path)
-                .with_context(|| format!("Failed to remove unit file: {}", path.display()))?;
-            removed = true;
+    for file in [&path, &timer_path] {
+        if file.exists() {
+            crate::record_change("remove", file);
+            fs::remove_file(file)
+                .with_context(|| format!("Failed to remove unit file: {}", file.display()))?;
         }
     }
-    if !removed {
-        bail!("No unit files found for '{}'", name);
+    let drop_ins = path.with_file_name(format!("{file_name}.d"));
+    if drop_ins.is_dir() {
+        crate::record_change("remove", &drop_ins);
+        fs::remove_dir_all(&drop_ins)
+            .with_context(|| format!("Failed to remove {}", drop_ins.display()))?;
     }
 
-    refresh_daemon()?;
+    if backend_unavailable().is_none() {
+        let mut cmd = systemctl();
+        cmd.arg("daemon-reload");
+        print_command(&cmd);
+        let output = crate::interrupt::output(&mut cmd)
+            .context("Failed to execute systemctl daemon-reload")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("systemctl daemon-reload failed: {}", stderr.trim());
+        }
+    }
+    log_event("delete", base_name);
     Ok(())
 }
 
 pub fn create_service(details: &ServiceDetails) -> Result<()> {
+    create_services(std::slice::from_ref(details))
+        .pop()
+        .expect("one result per service")
+        .result
+}
+
+/// Change only the settings that differ between `before` and `after` in the
+/// unit at `path`; see [`super::update_definition`].
+pub fn update_service(path: &Path, before: &ServiceDetails, after: &ServiceDetails) -> Result<()> {
+    super::update_definition(path, before, after)?;
+    refresh_daemon()?;
+    log_event("update", &after.name);
+    Ok(())
+}
+
+/// Create or update several services with a single `daemon-reload`, rather
+/// than one per unit. Returns a result for each service, in order.
+pub fn create_services(services: &[ServiceDetails]) -> Vec<UnitResult> {
+    let mut results: Vec<UnitResult> = services
+        .iter()
+        .map(|details| UnitResult {
+            name: details.name.clone(),
+            result: write_unit_files(details),
+        })
+        .collect();
+
+    if results.iter().any(|r| r.result.is_ok()) {
+        if let Err(e) = refresh_daemon() {
+            for r in results.iter_mut().filter(|r| r.result.is_ok()) {
+                r.result = Err(anyhow!("Written, but daemon-reload failed: {e:#}"));
+            }
+        }
+    }
+
+    // `WantedBy=` only takes effect once the unit is enabled; do that now so
+    // run_at_load behaves like RunAtLoad on macOS and starts at boot.
+    for (details, r) in services.iter().zip(results.iter_mut()) {
+        if r.result.is_ok() && details.run_at_load && details.schedule.is_none() {
+            r.result = enable_at_boot(&format!("{}.service", details.name));
+        }
+    }
+    for r in results.iter().filter(|r| r.result.is_ok()) {
+        log_event("create", &r.name);
+    }
+    results
+}
+
+/// Write the `.service` (and `.timer`, if scheduled) for a service.
+fn write_unit_files(details: &ServiceDetails) -> Result<()> {
     let systemd_system_dir = PathBuf::from("/etc/systemd/system");
 
     // Ensure the directory exists
@@ -317,54 +910,268 @@ pub fn create_service(details: &ServiceDetails) -> Result<()> {
     // Always create the service file
     let service_path = systemd_system_dir.join(format!("{}.service", details.name));
     let service_content = generate_file(details)?;
-    fs::write(&service_path, service_content)
-        .with_context(|| format!("Failed to write unit file: {}", service_path.display()))?;
+    write_service_file(&service_path, &service_content)?;
 
     // If scheduled, also create timer file
     if details.schedule.is_some() {
         let timer_path = systemd_system_dir.join(format!("{}.timer", details.name));
         let timer_content = crate::systemd::generate_timer_file(details)?;
-        fs::write(&timer_path, timer_content)
-            .with_context(|| format!("Failed to write timer file: {}", timer_path.display()))?;
+        write_service_file(&timer_path, &timer_content)?;
     }
+    Ok(())
+}
 
-    // Reload systemd daemon
-    refresh_daemon()?;
+/// Install a ready-made `.service` or `.timer` file into the system (or, with
+/// `user`, the per-user) unit directory, marked as managed by ser, then
+/// reload systemd and optionally enable and start it. Returns the new path.
+pub fn install_service_file(source: &Path, user: bool, start: bool) -> Result<PathBuf> {
+    let contents = fs::read_to_string(source)
+        .with_context(|| format!("Failed to read {}", source.display()))?;
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file", source.display()))?;
+    match source.extension().and_then(|e| e.to_str()) {
+        Some("service") => {
+            parse_systemd(&contents).with_context(|| format!("Invalid {}", source.display()))?;
+        }
+        Some("timer") => {
+            if !contents.lines().any(|l| l.trim() == "[Timer]") {
+                bail!("{} has no [Timer] section", source.display());
+            }
+        }
+        _ => bail!(
+            "Expected a .service or .timer file, got {}",
+            source.display()
+        ),
+    }
+
+    let dir = if user {
+        dirs::config_dir()
+            .ok_or_else(|| anyhow!("No config directory found for user units"))?
+            .join("systemd/user")
+    } else {
+        PathBuf::from("/etc/systemd/system")
+    };
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let target = dir.join(file_name);
+
+    let contents = if contents.starts_with(crate::systemd::MANAGED_BY_COMMENT) {
+        contents
+    } else {
+        format!("{}\n{}", crate::systemd::MANAGED_BY_COMMENT, contents)
+    };
+    write_service_file(&target, &contents)?;
+    if start {
+        super::require_backend(&format!("start '{}'", file_name.to_string_lossy()))?;
+    } else if backend_unavailable().is_some() {
+        return Ok(target);
+    }
+
+    let systemctl = || {
+        let mut cmd = Command::new("systemctl");
+        if user {
+            cmd.arg("--user");
+        }
+        cmd
+    };
+    let mut cmd = systemctl();
+    cmd.arg("daemon-reload");
+    print_command(&cmd);
+    crate::interrupt::status(&mut cmd).context("Failed to execute systemctl daemon-reload")?;
+
+    if start {
+        let mut cmd = systemctl();
+        cmd.args(["enable", "--now"]).arg(file_name);
+        print_command(&cmd);
+        let output = crate::interrupt::output(&mut cmd).context("Failed to execute systemctl")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(
+                "Failed to start '{}': {}",
+                file_name.to_string_lossy(),
+                stderr
+            );
+        }
+    }
+    Ok(target)
+}
 
+/// `systemctl enable` a unit (without starting it) and confirm it took.
+fn enable_at_boot(unit: &str) -> Result<()> {
+    super::require_backend(&format!("enable '{unit}'"))?;
+    let mut cmd = Command::new("systemctl");
+    cmd.arg("enable").arg(unit);
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute systemctl")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("Failed to enable '{}': {}", unit, stderr);
+    }
+
+    let mut cmd = Command::new("systemctl");
+    cmd.args(["is-enabled", "--quiet"]).arg(unit);
+    print_command(&cmd);
+    let enabled = crate::interrupt::query(&mut cmd)
+        .context("Failed to execute systemctl")?
+        .status
+        .success();
+    if !enabled {
+        bail!(
+            "'{}' was enabled but systemctl does not report it as enabled",
+            unit
+        );
+    }
     Ok(())
 }
 
 pub fn is_service_running(name: &str) -> Result<bool> {
+    if backend_unavailable().is_some() {
+        return Ok(false);
+    }
     let mut cmd = Command::new("systemctl");
     cmd.args(["is-active", "--quiet"]).arg(name);
     print_command(&cmd);
-    let output = cmd.output().context("Failed to execute systemctl")?;
+    let output = crate::interrupt::query(&mut cmd).context("Failed to execute systemctl")?;
 
     Ok(output.status.success())
 }
 
-pub fn show_service_logs(name: &str, lines: u32, follow: bool) -> Result<()> {
+/// The unit as systemd has it loaded, from `systemctl show`.
+pub fn runtime_properties(name: &str) -> Result<RuntimeProperties> {
+    if backend_unavailable().is_some() {
+        return Ok(RuntimeProperties::default());
+    }
+    let mut cmd = Command::new("systemctl");
+    cmd.args([
+        "show",
+        name,
+        "--property=FragmentPath,ExecStart,ActiveState,SubState,MainPID,NRestarts",
+    ]);
+    print_command(&cmd);
+    let output = crate::interrupt::query(&mut cmd).context("Failed to execute systemctl show")?;
+    if !output.status.success() {
+        bail!(
+            "systemctl show failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_systemctl_show(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+fn parse_systemctl_show(output: &str) -> RuntimeProperties {
+    let mut props = RuntimeProperties::default();
+    let (mut active, mut sub) = (None, None);
+    let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_string());
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key {
+            "FragmentPath" => props.fragment_path = non_empty(value),
+            // `{ path=/usr/bin/web ; argv[]=/usr/bin/web --port 80 ; ... }`
+            "ExecStart" => {
+                props.exec_start = value
+                    .split_once("argv[]=")
+                    .map(|(_, rest)| rest.split(" ; ").next().unwrap_or(rest).trim().to_string())
+            }
+            "ActiveState" => active = non_empty(value),
+            "SubState" => sub = non_empty(value),
+            // systemd reports 0 when there's no main process.
+            "MainPID" => props.main_pid = value.parse().ok().filter(|pid| *pid != 0),
+            "NRestarts" => props.restarts = value.parse().ok(),
+            _ => {}
+        }
+    }
+    props.state = match (active, sub) {
+        (Some(active), Some(sub)) => Some(format!("{active} ({sub})")),
+        (active, _) => active,
+    };
+    props
+}
+
+/// Whether systemd considers the unit failed: its last run exited non-zero,
+/// crashed, or hit its restart limit.
+pub fn is_service_failed(name: &str) -> bool {
+    if backend_unavailable().is_some() {
+        return false;
+    }
+    let mut cmd = Command::new("systemctl");
+    cmd.args(["is-failed", "--quiet"]).arg(name);
+    print_command(&cmd);
+    crate::interrupt::query(&mut cmd).is_ok_and(|output| output.status.success())
+}
+
+/// Show the journal for one or more units. journalctl merges several units
+/// into a single stream; timestamps are normalized by [`crate::logs`].
+pub fn show_service_logs(names: &[String], options: &LogOptions) -> Result<()> {
+    if super::is_offline() {
+        bail!("Can't read logs: ser is running with --offline");
+    }
     let mut cmd = Command::new("journalctl");
-    cmd.args(["-u", name]);
+    match options.invocation {
+        // Matching on the invocation ID alone (rather than `-u` and the ID,
+        // whose matches journalctl would OR together) selects exactly the
+        // current run's output, on any systemd that records invocation IDs.
+        Some(Invocation::Latest) => {
+            for name in names {
+                cmd.arg(format!("_SYSTEMD_INVOCATION_ID={}", invocation_id(name)?));
+            }
+        }
+        Some(Invocation::Offset(offset)) => {
+            for name in names {
+                cmd.args(["-u", name]);
+            }
+            // Needs systemd 257 or later; older journalctl reports the
+            // unknown option itself.
+            cmd.arg(format!("--invocation={offset}"));
+        }
+        None => {
+            for name in names {
+                cmd.args(["-u", name]);
+            }
+        }
+    }
+    if options.boot {
+        cmd.arg("-b");
+    }
+    if let Some(since) = options.since {
+        cmd.arg(format!("--since=@{}", since.timestamp()));
+    }
 
     // Limit number of lines
-    cmd.arg("-n").arg(lines.to_string());
+    cmd.arg("-n").arg(options.lines.to_string());
+
+    if let Some(priority) = options.priority {
+        cmd.arg("-p").arg(priority.journald_level());
+    }
 
-    if follow {
+    if options.follow {
         cmd.arg("-f");
     }
 
-    // Show output with colors and pager disabled for better integration
-    cmd.arg("--no-pager");
+    // Unix timestamps parse unambiguously; pager disabled since we print
+    cmd.args(["-o", "short-unix", "--no-pager"]);
+    if options.follow {
+        return crate::logs::follow(cmd, options.timestamps);
+    }
+    cmd.stdout(std::process::Stdio::piped());
 
     print_command(&cmd);
     let mut child = cmd
         .spawn()
         .context("Failed to execute journalctl command")?;
+    let tracked = crate::interrupt::StopOnInterrupt::track(&child);
+
+    if let Some(stdout) = child.stdout.take() {
+        crate::logs::print_logs(stdout, options.timestamps, false, options.lines)?;
+    }
 
     let status = child
         .wait()
         .context("Failed to wait for journalctl command")?;
+    tracked.finish().context("Failed to read the journal")?;
 
     if !status.success() {
         return Err(anyhow!("Journalctl command failed with status: {}", status));
@@ -373,24 +1180,261 @@ pub fn show_service_logs(name: &str, lines: u32, follow: bool) -> Result<()> {
     Ok(())
 }
 
+/// The ID systemd assigned to the current (or last) run of a unit.
+fn invocation_id(name: &str) -> Result<String> {
+    let mut cmd = Command::new("systemctl");
+    cmd.args(["show", "-p", "InvocationID", "--value", name]);
+    print_command(&cmd);
+    let output = crate::interrupt::query(&mut cmd).context("Failed to execute systemctl show")?;
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if id.is_empty() {
+        bail!(
+            "'{}' has not run since boot, so it has no invocation to show",
+            name
+        );
+    }
+    Ok(id)
+}
+
+/// `systemctl status` for a unit, without log lines, for alerts.
+pub fn status_report(name: &str) -> Result<String> {
+    super::require_backend("get a status report")?;
+    let mut cmd = Command::new("systemctl");
+    cmd.args(["status", "--no-pager", "--lines=0", name]);
+    print_command(&cmd);
+    // `systemctl status` exits non-zero for stopped/failed units, which is
+    // exactly when this is wanted, so only the output matters.
+    let output = crate::interrupt::query(&mut cmd).context("Failed to execute systemctl status")?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+/// Alert through `ser notify` whenever a unit fails: installs a
+/// `ser-notify@.service` template running `ser notify send`, and a drop-in
+/// adding `OnFailure=ser-notify@%n.service` to the unit. `ser` is the path of
+/// the ser binary to call.
+pub fn install_failure_alert(name: &str, ser: &Path) -> Result<()> {
+    let config = crate::config::config_path().filter(|path| path.exists());
+    if let Some(path) = &config {
+        use std::os::unix::fs::MetadataExt;
+        let meta =
+            fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if meta.uid() != 0 || meta.mode() & 0o022 != 0 {
+            bail!(
+                "The alert runs as root with the channels in {}, so it must belong to root and \
+                 not be writable by others",
+                path.display()
+            );
+        }
+    }
+    for (path, contents) in failure_alert_files(&unit_name(name), ser, config.as_deref()) {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        write_service_file(&path, contents)?;
+    }
+    refresh_daemon()
+}
+
+fn unit_name(name: &str) -> String {
+    if name.contains('.') {
+        name.to_string()
+    } else {
+        format!("{name}.service")
+    }
+}
+
+/// What [`install_failure_alert`] writes for `unit`. The alert runs as root,
+/// whose config needn't be the one with the channels in it, so the alert's
+/// own instance also gets a drop-in pointing `ser` at `config` (the config
+/// of whoever installed it), as `SER_CONFIG`.
+fn failure_alert_files(unit: &str, ser: &Path, config: Option<&Path>) -> Vec<(PathBuf, String)> {
+    let dir = PathBuf::from("/etc/systemd/system");
+    let mut files = vec![
+        (
+            dir.join("ser-notify@.service"),
+            format!(
+                "{}\n[Unit]\nDescription=ser failure alert for %i\n\n[Service]\nType=oneshot\nExecStart={} notify send %i --state failed\n",
+                crate::systemd::MANAGED_BY_COMMENT,
+                ser.display()
+            ),
+        ),
+        (
+            dir.join(format!("{unit}.d/ser-notify.conf")),
+            format!(
+                "{}\n[Unit]\nOnFailure=ser-notify@%n.service\n",
+                crate::systemd::MANAGED_BY_COMMENT
+            ),
+        ),
+    ];
+    if let Some(config) = config {
+        files.push((
+            dir.join(format!("ser-notify@{unit}.service.d/ser-config.conf")),
+            format!(
+                "{}\n[Service]\nEnvironment={}\n",
+                crate::systemd::MANAGED_BY_COMMENT,
+                crate::systemd::quote_env_assignment(&format!(
+                    "SER_CONFIG={}",
+                    config.display().to_string().replace('%', "%%")
+                ))
+            ),
+        ));
+    }
+    files
+}
+
+/// Undo [`install_failure_alert`] for one unit (the template is shared and kept).
+pub fn remove_failure_alert(name: &str) -> Result<()> {
+    let unit = unit_name(name);
+    let dir = PathBuf::from("/etc/systemd/system");
+    let dropin = dir.join(format!("{unit}.d/ser-notify.conf"));
+    if !dropin.exists() {
+        bail!("No failure alert is installed for '{}'", unit);
+    }
+    let config = dir.join(format!("ser-notify@{unit}.service.d/ser-config.conf"));
+    for path in [dropin, config] {
+        if path.exists() {
+            crate::record_change("remove", &path);
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    refresh_daemon()
+}
+
+/// The last `lines` journal lines for a unit, for alerts and reports.
+pub fn recent_logs(name: &str, lines: u32) -> Result<Vec<String>> {
+    if super::is_offline() {
+        return Ok(Vec::new());
+    }
+    let mut cmd = Command::new("journalctl");
+    cmd.args(["-u", name, "--no-pager", "-o", "short"])
+        .arg("-n")
+        .arg(lines.to_string());
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute journalctl")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("journalctl failed: {}", stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
 fn refresh_daemon() -> anyhow::Result<()> {
+    // Without a running systemd there's nothing to reload; it reads the
+    // files when it next starts.
+    if backend_unavailable().is_some() {
+        return Ok(());
+    }
     let mut cmd = Command::new("systemctl");
     cmd.arg("daemon-reload");
     print_command(&cmd);
-    cmd.status()
-        .context("Failed to execute systemctl daemon-reload")?;
+    let output =
+        crate::interrupt::output(&mut cmd).context("Failed to execute systemctl daemon-reload")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("systemctl daemon-reload failed: {}", stderr.trim());
+    }
+    Ok(())
+}
+
+/// Reload systemd before acting on a unit, but only when its definition may
+/// have changed since `ser` last (re)started it, per the state registry.
+/// Without a record, reload anyway but only warn on failure: the unit is most
+/// likely unchanged, and the caller may lack privileges to reload.
+fn reload_if_changed(name: &str) -> Result<()> {
+    let Ok(service) = super::get_service(name) else {
+        return refresh_daemon();
+    };
+    let recorded = crate::state::Registry::load()
+        .ok()
+        .and_then(|r| r.service(name).and_then(|s| s.loaded_checksum.clone()));
+    let current = crate::state::definition_checksum(Path::new(&service.path)).ok();
+    match recorded {
+        Some(recorded) if Some(&recorded) == current.as_ref() => Ok(()),
+        Some(_) => refresh_daemon(),
+        None => {
+            if let Err(e) = refresh_daemon() {
+                eprintln!("Warning: {e:#}");
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Record a change `ser` made to a unit in the journal, with structured
+/// fields (`SER_ACTION`, `SER_USER`, `OBJECT_SYSTEMD_UNIT`) so it shows up in
+/// `journalctl -u <unit>` next to the unit's own output, and can be queried
+/// with e.g. `journalctl SER_ACTION=stop`. Best-effort: a logging failure
+/// never fails the change itself.
+pub fn log_event(action: &str, name: &str) {
+    if super::is_offline() {
+        return;
+    }
+    let unit = if name.contains('.') {
+        name.to_string()
+    } else {
+        format!("{name}.service")
+    };
+    let user = current_user().unwrap_or_default();
+    let fields = [
+        format!("MESSAGE=ser: {action} {unit} (by {user})"),
+        "PRIORITY=5".to_string(),
+        "SYSLOG_IDENTIFIER=ser".to_string(),
+        format!("SER_ACTION={action}"),
+        format!("SER_USER={user}"),
+        format!("OBJECT_SYSTEMD_UNIT={unit}"),
+    ];
+    let mut cmd = Command::new("logger");
+    cmd.arg("--journald")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    print_command(&cmd);
+    let Ok(mut child) = cmd.spawn() else {
+        return;
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        use std::io::Write;
+        // One field per line; a stray newline would start a bogus field.
+        let entry: String = fields
+            .iter()
+            .map(|f| format!("{}\n", f.replace('\n', " ")))
+            .collect();
+        let _ = stdin.write_all(entry.as_bytes());
+    }
+    let _ = child.wait();
+}
+
+/// Show a desktop notification via `notify-send` (libnotify).
+pub fn notify_desktop(title: &str, body: &str) -> Result<()> {
+    let mut cmd = Command::new("notify-send");
+    cmd.args(["--app-name=ser", title, body]);
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute notify-send")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("notify-send failed: {}", stderr.trim());
+    }
     Ok(())
 }
 
 /// Check if a service has an associated timer file.
 pub fn has_timer(name: &str) -> bool {
     let base_name = name.trim_end_matches(".service").trim_end_matches(".timer");
-    let timer_path = PathBuf::from("/etc/systemd/system").join(format!("{}.timer", base_name));
-    timer_path.exists()
+    unit_file_exists(&format!("{}.timer", base_name))
 }
 
 /// Get the next trigger time for a timer.
 pub fn get_timer_next_trigger(name: &str) -> Result<Option<String>> {
+    if backend_unavailable().is_some() {
+        return Ok(None);
+    }
     let base_name = name.trim_end_matches(".service").trim_end_matches(".timer");
     let timer_name = format!("{}.timer", base_name);
 
@@ -402,7 +1446,7 @@ pub fn get_timer_next_trigger(name: &str) -> Result<Option<String>> {
         "--value",
     ]);
     print_command(&cmd);
-    let output = cmd.output().context("Failed to execute systemctl")?;
+    let output = crate::interrupt::query(&mut cmd).context("Failed to execute systemctl")?;
 
     if output.status.success() {
         let next = String::from_utf8_lossy(&output.stdout).trim().to_string();
@@ -413,8 +1457,42 @@ pub fn get_timer_next_trigger(name: &str) -> Result<Option<String>> {
     Ok(None)
 }
 
+/// When a timer last fired (`LastTriggerUSec`) and how its service's last
+/// run ended (`Result`, plus the exit status when it failed).
+pub fn last_run(name: &str) -> LastRun {
+    if backend_unavailable().is_some() {
+        return LastRun::default();
+    }
+    let base_name = name.trim_end_matches(".service").trim_end_matches(".timer");
+    let show = |unit: String, properties: &str| {
+        let mut cmd = Command::new("systemctl");
+        cmd.args(["show", &unit, "--property", properties, "--value"]);
+        query_output(&mut cmd).unwrap_or_default()
+    };
+    let time = show(format!("{base_name}.timer"), "LastTriggerUSec");
+    let service = show(format!("{base_name}.service"), "Result,ExecMainStatus");
+    let mut values = service.lines();
+    let result = values.next().unwrap_or("").trim();
+    let status = values.next().unwrap_or("").trim();
+    let time = time.trim();
+    let fired = !time.is_empty() && time != "n/a";
+    LastRun {
+        time: fired.then(|| time.to_string()),
+        result: match result {
+            "" => None,
+            // A service that never ran still reports `success`.
+            "success" if !fired => None,
+            "success" => Some(result.to_string()),
+            _ => Some(format!("{result} (status {status})")),
+        },
+    }
+}
+
 /// Check if a timer is enabled.
 pub fn is_timer_enabled(name: &str) -> bool {
+    if backend_unavailable().is_some() {
+        return false;
+    }
     let base_name = name.trim_end_matches(".service").trim_end_matches(".timer");
     let timer_name = format!("{}.timer", base_name);
 
@@ -422,9 +1500,514 @@ pub fn is_timer_enabled(name: &str) -> bool {
     cmd.args(["is-enabled", &timer_name]);
     print_command(&cmd);
 
-    if let Ok(output) = cmd.output() {
+    if let Ok(output) = crate::interrupt::query(&mut cmd) {
         let status = String::from_utf8_lossy(&output.stdout);
         return status.trim() == "enabled";
     }
     false
 }
+
+/// Report the systemd version and whether D-Bus and journald are reachable.
+pub fn host_info() -> HostInfo {
+    let mut cmd = Command::new("systemctl");
+    cmd.arg("--version");
+    print_command(&cmd);
+    let init_version = (!super::is_offline())
+        .then(|| crate::interrupt::query(&mut cmd).ok())
+        .flatten()
+        .filter(|o| o.status.success())
+        .and_then(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .next()
+                .map(|l| l.trim().to_string())
+        });
+
+    let facilities = vec![
+        (
+            "systemd running".to_string(),
+            Path::new("/run/systemd/system").exists(),
+        ),
+        (
+            "D-Bus".to_string(),
+            Path::new("/run/dbus/system_bus_socket").exists(),
+        ),
+        (
+            "journald".to_string(),
+            Path::new("/run/systemd/journal/socket").exists(),
+        ),
+    ];
+
+    let environment = wsl_version().map(|version| match std::env::var("WSL_DISTRO_NAME") {
+        Ok(distro) => format!("WSL {version} ({distro})"),
+        Err(_) => format!("WSL {version}"),
+    });
+
+    HostInfo {
+        init_system: "systemd".to_string(),
+        init_version,
+        facilities,
+        environment,
+    }
+}
+
+/// Whether a unit file belongs to the distribution (under `/lib` or
+/// `/usr/lib`) and will be overwritten by package upgrades.
+pub fn is_vendor_path(path: &Path) -> bool {
+    ["/lib/systemd/", "/usr/lib/systemd/"]
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+/// systemd has no equivalent of macOS's SIP-protected jobs; nothing is
+/// off-limits beyond normal permissions.
+pub fn is_protected_path(_path: &Path) -> bool {
+    false
+}
+
+/// Prepare an admin-owned place to edit a vendor unit without touching the
+/// packaged file: a drop-in at `/etc/systemd/system/<unit>.d/override.conf`.
+/// Returns the path to edit, creating it with a header if it doesn't exist.
+pub fn prepare_override(service: &ServiceRef) -> Result<PathBuf> {
+    let dir = PathBuf::from("/etc/systemd/system").join(format!("{}.d", service.name));
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create drop-in directory: {}", dir.display()))?;
+    let path = dir.join("override.conf");
+    if !path.exists() {
+        let header = format!(
+            "# Overrides for {} ({}).\n# Add only the sections and keys you want to change, e.g.\n# [Service]\n# Environment=\"KEY=value\"\n",
+            service.name, service.path
+        );
+        fs::write(&path, header)
+            .with_context(|| format!("Failed to write drop-in: {}", path.display()))?;
+    }
+    Ok(path)
+}
+
+/// Run `program` in place of the service's own, with the same arguments,
+/// through an `ExecStart=` drop-in, so the unit file stays as written and
+/// removing the drop-in goes back to it. Used by `ser deploy`.
+pub fn override_program(name: &str, program: &str) -> Result<PathBuf> {
+    let mut details = super::get_service_details(name)?.service;
+    details.program = program.to_string();
+    let exec_start = crate::systemd::raw_fields(&generate_file(&details)?)
+        .into_iter()
+        .find(|f| f.section == "Service" && f.key == "ExecStart")
+        .map(|f| f.value)
+        .context("Generated unit has no ExecStart")?;
+    let unit = if name.contains('.') {
+        name.to_string()
+    } else {
+        format!("{name}.service")
+    };
+    let dir = PathBuf::from("/etc/systemd/system").join(format!("{unit}.d"));
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create drop-in directory: {}", dir.display()))?;
+    let path = dir.join("ser-deploy.conf");
+    write_service_file(
+        &path,
+        format!(
+            "# Written by `ser deploy`; delete it to run the unit's own ExecStart again.\n\
+             [Service]\nExecStart=\nExecStart={exec_start}\n"
+        ),
+    )?;
+    refresh_daemon()?;
+    log_event("deploy", name);
+    Ok(path)
+}
+
+/// Ask the system package manager (dpkg, rpm, or pacman) which package owns
+/// a unit file. Tries the path as given and its canonical form, since merged-
+/// `/usr` systems may record either `/lib/...` or `/usr/lib/...`.
+pub fn owning_package(path: &Path) -> Option<PackageInfo> {
+    let mut candidates = vec![path.to_path_buf()];
+    if let Ok(canonical) = fs::canonicalize(path) {
+        if canonical != path {
+            candidates.push(canonical);
+        }
+    }
+    candidates.iter().find_map(|p| {
+        query_dpkg(p)
+            .or_else(|| query_rpm(p))
+            .or_else(|| query_pacman(p))
+    })
+}
+
+fn query_output(cmd: &mut Command) -> Option<String> {
+    print_command(cmd);
+    let output = crate::interrupt::output(cmd)
+        .ok()
+        .filter(|o| o.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!stdout.is_empty()).then_some(stdout)
+}
+
+fn query_dpkg(path: &Path) -> Option<PackageInfo> {
+    // `dpkg -S` prints `pkg[:arch][, pkg2]: /path`.
+    let owner = query_output(Command::new("dpkg").arg("-S").arg(path))?;
+    let name = owner
+        .split(':')
+        .next()?
+        .split(',')
+        .next()?
+        .trim()
+        .to_string();
+    let version = query_output(
+        Command::new("dpkg-query")
+            .args(["-W", "-f=${Version}"])
+            .arg(&name),
+    );
+    Some(PackageInfo {
+        manager: "dpkg".to_string(),
+        name,
+        version,
+    })
+}
+
+fn query_rpm(path: &Path) -> Option<PackageInfo> {
+    let owner = query_output(
+        Command::new("rpm")
+            .args(["-qf", "--qf", "%{NAME} %{VERSION}-%{RELEASE}"])
+            .arg(path),
+    )?;
+    let (name, version) = owner.split_once(' ')?;
+    Some(PackageInfo {
+        manager: "rpm".to_string(),
+        name: name.to_string(),
+        version: Some(version.to_string()),
+    })
+}
+
+fn query_pacman(path: &Path) -> Option<PackageInfo> {
+    // `pacman -Qo` prints `/path is owned by pkg version`.
+    let owner = query_output(Command::new("pacman").arg("-Qo").arg(path))?;
+    let rest = owner.split(" is owned by ").nth(1)?;
+    let mut parts = rest.split_whitespace();
+    Some(PackageInfo {
+        manager: "pacman".to_string(),
+        name: parts.next()?.to_string(),
+        version: parts.next().map(|v| v.to_string()),
+    })
+}
+
+/// The login name of the user running `ser`.
+pub fn current_user() -> Option<String> {
+    if let Some(user) = std::env::var("USER").ok().filter(|u| !u.is_empty()) {
+        return Some(user);
+    }
+    let output = Command::new("id").arg("-un").output().ok()?;
+    let user = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!user.is_empty()).then_some(user)
+}
+
+/// Create a system account for a service to run as, with no login shell and
+/// a home under `/var/lib` that it owns. An existing account is left as it
+/// is. Returns the home directory, from the passwd database for an existing
+/// account.
+pub fn create_system_user(name: &str) -> Result<PathBuf> {
+    if super::user_exists(name) {
+        return user_home(name)
+            .with_context(|| format!("'{name}' exists but has no home directory in passwd"));
+    }
+    let home = PathBuf::from("/var/lib").join(name);
+    let mut cmd = Command::new("useradd");
+    cmd.args(["--system", "--user-group", "--home-dir"])
+        .arg(&home)
+        .args(["--shell", "/usr/sbin/nologin", name]);
+    super::run_account_tool(&mut cmd)?;
+    fs::create_dir_all(&home).with_context(|| format!("Failed to create {}", home.display()))?;
+    crate::record_change("create", &home);
+    super::chown_to_user(&home, name)?;
+    Ok(home)
+}
+
+/// An account's home directory, from `getent passwd`.
+fn user_home(name: &str) -> Option<PathBuf> {
+    let mut cmd = Command::new("getent");
+    cmd.args(["passwd", name]);
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).ok()?;
+    let entry = String::from_utf8_lossy(&output.stdout);
+    let home = entry.trim_end().split(':').nth(5)?;
+    (!home.is_empty()).then(|| PathBuf::from(home))
+}
+
+/// Whether systemd keeps `user`'s service manager running without a login
+/// session, which user services need to start at boot.
+pub fn is_linger_enabled(user: &str) -> bool {
+    Path::new("/var/lib/systemd/linger").join(user).exists()
+}
+
+/// Turn on lingering for `user` via `loginctl enable-linger`.
+pub fn enable_linger(user: &str) -> Result<()> {
+    super::require_backend("enable lingering")?;
+    let mut cmd = Command::new("loginctl");
+    cmd.arg("enable-linger").arg(user);
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute loginctl")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("Failed to enable linger for '{}': {}", user, stderr);
+    }
+    Ok(())
+}
+
+/// The main process of a running unit, from `systemctl show -p MainPID`.
+pub fn main_pid(name: &str) -> Option<u32> {
+    if backend_unavailable().is_some() {
+        return None;
+    }
+    let mut cmd = Command::new("systemctl");
+    cmd.args(["show", "-p", "MainPID", "--value", name]);
+    let pid: u32 = query_output(&mut cmd)?.trim().parse().ok()?;
+    (pid != 0).then_some(pid)
+}
+
+/// Cumulative CPU time (ns) and current memory (bytes) of each service's
+/// unit, from its cgroup accounting (`systemctl show -p CPUUsageNSec -p
+/// MemoryCurrent`), in order. One call per scope; `None` for a unit without
+/// accounting.
+pub fn cgroup_usage(services: &[ServiceRef]) -> Vec<Option<(u64, u64)>> {
+    let mut usage = vec![None; services.len()];
+    if backend_unavailable().is_some() {
+        return usage;
+    }
+    for user in [false, true] {
+        let (indexes, units): (Vec<usize>, Vec<String>) = services
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.path.contains("/systemd/user/") == user)
+            .map(|(i, s)| {
+                (
+                    i,
+                    format!("{}.service", s.name.trim_end_matches(".service")),
+                )
+            })
+            .unzip();
+        if units.is_empty() {
+            continue;
+        }
+        let mut cmd = Command::new("systemctl");
+        if user {
+            cmd.arg("--user");
+        }
+        cmd.args(["show", "-p", "CPUUsageNSec", "-p", "MemoryCurrent"])
+            .args(&units);
+        let Some(output) = query_output(&mut cmd) else {
+            continue;
+        };
+        for (i, unit_usage) in indexes.into_iter().zip(parse_cgroup_usage(&output)) {
+            usage[i] = unit_usage;
+        }
+    }
+    usage
+}
+
+/// `systemctl show` prints one block per unit, separated by blank lines;
+/// unset accounting reads `[not set]`.
+fn parse_cgroup_usage(output: &str) -> Vec<Option<(u64, u64)>> {
+    output
+        .split("\n\n")
+        .map(|block| {
+            let value = |key: &str| {
+                block
+                    .lines()
+                    .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+                    .and_then(|v| v.trim().parse::<u64>().ok())
+            };
+            Some((value("CPUUsageNSec")?, value("MemoryCurrent")?))
+        })
+        .collect()
+}
+
+/// Map a process to the unit that owns it by reading its cgroup path, e.g.
+/// `0::/system.slice/nginx.service` -> `nginx.service`.
+pub fn service_for_pid(pid: u32) -> Result<Option<String>> {
+    let cgroup = fs::read_to_string(format!("/proc/{pid}/cgroup"))
+        .with_context(|| format!("No such process: {pid}"))?;
+    Ok(unit_from_cgroup(&cgroup))
+}
+
+fn unit_from_cgroup(cgroup: &str) -> Option<String> {
+    // Prefer the unified (v2) hierarchy line; fall back to the systemd v1 one.
+    let line = cgroup
+        .lines()
+        .find(|l| l.starts_with("0::"))
+        .or_else(|| cgroup.lines().find(|l| l.contains("name=systemd")))?;
+    let path = line.splitn(3, ':').nth(2)?;
+    path.rsplit('/')
+        .find(|segment| segment.ends_with(".service") || segment.ends_with(".scope"))
+        .map(|s| s.to_string())
+}
+
+/// Find the processes listening on a TCP port by matching socket inodes from
+/// `/proc/net/tcp{,6}` against each process's open file descriptors.
+pub fn pids_listening_on(port: u16) -> Result<Vec<u32>> {
+    let mut inodes = Vec::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let Ok(contents) = fs::read_to_string(table) else {
+            continue;
+        };
+        for line in contents.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // local_address is HEXIP:HEXPORT, state 0A is LISTEN, inode is field 9.
+            let (Some(local), Some(state), Some(inode)) =
+                (fields.get(1), fields.get(3), fields.get(9))
+            else {
+                continue;
+            };
+            let local_port = local
+                .rsplit(':')
+                .next()
+                .and_then(|p| u16::from_str_radix(p, 16).ok());
+            if *state == "0A" && local_port == Some(port) {
+                inodes.push(format!("socket:[{inode}]"));
+            }
+        }
+    }
+    if inodes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut pids = Vec::new();
+    for entry in fs::read_dir("/proc")?.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        let owns_socket = fds.flatten().any(|fd| {
+            fs::read_link(fd.path())
+                .map(|target| inodes.iter().any(|i| target.as_os_str() == i.as_str()))
+                .unwrap_or(false)
+        });
+        if owns_socket {
+            pids.push(pid);
+        }
+    }
+    Ok(pids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_wants_links_under_offline_roots() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("multi-user.target.wants")).unwrap();
+        fs::write(root.path().join("web.service"), "[Service]\n").unwrap();
+        std::os::unix::fs::symlink(
+            "../web.service",
+            root.path().join("multi-user.target.wants/web.service"),
+        )
+        .unwrap();
+        let roots = [root.path().to_path_buf()];
+        assert!(is_wanted_in(&roots, "web.service"));
+        assert!(!is_wanted_in(&roots, "worker.service"));
+    }
+
+    #[test]
+    fn failure_alert_points_at_the_installers_config() {
+        let ser = Path::new("/usr/local/bin/ser");
+        let files = failure_alert_files(
+            "web.service",
+            ser,
+            Some(Path::new("/root/.config/ser/config.toml")),
+        );
+        assert_eq!(files.len(), 3);
+        assert_eq!(
+            files[2].0,
+            Path::new("/etc/systemd/system/ser-notify@web.service.service.d/ser-config.conf")
+        );
+        assert!(files[2]
+            .1
+            .contains("Environment=\"SER_CONFIG=/root/.config/ser/config.toml\""));
+        assert!(files[1].1.contains("OnFailure=ser-notify@%n.service"));
+
+        let files = failure_alert_files("web.service", ser, None);
+        assert_eq!(files.len(), 2);
+    }
+
+    #[test]
+    fn parses_systemctl_show() {
+        let output = "FragmentPath=/etc/systemd/system/web.service\nExecStart={ path=/usr/bin/web ; argv[]=/usr/bin/web --port 80 ; ignore_errors=no ; start_time=[n/a] ; stop_time=[n/a] ; pid=0 ; code=(null) ; status=0/0 }\nActiveState=active\nSubState=running\nMainPID=412\nNRestarts=2\n";
+        let props = parse_systemctl_show(output);
+        assert_eq!(
+            props.fragment_path.as_deref(),
+            Some("/etc/systemd/system/web.service")
+        );
+        assert_eq!(props.exec_start.as_deref(), Some("/usr/bin/web --port 80"));
+        assert_eq!(props.state.as_deref(), Some("active (running)"));
+        assert_eq!(props.main_pid, Some(412));
+        assert_eq!(props.restarts, Some(2));
+
+        let stopped = parse_systemctl_show("MainPID=0\nActiveState=inactive\nSubState=dead\n");
+        assert_eq!(
+            parse_cgroup_usage(
+                "CPUUsageNSec=1500\nMemoryCurrent=4096\n\nCPUUsageNSec=[not set]\nMemoryCurrent=[not set]"
+            ),
+            vec![Some((1500, 4096)), None]
+        );
+        assert_eq!(stopped.main_pid, None);
+    }
+
+    #[test]
+    fn parses_unit_files_with_presets() {
+        let output = "nginx.service      disabled enabled\nssh.service        enabled  enabled\ngetty@.service     static   -\nold.service        masked   disabled\nlegacy.service     enabled\n";
+        let states = parse_unit_files(output);
+        assert_eq!(states.len(), 5);
+        assert_eq!(states[0].preset.as_deref(), Some("enabled"));
+        assert!(states[0].drifted());
+        assert!(!states[1].drifted());
+        assert_eq!(states[2].preset, None);
+        assert!(!states[3].drifted());
+        assert_eq!(states[4].preset, None);
+    }
+
+    #[test]
+    fn tells_wsl_versions_apart() {
+        assert_eq!(
+            wsl_version_from("5.15.153.1-microsoft-standard-wsl2", true),
+            Some(2)
+        );
+        assert_eq!(wsl_version_from("4.4.0-19041-microsoft", true), Some(1));
+        assert_eq!(wsl_version_from("6.6.0-custom", true), Some(2));
+        assert_eq!(wsl_version_from("6.8.0-45-generic", false), None);
+    }
+
+    #[test]
+    fn tells_explicit_presets_from_catch_alls() {
+        let patterns = parse_preset_patterns(
+            "# Debian\nenable ssh.service\ndisable getty@.service\nenable systemd-*.timer\n\nenable *\n",
+        );
+        assert_eq!(patterns.len(), 4);
+        assert!(names_explicitly(&patterns, "ssh.service"));
+        assert!(names_explicitly(&patterns, "systemd-tmpfiles-clean.timer"));
+        assert!(!names_explicitly(&patterns, "nginx.service"));
+        assert!(!names_explicitly(&[], "nginx.service"));
+    }
+
+    #[test]
+    fn finds_unit_in_cgroup() {
+        assert_eq!(
+            unit_from_cgroup("0::/system.slice/nginx.service\n").as_deref(),
+            Some("nginx.service")
+        );
+        assert_eq!(
+            unit_from_cgroup("12:pids:/user.slice\n1:name=systemd:/system.slice/cron.service\n")
+                .as_deref(),
+            Some("cron.service")
+        );
+        assert_eq!(
+            unit_from_cgroup("0::/init.scope/child\n"),
+            Some("init.scope".to_string())
+        );
+        assert_eq!(unit_from_cgroup("0::/\n"), None);
+    }
+}
@@ -1,21 +1,30 @@
-use super::{Config, ServiceRef};
+use super::{
+    write_service_file, Config, HostInfo, LastRun, LogOptions, LogPriority, PackageInfo,
+    RuntimeProperties, ServiceRef, Source, UnitResult,
+};
 use crate::platform::ListLevel;
 pub use crate::plist::generate_file;
-use crate::{print_command, CalendarSchedule, FsServiceDetails, Schedule, ServiceDetails};
+use crate::{print_command, FsServiceDetails, ServiceDetails};
 use anyhow::{anyhow, Context, Result};
 use plist::Value;
 use std::fs;
+use std::io::BufRead;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// The `PATH` launchd gives a job that doesn't set one.
+pub const DEFAULT_PATH: &str = "/usr/bin:/bin:/usr/sbin:/sbin";
 
 pub(super) fn get_service_directories() -> Config {
     let mut user_dirs = Vec::new();
     let mut system_dirs = Vec::new();
 
-    // User-specific launch agents
-    if let Some(home) = std::env::var_os("HOME") {
-        let user_agents = PathBuf::from(home).join("Library/LaunchAgents");
-        user_dirs.push(user_agents);
+    // User-specific launch agents. Without a home directory we simply fall
+    // back to system scope.
+    if let Some(home) = dirs::home_dir() {
+        user_dirs.push(home.join("Library/LaunchAgents"));
     }
 
     // System-wide launch agents
@@ -33,28 +42,17 @@ pub(super) fn get_service_directories() -> Config {
     }
 }
 
-pub(super) fn scan_directory(dir: &Path) -> Result<Vec<ServiceRef>> {
-    let mut services = Vec::new();
-
-    if !dir.exists() {
-        return Ok(services);
-    }
-
-    let entries = fs::read_dir(dir)?;
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.extension().and_then(|s| s.to_str()) == Some("plist") {
-            if let Ok(service) = parse_plist_into_service_ref(&path) {
-                services.push(service);
-            }
-        }
-    }
-    Ok(services)
+/// Whether `path` is a plist, by its extension.
+pub(super) fn is_definition(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("plist")
 }
 
-fn parse_plist_into_service_ref(path: &Path) -> Result<ServiceRef> {
+/// Read a plist for listing, failing if it can't be read or parsed. There
+/// are no warnings short of that.
+pub(super) fn parse_service_ref(
+    path: &Path,
+    root: &Path,
+) -> Result<(ServiceRef, Option<anyhow::Error>)> {
     let contents = fs::read(path)?;
     let plist: Value = plist::from_bytes(&contents)?;
     let name = if let Some(label) = plist
@@ -78,11 +76,16 @@ fn parse_plist_into_service_ref(path: &Path) -> Result<ServiceRef> {
         .and_then(|v| v.as_boolean())
         .unwrap_or(false);
 
-    Ok(ServiceRef {
+    let managed = crate::plist::provenance(&plist).is_some();
+    let source = Source::classify(&name, root, managed);
+
+    let service = ServiceRef {
         name,
         path: path.to_string_lossy().to_string(),
         enabled,
-    })
+        source,
+    };
+    Ok((service, None))
 }
 
 fn get_service_path(name: &str) -> Result<String> {
@@ -98,117 +101,6 @@ pub fn get_service_file_path(name: &str) -> Result<String> {
     get_service_path(name)
 }
 
-pub fn parse_plist_into_service(plist: Value) -> Result<ServiceDetails> {
-    let dict = plist
-        .as_dictionary()
-        .ok_or_else(|| anyhow!("Invalid plist format"))?;
-
-    let name = dict
-        .get("Label")
-        .and_then(|v| v.as_string())
-        .unwrap_or_default()
-        .to_string();
-
-    let mut program = dict
-        .get("Program")
-        .and_then(|v| v.as_string())
-        .map(|s| s.to_string());
-
-    let mut arguments: Vec<String> = dict
-        .get("ProgramArguments")
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|v| v.as_string())
-                .map(|s| s.to_string())
-                .collect()
-        })
-        .unwrap_or_default();
-
-    if program.is_none() && !arguments.is_empty() {
-        program = Some(arguments.remove(0));
-    }
-
-    let program = program.context("Missing 'Program' or 'ProgramArguments' in plist")?;
-
-    let working_directory = dict
-        .get("WorkingDirectory")
-        .and_then(|v| v.as_string())
-        .map(|s| s.to_string());
-
-    let run_at_load = dict
-        .get("RunAtLoad")
-        .and_then(|v| v.as_boolean())
-        .unwrap_or(false);
-
-    let keep_alive = dict
-        .get("KeepAlive")
-        .and_then(|v| v.as_boolean())
-        .unwrap_or(false);
-
-    // Parse schedule: a simple repeating StartInterval, or a calendar pattern.
-    let schedule = if let Some(secs) = dict
-        .get("StartInterval")
-        .and_then(|v| v.as_signed_integer())
-    {
-        Some(Schedule::Interval(secs.max(0) as u64))
-    } else {
-        dict.get("StartCalendarInterval")
-            .and_then(parse_calendar_interval)
-            .map(Schedule::Calendar)
-    };
-
-    let env_vars = dict
-        .get("EnvironmentVariables")
-        .and_then(|v| v.as_dictionary())
-        .map(|d| {
-            d.iter()
-                .filter_map(|(k, v)| v.as_string().map(|s| (k.clone(), s.to_string())))
-                .collect()
-        })
-        .unwrap_or_default();
-
-    Ok(ServiceDetails {
-        name,
-        program,
-        arguments,
-        working_directory,
-        run_at_load,
-        keep_alive,
-        env_file: None,
-        env_vars,
-        after: vec![],
-        schedule,
-    })
-}
-
-fn parse_calendar_interval(value: &Value) -> Option<CalendarSchedule> {
-    let dict = value.as_dictionary()?;
-
-    Some(CalendarSchedule {
-        month: dict
-            .get("Month")
-            .and_then(|v| v.as_signed_integer())
-            .map(|v| v as u8),
-        day: dict
-            .get("Day")
-            .and_then(|v| v.as_signed_integer())
-            .map(|v| v as u8),
-        weekday: dict
-            .get("Weekday")
-            .and_then(|v| v.as_signed_integer())
-            .map(|v| v as u8),
-        hour: dict
-            .get("Hour")
-            .and_then(|v| v.as_signed_integer())
-            .map(|v| v as u8),
-        minute: dict
-            .get("Minute")
-            .and_then(|v| v.as_signed_integer())
-            .map(|v| v as u8),
-    })
-}
-
 /// Check if a service has a schedule (is a timer).
 pub fn has_timer(name: &str) -> bool {
     if let Ok(details) = get_service_details(name) {
@@ -217,39 +109,89 @@ pub fn has_timer(name: &str) -> bool {
     false
 }
 
+/// Parse a plist on disk without querying launchd for runtime state.
+pub fn parse_service_file(path: &Path) -> Result<ServiceDetails> {
+    let contents = fs::read(path)
+        .with_context(|| format!("Failed to read service file: {}", path.display()))?;
+    let plist: Value = plist::from_bytes(&contents)
+        .with_context(|| format!("Failed to parse plist: {}", path.display()))?;
+    crate::plist::parse_value(plist)
+}
+
 pub fn get_service_details(name: &str) -> Result<FsServiceDetails> {
     // Find the service first
     let sref = super::get_service(name)?;
 
     // Parse the plist for detailed information
-    let contents = fs::read(&sref.path)
-        .with_context(|| format!("Failed to read service file: {}", sref.path))?;
-    let plist: Value = plist::from_bytes(&contents)
-        .with_context(|| format!("Failed to parse plist: {}", sref.path))?;
+    let service = parse_service_file(Path::new(&sref.path))?;
 
-    let service = parse_plist_into_service(plist)?;
-
-    let running = is_service_running(name)?;
+    let pid = loaded_job(name)?.and_then(|job| job.pid);
 
     Ok(FsServiceDetails {
         service,
         path: sref.path,
         enabled: sref.enabled,
-        running,
+        running: pid.is_some(),
+        pid,
+        trigger: None,
+    })
+}
+
+/// Whether a plist is part of macOS itself. These jobs are read-only to `ser`:
+/// unloading Apple daemons can break the login session and usually fails
+/// under SIP anyway.
+pub fn is_protected_path(path: &Path) -> bool {
+    path.starts_with("/System/Library")
+}
+
+/// Refuse to operate on a protected (Apple system) job, unless allowed.
+fn ensure_not_protected(name: &str, path: &str) -> Result<()> {
+    if !super::allows_protected() && is_protected_path(Path::new(path)) {
+        return Err(anyhow!(
+            "'{}' is an Apple system service ({}); ser treats these as read-only because \
+             changing them can destabilize the session and is usually blocked by SIP \
+             (pass --allow-apple to try anyway)",
+            name,
+            path
+        ));
+    }
+    Ok(())
+}
+
+/// Why launchd can't be used here, if it can't: `launchctl` is missing.
+/// Plists can still be read and written.
+pub fn backend_unavailable() -> Option<&'static str> {
+    if super::is_offline() {
+        return Some("ser is running with --offline");
+    }
+    static REASON: OnceLock<Option<&'static str>> = OnceLock::new();
+    *REASON.get_or_init(|| {
+        crate::migrate::find_in_path("launchctl")
+            .is_none()
+            .then_some("launchctl isn't installed")
     })
 }
 
 pub fn start_service(name: &str) -> Result<()> {
+    super::require_backend(&format!("start '{name}'"))?;
+    let path = get_service_path(name)?;
+    ensure_not_protected(name, &path)?;
+    // Recreate managed directories the user (or a reboot, for the runtime
+    // one) may have removed since the service was created.
+    if let Ok(details) = parse_service_file(Path::new(&path)) {
+        crate::plist::create_directories(&details)?;
+    }
     let mut cmd = Command::new("launchctl");
-    cmd.args(["load", "-w"]).arg(get_service_path(name)?);
+    cmd.args(["load", "-w"]).arg(path);
     print_command(&cmd);
-    let output = cmd.output().context("Failed to execute launchctl")?;
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute launchctl")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(anyhow!("Failed to start service '{}': {}", name, stderr));
     }
 
+    log_event("start", name);
     Ok(())
 }
 
@@ -258,117 +200,598 @@ pub fn start_service(name: &str) -> Result<()> {
 /// right now via `launchctl start <label>`. The job must be loaded first, so we
 /// load it (idempotently) before starting.
 pub fn run_service_now(name: &str) -> Result<()> {
+    super::require_backend(&format!("run '{name}'"))?;
     // Ensure the job is loaded; ignore errors since it may already be loaded.
     let path = get_service_path(name)?;
-    let _ = Command::new("launchctl").args(["load", &path]).output();
+    ensure_not_protected(name, &path)?;
+    let _ = crate::interrupt::output(Command::new("launchctl").args(["load", &path]));
 
     // The launchd label matches the service name for ser-managed units.
     let mut cmd = Command::new("launchctl");
     cmd.arg("start").arg(name);
     print_command(&cmd);
-    let output = cmd.output().context("Failed to execute launchctl")?;
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute launchctl")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(anyhow!("Failed to run service '{}': {}", name, stderr));
     }
 
+    log_event("run", name);
     Ok(())
 }
 
 pub fn stop_service(name: &str) -> Result<()> {
+    super::require_backend(&format!("stop '{name}'"))?;
+    let path = get_service_path(name)?;
+    ensure_not_protected(name, &path)?;
     let mut cmd = Command::new("launchctl");
-    cmd.args(["unload", "-w"]).arg(get_service_path(name)?);
+    cmd.args(["unload", "-w"]).arg(path);
     print_command(&cmd);
-    let output = cmd.output().context("Failed to execute launchctl")?;
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute launchctl")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(anyhow!("Failed to stop service '{}': {}", name, stderr));
     }
 
+    log_event("stop", name);
     Ok(())
 }
 
+/// Let a job load at login or boot without loading it now: clears the plist's
+/// `Disabled` key and launchd's own disabled override.
+pub fn enable_service(name: &str) -> Result<()> {
+    set_enabled(name, true)
+}
+
+/// Keep a job from loading at login or boot, leaving it running if it is.
+pub fn disable_service(name: &str) -> Result<()> {
+    set_enabled(name, false)
+}
+
+fn set_enabled(name: &str, enabled: bool) -> Result<()> {
+    let action = if enabled { "enable" } else { "disable" };
+    super::require_backend(&format!("{action} '{name}'"))?;
+    let path = get_service_path(name)?;
+    ensure_not_protected(name, &path)?;
+    set_disabled_key(Path::new(&path), !enabled)?;
+
+    // launchd consults its override database before the plist's key.
+    let mut cmd = Command::new("launchctl");
+    cmd.arg(action).arg(service_target(name, &path));
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute launchctl")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "Failed to {} service '{}': {}",
+            action,
+            name,
+            stderr
+        ));
+    }
+
+    log_event(action, name);
+    Ok(())
+}
+
+/// Set or clear a plist's `Disabled` key, rewriting it (in the format it was
+/// in) only if that changes anything.
+fn set_disabled_key(path: &Path, disabled: bool) -> Result<()> {
+    let contents = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut value: Value = plist::from_bytes(&contents)
+        .with_context(|| format!("Failed to parse plist: {}", path.display()))?;
+    let dict = value
+        .as_dictionary_mut()
+        .with_context(|| format!("{} is not a plist dictionary", path.display()))?;
+    let current = dict
+        .get("Disabled")
+        .and_then(|v| v.as_boolean())
+        .unwrap_or(false);
+    if current == disabled {
+        return Ok(());
+    }
+    if disabled {
+        dict.insert("Disabled".to_string(), Value::Boolean(true));
+    } else {
+        dict.remove("Disabled");
+    }
+    let format = crate::plist::PlistFormat::detect(&contents);
+    write_service_file(path, crate::plist::encode(&value, format)?)
+}
+
+/// How long `restart_service` waits for a stopped job to exit before giving up.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub fn restart_service(name: &str) -> Result<()> {
+    let path = get_service_path(name)?;
     stop_service(name)?;
-    std::thread::sleep(std::time::Duration::from_millis(500));
+    wait_for_exit(name, &path, STOP_TIMEOUT)?;
     start_service(name)?;
     Ok(())
 }
 
-pub fn create_service(details: &ServiceDetails) -> Result<()> {
-    let plist_data = generate_file(details)
-        .with_context(|| format!("Failed to generate plist for service '{}'", details.name))?;
+/// `launchctl` with `args` and the job's service target (`gui/<uid>/<label>`
+/// or `system/<label>`), for `ser raw`.
+pub fn native_command(name: &str, args: &[String]) -> Result<Command> {
+    super::require_backend(&format!("run launchctl for '{name}'"))?;
+    let path = get_service_path(name)?;
+    let mut cmd = Command::new("launchctl");
+    cmd.args(super::with_native_target(
+        args,
+        &service_target(name, &path),
+    ));
+    Ok(cmd)
+}
+
+/// The launchd service target for a job: `system/<label>` for a
+/// LaunchDaemon, `gui/<uid>/<label>` for a LaunchAgent.
+fn service_target(name: &str, path: &str) -> String {
+    if path.contains("/LaunchDaemons/") {
+        return format!("system/{name}");
+    }
+    let uid = crate::interrupt::output(Command::new("id").arg("-u"))
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+    format!("gui/{uid}/{name}")
+}
+
+/// Poll `launchctl print` until launchd has dropped the job, which happens
+/// once its process has actually exited after an unload.
+fn wait_for_exit(name: &str, path: &str, timeout: Duration) -> Result<()> {
+    let target = service_target(name, path);
+    let deadline = Instant::now() + timeout;
+    loop {
+        let mut cmd = Command::new("launchctl");
+        cmd.args(["print", &target]);
+        print_command(&cmd);
+        let output =
+            crate::interrupt::query(&mut cmd).context("Failed to execute launchctl print")?;
+        if !output.status.success() {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(anyhow!(
+                "Service '{}' was still running {}s after being stopped; not starting it again",
+                name,
+                timeout.as_secs()
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
 
-    let home = dirs::home_dir().context("HOME environment variable not set")?;
+pub fn create_service(details: &ServiceDetails) -> Result<()> {
+    let home = dirs::home_dir()
+        .context("No home directory found; cannot create a LaunchAgent for this user")?;
     let launch_agents_dir = home.join("Library/LaunchAgents");
     // Ensure the directory exists
     fs::create_dir_all(&launch_agents_dir).context("Failed to create LaunchAgents directory")?;
     let plist_path = launch_agents_dir.join(format!("{}.plist", details.name));
 
-    fs::write(&plist_path, plist_data)
-        .with_context(|| format!("Failed to write plist file: {}", plist_path.display()))?;
+    let provenance = crate::plist::Provenance::for_path(&plist_path);
+    let plist_data = crate::plist::generate_file_with_provenance(details, &provenance)
+        .with_context(|| format!("Failed to generate plist for service '{}'", details.name))?;
 
+    write_service_file(&plist_path, &plist_data)?;
+    crate::plist::create_directories(details)?;
+
+    log_event("create", &details.name);
     Ok(())
 }
 
-pub fn remove_service(name: &str) -> Result<()> {
-    let path = get_service_path(name)?;
+/// Change only the keys that differ between `before` and `after` in the
+/// plist at `path`; see [`super::update_definition`].
+pub fn update_service(path: &Path, before: &ServiceDetails, after: &ServiceDetails) -> Result<()> {
+    super::update_definition(path, before, after)?;
+    crate::plist::create_directories(after)?;
+    log_event("update", &after.name);
+    Ok(())
+}
 
-    // Best-effort unload so the job is stopped before its plist disappears.
-    let mut cmd = Command::new("launchctl");
-    cmd.args(["unload", "-w", &path]);
+/// Run `program` in place of the service's own, with the same arguments.
+/// launchd has no drop-ins, so only the program is changed in the plist.
+/// Used by `ser deploy`.
+pub fn override_program(name: &str, program: &str) -> Result<PathBuf> {
+    let found = super::get_service_details(name)?;
+    let mut details = found.service.clone();
+    details.program = program.to_string();
+    let path = PathBuf::from(&found.path);
+    update_service(&path, &found.service, &details)?;
+    Ok(path)
+}
+
+/// Create or update several services. launchd has no daemon-reload step, so
+/// this is just [`create_service`] per service, with a result for each.
+pub fn create_services(services: &[ServiceDetails]) -> Vec<UnitResult> {
+    services
+        .iter()
+        .map(|details| UnitResult {
+            name: details.name.clone(),
+            result: create_service(details),
+        })
+        .collect()
+}
+
+/// Install a ready-made plist into ~/Library/LaunchAgents (or, without `user`,
+/// /Library/LaunchDaemons), optionally loading it. Returns the new path.
+pub fn install_service_file(source: &Path, user: bool, start: bool) -> Result<PathBuf> {
+    if source.extension().is_none_or(|ext| ext != "plist") {
+        return Err(anyhow!("Expected a .plist file, got {}", source.display()));
+    }
+    let details =
+        parse_service_file(source).with_context(|| format!("Invalid {}", source.display()))?;
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file", source.display()))?;
+    if details.name != source.file_stem().unwrap_or_default().to_string_lossy() {
+        eprintln!(
+            "Warning: the plist Label '{}' doesn't match its file name; ser finds services by file name.",
+            details.name
+        );
+    }
+
+    let dir = if user {
+        dirs::home_dir()
+            .context("No home directory found; cannot install a LaunchAgent for this user")?
+            .join("Library/LaunchAgents")
+    } else {
+        PathBuf::from("/Library/LaunchDaemons")
+    };
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let target = dir.join(file_name);
+    let bytes = fs::read(source).with_context(|| format!("Failed to read {}", source.display()))?;
+    // Binary plists are installed as XML so they stay readable and diffable.
+    let contents = match crate::plist::PlistFormat::detect(&bytes) {
+        crate::plist::PlistFormat::Binary => crate::plist::format_plist(&bytes)?.into_bytes(),
+        _ => bytes,
+    };
+    write_service_file(&target, &contents)?;
+
+    if start {
+        super::require_backend(&format!("load {}", target.display()))?;
+        let mut cmd = Command::new("launchctl");
+        cmd.args(["load", "-w"]).arg(&target);
+        print_command(&cmd);
+        let output = crate::interrupt::output(&mut cmd).context("Failed to execute launchctl")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("Failed to load {}: {}", target.display(), stderr));
+        }
+    }
+    Ok(target)
+}
+
+/// Record a change `ser` made to a job in the unified log (via `logger`,
+/// tagged `ser`), so `log show --predicate 'senderImagePath ENDSWITH "logger"'`
+/// or `log show --predicate 'eventMessage BEGINSWITH "ser:"'` lists lifecycle
+/// changes. Best-effort: a logging failure never fails the change itself.
+pub fn log_event(action: &str, name: &str) {
+    if super::is_offline() {
+        return;
+    }
+    let user = std::env::var("USER").unwrap_or_default();
+    let mut cmd = Command::new("logger");
+    cmd.args(["-t", "ser", "-p", "user.notice"])
+        .arg(format!("ser: {action} {name} (by {user})"));
+    print_command(&cmd);
+    let _ = crate::interrupt::output(&mut cmd);
+}
+
+/// Show a Notification Center banner via `osascript`.
+pub fn notify_desktop(title: &str, body: &str) -> Result<()> {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let script = format!(
+        "display notification {} with title {}",
+        quote(body),
+        quote(title)
+    );
+    let mut cmd = Command::new("osascript");
+    cmd.args(["-e", &script]);
     print_command(&cmd);
-    let _ = cmd.output();
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute osascript")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("osascript failed: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+/// Uninstall a service: boot the job out of the launchd domain its plist
+/// belongs to (`system/` for a LaunchDaemon, the user's `gui/` domain for a
+/// LaunchAgent), then delete the plist.
+pub fn delete_service(name: &str) -> Result<()> {
+    let path = get_service_path(name)?;
+    ensure_not_protected(name, &path)?;
 
+    // Best-effort: a job that isn't loaded has nothing to boot out.
+    if backend_unavailable().is_none() {
+        let mut cmd = Command::new("launchctl");
+        cmd.arg("bootout").arg(service_target(name, &path));
+        print_command(&cmd);
+        let _ = crate::interrupt::output(&mut cmd);
+    }
+
+    crate::record_change("remove", Path::new(&path));
     fs::remove_file(&path).with_context(|| format!("Failed to remove plist file: {path}"))?;
+    log_event("delete", name);
     Ok(())
 }
 
-pub fn is_service_running(name: &str) -> Result<bool> {
+/// A row of `launchctl list`: a job launchd has loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedJob {
+    /// `None` when the job is loaded but not running.
+    pub pid: Option<u32>,
+    /// The last exit status (negative for a signal); `None` before any exit.
+    pub status: Option<i32>,
+    pub label: String,
+}
+
+/// The jobs in `launchctl list`; empty when launchd can't be asked.
+fn loaded_jobs() -> Result<Vec<LoadedJob>> {
+    if backend_unavailable().is_some() {
+        return Ok(Vec::new());
+    }
     let mut cmd = Command::new("launchctl");
     cmd.args(["list"]);
     print_command(&cmd);
-    let output = cmd.output().context("Failed to execute launchctl list")?;
+    let output = crate::interrupt::query(&mut cmd).context("Failed to execute launchctl list")?;
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+    Ok(parse_launchctl_list(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
 
+/// Columns are PID (`-` when not running), last exit status, and label,
+/// under a header line.
+fn parse_launchctl_list(output: &str) -> Vec<LoadedJob> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let pid = fields.next()?.trim();
+            let status = fields.next()?.trim();
+            let label = fields.next()?.trim();
+            if pid == "PID" {
+                return None;
+            }
+            Some(LoadedJob {
+                pid: pid.parse().ok(),
+                status: status.parse().ok(),
+                label: label.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The loaded job with exactly this label.
+fn loaded_job(name: &str) -> Result<Option<LoadedJob>> {
+    Ok(loaded_jobs()?.into_iter().find(|job| job.label == name))
+}
+
+pub fn is_service_running(name: &str) -> Result<bool> {
+    Ok(loaded_job(name)?.is_some_and(|job| job.pid.is_some()))
+}
+
+/// [`super::running_states`]: one `launchctl list` for every job.
+pub(super) fn running_states_at_once(services: &[ServiceRef]) -> Vec<bool> {
+    let jobs = loaded_jobs().unwrap_or_default();
+    services
+        .iter()
+        .map(|s| {
+            jobs.iter()
+                .any(|job| job.label == s.name && job.pid.is_some())
+        })
+        .collect()
+}
+
+/// The job as launchd has it loaded, from `launchctl print`.
+pub fn runtime_properties(name: &str) -> Result<RuntimeProperties> {
+    if backend_unavailable().is_some() {
+        return Ok(RuntimeProperties::default());
+    }
+    let path = get_service_path(name)?;
+    let mut cmd = Command::new("launchctl");
+    cmd.args(["print", &service_target(name, &path)]);
+    print_command(&cmd);
+    let output = crate::interrupt::query(&mut cmd).context("Failed to execute launchctl print")?;
     if !output.status.success() {
-        return Ok(false);
+        // Not loaded: launchd has nothing beyond the plist.
+        return Ok(RuntimeProperties::default());
     }
+    Ok(parse_launchctl_print(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(stdout.lines().any(|line| line.contains(name)))
+/// Read the top-level `key = value` lines (and the `arguments` block) of
+/// `launchctl print` output.
+fn parse_launchctl_print(output: &str) -> RuntimeProperties {
+    let mut props = RuntimeProperties::default();
+    let mut program = None;
+    let mut arguments: Option<Vec<String>> = None;
+    let mut lines = output.lines();
+    while let Some(line) = lines.next() {
+        // Top-level properties are indented by exactly one tab.
+        let Some(line) = line.strip_prefix('\t').filter(|l| !l.starts_with('\t')) else {
+            continue;
+        };
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        match key {
+            "path" => props.fragment_path = Some(value.to_string()),
+            "state" => props.state = Some(value.to_string()),
+            "pid" => props.main_pid = value.parse().ok(),
+            "program" => program = Some(value.to_string()),
+            "arguments" if value == "{" => {
+                arguments = Some(
+                    lines
+                        .by_ref()
+                        .map(str::trim)
+                        .take_while(|l| *l != "}")
+                        .map(String::from)
+                        .collect(),
+                );
+            }
+            _ => {}
+        }
+    }
+    props.exec_start = match arguments {
+        Some(arguments) if !arguments.is_empty() => Some(match unwrap_printed(&arguments) {
+            Some((program, rest)) => std::iter::once(program)
+                .chain(rest)
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => arguments.join(" "),
+        }),
+        _ => program,
+    };
+    props
+}
+
+/// The real command inside a ser env wrapper, as `launchctl print` lists
+/// it: one argument per line, so a multi-line wrapper script spans several.
+fn unwrap_printed(lines: &[String]) -> Option<(String, Vec<String>)> {
+    let [sh, c, rest @ ..] = lines else {
+        return None;
+    };
+    let end = rest
+        .iter()
+        .position(|line| line.ends_with(crate::plist::WRAPPER_EXEC))?;
+    let mut arguments = vec![sh.clone(), c.clone(), rest[..=end].join("\n")];
+    arguments.extend_from_slice(&rest[end + 1..]);
+    crate::plist::unwrap_wrapper(&arguments)
+}
+
+/// Whether the job isn't running and its last run exited non-zero (the
+/// closest launchd has to systemd's failed state).
+pub fn is_service_failed(name: &str) -> bool {
+    matches!(
+        loaded_job(name),
+        Ok(Some(LoadedJob { pid: None, status: Some(status), .. })) if status != 0
+    )
+}
+
+/// `launchctl list <label>` for a job (PID, last exit status), for alerts.
+pub fn status_report(name: &str) -> Result<String> {
+    super::require_backend("get a status report")?;
+    let mut cmd = Command::new("launchctl");
+    cmd.args(["list", name]);
+    print_command(&cmd);
+    let output = crate::interrupt::query(&mut cmd).context("Failed to execute launchctl list")?;
+    if !output.status.success() {
+        return Err(anyhow!("'{}' is not loaded", name));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
 }
 
-pub fn show_service_logs(name: &str, lines: u32, follow: bool) -> Result<()> {
-    // First try to find logs using the unified logging system
+/// The last `lines` unified-log lines from the past hour mentioning a job,
+/// for alerts and reports.
+pub fn recent_logs(name: &str, lines: u32) -> Result<Vec<String>> {
+    if super::is_offline() {
+        return Ok(Vec::new());
+    }
     let mut cmd = Command::new("log");
-    cmd.arg("show");
+    cmd.args(["show", "--last", "1h", "--style", "syslog", "--predicate"])
+        .arg(format!(
+            "process CONTAINS[c] '{name}' OR subsystem CONTAINS[c] '{name}'"
+        ));
+    print_command(&cmd);
+    let output =
+        crate::interrupt::output(&mut cmd).context("Failed to execute log show command")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("log show failed: {}", stderr.trim()));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let all: Vec<&str> = stdout.lines().collect();
+    let start = all.len().saturating_sub(lines as usize);
+    Ok(all[start..].iter().map(|l| l.to_string()).collect())
+}
 
-    // Show logs from the last hour to capture recent activity
-    cmd.arg("--last").arg("1h");
+/// Show unified-log entries for one or more jobs, merged and with timestamps
+/// normalized by [`crate::logs`].
+pub fn show_service_logs(names: &[String], options: &LogOptions) -> Result<()> {
+    if super::is_offline() {
+        return Err(anyhow!("Can't read logs: ser is running with --offline"));
+    }
+    if options.invocation.is_some() {
+        return Err(anyhow!(
+            "launchd doesn't track separate runs of a job; use --follow or --boot instead"
+        ));
+    }
+    // A follow that should include recent history (e.g. since a restart
+    // began): `log stream` can't look back, so print that part first.
+    if let (true, Some(_)) = (options.follow, options.since) {
+        show_service_logs(
+            names,
+            &LogOptions {
+                follow: false,
+                ..options.clone()
+            },
+        )?;
+    }
+
+    // First try to find logs using the unified logging system. Following
+    // needs `log stream`; `log show` only reads what is already recorded.
+    let mut cmd = Command::new("log");
+    if options.follow {
+        cmd.arg("stream");
+        if let Some(priority) = options.priority.filter(|p| p.includes_info()) {
+            cmd.arg("--level").arg(priority.journald_level());
+        }
+    } else {
+        cmd.arg("show");
+        if let Some(since) = options.since {
+            let local = since.with_timezone(&chrono::Local);
+            cmd.arg("--start")
+                .arg(local.format("%Y-%m-%d %H:%M:%S").to_string());
+        } else if options.boot {
+            cmd.arg("--last").arg("boot");
+        } else {
+            // Show logs from the last hour to capture recent activity
+            cmd.arg("--last").arg("1h");
+        }
+        if let Some(priority) = options.priority.filter(|p| p.includes_info()) {
+            cmd.arg("--info");
+            if priority == LogPriority::Debug {
+                cmd.arg("--debug");
+            }
+        }
+    }
 
     // Add predicate to filter by service name - try multiple approaches
-    let predicate = format!(
-        "process CONTAINS[c] '{name}' OR subsystem CONTAINS[c] '{name}' OR category CONTAINS[c] '{name}' OR eventMessage CONTAINS[c] '{name}'"
-    );
+    let by_name: Vec<String> = names
+        .iter()
+        .map(|name| format!(
+            "process CONTAINS[c] '{name}' OR subsystem CONTAINS[c] '{name}' OR category CONTAINS[c] '{name}' OR eventMessage CONTAINS[c] '{name}'"
+        ))
+        .collect();
+    let mut predicate = format!("({})", by_name.join(" OR "));
+    if let Some(level) = options.priority.and_then(|p| p.unified_log_predicate()) {
+        predicate = format!("{predicate} AND ({level})");
+    }
     cmd.arg("--predicate").arg(predicate);
 
     cmd.arg("--style").arg("syslog");
 
-    if follow {
-        cmd.arg("--stream");
-        // For follow mode, spawn and let it run
-        print_command(&cmd);
-        let mut child = cmd.spawn().context("Failed to execute log show command")?;
-        let status = child.wait().context("Failed to wait for log command")?;
-        if !status.success() {
-            return Err(anyhow!("Log command failed with status: {}", status));
-        }
+    if options.follow {
+        // For follow mode, stream lines through as they arrive
+        crate::logs::follow(cmd, options.timestamps)?;
     } else {
         // For static logs, capture output and show last N lines
         print_command(&cmd);
-        let output = cmd.output().context("Failed to execute log show command")?;
+        let output =
+            crate::interrupt::output(&mut cmd).context("Failed to execute log show command")?;
+        let name = names.join(", ");
 
         if !output.status.success() {
             // Fallback: try to show launchctl logs or suggest manual approaches
@@ -380,27 +803,263 @@ pub fn show_service_logs(name: &str, lines: u32, follow: bool) -> Result<()> {
             return Ok(());
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let log_lines: Vec<&str> = stdout.lines().collect();
-
-        // Show last N lines
-        let start_idx = if log_lines.len() > lines as usize {
-            log_lines.len() - lines as usize
-        } else {
-            0
-        };
-
-        for &line in &log_lines[start_idx..] {
-            println!("{line}");
-        }
-
-        if log_lines.is_empty() {
+        // `log show` prints a header line even when nothing matched
+        if output.stdout.lines().nth(1).is_none() {
             println!("No recent logs found for service '{name}'");
             println!("Note: macOS services may log to different locations:");
             println!("  • System logs: Check Console.app");
             println!("  • Service-specific logs: Check /var/log/ or ~/Library/Logs/");
+            return Ok(());
         }
+
+        crate::logs::print_logs(&output.stdout[..], options.timestamps, false, options.lines)?;
     }
 
     Ok(())
 }
+
+/// Report the launchd version and whether the unified log is available.
+pub fn host_info() -> HostInfo {
+    let mut cmd = Command::new("launchctl");
+    cmd.arg("version");
+    print_command(&cmd);
+    let init_version = (!super::is_offline())
+        .then(|| crate::interrupt::query(&mut cmd).ok())
+        .flatten()
+        .filter(|o| o.status.success())
+        .and_then(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .next()
+                .map(|l| l.trim().to_string())
+        });
+
+    let facilities = vec![(
+        "unified log (log)".to_string(),
+        Path::new("/usr/bin/log").exists(),
+    )];
+
+    HostInfo {
+        init_system: "launchd".to_string(),
+        init_version,
+        facilities,
+        environment: None,
+    }
+}
+
+/// Whether a plist ships with macOS itself (`/System/Library`) and is
+/// protected by SIP / replaced on OS updates.
+pub fn is_vendor_path(path: &Path) -> bool {
+    path.starts_with("/System/Library")
+}
+
+/// launchd has no drop-ins: a plist is the whole definition.
+pub fn drop_in_paths(_name: &str, _path: &Path) -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// Prepare an editable copy of a vendor plist in `~/Library/LaunchAgents`,
+/// which launchd loads for this user ahead of the system copy. Returns the
+/// path to edit, copying the original there if no copy exists yet.
+pub fn prepare_override(service: &ServiceRef) -> Result<PathBuf> {
+    let home = dirs::home_dir().context("No home directory found for a user-level copy")?;
+    let dir = home.join("Library/LaunchAgents");
+    fs::create_dir_all(&dir).context("Failed to create LaunchAgents directory")?;
+    let file_name = Path::new(&service.path)
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid service path: {}", service.path))?;
+    let path = dir.join(file_name);
+    if !path.exists() {
+        fs::copy(&service.path, &path)
+            .with_context(|| format!("Failed to copy {} to {}", service.path, path.display()))?;
+    }
+    Ok(path)
+}
+
+/// Homebrew generates `homebrew.mxcl.<formula>.plist` for `brew services`;
+/// report the formula and its installed version.
+pub fn owning_package(path: &Path) -> Option<PackageInfo> {
+    let stem = path.file_stem()?.to_str()?;
+    let formula = stem.strip_prefix("homebrew.mxcl.")?.to_string();
+
+    let mut cmd = Command::new("brew");
+    cmd.args(["list", "--versions", &formula]);
+    print_command(&cmd);
+    // Output is `formula 1.2.3 [1.2.2 ...]`; the first version is current.
+    let version = crate::interrupt::output(&mut cmd)
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .split_whitespace()
+                .nth(1)
+                .map(|v| v.to_string())
+        });
+    Some(PackageInfo {
+        manager: "brew".to_string(),
+        name: formula,
+        version,
+    })
+}
+
+/// The process of a running job, from the `"PID" = n;` line of
+/// `launchctl list <label>`.
+pub fn main_pid(name: &str) -> Option<u32> {
+    if backend_unavailable().is_some() {
+        return None;
+    }
+    let mut cmd = Command::new("launchctl");
+    cmd.args(["list", name]);
+    print_command(&cmd);
+    let output = crate::interrupt::query(&mut cmd).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let value = line.trim().strip_prefix("\"PID\" = ")?;
+            value.trim_end_matches(';').parse().ok()
+        })
+}
+
+/// How a job's last run ended, from `LastExitStatus` in `launchctl list
+/// <label>`. launchd doesn't record when a job last ran.
+pub fn last_run(name: &str) -> LastRun {
+    if backend_unavailable().is_some() {
+        return LastRun::default();
+    }
+    let mut cmd = Command::new("launchctl");
+    cmd.args(["list", name]);
+    print_command(&cmd);
+    let Ok(output) = crate::interrupt::query(&mut cmd) else {
+        return LastRun::default();
+    };
+    let status = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let value = line.trim().strip_prefix("\"LastExitStatus\" = ")?;
+            value.trim_end_matches(';').parse::<i64>().ok()
+        });
+    LastRun {
+        time: None,
+        result: status.map(|code| match code {
+            0 => "success".to_string(),
+            code => format!("exit-code (status {code})"),
+        }),
+    }
+}
+
+/// Map a process to the launchd job that owns it via the PID column of
+/// `launchctl list`.
+pub fn service_for_pid(pid: u32) -> Result<Option<String>> {
+    super::require_backend("look up a process's job")?;
+    Ok(loaded_jobs()?
+        .into_iter()
+        .find(|job| job.pid == Some(pid))
+        .map(|job| job.label))
+}
+
+/// Create a hidden system account (and group of the same name) for a daemon
+/// to run as, with no login shell and a home under `/usr/local/var` that it
+/// owns. An existing account is left as it is. Returns the home directory,
+/// from its directory record for an existing account.
+pub fn create_system_user(name: &str) -> Result<PathBuf> {
+    if super::user_exists(name) {
+        return user_home(name)
+            .with_context(|| format!("'{name}' exists but has no NFSHomeDirectory"));
+    }
+    let home = PathBuf::from("/usr/local/var").join(name.trim_start_matches('_'));
+    let id = free_system_id()?.to_string();
+    let group = format!("/Groups/{name}");
+    let user = format!("/Users/{name}");
+    let home_str = home.to_string_lossy();
+    let records: [(&str, &[&str]); 8] = [
+        (&group, &[]),
+        (&group, &["PrimaryGroupID", &id]),
+        (&user, &[]),
+        (&user, &["UniqueID", &id]),
+        (&user, &["PrimaryGroupID", &id]),
+        (&user, &["UserShell", "/usr/bin/false"]),
+        (&user, &["NFSHomeDirectory", &home_str]),
+        (&user, &["IsHidden", "1"]),
+    ];
+    for (record, values) in records {
+        let mut cmd = Command::new("dscl");
+        cmd.args([".", "-create", record]).args(values);
+        super::run_account_tool(&mut cmd)?;
+    }
+    fs::create_dir_all(&home).with_context(|| format!("Failed to create {}", home.display()))?;
+    crate::record_change("create", &home);
+    super::chown_to_user(&home, name)?;
+    Ok(home)
+}
+
+/// An account's home directory, from `dscl`.
+fn user_home(name: &str) -> Option<PathBuf> {
+    let mut cmd = Command::new("dscl");
+    cmd.args([".", "-read", &format!("/Users/{name}"), "NFSHomeDirectory"]);
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).ok()?;
+    let record = String::from_utf8_lossy(&output.stdout);
+    let home = record.trim().strip_prefix("NFSHomeDirectory:")?.trim();
+    (!home.is_empty()).then(|| PathBuf::from(home))
+}
+
+/// The first id in the range macOS leaves for daemons (200-400) that no user
+/// or group has.
+fn free_system_id() -> Result<u32> {
+    let mut taken = Vec::new();
+    for (path, key) in [("/Users", "UniqueID"), ("/Groups", "PrimaryGroupID")] {
+        let mut cmd = Command::new("dscl");
+        cmd.args([".", "-list", path, key]);
+        print_command(&cmd);
+        let output = crate::interrupt::output(&mut cmd).context("Failed to execute dscl")?;
+        taken.extend(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| line.split_whitespace().last()?.parse::<u32>().ok()),
+        );
+    }
+    (200..400)
+        .find(|id| !taken.contains(id))
+        .ok_or_else(|| anyhow!("No free system user id between 200 and 400"))
+}
+
+/// Find the processes listening on a TCP port using `lsof`.
+pub fn pids_listening_on(port: u16) -> Result<Vec<u32>> {
+    let mut cmd = Command::new("lsof");
+    cmd.args(["-nP", "-t", "-sTCP:LISTEN"])
+        .arg(format!("-iTCP:{port}"));
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute lsof")?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| l.trim().parse().ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_launchctl_list() {
+        let output = "PID\tStatus\tLabel\n-\t0\tcom.example.foo\n412\t0\tcom.example.foobar\n-\t-9\tcom.example.crashed\n-\t-\tcom.example.fresh\n";
+        let jobs = parse_launchctl_list(output);
+        assert_eq!(jobs.len(), 4);
+        let foo = jobs.iter().find(|j| j.label == "com.example.foo").unwrap();
+        assert_eq!(foo.pid, None);
+        assert_eq!(jobs[1].pid, Some(412));
+        assert_eq!(jobs[2].status, Some(-9));
+        assert_eq!(jobs[3].status, None);
+    }
+
+    #[test]
+    fn reports_the_wrapped_command() {
+        let output = "gui/501/web = {\n\tpath = /Users/kurt/Library/LaunchAgents/web.plist\n\truns = 3\n\targuments = {\n\t\t/bin/sh\n\t\t-c\n\t\tser_env() {\n\t\t}; ser_env /etc/web.env || exit 1; exec \"$0\" \"$@\"\n\t\t/usr/bin/web\n\t\t--port\n\t\t80\n\t}\n}\n";
+        let props = parse_launchctl_print(output);
+        assert_eq!(props.exec_start.as_deref(), Some("/usr/bin/web --port 80"));
+        assert_eq!(props.restarts, None);
+    }
+}
@@ -0,0 +1,436 @@
+//! Service managers as values rather than the cfg-selected free functions,
+//! so callers can hold a `Box<dyn ServiceManager>`: the init system of this
+//! machine ([`NativeManager`]), or a directory of definitions in another
+//! init system's format ([`DirectoryManager`]), e.g. systemd units written
+//! on a Mac for a Linux box. Tests can substitute their own.
+
+use super::{ListLevel, LogOptions, ScanError, ServiceRef, ServiceScan, Source};
+use crate::{FsServiceDetails, ServiceDetails};
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The operations common to every backend.
+pub trait ServiceManager: Send + Sync {
+    /// What it manages, for messages: `systemd`, `launchd`, ...
+    fn name(&self) -> &str;
+
+    /// Whether this is the init system of this machine, as opposed to files
+    /// for another. Only then do commands touch the host around a service:
+    /// its firewall, its users, and `ser`'s state.
+    fn is_native(&self) -> bool {
+        false
+    }
+
+    /// Why services can't be run through this manager, only read and
+    /// written, if they can't.
+    fn unavailable(&self) -> Option<String>;
+
+    /// Fail with a clear message if services can't be run here, for
+    /// operations that need it (start, stop, ...) rather than just files.
+    fn require_backend(&self, action: &str) -> Result<()> {
+        match self.unavailable() {
+            Some(reason) => bail!(
+                "Can't {action}: {reason}; ser can still list, show, check, and generate services from their files"
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// The services for `level`, along with the files that couldn't be read.
+    fn scan(&self, level: ListLevel) -> ServiceScan;
+
+    fn list(&self, level: ListLevel) -> Result<Vec<ServiceRef>> {
+        Ok(self.scan(level).services)
+    }
+
+    /// The full name of a service given as e.g. `web` for `web.service`.
+    fn resolve(&self, name: &str) -> Result<String> {
+        let wanted = super::normalize_service_name(name);
+        self.list(ListLevel::System)?
+            .into_iter()
+            .find(|s| super::normalize_service_name(&s.name) == wanted)
+            .map(|s| s.name)
+            .ok_or_else(|| anyhow!("Service '{}' not found", name))
+    }
+
+    fn get(&self, name: &str) -> Result<FsServiceDetails>;
+
+    fn is_running(&self, name: &str) -> Result<bool>;
+
+    fn start(&self, name: &str) -> Result<()>;
+
+    fn stop(&self, name: &str) -> Result<()>;
+
+    fn restart(&self, name: &str) -> Result<()>;
+
+    /// Have a service (or a scheduled service's timer) start at boot.
+    fn enable(&self, name: &str) -> Result<()>;
+
+    fn disable(&self, name: &str) -> Result<()>;
+
+    /// Create or update a service from `details`.
+    fn create(&self, details: &ServiceDetails) -> Result<()>;
+
+    /// Change the service whose definition at `path` reads as `before` so it
+    /// reads as `after`, touching only the settings that differ.
+    fn update(&self, path: &Path, before: &ServiceDetails, after: &ServiceDetails) -> Result<()>;
+
+    /// Stop a service and delete its definition.
+    fn delete(&self, name: &str) -> Result<()>;
+
+    fn logs(&self, names: &[String], options: &LogOptions) -> Result<()>;
+}
+
+/// This machine's init system, through the free functions in
+/// [`crate::platform`].
+pub struct NativeManager;
+
+impl ServiceManager for NativeManager {
+    fn name(&self) -> &str {
+        if cfg!(target_os = "macos") {
+            "launchd"
+        } else if cfg!(windows) {
+            "scm"
+        } else {
+            "systemd"
+        }
+    }
+
+    fn is_native(&self) -> bool {
+        true
+    }
+
+    fn unavailable(&self) -> Option<String> {
+        super::backend_unavailable().map(str::to_string)
+    }
+
+    fn scan(&self, level: ListLevel) -> ServiceScan {
+        super::scan_services(level)
+    }
+
+    fn resolve(&self, name: &str) -> Result<String> {
+        super::resolve_service_name(name)
+    }
+
+    fn get(&self, name: &str) -> Result<FsServiceDetails> {
+        super::get_service_details(name)
+    }
+
+    fn is_running(&self, name: &str) -> Result<bool> {
+        super::is_service_running(name)
+    }
+
+    fn start(&self, name: &str) -> Result<()> {
+        super::start_service(name)
+    }
+
+    fn stop(&self, name: &str) -> Result<()> {
+        super::stop_service(name)
+    }
+
+    fn restart(&self, name: &str) -> Result<()> {
+        super::restart_service(name)
+    }
+
+    fn enable(&self, name: &str) -> Result<()> {
+        super::enable_service(name)
+    }
+
+    fn disable(&self, name: &str) -> Result<()> {
+        super::disable_service(name)
+    }
+
+    fn create(&self, details: &ServiceDetails) -> Result<()> {
+        super::create_service(details)
+    }
+
+    fn update(&self, path: &Path, before: &ServiceDetails, after: &ServiceDetails) -> Result<()> {
+        super::update_service(path, before, after)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        super::delete_service(name)
+    }
+
+    fn logs(&self, names: &[String], options: &LogOptions) -> Result<()> {
+        super::show_service_logs(names, options)
+    }
+}
+
+/// A definition format a [`DirectoryManager`] reads and writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionFormat {
+    Systemd,
+    Launchd,
+}
+
+impl DefinitionFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            DefinitionFormat::Systemd => "service",
+            DefinitionFormat::Launchd => "plist",
+        }
+    }
+}
+
+/// Definitions in one directory, in a format that needn't be this
+/// machine's. Services can be listed, read, created, and deleted, but
+/// nothing runs them here, so starting, stopping, enabling, and logs fail.
+pub struct DirectoryManager {
+    format: DefinitionFormat,
+    dir: PathBuf,
+}
+
+impl DirectoryManager {
+    pub fn new(format: DefinitionFormat, dir: impl Into<PathBuf>) -> DirectoryManager {
+        DirectoryManager {
+            format,
+            dir: dir.into(),
+        }
+    }
+
+    fn path_for(&self, name: &str) -> PathBuf {
+        let base = name.trim_end_matches(".service").trim_end_matches(".plist");
+        self.dir.join(format!("{base}.{}", self.format.extension()))
+    }
+
+    fn parse(&self, path: &Path) -> Result<ServiceDetails> {
+        let contents =
+            fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut details = match self.format {
+            DefinitionFormat::Systemd => {
+                crate::systemd::parse_systemd(&String::from_utf8_lossy(&contents))?
+            }
+            DefinitionFormat::Launchd => crate::plist::parse_plist(&contents)?,
+        };
+        if details.name.is_empty() {
+            details.name = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string();
+        }
+        if self.format == DefinitionFormat::Systemd {
+            if let Ok(timer) = fs::read_to_string(path.with_extension("timer")) {
+                crate::systemd::parse_timer_into(&mut details, &timer);
+            }
+        }
+        Ok(details)
+    }
+
+    fn service_ref(&self, path: &Path) -> Result<ServiceRef> {
+        let details = self.parse(path)?;
+        let name = match self.format {
+            DefinitionFormat::Systemd => format!("{}.service", details.name),
+            DefinitionFormat::Launchd => details.name.clone(),
+        };
+        let managed = match self.format {
+            DefinitionFormat::Systemd => {
+                fs::read_to_string(path)?.starts_with(crate::systemd::MANAGED_BY_COMMENT)
+            }
+            DefinitionFormat::Launchd => {
+                let plist: plist::Value = plist::from_file(path)?;
+                crate::plist::provenance(&plist).is_some()
+            }
+        };
+        Ok(ServiceRef {
+            source: Source::classify(
+                &name,
+                &self.dir.canonicalize().unwrap_or_else(|_| self.dir.clone()),
+                managed,
+            ),
+            name,
+            path: path.to_string_lossy().to_string(),
+            enabled: details.run_at_load,
+        })
+    }
+
+    /// The file of a service, which for a plist needn't be named after its
+    /// label.
+    fn find(&self, name: &str) -> Result<PathBuf> {
+        let wanted = super::normalize_service_name(name);
+        self.list(ListLevel::System)?
+            .into_iter()
+            .find(|s| super::normalize_service_name(&s.name) == wanted)
+            .map(|s| PathBuf::from(s.path))
+            .ok_or_else(|| anyhow!("Service '{}' not found", name))
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> Result<()> {
+        crate::record_change("write", path);
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+impl ServiceManager for DirectoryManager {
+    fn name(&self) -> &str {
+        match self.format {
+            DefinitionFormat::Systemd => "systemd",
+            DefinitionFormat::Launchd => "launchd",
+        }
+    }
+
+    fn unavailable(&self) -> Option<String> {
+        Some(format!(
+            "the {} definitions in {} are only files here",
+            self.name(),
+            self.dir.display()
+        ))
+    }
+
+    /// Every level is the one directory.
+    fn scan(&self, _level: ListLevel) -> ServiceScan {
+        let mut scan = ServiceScan::default();
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return scan,
+            Err(e) => {
+                scan.add(&self.dir, Err(e.into()));
+                return scan;
+            }
+        };
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    scan.errors.push(ScanError {
+                        path: self.dir.clone(),
+                        error: e.into(),
+                    });
+                    continue;
+                }
+            };
+            if path.extension().and_then(|s| s.to_str()) == Some(self.format.extension()) {
+                scan.add(
+                    &path,
+                    self.service_ref(&path).map(|service| (service, None)),
+                );
+            }
+        }
+        scan
+    }
+
+    fn get(&self, name: &str) -> Result<FsServiceDetails> {
+        let path = self.find(name)?;
+        let service = self.parse(&path)?;
+        Ok(FsServiceDetails {
+            enabled: service.run_at_load,
+            service,
+            path: path.to_string_lossy().to_string(),
+            running: false,
+            pid: None,
+            trigger: None,
+        })
+    }
+
+    fn is_running(&self, _name: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn start(&self, name: &str) -> Result<()> {
+        self.require_backend(&format!("start '{name}'"))
+    }
+
+    fn stop(&self, name: &str) -> Result<()> {
+        self.require_backend(&format!("stop '{name}'"))
+    }
+
+    fn restart(&self, name: &str) -> Result<()> {
+        self.require_backend(&format!("restart '{name}'"))
+    }
+
+    fn enable(&self, name: &str) -> Result<()> {
+        self.require_backend(&format!("enable '{name}'"))
+    }
+
+    fn disable(&self, name: &str) -> Result<()> {
+        self.require_backend(&format!("disable '{name}'"))
+    }
+
+    /// Write the definition, and for a scheduled systemd service its timer.
+    /// launchd definitions have systemd specifiers expanded first.
+    fn create(&self, details: &ServiceDetails) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.path_for(&details.name);
+        match self.format {
+            DefinitionFormat::Systemd => {
+                self.write(&path, &crate::systemd::generate_file(details)?)?;
+                let timer = path.with_extension("timer");
+                if details.schedule.is_some() {
+                    self.write(&timer, &crate::systemd::generate_timer_file(details)?)?;
+                } else if timer.exists() {
+                    crate::record_change("remove", &timer);
+                    fs::remove_file(&timer)?;
+                }
+            }
+            DefinitionFormat::Launchd => {
+                let (translated, warnings) = crate::specifiers::translate_for_launchd(details);
+                for warning in warnings {
+                    eprintln!("Warning: {warning}");
+                }
+                let provenance = crate::plist::Provenance::for_path(&path);
+                let plist = crate::plist::generate_file_with_provenance(&translated, &provenance)?;
+                self.write(&path, &plist)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn update(&self, path: &Path, before: &ServiceDetails, after: &ServiceDetails) -> Result<()> {
+        super::update_definition(path, before, after)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let path = self.find(name)?;
+        for file in [path.clone(), path.with_extension("timer")] {
+            if file.exists() {
+                crate::record_change("remove", &file);
+                fs::remove_file(&file)
+                    .with_context(|| format!("Failed to remove {}", file.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn logs(&self, names: &[String], _options: &LogOptions) -> Result<()> {
+        self.require_backend(&format!("show logs for '{}'", names.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Schedule;
+
+    #[test]
+    fn directory_round_trips_definitions() {
+        let dir = std::env::temp_dir().join(format!("ser-manager-test-{}", std::process::id()));
+        let details = ServiceDetails {
+            name: "backup".to_string(),
+            program: "/usr/bin/true".to_string(),
+            run_at_load: true,
+            schedule: Some(Schedule::Interval(3600)),
+            ..Default::default()
+        };
+        for format in [DefinitionFormat::Systemd, DefinitionFormat::Launchd] {
+            let manager: Box<dyn ServiceManager> = Box::new(DirectoryManager::new(format, &dir));
+            manager.create(&details).unwrap();
+            let listed = manager.list(ListLevel::Default).unwrap();
+            assert_eq!(listed.len(), 1);
+            assert_eq!(listed[0].source, Source::Ser);
+
+            let found = manager.get("backup").unwrap();
+            assert_eq!(found.service.program, "/usr/bin/true");
+            assert!(found.service.schedule.is_some());
+            assert!(manager.start("backup").is_err());
+            assert!(manager.enable("backup").is_err());
+            assert!(!manager.is_native());
+
+            manager.delete("backup").unwrap();
+            assert!(manager.list(ListLevel::Default).unwrap().is_empty());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
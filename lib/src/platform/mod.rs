@@ -2,15 +2,102 @@
 mod linux;
 #[cfg(target_os = "macos")]
 mod macos;
+mod manager;
+#[cfg(windows)]
+mod windows;
 
-use anyhow::Result;
-use std::path::PathBuf;
+#[cfg(unix)]
+use anyhow::bail;
+use anyhow::{Context, Result};
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct ServiceRef {
     pub name: String,
     pub path: String,
     pub enabled: bool,
+    /// Where the definition came from, classified when the directory is scanned.
+    pub source: Source,
+}
+
+/// The package that installed a service definition.
+#[derive(Debug, Clone)]
+pub struct PackageInfo {
+    /// Package manager that owns the file: `dpkg`, `rpm`, `pacman`, or `brew`.
+    pub manager: String,
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl PackageInfo {
+    pub fn display(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{} {} ({})", self.name, version, self.manager),
+            None => format!("{} ({})", self.name, self.manager),
+        }
+    }
+}
+
+/// Provenance of a service definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// Created by `ser` (carries the managed-by marker).
+    Ser,
+    /// Installed by Homebrew (`homebrew.mxcl.*`).
+    Homebrew,
+    /// Shipped by a distribution package (`/lib`, `/usr/lib`).
+    Vendor,
+    /// Part of macOS itself (`/System/Library`).
+    Apple,
+    /// Anything else: hand-written or installed by other tools.
+    Custom,
+}
+
+impl Source {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Ser => "ser",
+            Source::Homebrew => "homebrew",
+            Source::Vendor => "vendor",
+            Source::Apple => "apple",
+            Source::Custom => "custom",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Source> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ser" | "ser-managed" | "managed" => Some(Source::Ser),
+            "homebrew" | "brew" => Some(Source::Homebrew),
+            "vendor" | "vendor-package" | "package" => Some(Source::Vendor),
+            "apple" | "apple-system" | "system" => Some(Source::Apple),
+            "custom" | "user-custom" => Some(Source::Custom),
+            _ => None,
+        }
+    }
+
+    /// Classify a definition from its name, the directory it was scanned
+    /// from (symlinks resolved, so `/lib` on a merged-`/usr` system and unit
+    /// dirs linked elsewhere go by where they really are), and whether it
+    /// carries the `ser` managed-by marker.
+    pub fn classify(name: &str, root: &std::path::Path, managed: bool) -> Source {
+        if managed {
+            Source::Ser
+        } else if name.starts_with("homebrew.mxcl.") {
+            Source::Homebrew
+        } else if is_protected_path(root) {
+            Source::Apple
+        } else if is_vendor_path(root) {
+            Source::Vendor
+        } else {
+            Source::Custom
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -20,12 +107,277 @@ pub struct Config {
     pub system_dirs: Vec<PathBuf>,
 }
 
+/// The outcome for one service in a batched operation such as
+/// [`create_services`].
+#[derive(Debug)]
+pub struct UnitResult {
+    pub name: String,
+    pub result: Result<()>,
+}
+
+/// What `ser` detected about the host's init system, for `ser info`.
+#[derive(Debug, Clone)]
+pub struct HostInfo {
+    pub init_system: String,
+    pub init_version: Option<String>,
+    /// Backend facilities and whether they are available, e.g. `("journald", true)`.
+    pub facilities: Vec<(String, bool)>,
+    /// A special environment that changes what works, e.g. `WSL (Ubuntu)`.
+    pub environment: Option<String>,
+}
+
+/// The most recent run of a scheduled service, as far as the backend knows.
+#[derive(Debug, Clone, Default)]
+pub struct LastRun {
+    /// When it last fired, in the backend's own format.
+    pub time: Option<String>,
+    /// How it ended, e.g. `success` or `exit-code (status 1)`.
+    pub result: Option<String>,
+}
+
+/// What the service manager itself reports for a loaded unit, which can
+/// differ from its files: generated units, a pending daemon-reload, or
+/// runtime state no file records.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuntimeProperties {
+    /// The file it was loaded from (`FragmentPath`, or launchd's `path`).
+    pub fragment_path: Option<String>,
+    /// The command it runs, as `program arg...`.
+    pub exec_start: Option<String>,
+    /// e.g. `active (running)` or `inactive (dead)`; launchd's `state`.
+    pub state: Option<String>,
+    pub main_pid: Option<u32>,
+    /// Automatic restarts since it was started (`NRestarts`). launchd keeps
+    /// no such count (its `runs` includes every scheduled or on-demand
+    /// launch), so this is `None` there.
+    pub restarts: Option<u32>,
+}
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// File-only mode (`--offline`): never run systemctl/launchctl or read
+/// runtime state, and with `--dir`, only look at those directories. Lets
+/// ser work as a unit file toolchain on build machines and in CI images.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::SeqCst);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+}
+
+static ALLOW_PROTECTED: AtomicBool = AtomicBool::new(false);
+
+/// Let start, stop, run, remove, and edit act on protected (Apple system)
+/// services anyway (`--allow-apple`).
+pub fn set_allow_protected(allow: bool) {
+    ALLOW_PROTECTED.store(allow, Ordering::SeqCst);
+}
+
+pub fn allows_protected() -> bool {
+    ALLOW_PROTECTED.load(Ordering::SeqCst)
+}
+
+/// Fail with a clear message if the init system can't be used here, for
+/// operations that need it (start, stop, ...) rather than just its files.
+pub fn require_backend(action: &str) -> Result<()> {
+    NativeManager.require_backend(action)
+}
+
+/// Whether an account named `name` exists on this machine.
+pub fn user_exists(name: &str) -> bool {
+    let mut cmd = Command::new("id");
+    cmd.args(["-u", name]);
+    crate::print_command(&cmd);
+    crate::interrupt::output(&mut cmd).is_ok_and(|output| output.status.success())
+}
+
+/// Give `path` (not what's under it) to `user` and their primary group.
+#[cfg(unix)]
+fn chown_to_user(path: &Path, user: &str) -> Result<()> {
+    let mut cmd = Command::new("chown");
+    cmd.arg(format!("{user}:")).arg(path);
+    crate::print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute chown")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to give {} to '{user}': {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Run an account management tool (useradd, dscl), failing with its stderr.
+#[cfg(unix)]
+fn run_account_tool(cmd: &mut Command) -> Result<()> {
+    crate::print_command(cmd);
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let output =
+        crate::interrupt::output(cmd).with_context(|| format!("Failed to execute {program}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} failed: {} (creating accounts needs root; try sudo)",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Arguments for a native tool invocation from `ser raw`: the service's
+/// target replaces a `{}` argument, or goes last when there isn't one (where
+/// both systemctl and launchctl expect it for most subcommands).
+pub fn with_native_target(args: &[String], target: &str) -> Vec<String> {
+    if args.iter().any(|a| a == "{}") {
+        args.iter()
+            .map(|a| if a == "{}" { target } else { a }.to_string())
+            .collect()
+    } else {
+        args.iter()
+            .cloned()
+            .chain(std::iter::once(target.to_string()))
+            .collect()
+    }
+}
+
+/// Where the service manager's view differs from what the files say, as
+/// sentences for `ser show`.
+pub fn discrepancies(
+    details: &crate::FsServiceDetails,
+    runtime: &RuntimeProperties,
+) -> Vec<String> {
+    let mut found = Vec::new();
+    if let Some(fragment) = &runtime.fragment_path {
+        let same = fs::canonicalize(fragment).ok() == fs::canonicalize(&details.path).ok();
+        if !same {
+            found.push(format!("loaded from {fragment}, not {}", details.path));
+        }
+    }
+    let service = &details.service;
+    let command = std::iter::once(&service.program)
+        .chain(&service.arguments)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+    // Specifiers and variables are expanded in what's loaded, so only a
+    // literal command can be compared.
+    let literal = !command.contains('%') && !command.contains('$');
+    if let Some(exec_start) = &runtime.exec_start {
+        if details.trigger.is_none()
+            && literal
+            && !service.program.is_empty()
+            && *exec_start != command
+        {
+            found.push(format!(
+                "runs `{exec_start}`, but the file says `{command}` (reload pending?)"
+            ));
+        }
+    }
+    found
+}
+
+/// CPU and memory use of a service, for `ser sample`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceUsage {
+    /// 100.0 is one full core.
+    pub cpu_percent: f64,
+    pub memory_bytes: u64,
+}
+
+/// Minimum severity for `ser logs --priority`, most severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogPriority {
+    Err,
+    Warning,
+    Info,
+    Debug,
+}
+
+impl LogPriority {
+    /// The level name journalctl's `-p` takes; it includes everything more
+    /// severe, too.
+    pub fn journald_level(&self) -> &'static str {
+        match self {
+            LogPriority::Err => "err",
+            LogPriority::Warning => "warning",
+            LogPriority::Info => "info",
+            LogPriority::Debug => "debug",
+        }
+    }
+
+    /// A unified-log predicate for the level. The unified log has no warning
+    /// level, so `Warning` keeps `default` messages, where warnings usually
+    /// end up. `None` means no predicate is needed: `Info` and `Debug` are
+    /// instead shown by including those levels (see [`Self::includes_info`]).
+    pub fn unified_log_predicate(&self) -> Option<&'static str> {
+        match self {
+            LogPriority::Err => Some("messageType == error OR messageType == fault"),
+            LogPriority::Warning => {
+                Some("messageType == default OR messageType == error OR messageType == fault")
+            }
+            LogPriority::Info | LogPriority::Debug => None,
+        }
+    }
+
+    /// Whether the unified log should include info-level messages, which
+    /// `log show`/`log stream` leave out by default.
+    pub fn includes_info(&self) -> bool {
+        *self >= LogPriority::Info
+    }
+}
+
+/// Which run of a unit to show logs for (systemd invocation IDs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Invocation {
+    /// The current (or most recent) run.
+    Latest,
+    /// An offset as journalctl's `--invocation` takes it: 0 is the latest
+    /// run, -1 the one before, and positive values count from the first.
+    Offset(i32),
+}
+
+impl std::str::FromStr for Invocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(Invocation::Latest),
+            _ => s
+                .parse()
+                .map(Invocation::Offset)
+                .map_err(|_| format!("expected 'latest' or a number, got '{s}'")),
+        }
+    }
+}
+
+/// What `show_service_logs` shows.
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    pub lines: u32,
+    pub follow: bool,
+    /// Only messages at this severity or above.
+    pub priority: Option<LogPriority>,
+    pub timestamps: crate::logs::TimestampStyle,
+    /// Only messages from the current boot.
+    pub boot: bool,
+    /// Only messages from one run of the service.
+    pub invocation: Option<Invocation>,
+    /// Only messages at or after this time, e.g. since a restart began.
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
 #[cfg(target_os = "macos")]
 pub use macos::*;
 
 #[cfg(target_os = "linux")]
 pub use linux::*;
 
+#[cfg(windows)]
+pub use windows::*;
+
+pub use manager::{DefinitionFormat, DirectoryManager, NativeManager, ServiceManager};
+
 #[derive(Copy, Clone)]
 pub enum ListLevel {
     Default,
@@ -33,35 +385,393 @@ pub enum ListLevel {
     System,
 }
 
-pub fn list_services(level: ListLevel) -> Result<Vec<ServiceRef>> {
+/// The directories scanned for services: the platform built-ins plus any
+/// extra directories from `--dir` flags or the config file. Extra directories
+/// are part of the default view and are searched when resolving names.
+pub fn service_directories() -> Config {
+    if let Some(roots) = offline_roots() {
+        return Config {
+            default_dirs: roots.clone(),
+            user_dirs: Vec::new(),
+            system_dirs: roots,
+        };
+    }
+    let extra = crate::config::extra_dirs();
+    let mut config = get_service_directories();
+    for dir in extra {
+        if !config.default_dirs.contains(&dir) {
+            config.default_dirs.push(dir.clone());
+        }
+        if !config.system_dirs.contains(&dir) && !config.user_dirs.contains(&dir) {
+            config.system_dirs.push(dir);
+        }
+    }
+    config
+}
+
+/// With `--offline --dir`, the `--dir` directories stand in for the unit
+/// directories of this machine: services, drop-ins, timers, and `.wants`
+/// links are all looked up there.
+pub(crate) fn offline_roots() -> Option<Vec<PathBuf>> {
+    let extra = crate::config::extra_dirs();
+    (is_offline() && !extra.is_empty()).then_some(extra)
+}
+
+/// Whether a service was created by `ser`. systemd units carry the
+/// `MANAGED_BY_COMMENT` header and plists a `SerProvenance` key; plists from
+/// before that key, like everything else in the launchd user dirs, count too.
+pub fn is_managed(service: &ServiceRef) -> bool {
+    if service.path.contains("systemd") {
+        service.source == Source::Ser
+    } else {
+        service.source == Source::Ser || service_scope(service) == "user"
+    }
+}
+
+/// `"user"` if the service lives in one of the user directories, else `"system"`.
+pub fn service_scope(service: &ServiceRef) -> &'static str {
     let config = get_service_directories();
-    let mut services = Vec::new();
+    let path = std::path::Path::new(&service.path);
+    if config.user_dirs.iter().any(|dir| path.starts_with(dir)) {
+        "user"
+    } else {
+        "system"
+    }
+}
 
-    match level {
-        ListLevel::Default => {
-            for dir in &config.default_dirs {
-                let user_services = scan_directory(dir)?;
-                services.extend(user_services);
-            }
-        }
-        ListLevel::User => {
-            for dir in &config.user_dirs {
-                let user_services = scan_directory(dir)?;
-                services.extend(user_services);
+/// Whether `ser` refuses to modify this service (Apple system jobs on macOS)
+/// unless `--allow-apple` is given.
+pub fn is_protected(service: &ServiceRef) -> bool {
+    is_protected_path(std::path::Path::new(&service.path))
+}
+
+/// A service definition that couldn't be read or parsed while scanning.
+#[derive(Debug)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub error: anyhow::Error,
+}
+
+impl std::fmt::Display for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {:#}", self.path.display(), self.error)
+    }
+}
+
+/// What [`scan_services`] found: the services, the files it skipped, and
+/// problems with files it listed anyway.
+#[derive(Debug, Default)]
+pub struct ServiceScan {
+    pub services: Vec<ServiceRef>,
+    pub errors: Vec<ScanError>,
+    pub warnings: Vec<ScanError>,
+}
+
+impl ServiceScan {
+    /// Record the outcome of reading one file. A file that disappeared since
+    /// its directory was listed (another `ser`, or a package upgrade, removing
+    /// it) is simply gone rather than an error.
+    fn add(&mut self, path: &Path, result: Result<(ServiceRef, Option<anyhow::Error>)>) {
+        match result {
+            Ok((service, warning)) => {
+                if let Some(error) = warning {
+                    self.warnings.push(ScanError {
+                        path: path.to_path_buf(),
+                        error,
+                    });
+                }
+                self.services.push(service);
             }
+            Err(e) if is_not_found(&e) => {}
+            Err(error) => self.errors.push(ScanError {
+                path: path.to_path_buf(),
+                error,
+            }),
         }
-        ListLevel::System => {
-            for dir in &config.user_dirs {
-                let user_services = scan_directory(dir)?;
-                services.extend(user_services);
+    }
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+    })
+}
+
+/// The services in the directories for `level`, without the files that
+/// couldn't be read or parsed; see [`scan_services`] for those.
+pub fn list_services(level: ListLevel) -> Result<Vec<ServiceRef>> {
+    Ok(scan_services(level).services)
+}
+
+/// Scan the directories for `level`, keeping unreadable or unparseable files
+/// (and directories) as errors alongside the services that did parse.
+pub fn scan_services(level: ListLevel) -> ServiceScan {
+    let config = service_directories();
+    let dirs: Vec<&PathBuf> = match level {
+        ListLevel::Default => config.default_dirs.iter().collect(),
+        ListLevel::User => config.user_dirs.iter().collect(),
+        ListLevel::System => config
+            .user_dirs
+            .iter()
+            .chain(config.system_dirs.iter())
+            .collect(),
+    };
+
+    let mut scan = ServiceScan::default();
+    for dir in dirs {
+        let root = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                scan.add(dir, Err(e.into()));
+                continue;
             }
-            for dir in &config.system_dirs {
-                let system_services = scan_directory(dir)?;
-                services.extend(system_services);
+        };
+        for entry in entries {
+            match entry {
+                Ok(entry) => {
+                    let path = entry.path();
+                    if is_definition(&path) {
+                        scan.add(&path, parse_service_ref(&path, &root));
+                    }
+                }
+                Err(e) => scan.add(dir, Err(e.into())),
             }
         }
     }
-    Ok(services)
+    scan
+}
+
+/// Replace the scan's guess at whether each of `services` is enabled with
+/// the init system's answer, for the units about to be shown. Only Linux
+/// needs this: elsewhere the definition itself says.
+pub fn resolve_enabled(services: &mut [ServiceRef]) {
+    #[cfg(target_os = "linux")]
+    linux::resolve_enabled(services);
+    #[cfg(not(target_os = "linux"))]
+    let _ = services;
+}
+
+/// Whether each of `services` is running, in order. systemd and launchd are
+/// asked once (per scope) for all of them, not once per service.
+pub fn running_states(services: &[ServiceRef]) -> Vec<bool> {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    return running_states_at_once(services);
+    #[cfg(windows)]
+    services
+        .iter()
+        .map(|s| is_service_running(&s.name).unwrap_or(false))
+        .collect()
+}
+
+/// Write a unit file or plist with the ownership and mode the init system
+/// expects, then verify them.
+///
+/// The file takes the owner of its directory (root for system dirs, the user
+/// for ~/Library/LaunchAgents), gid 0 when that owner is root, and mode 0644.
+/// launchd silently ignores plists that are group/world-writable or owned by
+/// someone else, so a mismatch is an error rather than a surprise later.
+/// Change only what differs between `before` and `after` in the definition
+/// at `path`, and in a unit's `.timer` beside it, for `ser set`. Rewriting
+/// the file from `after` would drop whatever ser doesn't model, and write
+/// the settings of the unit's drop-ins into the unit itself.
+pub fn update_definition(
+    path: &Path,
+    before: &crate::ServiceDetails,
+    after: &crate::ServiceDetails,
+) -> Result<()> {
+    if path.extension().is_some_and(|ext| ext == "plist") {
+        let contents =
+            fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut file: plist::Value = plist::from_bytes(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        let dict = file
+            .as_dictionary_mut()
+            .with_context(|| format!("{} is not a dictionary", path.display()))?;
+        crate::plist::apply_changes(dict, before, after)?;
+        let format = crate::plist::PlistFormat::detect(&contents);
+        return write_service_file(path, crate::plist::encode(&file, format)?);
+    }
+    let edit = |path: &Path, before: String, after: String| -> Result<()> {
+        let changes = crate::systemd::changed_directives(&before, &after);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        write_service_file(path, crate::systemd::apply_directives(&contents, &changes))
+    };
+    edit(
+        path,
+        crate::systemd::generate_file(before)?,
+        crate::systemd::generate_file(after)?,
+    )?;
+    let timer = path.with_extension("timer");
+    if before.schedule.is_some() && after.schedule.is_some() && timer.exists() {
+        edit(
+            &timer,
+            crate::systemd::generate_timer_file(before)?,
+            crate::systemd::generate_timer_file(after)?,
+        )?;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_service_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    crate::record_change("write", path);
+    fs::write(path, contents)
+        .with_context(|| format!("Failed to write service file: {}", path.display()))?;
+    secure_service_file(path)
+}
+
+#[cfg(unix)]
+fn secure_service_file(path: &Path) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("/"));
+    let dir_uid = fs::metadata(dir)
+        .with_context(|| format!("Failed to stat {}", dir.display()))?
+        .uid();
+    let expected_gid = if dir_uid == 0 { Some(0) } else { None };
+
+    // fs::write keeps the mode of an existing file, so always reset it.
+    fs::set_permissions(path, fs::Permissions::from_mode(0o644))
+        .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+
+    let meta = fs::metadata(path)?;
+    if meta.uid() != dir_uid || expected_gid.is_some_and(|gid| meta.gid() != gid) {
+        // Only root can hand files to another owner; report below if this fails.
+        let _ = std::os::unix::fs::chown(path, Some(dir_uid), expected_gid);
+    }
+
+    let meta = fs::metadata(path)?;
+    if meta.uid() != dir_uid || expected_gid.is_some_and(|gid| meta.gid() != gid) {
+        let wanted = format!("{}:{}", dir_uid, expected_gid.unwrap_or(meta.gid()));
+        bail!(
+            "{} is owned by {}:{} but must be owned by {}; fix it with `sudo chown {} {}` or re-run with sudo",
+            path.display(),
+            meta.uid(),
+            meta.gid(),
+            wanted,
+            wanted,
+            path.display(),
+        );
+    }
+    if meta.mode() & 0o022 != 0 {
+        bail!(
+            "{} is group/world-writable (mode {:o}) and would be rejected; fix it with `chmod 644 {}`",
+            path.display(),
+            meta.mode() & 0o777,
+            path.display(),
+        );
+    }
+    Ok(())
+}
+
+/// Windows definitions inherit the ACL of their directory, which ser
+/// restricts to Administrators and SYSTEM.
+#[cfg(not(unix))]
+fn secure_service_file(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// How far apart the two readings of a unit's CPU time are taken.
+#[cfg(target_os = "linux")]
+const USAGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Resource usage of running services, in order. Under systemd this is each
+/// unit's cgroup accounting, covering all of its processes, with CPU time
+/// read twice [`USAGE_INTERVAL`] apart for a current rate. Elsewhere, or for
+/// a unit without accounting, it's `ps` on the main process, whose `%cpu` is
+/// a lifetime average. `None` for a service with neither.
+pub fn resource_usage(services: &[ServiceRef]) -> Vec<Option<ResourceUsage>> {
+    #[cfg(target_os = "linux")]
+    if backend_unavailable().is_none() {
+        let before = cgroup_usage(services);
+        let started = std::time::Instant::now();
+        std::thread::sleep(USAGE_INTERVAL);
+        let after = cgroup_usage(services);
+        let elapsed = started.elapsed().as_nanos() as f64;
+        return services
+            .iter()
+            .zip(before.into_iter().zip(after))
+            .map(|(service, usage)| match usage {
+                (Some((cpu_before, _)), Some((cpu_after, memory))) => Some(ResourceUsage {
+                    cpu_percent: cpu_after.saturating_sub(cpu_before) as f64 * 100.0 / elapsed,
+                    memory_bytes: memory,
+                }),
+                _ => process_usage(&service.name),
+            })
+            .collect();
+    }
+    services.iter().map(|s| process_usage(&s.name)).collect()
+}
+
+/// Usage of a service's main process, read with `ps`.
+fn process_usage(name: &str) -> Option<ResourceUsage> {
+    let pid = main_pid(name)?;
+    let output = crate::interrupt::output(
+        Command::new("ps")
+            .args(["-o", "%cpu=,rss=", "-p"])
+            .arg(pid.to_string()),
+    )
+    .ok()?;
+    parse_ps_usage(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_ps_usage(line: &str) -> Option<ResourceUsage> {
+    let mut fields = line.split_whitespace();
+    let cpu_percent = fields.next()?.parse().ok()?;
+    let rss_kb: u64 = fields.next()?.parse().ok()?;
+    Some(ResourceUsage {
+        cpu_percent,
+        memory_bytes: rss_kb * 1024,
+    })
+}
+
+/// The raw key/values of a service file: a plist on macOS, a unit file
+/// otherwise.
+pub fn raw_fields(path: &Path) -> Result<Vec<crate::RawField>> {
+    let contents = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if path.extension().is_some_and(|ext| ext == "plist") {
+        crate::plist::raw_fields(&contents)
+    } else {
+        Ok(crate::systemd::raw_fields(&String::from_utf8_lossy(
+            &contents,
+        )))
+    }
+}
+
+/// A systemd unit's settings with `drop_ins` (from [`drop_in_paths`])
+/// applied, each with the file it came from.
+pub fn effective_fields(
+    path: &Path,
+    drop_ins: &[PathBuf],
+) -> Result<Vec<crate::systemd::EffectiveField>> {
+    let mut files = Vec::new();
+    for file in std::iter::once(path).chain(drop_ins.iter().map(PathBuf::as_path)) {
+        let contents = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        files.push((file.to_path_buf(), contents));
+    }
+    Ok(crate::systemd::merge_unit_files(&files))
+}
+
+/// The tags in a scanned service's definition, read from its file alone, so
+/// filtering a scan by tag doesn't look each service up again.
+pub fn service_tags(service: &ServiceRef) -> Vec<String> {
+    let path = Path::new(&service.path);
+    let details = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("plist") => fs::read(path)
+            .ok()
+            .and_then(|contents| crate::plist::parse_plist(&contents).ok()),
+        Some("service") => fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| crate::systemd::parse_systemd(&contents).ok()),
+        _ => parse_service_file(path).ok(),
+    };
+    details.map(|d| d.tags).unwrap_or_default()
 }
 
 pub fn normalize_service_name(name: &str) -> &str {
@@ -89,3 +799,62 @@ pub fn resolve_service_name(name: &str) -> Result<String> {
     let service = get_service(name)?;
     Ok(service.name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn places_native_target() {
+        let args = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(
+            with_native_target(&args("kill -s TERM"), "web.service"),
+            args("kill -s TERM web.service")
+        );
+        assert_eq!(
+            with_native_target(&args("kickstart -k {}"), "gui/501/web"),
+            args("kickstart -k gui/501/web")
+        );
+    }
+
+    #[test]
+    fn maps_log_priorities() {
+        assert_eq!(LogPriority::Err.journald_level(), "err");
+        assert!(LogPriority::Err.unified_log_predicate().is_some());
+        assert!(!LogPriority::Warning.includes_info());
+        assert!(LogPriority::Debug.includes_info());
+    }
+
+    #[test]
+    fn parses_invocations() {
+        assert_eq!("latest".parse(), Ok(Invocation::Latest));
+        assert_eq!("-1".parse(), Ok(Invocation::Offset(-1)));
+        assert!("previous".parse::<Invocation>().is_err());
+    }
+
+    #[test]
+    fn parses_ps_usage() {
+        assert_eq!(
+            parse_ps_usage("  1.5  2048\n"),
+            Some(ResourceUsage {
+                cpu_percent: 1.5,
+                memory_bytes: 2048 * 1024
+            })
+        );
+        assert_eq!(parse_ps_usage(""), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn service_file_is_not_group_writable() {
+        let dir = std::env::temp_dir().join(format!("ser-write-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("demo.service");
+        fs::write(&path, "").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o666)).unwrap();
+
+        write_service_file(&path, "[Unit]\n").unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().mode() & 0o777, 0o644);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
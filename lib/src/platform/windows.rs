@@ -0,0 +1,1101 @@
+use super::{
+    write_service_file, Config, HostInfo, LastRun, LogOptions, LogPriority, PackageInfo,
+    RuntimeProperties, ServiceRef, Source, UnitResult,
+};
+use crate::platform::ListLevel;
+use crate::{print_command, FsServiceDetails, ServiceDetails};
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// The `PATH` a service gets from the system environment by default.
+pub const DEFAULT_PATH: &str = r"C:\Windows\system32;C:\Windows;C:\Windows\System32\Wbem";
+
+/// Win32 errors `sc.exe` exits with that ser handles rather than reports.
+const ERROR_SERVICE_ALREADY_RUNNING: i32 = 1056;
+const ERROR_SERVICE_DOES_NOT_EXIST: i32 = 1060;
+const ERROR_SERVICE_NOT_ACTIVE: i32 = 1062;
+const ERROR_SERVICE_NEVER_STARTED: u32 = 1077;
+
+/// Where ser keeps service definitions. The Service Control Manager only
+/// stores a command line per service, so the full definition is written
+/// here as JSON and is what ser lists and reads back.
+fn definitions_dir() -> PathBuf {
+    std::env::var_os("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+        .join("ser")
+        .join("services")
+}
+
+/// The SIDs that may own or change the definitions directory:
+/// `NT AUTHORITY\SYSTEM` and `BUILTIN\Administrators`, in their SDDL short
+/// and long forms.
+const TRUSTED_SIDS: &[&str] = &["SY", "BA", "S-1-5-18", "S-1-5-32-544"];
+
+/// Create the definitions directory, owned by Administrators and writable
+/// only by them and SYSTEM. Any user may create folders in `%ProgramData%`,
+/// so an existing directory is taken over and its ACL replaced.
+fn create_definitions_dir() -> Result<PathBuf> {
+    let dir = definitions_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    for args in [
+        &["/setowner", "*S-1-5-32-544"][..],
+        &[
+            "/inheritance:r",
+            "/grant:r",
+            "*S-1-5-18:(OI)(CI)F",
+            "*S-1-5-32-544:(OI)(CI)F",
+        ],
+    ] {
+        let mut cmd = Command::new("icacls.exe");
+        cmd.arg(&dir).args(args);
+        print_command(&cmd);
+        let output = crate::interrupt::output(&mut cmd).context("Failed to execute icacls.exe")?;
+        if !output.status.success() {
+            bail!(
+                "Failed to restrict {} to Administrators: {}",
+                dir.display(),
+                String::from_utf8_lossy(&output.stdout).trim()
+            );
+        }
+    }
+    Ok(dir)
+}
+
+/// Refuse to read definitions from a directory someone other than
+/// Administrators or SYSTEM owns or can write to: whoever controls the
+/// definitions controls what ser installs and starts as LocalSystem.
+/// Checked once per run.
+fn check_definitions_dir() -> Result<()> {
+    static CHECKED: OnceLock<std::result::Result<(), String>> = OnceLock::new();
+    CHECKED
+        .get_or_init(|| {
+            let dir = definitions_dir();
+            let mut cmd = Command::new("powershell.exe");
+            cmd.args(["-NoProfile", "-NonInteractive", "-Command"])
+                .arg(format!(
+                    "(Get-Acl -LiteralPath '{}').Sddl",
+                    dir.display().to_string().replace('\'', "''")
+                ));
+            print_command(&cmd);
+            let output = crate::interrupt::query(&mut cmd)
+                .map_err(|e| format!("Failed to read the ACL of {}: {e}", dir.display()))?;
+            let sddl = String::from_utf8_lossy(&output.stdout);
+            if !output.status.success() || !sddl_is_trusted(sddl.trim()) {
+                return Err(format!(
+                    "{} can be changed by accounts other than Administrators and SYSTEM; \
+                     not reading service definitions from it. Fix it from an elevated prompt with \
+                     `icacls {} /setowner *S-1-5-32-544` and \
+                     `icacls {} /inheritance:r /grant:r *S-1-5-18:(OI)(CI)F *S-1-5-32-544:(OI)(CI)F`",
+                    dir.display(),
+                    dir.display(),
+                    dir.display()
+                ));
+            }
+            Ok(())
+        })
+        .clone()
+        .map_err(|e| anyhow!(e))
+}
+
+/// Whether a security descriptor in SDDL form is owned by, and only grants
+/// access to, [`TRUSTED_SIDS`]. Deny entries are fine.
+fn sddl_is_trusted(sddl: &str) -> bool {
+    let Some(owner) = sddl
+        .strip_prefix("O:")
+        .and_then(|rest| rest.split(['G', 'D']).next())
+    else {
+        return false;
+    };
+    let Some((_, dacl)) = sddl.split_once("D:") else {
+        return false;
+    };
+    let dacl = dacl.split("S:").next().unwrap_or(dacl);
+    TRUSTED_SIDS.contains(&owner.trim_end_matches(':'))
+        && dacl
+            .split('(')
+            .skip(1)
+            .filter_map(|ace| ace.split(')').next())
+            .all(|ace| {
+                let fields: Vec<&str> = ace.split(';').collect();
+                match fields.first() {
+                    Some(&"D") | Some(&"OD") => true,
+                    _ => fields.get(5).is_some_and(|sid| TRUSTED_SIDS.contains(sid)),
+                }
+            })
+}
+
+/// Windows services are system-wide, so there are no user directories.
+pub(super) fn get_service_directories() -> Config {
+    let dir = definitions_dir();
+    Config {
+        default_dirs: vec![dir.clone()],
+        user_dirs: Vec::new(),
+        system_dirs: vec![dir],
+    }
+}
+
+/// Whether `path` is a definition, by its extension.
+pub(super) fn is_definition(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("json")
+}
+
+/// Read a definition for listing, failing if it can't be read or parsed.
+/// There are no warnings short of that.
+pub(super) fn parse_service_ref(
+    path: &Path,
+    root: &Path,
+) -> Result<(ServiceRef, Option<anyhow::Error>)> {
+    let details = parse_service_file(path)?;
+    let name = if details.name.is_empty() {
+        path.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("unknown")
+            .to_string()
+    } else {
+        details.name
+    };
+    // Everything in the definitions directory was written by ser.
+    let source = Source::classify(&name, root, true);
+
+    let service = ServiceRef {
+        name,
+        path: path.to_string_lossy().to_string(),
+        enabled: details.run_at_load,
+        source,
+    };
+    Ok((service, None))
+}
+
+fn get_service_path(name: &str) -> Result<String> {
+    let all_services = super::list_services(ListLevel::System)?;
+    let service = all_services
+        .iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| anyhow!("Service '{}' not found", name))?;
+    Ok(service.path.clone())
+}
+
+pub fn get_service_file_path(name: &str) -> Result<String> {
+    get_service_path(name)
+}
+
+/// Windows services have no schedules; ser leaves those to Task Scheduler.
+pub fn has_timer(_name: &str) -> bool {
+    false
+}
+
+/// A definition as ser writes it: [`ServiceDetails`] as pretty JSON.
+pub fn generate_file(details: &ServiceDetails) -> Result<String> {
+    Ok(serde_json::to_string_pretty(details)? + "\n")
+}
+
+/// Parse a definition on disk without querying the SCM for runtime state.
+pub fn parse_service_file(path: &Path) -> Result<ServiceDetails> {
+    if path.starts_with(definitions_dir()) {
+        check_definitions_dir()?;
+    }
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read service file: {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse service definition: {}", path.display()))
+}
+
+pub fn get_service_details(name: &str) -> Result<FsServiceDetails> {
+    let sref = super::get_service(name)?;
+    let service = parse_service_file(Path::new(&sref.path))?;
+    let running = is_service_running(name)?;
+
+    Ok(FsServiceDetails {
+        service,
+        path: sref.path,
+        enabled: sref.enabled,
+        running,
+        pid: running.then(|| main_pid(name)).flatten(),
+        trigger: None,
+    })
+}
+
+/// ser only sees its own definitions, so none of them are off limits.
+pub fn is_protected_path(_path: &Path) -> bool {
+    false
+}
+
+/// Why the SCM can't be used here, if it can't: `sc.exe` is missing.
+/// Definitions can still be read and written.
+pub fn backend_unavailable() -> Option<&'static str> {
+    if super::is_offline() {
+        return Some("ser is running with --offline");
+    }
+    static REASON: OnceLock<Option<&'static str>> = OnceLock::new();
+    *REASON.get_or_init(|| {
+        crate::migrate::find_in_path("sc.exe")
+            .is_none()
+            .then_some("sc.exe isn't available")
+    })
+}
+
+/// Run `sc.exe` with `args`, returning what it printed. sc reports errors on
+/// stdout and exits with the Win32 error code; codes in `ok` count as
+/// success. Only the queries are given up on after the backend timeout:
+/// a start or stop cut short could be left half done.
+fn sc(args: &[&str], ok: &[i32]) -> Result<String> {
+    let mut cmd = Command::new("sc.exe");
+    cmd.args(args);
+    print_command(&cmd);
+    let output = if matches!(args.first(), Some(&("query" | "queryex" | "qc"))) {
+        crate::interrupt::query(&mut cmd)
+    } else {
+        crate::interrupt::output(&mut cmd)
+    }
+    .context("Failed to execute sc.exe")?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    match output.status.code() {
+        Some(0) => Ok(stdout),
+        Some(code) if ok.contains(&code) => Ok(stdout),
+        Some(5) => bail!("{stdout} (changing services needs an elevated prompt)"),
+        _ => bail!("{stdout}"),
+    }
+}
+
+/// The SCM's current status for a service, or `None` if it isn't installed.
+fn query(name: &str) -> Result<Option<ScEntry>> {
+    let output = sc(&["queryex", name], &[ERROR_SERVICE_DOES_NOT_EXIST])?;
+    Ok(parse_sc(&output).into_iter().next())
+}
+
+pub fn start_service(name: &str) -> Result<()> {
+    super::require_backend(&format!("start '{name}'"))?;
+    get_service_path(name)?;
+    sc(&["start", name], &[ERROR_SERVICE_ALREADY_RUNNING])
+        .with_context(|| format!("Failed to start service '{name}'"))?;
+    log_event("start", name);
+    Ok(())
+}
+
+/// Windows services have no schedules, so running one now is starting it.
+pub fn run_service_now(name: &str) -> Result<()> {
+    start_service(name)
+}
+
+pub fn stop_service(name: &str) -> Result<()> {
+    super::require_backend(&format!("stop '{name}'"))?;
+    get_service_path(name)?;
+    sc(&["stop", name], &[ERROR_SERVICE_NOT_ACTIVE])
+        .with_context(|| format!("Failed to stop service '{name}'"))?;
+    log_event("stop", name);
+    Ok(())
+}
+
+/// Start the service at boot (`start= auto`) without starting it now.
+pub fn enable_service(name: &str) -> Result<()> {
+    set_enabled(name, true)
+}
+
+/// Start the service only on demand (`start= demand`) without stopping it.
+pub fn disable_service(name: &str) -> Result<()> {
+    set_enabled(name, false)
+}
+
+/// Change the start type in the SCM and `run_at_load` in the definition, so
+/// the two agree.
+fn set_enabled(name: &str, enabled: bool) -> Result<()> {
+    let action = if enabled { "enable" } else { "disable" };
+    super::require_backend(&format!("{action} '{name}'"))?;
+    let path = PathBuf::from(get_service_path(name)?);
+    let mut details = parse_service_file(&path)?;
+    sc(&["config", name, "start=", start_type(enabled)], &[])
+        .with_context(|| format!("Failed to {action} service '{name}'"))?;
+    if details.run_at_load != enabled {
+        details.run_at_load = enabled;
+        write_service_file(&path, generate_file(&details)?)?;
+    }
+    log_event(action, name);
+    Ok(())
+}
+
+fn start_type(at_boot: bool) -> &'static str {
+    if at_boot {
+        "auto"
+    } else {
+        "demand"
+    }
+}
+
+/// How long `restart_service` waits for a stopped service to exit. Windows
+/// gives services 20 seconds to shut down by default, so allow a bit more.
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn restart_service(name: &str) -> Result<()> {
+    stop_service(name)?;
+    wait_for_exit(name, STOP_TIMEOUT)?;
+    start_service(name)?;
+    Ok(())
+}
+
+/// Poll the SCM until the service has reached `STOPPED`.
+fn wait_for_exit(name: &str, timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match query(name)? {
+            Some(entry) if entry.state() != Some("STOPPED") => {}
+            _ => return Ok(()),
+        }
+        if Instant::now() >= deadline {
+            bail!(
+                "Service '{}' was still running {}s after being stopped; not starting it again",
+                name,
+                timeout.as_secs()
+            );
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+}
+
+/// `sc.exe` with `args` and the service name, for `ser raw`.
+pub fn native_command(name: &str, args: &[String]) -> Result<Command> {
+    super::require_backend(&format!("run sc.exe for '{name}'"))?;
+    get_service_path(name)?;
+    let mut cmd = Command::new("sc.exe");
+    cmd.args(super::with_native_target(args, name));
+    Ok(cmd)
+}
+
+/// Write the definition, then install the service in the SCM (or update it)
+/// with its command line, start type, account, description, environment,
+/// and restart policy.
+///
+/// The program must be a Windows service, i.e. report to the SCM; an
+/// ordinary console program is stopped when it doesn't within 30 seconds.
+pub fn create_service(details: &ServiceDetails) -> Result<()> {
+    if details.schedule.is_some() {
+        bail!(
+            "Windows services can't run on a schedule; use Task Scheduler (schtasks) for '{}'",
+            details.name
+        );
+    }
+    let account = service_account(details.user.as_deref())?;
+    if details.working_directory.is_some() {
+        eprintln!(
+            "Warning: Windows services start in %SystemRoot%\\system32; the working directory of '{}' is ignored.",
+            details.name
+        );
+    }
+    // The SCM passes a service its environment from the registry, which
+    // every local user can read, so env files (where secrets usually live)
+    // aren't copied there.
+    if !details.env_files.is_empty() {
+        bail!(
+            "Windows services take their environment from the registry, which any local user \
+             can read; set the variables of '{}' inline, or have the program read its own \
+             config file",
+            details.name
+        );
+    }
+
+    let dir = create_definitions_dir()?;
+    if backend_unavailable().is_none() {
+        register(details, account, &details.env_vars)
+            .with_context(|| format!("Failed to install service '{}'", details.name))?;
+    }
+    // Only once the SCM has it, so a failed install leaves nothing behind.
+    let path = dir.join(format!("{}.json", details.name));
+    write_service_file(&path, generate_file(details)?)?;
+
+    log_event("create", &details.name);
+    Ok(())
+}
+
+/// The account a service runs as. Other accounts need their password given
+/// to the SCM, which ser has no way to ask for.
+fn service_account(user: Option<&str>) -> Result<&'static str> {
+    match user.map(str::to_ascii_lowercase).as_deref() {
+        None | Some("localsystem") => Ok("LocalSystem"),
+        Some("localservice") => Ok(r"NT AUTHORITY\LocalService"),
+        Some("networkservice") => Ok(r"NT AUTHORITY\NetworkService"),
+        Some(_) => bail!(
+            "Windows services can only run as LocalSystem, LocalService, or NetworkService \
+             through ser; set another account with `sc.exe config <name> obj= <account> password= <password>`"
+        ),
+    }
+}
+
+fn register(details: &ServiceDetails, account: &str, env: &[(String, String)]) -> Result<()> {
+    let name = details.name.as_str();
+    let bin_path = command_line(&details.program, &details.arguments);
+    let verb = if query(name)?.is_some() {
+        "config"
+    } else {
+        "create"
+    };
+    let mut args = vec![
+        verb,
+        name,
+        "binPath=",
+        &bin_path,
+        "start=",
+        start_type(details.run_at_load),
+        "obj=",
+        account,
+    ];
+    // Built-in accounts other than LocalSystem still expect an (empty)
+    // password.
+    if account != "LocalSystem" {
+        args.extend(["password=", ""]);
+    }
+    sc(&args, &[])?;
+
+    if let Some(description) = &details.description {
+        sc(&["description", name, description], &[])?;
+    }
+
+    let key = format!(r"HKLM\SYSTEM\CurrentControlSet\Services\{name}");
+    let mut cmd = Command::new("reg.exe");
+    if env.is_empty() {
+        cmd.args(["delete", &key, "/v", "Environment", "/f"]);
+    } else {
+        let value: Vec<String> = env.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        cmd.args(["add", &key, "/v", "Environment", "/t", "REG_MULTI_SZ", "/d"])
+            .arg(value.join(r"\0"))
+            .arg("/f");
+    }
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute reg.exe")?;
+    if !env.is_empty() && !output.status.success() {
+        bail!(
+            "Failed to set the environment: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    if details.keep_alive {
+        sc(
+            &[
+                "failure",
+                name,
+                "reset=",
+                "86400",
+                "actions=",
+                "restart/5000/restart/5000/restart/5000",
+            ],
+            &[],
+        )?;
+    } else {
+        sc(&["failure", name, "reset=", "0", "actions=", ""], &[])?;
+    }
+    Ok(())
+}
+
+/// Join a program and its arguments into one command line, quoting as the
+/// Microsoft C runtime parses it: backslashes are literal except before a
+/// quote.
+fn command_line(program: &str, arguments: &[String]) -> String {
+    std::iter::once(program)
+        .chain(arguments.iter().map(String::as_str))
+        .map(quote_arg)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// A Windows service is its SCM registration, which `sc config` replaces as
+/// a whole, so an update is a [`create_service`].
+pub fn update_service(
+    _path: &Path,
+    _before: &ServiceDetails,
+    after: &ServiceDetails,
+) -> Result<()> {
+    create_service(after)
+}
+
+/// Run `program` in place of the service's own, with the same arguments.
+/// Used by `ser deploy`.
+pub fn override_program(name: &str, program: &str) -> Result<PathBuf> {
+    let found = super::get_service_details(name)?;
+    let mut details = found.service.clone();
+    details.program = program.to_string();
+    let path = PathBuf::from(&found.path);
+    update_service(&path, &found.service, &details)?;
+    Ok(path)
+}
+
+/// Create or update several services. The SCM picks up each change as it is
+/// made, so this is just [`create_service`] per service, with a result for
+/// each.
+pub fn create_services(services: &[ServiceDetails]) -> Vec<UnitResult> {
+    services
+        .iter()
+        .map(|details| UnitResult {
+            name: details.name.clone(),
+            result: create_service(details),
+        })
+        .collect()
+}
+
+/// Install a ready-made definition (a `.json` file as ser writes them),
+/// optionally starting it. Returns the new path.
+pub fn install_service_file(source: &Path, user: bool, start: bool) -> Result<PathBuf> {
+    if user {
+        bail!("Windows services are system-wide; install without --user");
+    }
+    if !is_definition(source) {
+        bail!(
+            "Expected a .json service definition, got {}",
+            source.display()
+        );
+    }
+    let details =
+        parse_service_file(source).with_context(|| format!("Invalid {}", source.display()))?;
+    create_service(&details)?;
+    if start {
+        start_service(&details.name)?;
+    }
+    Ok(definitions_dir().join(format!("{}.json", details.name)))
+}
+
+/// Record a change `ser` made to a service in the Application event log
+/// (source `ser`), so `wevtutil qe Application /q:"*[System[Provider[@Name='ser']]]"`
+/// lists lifecycle changes. Best-effort: a logging failure never fails the
+/// change itself.
+pub fn log_event(action: &str, name: &str) {
+    if super::is_offline() {
+        return;
+    }
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    let mut cmd = Command::new("eventcreate.exe");
+    cmd.args([
+        "/L",
+        "APPLICATION",
+        "/T",
+        "INFORMATION",
+        "/SO",
+        "ser",
+        "/ID",
+        "1",
+    ])
+    .arg("/D")
+    .arg(format!("ser: {action} {name} (by {user})"));
+    print_command(&cmd);
+    let _ = crate::interrupt::output(&mut cmd);
+}
+
+pub fn notify_desktop(_title: &str, _body: &str) -> Result<()> {
+    bail!("Desktop notifications aren't supported on Windows")
+}
+
+/// Uninstall a service: stop it, delete it from the SCM (which also drops
+/// its start type), and remove its definition.
+pub fn delete_service(name: &str) -> Result<()> {
+    let path = get_service_path(name)?;
+
+    // Best-effort stop so the service exits before it's deleted; the SCM
+    // only removes a service once it has stopped.
+    if backend_unavailable().is_none() {
+        let _ = sc(&["stop", name], &[]);
+        sc(&["delete", name], &[ERROR_SERVICE_DOES_NOT_EXIST])
+            .with_context(|| format!("Failed to delete service '{name}'"))?;
+    }
+
+    crate::record_change("remove", Path::new(&path));
+    fs::remove_file(&path).with_context(|| format!("Failed to remove definition: {path}"))?;
+    log_event("delete", name);
+    Ok(())
+}
+
+pub fn is_service_running(name: &str) -> Result<bool> {
+    if backend_unavailable().is_some() {
+        return Ok(false);
+    }
+    Ok(query(name)?.is_some_and(|entry| entry.state() == Some("RUNNING")))
+}
+
+/// The service as the SCM has it, from `sc qc` and `sc queryex`.
+pub fn runtime_properties(name: &str) -> Result<RuntimeProperties> {
+    if backend_unavailable().is_some() {
+        return Ok(RuntimeProperties::default());
+    }
+    let Some(status) = query(name)? else {
+        return Ok(RuntimeProperties::default());
+    };
+    let config = sc(&["qc", name], &[])?;
+    let config = parse_sc(&config).into_iter().next().unwrap_or_default();
+    Ok(RuntimeProperties {
+        fragment_path: None,
+        exec_start: config.get("BINARY_PATH_NAME").map(String::from),
+        state: status.state().map(|s| s.to_ascii_lowercase()),
+        main_pid: status.pid(),
+        restarts: None,
+    })
+}
+
+/// Whether the service is stopped and exited with an error.
+pub fn is_service_failed(name: &str) -> bool {
+    if backend_unavailable().is_some() {
+        return false;
+    }
+    let Ok(Some(entry)) = query(name) else {
+        return false;
+    };
+    entry.state() == Some("STOPPED")
+        && entry
+            .exit_code()
+            .is_some_and(|code| code != 0 && code != ERROR_SERVICE_NEVER_STARTED)
+}
+
+/// `sc queryex <name>` for a service (state, PID, exit codes), for alerts.
+pub fn status_report(name: &str) -> Result<String> {
+    super::require_backend("get a status report")?;
+    match query(name)? {
+        Some(_) => sc(&["queryex", name], &[]),
+        None => Err(anyhow!("'{}' is not installed", name)),
+    }
+}
+
+/// The last `lines` events the service wrote to the Application log, for
+/// alerts and reports.
+pub fn recent_logs(name: &str, lines: u32) -> Result<Vec<String>> {
+    if super::is_offline() {
+        return Ok(Vec::new());
+    }
+    query_events(&[name.to_string()], None, lines)
+}
+
+/// Show the Application log events of one or more services, merged and with
+/// timestamps normalized by [`crate::logs`].
+pub fn show_service_logs(names: &[String], options: &LogOptions) -> Result<()> {
+    if super::is_offline() {
+        bail!("Can't read logs: ser is running with --offline");
+    }
+    if options.follow {
+        bail!("wevtutil can't follow the event log; run without --follow");
+    }
+    if options.invocation.is_some() {
+        bail!("The event log doesn't track separate runs of a service; use --boot instead");
+    }
+    let mut events = query_events(names, options.priority, options.lines)?;
+    if events.is_empty() {
+        println!(
+            "No events found for service '{}' in the Application log",
+            names.join(", ")
+        );
+        return Ok(());
+    }
+    if let Some(since) = options.since {
+        events.retain(|line| {
+            crate::logs::parse_timestamp(line).is_none_or(|(time, _)| time >= since)
+        });
+    } else if options.boot {
+        if let Some(boot) = boot_time() {
+            events.retain(|line| {
+                crate::logs::parse_timestamp(line).is_none_or(|(time, _)| time >= boot)
+            });
+        }
+    }
+    crate::logs::print_logs(
+        events.join("\n").as_bytes(),
+        options.timestamps,
+        false,
+        options.lines,
+    )
+}
+
+/// Read the newest `count` Application log events from the services'
+/// event sources with `wevtutil`, as one line each, oldest first.
+fn query_events(
+    names: &[String],
+    priority: Option<LogPriority>,
+    count: u32,
+) -> Result<Vec<String>> {
+    let providers: Vec<String> = names.iter().map(|name| format!("@Name='{name}'")).collect();
+    let mut filter = format!("Provider[{}]", providers.join(" or "));
+    if let Some(priority) = priority {
+        // Event levels: 1 critical, 2 error, 3 warning, 4 information, 5 verbose.
+        let level = match priority {
+            LogPriority::Err => 2,
+            LogPriority::Warning => 3,
+            LogPriority::Info => 4,
+            LogPriority::Debug => 5,
+        };
+        filter = format!("{filter} and (Level<={level})");
+    }
+    let mut cmd = Command::new("wevtutil.exe");
+    cmd.args(["qe", "Application", "/rd:true", "/f:text"])
+        .arg(format!("/q:*[System[{filter}]]"))
+        .arg(format!("/c:{count}"));
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute wevtutil")?;
+    if !output.status.success() {
+        bail!(
+            "wevtutil failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let mut events = parse_wevtutil_text(&String::from_utf8_lossy(&output.stdout));
+    events.reverse();
+    Ok(events)
+}
+
+/// Turn `wevtutil qe /f:text` output into one `time source: message` line
+/// per event. Times without an offset are local.
+fn parse_wevtutil_text(output: &str) -> Vec<String> {
+    let mut events = Vec::new();
+    let mut time = String::new();
+    let mut source = String::new();
+    let mut message: Option<Vec<&str>> = None;
+    let mut flush = |time: &str, source: &str, message: Option<Vec<&str>>| {
+        if let Some(message) = message {
+            events.push(format!("{time} {source}: {}", message.join(" ")));
+        }
+    };
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("Event[") {
+            flush(&time, &source, message.take());
+            continue;
+        }
+        if let Some(message) = message.as_mut() {
+            if !trimmed.is_empty() {
+                message.push(trimmed);
+            }
+            continue;
+        }
+        if let Some(value) = trimmed.strip_prefix("Date:") {
+            time = normalize_event_time(value.trim());
+        } else if let Some(value) = trimmed.strip_prefix("Source:") {
+            source = value.trim().to_string();
+        } else if let Some(value) = trimmed.strip_prefix("Description:") {
+            let value = value.trim();
+            message = Some(if value.is_empty() {
+                vec![]
+            } else {
+                vec![value]
+            });
+        }
+    }
+    flush(&time, &source, message);
+    events
+}
+
+fn normalize_event_time(value: &str) -> String {
+    if chrono::DateTime::parse_from_rfc3339(value).is_ok() {
+        return value.to_string();
+    }
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .and_then(|naive| {
+            use chrono::TimeZone;
+            chrono::Local.from_local_datetime(&naive).earliest()
+        })
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// When Windows last started, from `LastBootUpTime`.
+fn boot_time() -> Option<chrono::DateTime<chrono::Utc>> {
+    let mut cmd = Command::new("powershell.exe");
+    cmd.args([
+        "-NoProfile",
+        "-Command",
+        "(Get-CimInstance Win32_OperatingSystem).LastBootUpTime.ToUniversalTime().ToString('o')",
+    ]);
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    chrono::DateTime::parse_from_rfc3339(stdout.trim())
+        .ok()
+        .map(|time| time.with_timezone(&chrono::Utc))
+}
+
+/// Report the Windows version and whether the event log tools are available.
+pub fn host_info() -> HostInfo {
+    let mut cmd = Command::new("cmd.exe");
+    cmd.args(["/C", "ver"]);
+    print_command(&cmd);
+    // `ver` prints e.g. `Microsoft Windows [Version 10.0.22631.4317]`.
+    let init_version = (!super::is_offline())
+        .then(|| crate::interrupt::output(&mut cmd).ok())
+        .flatten()
+        .filter(|o| o.status.success())
+        .and_then(|o| {
+            let stdout = String::from_utf8_lossy(&o.stdout);
+            let version = stdout.split("Version ").nth(1)?;
+            Some(version.trim().trim_end_matches(']').to_string())
+        });
+
+    let facilities = vec![(
+        "event log (wevtutil)".to_string(),
+        crate::migrate::find_in_path("wevtutil.exe").is_some(),
+    )];
+
+    HostInfo {
+        init_system: "Service Control Manager".to_string(),
+        init_version,
+        facilities,
+        environment: None,
+    }
+}
+
+/// ser only sees its own definitions, none of which ship with Windows.
+pub fn is_vendor_path(_path: &Path) -> bool {
+    false
+}
+
+/// The SCM has no drop-ins: a definition is the whole service.
+pub fn drop_in_paths(_name: &str, _path: &Path) -> Vec<PathBuf> {
+    Vec::new()
+}
+
+pub fn prepare_override(service: &ServiceRef) -> Result<PathBuf> {
+    bail!(
+        "Windows has no vendor services to override; edit {} directly",
+        service.path
+    )
+}
+
+/// No package manager owns ser's definitions.
+pub fn owning_package(_path: &Path) -> Option<PackageInfo> {
+    None
+}
+
+/// The process of a running service, from `sc queryex`.
+pub fn main_pid(name: &str) -> Option<u32> {
+    if backend_unavailable().is_some() {
+        return None;
+    }
+    query(name).ok()??.pid()
+}
+
+/// How a service's last run ended, from its `WIN32_EXIT_CODE`. The SCM
+/// doesn't record when a service last ran.
+pub fn last_run(name: &str) -> LastRun {
+    if backend_unavailable().is_some() {
+        return LastRun::default();
+    }
+    let Ok(Some(entry)) = query(name) else {
+        return LastRun::default();
+    };
+    let result = match (entry.state(), entry.exit_code()) {
+        (Some("STOPPED"), Some(0 | ERROR_SERVICE_NEVER_STARTED)) => Some("success".to_string()),
+        (Some("STOPPED"), Some(code)) => Some(format!("exit-code (status {code})")),
+        _ => None,
+    };
+    LastRun { time: None, result }
+}
+
+/// Map a process to the service that owns it via the PIDs in `sc queryex`.
+pub fn service_for_pid(pid: u32) -> Result<Option<String>> {
+    super::require_backend("look up a process's service")?;
+    let output = sc(&["queryex", "type=", "service", "state=", "all"], &[])?;
+    Ok(parse_sc(&output)
+        .into_iter()
+        .find(|entry| entry.pid() == Some(pid))
+        .and_then(|entry| entry.get("SERVICE_NAME").map(String::from)))
+}
+
+pub fn create_system_user(_name: &str) -> Result<PathBuf> {
+    bail!("ser can't create accounts on Windows; run the service as LocalService or NetworkService")
+}
+
+/// Find the processes listening on a TCP port using `netstat -ano`.
+pub fn pids_listening_on(port: u16) -> Result<Vec<u32>> {
+    let mut cmd = Command::new("netstat.exe");
+    cmd.args(["-a", "-n", "-o", "-p", "TCP"]);
+    print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd).context("Failed to execute netstat")?;
+    let suffix = format!(":{port}");
+    let mut pids: Vec<u32> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            // Proto, local address, foreign address, state, PID.
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [_, local, _, "LISTENING", pid] if local.ends_with(&suffix) => pid.parse().ok(),
+                _ => None,
+            }
+        })
+        .collect();
+    pids.sort_unstable();
+    pids.dedup();
+    Ok(pids)
+}
+
+/// One service's block of `sc queryex` or `sc qc` output.
+#[derive(Debug, Default)]
+struct ScEntry {
+    fields: Vec<(String, String)>,
+}
+
+impl ScEntry {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The state's name, e.g. `RUNNING` from `4  RUNNING`.
+    fn state(&self) -> Option<&str> {
+        self.get("STATE")?.split_whitespace().nth(1)
+    }
+
+    /// The process of a running service; the SCM reports 0 otherwise.
+    fn pid(&self) -> Option<u32> {
+        self.get("PID")?.parse().ok().filter(|&pid| pid != 0)
+    }
+
+    /// The Win32 error the service last exited with, e.g. 0 from `0  (0x0)`.
+    fn exit_code(&self) -> Option<u32> {
+        self.get("WIN32_EXIT_CODE")?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    }
+}
+
+/// Split `sc` output into one entry per `SERVICE_NAME:` block of `KEY :
+/// value` lines.
+fn parse_sc(output: &str) -> Vec<ScEntry> {
+    let mut entries: Vec<ScEntry> = Vec::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        {
+            continue;
+        }
+        if key == "SERVICE_NAME" {
+            entries.push(ScEntry::default());
+        }
+        if let Some(entry) = entries.last_mut() {
+            entry
+                .fields
+                .push((key.to_string(), value.trim().to_string()));
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sc_queryex() {
+        let output = "\
+SERVICE_NAME: web
+DISPLAY_NAME: web
+        TYPE               : 10  WIN32_OWN_PROCESS
+        STATE              : 4  RUNNING
+                                (STOPPABLE, NOT_PAUSABLE, ACCEPTS_SHUTDOWN)
+        WIN32_EXIT_CODE    : 0  (0x0)
+        PID                : 4321
+
+SERVICE_NAME: worker
+        STATE              : 1  STOPPED
+        WIN32_EXIT_CODE    : 1067  (0x42b)
+        PID                : 0
+";
+        let entries = parse_sc(output);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].state(), Some("RUNNING"));
+        assert_eq!(entries[0].pid(), Some(4321));
+        assert_eq!(entries[1].get("SERVICE_NAME"), Some("worker"));
+        assert_eq!(entries[1].pid(), None);
+        assert_eq!(entries[1].exit_code(), Some(1067));
+    }
+
+    #[test]
+    fn trusts_only_admin_owned_definitions() {
+        assert!(sddl_is_trusted(
+            "O:BAG:SYD:PAI(A;OICI;FA;;;SY)(A;OICI;FA;;;BA)"
+        ));
+        assert!(sddl_is_trusted(
+            "O:BAG:SYD:PAI(D;OICI;FA;;;WD)(A;OICI;FA;;;SY)(A;OICI;FA;;;BA)"
+        ));
+        // Inherited from %ProgramData%: Users may create files.
+        assert!(!sddl_is_trusted(
+            "O:BAG:SYD:AI(A;OICIID;FA;;;SY)(A;OICIID;FA;;;BA)(A;CIID;DCLCRPCR;;;BU)"
+        ));
+        // Created by an ordinary user, who can change the ACL.
+        assert!(!sddl_is_trusted(
+            "O:S-1-5-21-1-2-3-1001G:S-1-5-21-1-2-3-513D:PAI(A;OICI;FA;;;SY)(A;OICI;FA;;;BA)"
+        ));
+        assert!(!sddl_is_trusted(""));
+    }
+
+    #[test]
+    fn quotes_command_lines() {
+        let args = |s: &[&str]| s.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            command_line(r"C:\Program Files\web\web.exe", &args(&["--port", "80"])),
+            r#""C:\Program Files\web\web.exe" --port 80"#
+        );
+        assert_eq!(
+            command_line("web.exe", &args(&[r#"say "hi""#, r"C:\dir\", ""])),
+            r#"web.exe "say \"hi\"" C:\dir\ """#
+        );
+        assert_eq!(quote_arg(r"C:\my dir\"), r#""C:\my dir\\""#);
+    }
+
+    #[test]
+    fn parses_wevtutil_events() {
+        let output = "Event[0]:
+  Log Name: Application
+  Source: web
+  Date: 2026-10-16T12:03:27.5120000Z
+  Event ID: 0
+  Level: Error
+  Description: 
+listener failed
+  on port 80
+
+Event[1]:
+  Source: web
+  Date: 2026-10-16T12:00:00.0000000Z
+  Description: started
+";
+        assert_eq!(
+            parse_wevtutil_text(output),
+            [
+                "2026-10-16T12:03:27.5120000Z web: listener failed on port 80",
+                "2026-10-16T12:00:00.0000000Z web: started",
+            ]
+        );
+    }
+}
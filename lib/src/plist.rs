@@ -1,17 +1,629 @@
-use crate::{Schedule, ServiceDetails};
-use anyhow::{Context, Result};
+use crate::{
+    CalendarSchedule, Condition, DirectoryKind, EnvFile, ManagedDirectory, RawField, Schedule,
+    ServiceDetails,
+};
+use anyhow::{anyhow, Context, Result};
 use plist::Value;
+use std::path::Path;
+use std::sync::OnceLock;
 
-pub fn parse_plist(_content: &str) -> Result<ServiceDetails> {
-    unimplemented!()
+/// Custom plist keys for ser metadata; launchd ignores keys it doesn't know.
+pub const DESCRIPTION_KEY: &str = "SerDescription";
+pub const TAGS_KEY: &str = "SerTags";
+pub const OWNER_KEY: &str = "SerOwner";
+/// Jitter in seconds, recorded so the `sleep` wrapper can be undone on parse.
+pub const JITTER_KEY: &str = "SerJitter";
+/// Set to `false` when missed calendar runs should be skipped.
+pub const CATCH_UP_KEY: &str = "SerCatchUp";
+/// Managed directories, as `StateDirectory=name` strings; launchd has no
+/// equivalent, so ser creates them and passes their paths in the environment.
+pub const DIRECTORIES_KEY: &str = "SerDirectories";
+/// Where ser generated the plist from: a dictionary of `Version`, `Created`,
+/// and `Command`. Plists have no comments, so this is their counterpart to
+/// [`crate::systemd::MANAGED_BY_COMMENT`].
+pub const PROVENANCE_KEY: &str = "SerProvenance";
+/// Env files, as `EnvironmentFile=` values (`-` marks optional ones); the
+/// wrapper script sources them.
+pub const ENV_FILES_KEY: &str = "SerEnvFiles";
+
+/// The end of every wrapper script: run the real program (`$0`) with its
+/// arguments.
+pub(crate) const WRAPPER_EXEC: &str = "exec \"$0\" \"$@\"";
+
+/// Reads an env file by the rules of [`crate::parse_env_file`] and exports
+/// each variable, without running the file as shell code the way `.` would.
+const ENV_FILE_READER: &str = r#"ser_trim() { s=${1#"${1%%[![:space:]]*}"}; s=${s%"${s##*[![:space:]]}"}; }; ser_env() { [ -r "$1" ] || return 1; while IFS= read -r l || [ -n "$l" ]; do ser_trim "$l"; l=${s#export }; case $l in ''|\#*|\;*) continue;; *=*) ;; *) continue;; esac; ser_trim "${l%%=*}"; k=$s; ser_trim "${l#*=}"; v=$s; case $v in \"*\") v=${v#\"}; v=${v%\"};; \'*\') v=${v#\'}; v=${v%\'};; esac; case $k in ''|[!A-Za-z_]*|*[!A-Za-z0-9_]*) continue;; esac; export "$k=$v"; done < "$1"; }"#;
+
+/// launchd has no env files or randomized delay and always runs a calendar
+/// job it missed while asleep, so those behaviours are emulated by running the
+/// job through `sh -c` with this script.
+///
+/// Env files are read as systemd would and their variables exported; a
+/// missing required one fails the start. Without catch-up, it exits unless it
+/// started in the scheduled minute (a late start means the run was missed).
+/// With jitter, it sleeps a random part of it; `jot -r` uses arc4random, so
+/// machines firing in the same second still pick different delays. `None` if
+/// no wrapper is needed.
+fn wrapper_script(details: &ServiceDetails) -> Option<String> {
+    let mut steps = Vec::new();
+    if !details.env_files.is_empty() {
+        steps.push(ENV_FILE_READER.to_string());
+        for file in &details.env_files {
+            let path = shell_quote(&file.path);
+            if file.optional {
+                steps.push(format!("[ ! -e {path} ] || ser_env {path} || exit 1"));
+            } else {
+                steps.push(format!("ser_env {path} || exit 1"));
+            }
+        }
+    }
+    if let (Some(Schedule::Calendar(c)), Some(false)) = (&details.schedule, details.catch_up) {
+        match (c.hour, c.minute) {
+            (Some(h), Some(m)) => steps.push(format!(
+                "[ \"$(date +%H:%M)\" = \"{h:02}:{m:02}\" ] || exit 0"
+            )),
+            (None, Some(m)) => steps.push(format!("[ \"$(date +%M)\" = \"{m:02}\" ] || exit 0")),
+            // Firing every minute (or hourly at an unset minute), nothing is
+            // ever missed by more than the interval itself.
+            _ => {}
+        }
+    }
+    if let Some(jitter) = details.jitter.filter(|_| details.schedule.is_some()) {
+        steps.push(format!("sleep $(jot -r 1 0 {jitter})"));
+    }
+    if steps.is_empty() {
+        return None;
+    }
+    steps.push(WRAPPER_EXEC.to_string());
+    Some(steps.join("; "))
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Where a managed directory lives for a LaunchAgent, e.g.
+/// `~/Library/Application Support/myapp` for `StateDirectory=myapp`.
+pub fn directory_path(dir: &ManagedDirectory) -> Option<std::path::PathBuf> {
+    Some(dir.kind.launchd_base()?.join(&dir.name))
+}
+
+/// Create a service's managed directories, as systemd does before starting
+/// it.
+pub fn create_directories(details: &ServiceDetails) -> Result<()> {
+    for dir in &details.directories {
+        let path = directory_path(dir)
+            .with_context(|| format!("No base directory for {}", dir.kind.directive()))?;
+        std::fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Undo [`wrapper_script`]'s wrapping of `ProgramArguments`, returning the
+/// real program and arguments if `arguments` is such a wrapper.
+pub fn unwrap_wrapper(arguments: &[String]) -> Option<(String, Vec<String>)> {
+    match arguments {
+        [sh, c, script, program, rest @ ..]
+            if sh == "/bin/sh" && c == "-c" && script.ends_with(WRAPPER_EXEC) =>
+        {
+            Some((program.clone(), rest.to_vec()))
+        }
+        _ => None,
+    }
+}
+
+/// Parse a plist (XML or binary) into [`ServiceDetails`]. Works on any
+/// platform, so definitions from a Mac can be converted elsewhere.
+pub fn parse_plist(contents: &[u8]) -> Result<ServiceDetails> {
+    let plist: Value = plist::from_bytes(contents).context("Failed to parse plist")?;
+    parse_value(plist)
+}
+
+/// Read a parsed plist back into [`ServiceDetails`]. Keys launchd has no
+/// ser equivalent for are ignored.
+pub fn parse_value(plist: Value) -> Result<ServiceDetails> {
+    let dict = plist
+        .as_dictionary()
+        .ok_or_else(|| anyhow!("Invalid plist format"))?;
+
+    let name = dict
+        .get("Label")
+        .and_then(|v| v.as_string())
+        .unwrap_or_default()
+        .to_string();
+
+    let description = dict
+        .get(DESCRIPTION_KEY)
+        .and_then(|v| v.as_string())
+        .map(|s| s.to_string());
+    let tags = dict
+        .get(TAGS_KEY)
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_string())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let owner = dict
+        .get(OWNER_KEY)
+        .and_then(|v| v.as_string())
+        .map(|s| s.to_string());
+
+    let mut program = dict
+        .get("Program")
+        .and_then(|v| v.as_string())
+        .map(|s| s.to_string());
+
+    let mut arguments: Vec<String> = dict
+        .get("ProgramArguments")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_string())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let jitter = dict
+        .get(JITTER_KEY)
+        .and_then(integer)
+        .map(|secs| secs.max(0) as u64);
+    let catch_up = dict.get(CATCH_UP_KEY).and_then(boolean);
+    let directories: Vec<ManagedDirectory> = dict
+        .get(DIRECTORIES_KEY)
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_string()?.split_once('='))
+                .filter_map(|(key, name)| {
+                    let kind = DirectoryKind::ALL
+                        .into_iter()
+                        .find(|k| k.directive() == key)?;
+                    Some(ManagedDirectory {
+                        kind,
+                        name: name.to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let env_files: Vec<EnvFile> = dict
+        .get(ENV_FILES_KEY)
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_string())
+                .map(EnvFile::parse)
+                .collect()
+        })
+        .unwrap_or_default();
+    let wrapped = jitter.is_some() || catch_up == Some(false) || !env_files.is_empty();
+    if let Some((real_program, real_arguments)) =
+        wrapped.then(|| unwrap_wrapper(&arguments)).flatten()
+    {
+        program = Some(real_program);
+        arguments = real_arguments;
+    }
+
+    if program.is_none() && !arguments.is_empty() {
+        program = Some(arguments.remove(0));
+    }
+
+    let program = program.context("Missing 'Program' or 'ProgramArguments' in plist")?;
+
+    let working_directory = dict
+        .get("WorkingDirectory")
+        .and_then(|v| v.as_string())
+        .map(|s| s.to_string());
+
+    let user = dict
+        .get("UserName")
+        .and_then(|v| v.as_string())
+        .map(|s| s.to_string());
+
+    let run_at_load = dict.get("RunAtLoad").and_then(boolean).unwrap_or(false);
+
+    // KeepAlive is either a plain boolean or a dictionary of conditions; a
+    // PathState dictionary maps back onto `ConditionPathExists=`.
+    let (keep_alive, conditions) = match dict.get("KeepAlive") {
+        Some(Value::Dictionary(ka)) => {
+            let conditions = ka
+                .get("PathState")
+                .and_then(|v| v.as_dictionary())
+                .map(|states| {
+                    states
+                        .iter()
+                        .map(|(path, exists)| Condition {
+                            kind: "PathExists".to_string(),
+                            value: if boolean(exists).unwrap_or(true) {
+                                path.clone()
+                            } else {
+                                format!("!{path}")
+                            },
+                            assert: false,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            (true, conditions)
+        }
+        Some(v) => (boolean(v).unwrap_or(false), Vec::new()),
+        None => (false, Vec::new()),
+    };
+
+    // Parse schedule: a simple repeating StartInterval, or a calendar pattern.
+    let schedule = if let Some(secs) = dict.get("StartInterval").and_then(integer) {
+        Some(Schedule::Interval(secs.max(0) as u64))
+    } else {
+        dict.get("StartCalendarInterval")
+            .and_then(parse_calendar_interval)
+            .map(Schedule::Calendar)
+    };
+
+    // The directory variables are generated from `SerDirectories`.
+    let generated = |key: &str| directories.iter().any(|d| d.kind.env_var() == key);
+    let env_vars = dict
+        .get("EnvironmentVariables")
+        .and_then(|v| v.as_dictionary())
+        .map(|d| {
+            d.iter()
+                .filter(|(k, _)| !generated(k))
+                .filter_map(|(k, v)| v.as_string().map(|s| (k.clone(), s.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(ServiceDetails {
+        name,
+        description,
+        tags,
+        owner,
+        program,
+        arguments,
+        working_directory,
+        user,
+        run_at_load,
+        keep_alive,
+        env_files,
+        env_vars,
+        after: vec![],
+        schedule,
+        jitter,
+        catch_up,
+        conditions,
+        directories,
+        // launchd has no OOM score or policy; these stay unset on macOS.
+        ..Default::default()
+    })
+}
+
+fn parse_calendar_interval(value: &Value) -> Option<CalendarSchedule> {
+    let dict = value.as_dictionary()?;
+
+    Some(CalendarSchedule {
+        month: dict.get("Month").and_then(integer).map(|v| v as u8),
+        day: dict.get("Day").and_then(integer).map(|v| v as u8),
+        weekday: dict.get("Weekday").and_then(integer).map(|v| v as u8),
+        hour: dict.get("Hour").and_then(integer).map(|v| v as u8),
+        minute: dict.get("Minute").and_then(integer).map(|v| v as u8),
+    })
+}
+
+/// A boolean, including the `YES`/`NO` strings OpenStep plists write them
+/// as, having no boolean type.
+fn boolean(value: &Value) -> Option<bool> {
+    match value.as_string() {
+        Some(s) if s.eq_ignore_ascii_case("yes") || s.eq_ignore_ascii_case("true") => Some(true),
+        Some(s) if s.eq_ignore_ascii_case("no") || s.eq_ignore_ascii_case("false") => Some(false),
+        Some(_) => None,
+        None => value.as_boolean(),
+    }
+}
+
+/// An integer, including the digit strings OpenStep plists write them as.
+fn integer(value: &Value) -> Option<i64> {
+    match value.as_string() {
+        Some(s) => s.trim().parse().ok(),
+        None => value.as_signed_integer(),
+    }
+}
+
+/// Rewrite a plist (XML, binary, or OpenStep) as XML with dictionary keys
+/// sorted, so equivalent files compare equal.
+pub fn format_plist(contents: &[u8]) -> Result<String> {
+    Ok(String::from_utf8(format_plist_as(
+        contents,
+        PlistFormat::Xml,
+    )?)?)
+}
+
+/// [`format_plist`], but written as `format`.
+pub fn format_plist_as(contents: &[u8], format: PlistFormat) -> Result<Vec<u8>> {
+    let mut value: Value = plist::from_bytes(contents).context("Failed to parse plist")?;
+    sort_dictionaries(&mut value);
+    encode(&value, format)
+}
+
+/// Rewrite a plist as `format`, keeping its keys in their order.
+pub fn convert_plist(contents: &[u8], format: PlistFormat) -> Result<Vec<u8>> {
+    let value: Value = plist::from_bytes(contents).context("Failed to parse plist")?;
+    encode(&value, format)
+}
+
+/// How a plist is stored. All three parse; the OpenStep text format, which
+/// some older agents still ship, can't be written, so it's written as XML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlistFormat {
+    Xml,
+    Binary,
+    OpenStep,
+}
+
+impl PlistFormat {
+    /// The format of `contents`, by how it starts.
+    pub fn detect(contents: &[u8]) -> PlistFormat {
+        if contents.starts_with(b"bplist") {
+            return PlistFormat::Binary;
+        }
+        let text = contents.strip_prefix(b"\xef\xbb\xbf").unwrap_or(contents);
+        match text.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'<') => PlistFormat::Xml,
+            _ => PlistFormat::OpenStep,
+        }
+    }
+}
+
+pub(crate) fn encode(value: &Value, format: PlistFormat) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    if format == PlistFormat::Binary {
+        plist::to_writer_binary(&mut data, value).context("Failed to serialize plist")?;
+        return Ok(data);
+    }
+    plist::to_writer_xml(&mut data, value).context("Failed to serialize plist")?;
+    if !data.ends_with(b"\n") {
+        data.push(b'\n');
+    }
+    Ok(data)
+}
+
+/// Every key in a plist (XML or binary) with its value, flattening nested
+/// dictionaries and arrays of dictionaries into paths like `KeepAlive.Crashed`
+/// or `StartCalendarInterval[0].Hour`. Arrays of plain values stay one field.
+pub fn raw_fields(contents: &[u8]) -> Result<Vec<RawField>> {
+    let value: Value = plist::from_bytes(contents).context("Failed to parse plist")?;
+    let mut fields = Vec::new();
+    flatten_value("", &value, &mut fields);
+    Ok(fields)
+}
+
+fn flatten_value(path: &str, value: &Value, fields: &mut Vec<RawField>) {
+    let scalar = match value {
+        Value::Dictionary(dict) => {
+            for (key, v) in dict {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                flatten_value(&path, v, fields);
+            }
+            return;
+        }
+        Value::Array(items) if items.iter().any(|i| i.as_dictionary().is_some()) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten_value(&format!("{path}[{i}]"), item, fields);
+            }
+            return;
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(scalar_string).collect();
+            format!("[{}]", items.join(", "))
+        }
+        other => scalar_string(other),
+    };
+    fields.push(RawField {
+        section: String::new(),
+        key: path.to_string(),
+        value: scalar,
+    });
+}
+
+fn scalar_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(r) => r.to_string(),
+        Value::Date(d) => d.to_xml_format(),
+        Value::Data(bytes) => format!("<{} bytes>", bytes.len()),
+        Value::Uid(uid) => format!("<uid {}>", uid.get()),
+        Value::Array(_) | Value::Dictionary(_) => "…".to_string(),
+        _ => "?".to_string(),
+    }
 }
 
+fn sort_dictionaries(value: &mut Value) {
+    match value {
+        Value::Dictionary(dict) => {
+            dict.sort_keys();
+            for (_, v) in dict.iter_mut() {
+                sort_dictionaries(v);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(sort_dictionaries),
+        _ => {}
+    }
+}
+
+static COMMAND: OnceLock<String> = OnceLock::new();
+
+/// Name the subcommand this run of ser is, e.g. `new`, for provenance. Only
+/// the subcommand is recorded: its arguments may hold secrets (`--env
+/// TOKEN=...`) and plists are world-readable.
+pub fn set_command(subcommand: &str) {
+    let _ = COMMAND.set(format!("ser {subcommand}"));
+}
+
+/// Which ser wrote a plist, when, and from which subcommand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub version: String,
+    /// RFC 3339, in UTC; empty for output that has to be reproducible.
+    pub created: String,
+    pub command: String,
+}
+
+impl Provenance {
+    /// This run of ser, now.
+    pub fn current() -> Provenance {
+        Provenance {
+            created: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            ..Provenance::undated()
+        }
+    }
+
+    /// This run of ser, without a time, so the same input always generates
+    /// the same plist.
+    pub fn undated() -> Provenance {
+        Provenance {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            created: String::new(),
+            command: COMMAND.get().cloned().unwrap_or_else(|| "ser".to_string()),
+        }
+    }
+
+    /// The provenance to write at `path`: the existing plist's, so a
+    /// rewrite still says where the service was created and an unchanged
+    /// service writes an unchanged file, else this run's.
+    pub fn for_path(path: &Path) -> Provenance {
+        plist::from_file::<_, Value>(path)
+            .ok()
+            .and_then(|existing| provenance(&existing))
+            .unwrap_or_else(Provenance::current)
+    }
+
+    fn from_value(value: &Value) -> Option<Provenance> {
+        let dict = value.as_dictionary()?;
+        let field = |key: &str| {
+            dict.get(key)
+                .and_then(|v| v.as_string())
+                .unwrap_or_default()
+                .to_string()
+        };
+        Some(Provenance {
+            version: field("Version"),
+            created: field("Created"),
+            command: field("Command"),
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let mut dict = plist::Dictionary::new();
+        dict.insert("Version".to_string(), Value::String(self.version.clone()));
+        if !self.created.is_empty() {
+            dict.insert("Created".to_string(), Value::String(self.created.clone()));
+        }
+        dict.insert("Command".to_string(), Value::String(self.command.clone()));
+        Value::Dictionary(dict)
+    }
+}
+
+/// The provenance in a parsed plist: present when ser generated it.
+pub fn provenance(plist: &Value) -> Option<Provenance> {
+    Provenance::from_value(plist.as_dictionary()?.get(PROVENANCE_KEY)?)
+}
+
+/// `file` with the keys whose values differ between the plists generated
+/// for `before` and `after` set to `after`'s (or removed), and every other
+/// key as written; see [`crate::platform::update_definition`].
+pub fn apply_changes(
+    file: &mut plist::Dictionary,
+    before: &ServiceDetails,
+    after: &ServiceDetails,
+) -> Result<()> {
+    let provenance = Provenance::current();
+    let generated = |details: &ServiceDetails| -> Result<plist::Dictionary> {
+        let xml = generate_file_with_provenance(details, &provenance)?;
+        plist::from_bytes::<Value>(xml.as_bytes())?
+            .into_dictionary()
+            .context("Generated plist is not a dictionary")
+    };
+    let (before, after) = (generated(before)?, generated(after)?);
+    for key in before.keys().chain(after.keys()) {
+        match (before.get(key), after.get(key)) {
+            (old, new) if old == new => {}
+            (_, Some(new)) => {
+                file.insert(key.clone(), new.clone());
+            }
+            (_, None) => {
+                file.remove(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A plist for `details`, recording which ser generated it but not when, so
+/// `ser generate` output is reproducible.
 pub fn generate_file(details: &ServiceDetails) -> Result<String> {
+    generate_file_with_provenance(details, &Provenance::undated())
+}
+
+/// [`generate_file`] with the given provenance, e.g. the original one when
+/// regenerating an existing plist.
+pub fn generate_file_with_provenance(
+    details: &ServiceDetails,
+    provenance: &Provenance,
+) -> Result<String> {
     let mut plist_dict = plist::Dictionary::new();
 
     plist_dict.insert("Label".to_string(), Value::String(details.name.clone()));
+    plist_dict.insert(PROVENANCE_KEY.to_string(), provenance.to_value());
+    if let Some(description) = &details.description {
+        plist_dict.insert(
+            DESCRIPTION_KEY.to_string(),
+            Value::String(description.clone()),
+        );
+    }
+    if !details.tags.is_empty() {
+        let tags = details.tags.iter().cloned().map(Value::String).collect();
+        plist_dict.insert(TAGS_KEY.to_string(), Value::Array(tags));
+    }
+    if let Some(owner) = &details.owner {
+        plist_dict.insert(OWNER_KEY.to_string(), Value::String(owner.clone()));
+    }
 
-    if details.arguments.is_empty() {
+    if let Some(jitter) = details.jitter.filter(|_| details.schedule.is_some()) {
+        plist_dict.insert(
+            JITTER_KEY.to_string(),
+            Value::Integer((jitter as i64).into()),
+        );
+    }
+    if let Some(catch_up) = details.catch_up.filter(|_| details.schedule.is_some()) {
+        plist_dict.insert(CATCH_UP_KEY.to_string(), Value::Boolean(catch_up));
+    }
+    if !details.env_files.is_empty() {
+        let files = details
+            .env_files
+            .iter()
+            .map(|f| Value::String(f.to_systemd_value()))
+            .collect();
+        plist_dict.insert(ENV_FILES_KEY.to_string(), Value::Array(files));
+    }
+    if let Some(script) = wrapper_script(details) {
+        let mut args = vec![
+            Value::String("/bin/sh".to_string()),
+            Value::String("-c".to_string()),
+            Value::String(script),
+            Value::String(details.program.clone()),
+        ];
+        args.extend(details.arguments.iter().map(|v| Value::String(v.clone())));
+        plist_dict.insert("ProgramArguments".to_string(), Value::Array(args));
+    } else if details.arguments.is_empty() {
         plist_dict.insert(
             "Program".to_string(),
             Value::String(details.program.clone()),
@@ -24,6 +636,9 @@ pub fn generate_file(details: &ServiceDetails) -> Result<String> {
     if let Some(wd) = &details.working_directory {
         plist_dict.insert("WorkingDirectory".to_string(), Value::String(wd.clone()));
     }
+    if let Some(user) = &details.user {
+        plist_dict.insert("UserName".to_string(), Value::String(user.clone()));
+    }
 
     // Handle schedule - if scheduled, add StartCalendarInterval/StartInterval
     // instead of RunAtLoad/KeepAlive.
@@ -52,14 +667,57 @@ pub fn generate_file(details: &ServiceDetails) -> Result<String> {
             plist_dict.insert("RunAtLoad".to_string(), Value::Boolean(true));
         }
 
-        if details.keep_alive {
+        // launchd's nearest equivalent of ConditionPathExists= is a KeepAlive
+        // PathState dictionary: the job is kept running while the path
+        // (doesn't) exist. That only matches a job that's kept alive anyway;
+        // for any other it would turn a gate into relaunching.
+        let path_states: Vec<_> = details
+            .conditions
+            .iter()
+            .filter(|c| details.keep_alive && c.kind == "PathExists")
+            .collect();
+        if !path_states.is_empty() {
+            let mut state_dict = plist::Dictionary::new();
+            for condition in path_states {
+                let (path, exists) = match condition.value.strip_prefix('!') {
+                    Some(path) => (path, false),
+                    None => (condition.value.as_str(), true),
+                };
+                state_dict.insert(path.to_string(), Value::Boolean(exists));
+            }
+            let mut keep_alive = plist::Dictionary::new();
+            keep_alive.insert("PathState".to_string(), Value::Dictionary(state_dict));
+            plist_dict.insert("KeepAlive".to_string(), Value::Dictionary(keep_alive));
+        } else if details.keep_alive {
             plist_dict.insert("KeepAlive".to_string(), Value::Boolean(true));
         }
     }
 
-    if !details.env_vars.is_empty() {
+    if !details.directories.is_empty() {
+        let entries = details
+            .directories
+            .iter()
+            .map(|d| Value::String(format!("{}={}", d.kind.directive(), d.name)))
+            .collect();
+        plist_dict.insert(DIRECTORIES_KEY.to_string(), Value::Array(entries));
+    }
+
+    let mut env_vars = details.env_vars.clone();
+    for kind in DirectoryKind::ALL {
+        let paths: Vec<String> = details
+            .directories
+            .iter()
+            .filter(|d| d.kind == kind)
+            .filter_map(directory_path)
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        if !paths.is_empty() {
+            env_vars.push((kind.env_var().to_string(), paths.join(":")));
+        }
+    }
+    if !env_vars.is_empty() {
         let mut env_dict = plist::Dictionary::new();
-        for (key, value) in &details.env_vars {
+        for (key, value) in &env_vars {
             env_dict.insert(key.clone(), Value::String(value.clone()));
         }
         plist_dict.insert(
@@ -75,3 +733,213 @@ pub fn generate_file(details: &ServiceDetails) -> Result<String> {
     let plist_string = String::from_utf8(plist_data)?;
     Ok(plist_string)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_provenance() {
+        let details = ServiceDetails {
+            name: "web".to_string(),
+            program: "/usr/local/bin/web".to_string(),
+            ..Default::default()
+        };
+        let xml = generate_file(&details).unwrap();
+        let value: Value = plist::from_bytes(xml.as_bytes()).unwrap();
+        let recorded = provenance(&value).unwrap();
+        assert_eq!(recorded.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(recorded.created, "");
+        assert_eq!(generate_file(&details).unwrap(), xml);
+        assert_eq!(parse_value(value).unwrap().program, details.program);
+
+        // Regenerating with the recorded provenance changes nothing.
+        let again = generate_file_with_provenance(&details, &recorded).unwrap();
+        assert_eq!(again, xml);
+        let plain: Value = plist::from_bytes(b"<plist><dict/></plist>").unwrap();
+        assert_eq!(provenance(&plain), None);
+    }
+
+    #[test]
+    fn transcodes_between_formats() {
+        let openstep = br#"{ Label = "com.example.agent"; ProgramArguments = ("/usr/bin/true"); RunAtLoad = YES; }"#;
+        assert_eq!(PlistFormat::detect(openstep), PlistFormat::OpenStep);
+        let parsed = parse_plist(openstep).unwrap();
+        assert_eq!(parsed.name, "com.example.agent");
+        assert_eq!(parsed.program, "/usr/bin/true");
+        assert!(parsed.run_at_load);
+        let interval = parse_plist(
+            br#"{ Label = "x"; Program = "/bin/x"; StartInterval = 300; KeepAlive = NO; }"#,
+        )
+        .unwrap();
+        assert!(matches!(interval.schedule, Some(Schedule::Interval(300))));
+        assert!(!interval.keep_alive);
+
+        let binary = convert_plist(openstep, PlistFormat::Binary).unwrap();
+        assert_eq!(PlistFormat::detect(&binary), PlistFormat::Binary);
+        assert_eq!(parse_plist(&binary).unwrap().program, "/usr/bin/true");
+
+        let xml = format_plist(&binary).unwrap();
+        assert_eq!(PlistFormat::detect(xml.as_bytes()), PlistFormat::Xml);
+        assert_eq!(
+            format_plist_as(xml.as_bytes(), PlistFormat::Binary).unwrap(),
+            format_plist_as(&binary, PlistFormat::Binary).unwrap()
+        );
+    }
+
+    #[test]
+    fn wrapper_round_trips() {
+        let details = ServiceDetails {
+            name: "backup".to_string(),
+            program: "/usr/local/bin/backup".to_string(),
+            arguments: vec!["--all".to_string()],
+            schedule: Some(Schedule::Calendar(crate::CalendarSchedule {
+                hour: Some(3),
+                minute: Some(0),
+                ..Default::default()
+            })),
+            jitter: Some(300),
+            catch_up: Some(false),
+            ..Default::default()
+        };
+        let xml = generate_file(&details).unwrap();
+        let value: Value = plist::from_bytes(xml.as_bytes()).unwrap();
+        let arguments: Vec<String> = value.as_dictionary().unwrap()["ProgramArguments"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_string().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            arguments[2],
+            "[ \"$(date +%H:%M)\" = \"03:00\" ] || exit 0; sleep $(jot -r 1 0 300); exec \"$0\" \"$@\""
+        );
+        assert_eq!(
+            unwrap_wrapper(&arguments),
+            Some((
+                "/usr/local/bin/backup".to_string(),
+                vec!["--all".to_string()]
+            ))
+        );
+    }
+
+    #[test]
+    fn path_conditions_only_gate_kept_alive_jobs() {
+        let condition = Condition {
+            kind: "PathExists".to_string(),
+            value: "!/tmp/stop".to_string(),
+            assert: false,
+        };
+        let mut details = ServiceDetails {
+            name: "web".to_string(),
+            program: "/usr/local/bin/web".to_string(),
+            keep_alive: true,
+            conditions: vec![condition],
+            ..Default::default()
+        };
+        let parsed = parse_plist(generate_file(&details).unwrap().as_bytes()).unwrap();
+        assert!(parsed.keep_alive);
+        assert_eq!(parsed.conditions, details.conditions);
+
+        details.keep_alive = false;
+        let xml = generate_file(&details).unwrap();
+        assert!(!xml.contains("KeepAlive"), "{xml}");
+        let parsed = parse_plist(xml.as_bytes()).unwrap();
+        assert!(!parsed.keep_alive);
+        assert!(parsed.conditions.is_empty());
+    }
+
+    #[test]
+    fn env_files_round_trip() {
+        let details = ServiceDetails {
+            name: "web".to_string(),
+            program: "/usr/local/bin/web".to_string(),
+            env_files: vec![
+                EnvFile::parse("/etc/web.env"),
+                EnvFile::parse("-/Users/o'neil/web.env"),
+            ],
+            ..Default::default()
+        };
+        let xml = generate_file(&details).unwrap();
+        let value: Value = plist::from_bytes(xml.as_bytes()).unwrap();
+        let script = value.as_dictionary().unwrap()["ProgramArguments"]
+            .as_array()
+            .unwrap()[2]
+            .as_string()
+            .unwrap()
+            .to_string();
+        assert!(script.ends_with(
+            "ser_env '/etc/web.env' || exit 1; [ ! -e '/Users/o'\\''neil/web.env' ] || ser_env '/Users/o'\\''neil/web.env' || exit 1; exec \"$0\" \"$@\""
+        ));
+        let parsed = parse_plist(xml.as_bytes()).unwrap();
+        assert_eq!(parsed.env_files, details.env_files);
+        assert_eq!(parsed.program, details.program);
+        assert!(parsed.arguments.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn wrapper_reads_env_files_without_running_them() {
+        let contents = "# comment\nA=1\nexport B=\"two words\"\n\n  C = 'x' \nD=$(touch /tmp/ser-env-ran)\nE=a;b\nnot a var=1\n; note\nF";
+        let path = std::env::temp_dir().join(format!("ser-env-{}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        let details = ServiceDetails {
+            name: "web".to_string(),
+            program: "/usr/bin/env".to_string(),
+            env_files: vec![EnvFile::parse(path.to_str().unwrap())],
+            ..Default::default()
+        };
+        let script = wrapper_script(&details).unwrap();
+        let output = std::process::Command::new("/bin/sh")
+            .args(["-c", &script, "/usr/bin/env"])
+            .env_clear()
+            .output()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(output.status.success(), "{output:?}");
+        let env = String::from_utf8(output.stdout).unwrap();
+        for (key, value) in crate::parse_env_file(contents) {
+            if key.contains(' ') {
+                continue;
+            }
+            assert!(
+                env.lines().any(|l| l == format!("{key}={value}")),
+                "{key} in {env}"
+            );
+        }
+        assert!(!env.contains("not a var"));
+
+        let missing = ServiceDetails {
+            env_files: vec![EnvFile::parse("/nonexistent/web.env")],
+            ..details
+        };
+        let status = std::process::Command::new("/bin/sh")
+            .args(["-c", &wrapper_script(&missing).unwrap(), "/usr/bin/env"])
+            .status()
+            .unwrap();
+        assert!(!status.success());
+    }
+
+    #[test]
+    fn raw_fields_flatten_nested_keys() {
+        let xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict>
+<key>Label</key><string>web</string>
+<key>ProgramArguments</key><array><string>/bin/web</string><string>-v</string></array>
+<key>StartCalendarInterval</key><array><dict><key>Hour</key><integer>3</integer></dict></array>
+</dict></plist>"#;
+        let fields = raw_fields(xml).unwrap();
+        let pairs: Vec<_> = fields
+            .iter()
+            .map(|f| (f.key.as_str(), f.value.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("Label", "web"),
+                ("ProgramArguments", "[/bin/web, -v]"),
+                ("StartCalendarInterval[0].Hour", "3"),
+            ]
+        );
+    }
+}
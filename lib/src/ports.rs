@@ -0,0 +1,235 @@
+//! Port conflicts for `ser start`: the TCP ports a service will listen on,
+//! and whatever already listens there, so a clash is reported up front
+//! instead of the service crash-looping on `EADDRINUSE`.
+
+use crate::{platform, FsServiceDetails, ServiceDetails, TriggerKind};
+use std::net::{Ipv4Addr, TcpListener};
+use std::process::Command;
+
+/// Something already listening on a port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listener {
+    pub port: u16,
+    pub pid: Option<u32>,
+    /// The process name, e.g. `nginx`.
+    pub command: Option<String>,
+    /// The service the process belongs to, when the init system says.
+    pub service: Option<String>,
+}
+
+impl std::fmt::Display for Listener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let process = match (&self.command, self.pid) {
+            (Some(command), Some(pid)) => format!("{command} (pid {pid})"),
+            (None, Some(pid)) => format!("pid {pid}"),
+            (Some(command), None) => command.clone(),
+            (None, None) => "another process".to_string(),
+        };
+        match &self.service {
+            Some(service) => write!(f, "{process}, from {service}"),
+            None => write!(f, "{process}"),
+        }
+    }
+}
+
+/// The ports a service declares: its socket unit's `ListenStream=`, a
+/// `--port` argument or `PORT` variable, and the ports of any readiness
+/// probe URLs it's started with. A short `-p` isn't read as a port, since
+/// plenty of programs use it for something else (`autossh -p 22` is the
+/// port it connects to).
+pub fn declared_ports(details: &FsServiceDetails, probe_urls: &[&str]) -> Vec<u16> {
+    let mut ports = Vec::new();
+    if let Some(trigger) = details
+        .trigger
+        .as_ref()
+        .filter(|t| t.kind == TriggerKind::Socket)
+    {
+        ports.extend(
+            trigger
+                .settings
+                .iter()
+                .filter(|(key, _)| key == "ListenStream")
+                .filter_map(|(_, value)| parse_listen_port(value)),
+        );
+    }
+    let service = &details.service;
+    let mut args = service.arguments.iter();
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--port=") {
+            Some(value) => Some(value),
+            None if arg == "--port" => args.next().map(String::as_str),
+            None => None,
+        };
+        ports.extend(value.and_then(|v| v.parse::<u16>().ok()));
+    }
+    ports.extend(
+        service
+            .env_vars
+            .iter()
+            .filter(|(key, _)| key == "PORT")
+            .filter_map(|(_, value)| value.parse::<u16>().ok()),
+    );
+    ports.extend(probe_urls.iter().filter_map(|url| url_port(url)));
+    ports.retain(|port| *port != 0);
+    let mut seen = Vec::new();
+    ports.retain(|port| {
+        let new = !seen.contains(port);
+        seen.push(*port);
+        new
+    });
+    ports
+}
+
+/// Change the port a service listens on from `from` to `to` wherever
+/// [`declared_ports`] finds it in the definition: a `--port` argument or
+/// `PORT`. Returns whether anything changed.
+pub fn rewrite_port(details: &mut ServiceDetails, from: u16, to: u16) -> bool {
+    let (from, to) = (from.to_string(), to.to_string());
+    let mut changed = false;
+    let mut port_value_next = false;
+    for arg in &mut details.arguments {
+        if port_value_next && *arg == from {
+            *arg = to.clone();
+            changed = true;
+        } else if arg.strip_prefix("--port=") == Some(from.as_str()) {
+            *arg = format!("--port={to}");
+            changed = true;
+        }
+        port_value_next = arg == "--port";
+    }
+    for (key, value) in &mut details.env_vars {
+        if key == "PORT" && *value == from {
+            *value = to.clone();
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// The port of a `ListenStream=` value: `8080`, `0.0.0.0:8080`, or
+/// `[::]:8080`, but not a socket path.
+fn parse_listen_port(value: &str) -> Option<u16> {
+    let value = value.trim();
+    if value.starts_with('/') || value.starts_with('@') {
+        return None;
+    }
+    value.rsplit(':').next()?.parse().ok()
+}
+
+/// The port of a local `http(s)://` URL, explicit or implied by its scheme.
+fn url_port(url: &str) -> Option<u16> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    let (host, port) = match host.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (host, port.parse().ok()?),
+        _ => (host, if scheme == "https" { 443 } else { 80 }),
+    };
+    ["localhost", "127.0.0.1", "[::1]", "0.0.0.0"]
+        .contains(&host)
+        .then_some(port)
+}
+
+/// Whatever is listening on `port`, if anything. Socket activation, where
+/// the init system itself holds the port for the service, doesn't count.
+pub fn listener(port: u16) -> Option<Listener> {
+    let pids = platform::pids_listening_on(port).unwrap_or_default();
+    let Some(&pid) = pids.first() else {
+        // Another user's process may not show up without root, but a failed
+        // bind still shows the port is taken.
+        let in_use = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port))
+            .is_err_and(|e| e.kind() == std::io::ErrorKind::AddrInUse);
+        return in_use.then_some(Listener {
+            port,
+            pid: None,
+            command: None,
+            service: None,
+        });
+    };
+    let command = process_name(pid);
+    if pid == 1 || matches!(command.as_deref(), Some("systemd" | "launchd")) {
+        return None;
+    }
+    Some(Listener {
+        port,
+        pid: Some(pid),
+        command,
+        service: platform::service_for_pid(pid).ok().flatten(),
+    })
+}
+
+/// A process's executable name, from `ps`.
+fn process_name(pid: u32) -> Option<String> {
+    let mut cmd = Command::new("ps");
+    cmd.args(["-o", "comm=", "-p", &pid.to_string()]);
+    crate::print_command(&cmd);
+    let output = crate::interrupt::output(&mut cmd)
+        .ok()
+        .filter(|o| o.status.success())?;
+    let name = String::from_utf8_lossy(&output.stdout);
+    // macOS prints the full path.
+    let name = name.trim().rsplit('/').next()?.to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TriggerUnit;
+
+    #[test]
+    fn finds_declared_ports() {
+        let details = FsServiceDetails {
+            service: ServiceDetails {
+                arguments: vec![
+                    "--port".to_string(),
+                    "8080".to_string(),
+                    "--port=8081".to_string(),
+                    "-p".to_string(),
+                    "22".to_string(),
+                ],
+                env_vars: vec![("PORT".to_string(), "8080".to_string())],
+                ..Default::default()
+            },
+            path: String::new(),
+            enabled: false,
+            running: false,
+            pid: None,
+            trigger: Some(TriggerUnit {
+                name: "web.socket".to_string(),
+                kind: TriggerKind::Socket,
+                unit: "web.service".to_string(),
+                settings: vec![
+                    ("ListenStream".to_string(), "[::]:9000".to_string()),
+                    ("ListenStream".to_string(), "/run/web.sock".to_string()),
+                ],
+            }),
+        };
+        let ports = declared_ports(
+            &details,
+            &["http://localhost:9090/health", "https://example.com:9443/"],
+        );
+        assert_eq!(ports, [9000, 8080, 8081, 9090]);
+        assert_eq!(url_port("https://127.0.0.1/ready"), Some(443));
+
+        let mut service = details.service.clone();
+        assert!(rewrite_port(&mut service, 8080, 18080));
+        assert_eq!(
+            service.arguments,
+            ["--port", "18080", "--port=8081", "-p", "22"]
+        );
+        assert_eq!(service.env_vars[0].1, "18080");
+        assert!(!rewrite_port(&mut service, 7000, 7001));
+    }
+
+    #[test]
+    fn finds_a_listener() {
+        let socket = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let found = listener(port).expect("port is in use");
+        assert_eq!(found.port, port);
+        if found.pid.is_some() {
+            assert_eq!(found.pid, Some(std::process::id()));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Readiness probes for `ser start --wait-for`: block until a service is
+//! actually serving (a port accepts connections, a URL answers 2xx, or a log
+//! line appears), not just until its process has been forked.
+
+use crate::platform;
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use std::collections::HashSet;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::Command;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// How often probes are retried.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How many recent log lines a log probe searches.
+const LOG_LINES: u32 = 200;
+
+#[derive(Debug, Clone)]
+pub enum Probe {
+    /// `port:8080` (localhost) or `port:host:8080`: a TCP connect succeeds.
+    Port(String),
+    /// `url:http://localhost:8080/health`: a GET answers with a 2xx status.
+    Url(String),
+    /// `log:REGEX`: a line logged since the start matches.
+    Log(Regex),
+}
+
+impl FromStr for Probe {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Probe> {
+        let (kind, value) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("expected port:PORT, url:URL, or log:REGEX, got '{s}'"))?;
+        match kind {
+            "port" => {
+                let address = if value.contains(':') {
+                    value.to_string()
+                } else {
+                    format!("127.0.0.1:{value}")
+                };
+                let port = address.rsplit_once(':').map_or("", |(_, p)| p);
+                port.parse::<u16>()
+                    .map_err(|_| anyhow!("invalid port '{port}'"))?;
+                Ok(Probe::Port(address))
+            }
+            "url" => Ok(Probe::Url(value.to_string())),
+            "log" => {
+                Ok(Probe::Log(Regex::new(value).with_context(|| {
+                    format!("invalid log pattern '{value}'")
+                })?))
+            }
+            _ => bail!("unknown probe '{kind}'; expected port, url, or log"),
+        }
+    }
+}
+
+impl std::fmt::Display for Probe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Probe::Port(address) => write!(f, "port {address}"),
+            Probe::Url(url) => write!(f, "{url}"),
+            Probe::Log(pattern) => write!(f, "log /{pattern}/"),
+        }
+    }
+}
+
+/// The service's recent log lines, to tell a log probe which lines predate
+/// the start. Taken before starting it.
+pub fn log_baseline(name: &str) -> HashSet<String> {
+    platform::recent_logs(name, LOG_LINES)
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+}
+
+/// Wait until every probe passes, failing early if the service fails and
+/// after `timeout` otherwise.
+pub fn wait_for(
+    name: &str,
+    probes: &[Probe],
+    timeout: Duration,
+    baseline: &HashSet<String>,
+) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    let mut pending: Vec<&Probe> = probes.iter().collect();
+    loop {
+        pending.retain(|probe| !passes(name, probe, baseline));
+        if pending.is_empty() {
+            return Ok(());
+        }
+        if platform::is_service_failed(name) {
+            bail!("'{name}' failed before it was ready");
+        }
+        if Instant::now() >= deadline {
+            let waiting: Vec<String> = pending.iter().map(|p| p.to_string()).collect();
+            bail!(
+                "'{name}' wasn't ready after {}s; still waiting for {}",
+                timeout.as_secs(),
+                waiting.join(", ")
+            );
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn passes(name: &str, probe: &Probe, baseline: &HashSet<String>) -> bool {
+    match probe {
+        Probe::Port(address) => address.to_socket_addrs().is_ok_and(|mut addrs| {
+            addrs.any(|addr| TcpStream::connect_timeout(&addr, Duration::from_secs(1)).is_ok())
+        }),
+        Probe::Url(url) => {
+            let mut cmd = Command::new("curl");
+            cmd.args(["-sS", "-o", "/dev/null", "-w", "%{http_code}"])
+                .args(["--max-time", "2", url]);
+            crate::print_command(&cmd);
+            crate::interrupt::output(&mut cmd).is_ok_and(|output| output.stdout.starts_with(b"2"))
+        }
+        Probe::Log(pattern) => platform::recent_logs(name, LOG_LINES)
+            .unwrap_or_default()
+            .iter()
+            .filter(|line| !baseline.contains(*line))
+            .any(|line| pattern.is_match(line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_probes() {
+        let probe: Probe = "port:8080".parse().unwrap();
+        assert!(matches!(probe, Probe::Port(ref a) if a == "127.0.0.1:8080"));
+        let probe: Probe = "url:http://localhost:8080/health".parse().unwrap();
+        assert!(matches!(probe, Probe::Url(ref u) if u == "http://localhost:8080/health"));
+        let probe: Probe = "log:listening on \\d+".parse().unwrap();
+        assert_eq!(probe.to_string(), "log /listening on \\d+/");
+        assert!("port:http".parse::<Probe>().is_err());
+        assert!("tcp:80".parse::<Probe>().is_err());
+    }
+
+    #[test]
+    fn waits_for_a_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let probe: Probe = format!("port:{port}").parse().unwrap();
+        assert!(passes("ser-no-such-service", &probe, &HashSet::new()));
+    }
+}
@@ -0,0 +1,139 @@
+//! Checks that the unit file and plist parsers agree with the generators,
+//! shared by the tests below and the cargo-fuzz targets in `fuzz/`. Anything
+//! may be handed to the parsers (`edit`, `apply`, and `migrate` all read
+//! files from elsewhere), so they must not panic, and whatever parses must
+//! survive parse → generate → parse with the same [`crate::ServiceDetails`],
+//! and generate to the same text again.
+
+use crate::plist::{self, Provenance};
+use crate::systemd;
+use crate::ServiceDetails;
+
+/// Parse `input` as a unit file, and if that works, check that generating
+/// and reparsing it gives the same service and the same unit. The error
+/// describes what changed.
+pub fn check_systemd(input: &str) -> Result<(), String> {
+    let Ok(parsed) = systemd::parse_systemd(input) else {
+        return Ok(());
+    };
+    let first = systemd::generate_file(&parsed)
+        .map_err(|e| format!("the parsed unit doesn't generate: {e:#}"))?;
+    let reparsed = systemd::parse_systemd(&first)
+        .map_err(|e| format!("the generated unit doesn't parse: {e:#}\n{first}"))?;
+    same_service(&parsed, &reparsed)?;
+    let second = systemd::generate_file(&reparsed)
+        .map_err(|e| format!("the reparsed unit doesn't generate: {e:#}\n{first}"))?;
+    stable(&first, &second)
+}
+
+/// [`check_systemd`] for a plist (XML, binary, or OpenStep).
+pub fn check_plist(input: &[u8]) -> Result<(), String> {
+    let Ok(parsed) = plist::parse_plist(input) else {
+        return Ok(());
+    };
+    // Fixed, so only the service itself can differ.
+    let provenance = Provenance {
+        version: String::new(),
+        created: String::new(),
+        command: String::new(),
+    };
+    let first = plist::generate_file_with_provenance(&parsed, &provenance)
+        .map_err(|e| format!("the parsed plist doesn't generate: {e:#}"))?;
+    let reparsed = plist::parse_plist(first.as_bytes())
+        .map_err(|e| format!("the generated plist doesn't parse: {e:#}\n{first}"))?;
+    same_service(&parsed, &reparsed)?;
+    let second = plist::generate_file_with_provenance(&reparsed, &provenance)
+        .map_err(|e| format!("the reparsed plist doesn't generate: {e:#}\n{first}"))?;
+    stable(&first, &second)
+}
+
+/// Compare the services field by field, through their `Debug` output.
+fn same_service(parsed: &ServiceDetails, reparsed: &ServiceDetails) -> Result<(), String> {
+    stable(&format!("{parsed:#?}"), &format!("{reparsed:#?}"))
+        .map_err(|e| format!("the service changed when reparsed: {e}"))
+}
+
+fn stable(first: &str, second: &str) -> Result<(), String> {
+    if first == second {
+        return Ok(());
+    }
+    let changed = first
+        .lines()
+        .zip(second.lines())
+        .find(|(a, b)| a != b)
+        .map(|(a, b)| format!("{a:?} became {b:?}"))
+        .unwrap_or_else(|| "the line count changed".to_string());
+    Err(format!(
+        "not stable: {changed}\n--- first\n{first}\n--- second\n{second}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNITS: &[&str] = &[
+        "[Unit]\nDescription=Web server\nAfter=network.target\n\n[Service]\nExecStart=/usr/bin/web --port 8080\nEnvironment=\"A=1 2\" B=3\nWorkingDirectory=/srv/web\nUser=web\nRestart=always\n\n[Install]\nWantedBy=multi-user.target\n",
+        "[Service]\nExecStart=/bin/sh -c 'echo \"hi\"; sleep 1'\nStateDirectory=web\nEnvironmentFile=-/etc/default/web\nConditionPathExists=!/tmp/stop\nOOMScoreAdjust=-100\n",
+        "[Service]\nType=oneshot\nExecStart=/usr/bin/backup \\\n  --all\nAmbientCapabilities=CAP_NET_BIND_SERVICE\nIPAddressDeny=any\n",
+    ];
+
+    const PLISTS: &[&str] = &[
+        r#"<?xml version="1.0" encoding="UTF-8"?><plist version="1.0"><dict><key>Label</key><string>web</string><key>ProgramArguments</key><array><string>/usr/bin/web</string><string>--port</string><string>8080</string></array><key>RunAtLoad</key><true/><key>KeepAlive</key><true/><key>EnvironmentVariables</key><dict><key>A</key><string>1</string></dict></dict></plist>"#,
+        r#"<plist><dict><key>Label</key><string>backup</string><key>Program</key><string>/usr/bin/backup</string><key>StartCalendarInterval</key><dict><key>Hour</key><integer>3</integer><key>Minute</key><integer>0</integer></dict><key>WorkingDirectory</key><string>/tmp</string></dict></plist>"#,
+        r#"{ Label = "agent"; ProgramArguments = ("/usr/bin/true", "-v"); StartInterval = 300; }"#,
+    ];
+
+    /// Characters the parsers treat specially, for mutations to reach them.
+    const INTERESTING: &[u8] = b"[]=\\\"' \n\t%$#;:-<>/!@0123456789";
+
+    /// `input` with a few random insertions, deletions, and duplications.
+    fn mutate(rng: &mut fastrand::Rng, input: &[u8]) -> Vec<u8> {
+        let mut bytes = input.to_vec();
+        for _ in 0..rng.usize(1..8) {
+            let at = rng.usize(0..=bytes.len());
+            match rng.u8(0..4) {
+                0 => bytes.insert(at, INTERESTING[rng.usize(0..INTERESTING.len())]),
+                1 => bytes.insert(at, rng.u8(..)),
+                2 if at < bytes.len() => {
+                    bytes.remove(at);
+                }
+                _ => {
+                    let end = (at + rng.usize(0..16)).min(bytes.len());
+                    let copy = bytes[at..end].to_vec();
+                    let to = rng.usize(0..=bytes.len());
+                    bytes.splice(to..to, copy);
+                }
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn parsers_round_trip() {
+        for unit in UNITS {
+            assert!(systemd::parse_systemd(unit).is_ok(), "{unit}");
+            check_systemd(unit).unwrap();
+        }
+        for plist in PLISTS {
+            assert!(plist::parse_plist(plist.as_bytes()).is_ok(), "{plist}");
+            check_plist(plist.as_bytes()).unwrap();
+        }
+
+        // Fixed seed, so a failure reproduces.
+        let mut rng = fastrand::Rng::with_seed(0x5e7);
+        for _ in 0..2000 {
+            let seed = UNITS[rng.usize(0..UNITS.len())];
+            let unit = mutate(&mut rng, seed.as_bytes());
+            let unit = String::from_utf8_lossy(&unit);
+            if let Err(e) = check_systemd(&unit) {
+                panic!("{e}\n--- input\n{unit}");
+            }
+            let seed = PLISTS[rng.usize(0..PLISTS.len())];
+            let plist = mutate(&mut rng, seed.as_bytes());
+            if let Err(e) = check_plist(&plist) {
+                panic!("{e}\n--- input\n{}", String::from_utf8_lossy(&plist));
+            }
+        }
+    }
+}
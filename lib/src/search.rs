@@ -0,0 +1,92 @@
+use crate::ServiceDetails;
+
+/// One place a search query matched within a service.
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    /// Which field matched, e.g. `name`, `program`, `env`.
+    pub field: &'static str,
+    /// The matched value, for display.
+    pub context: String,
+    /// Relevance weight; higher is better.
+    pub score: u32,
+}
+
+/// Find every field of `details` containing `query` (case-insensitive).
+///
+/// `name` is passed separately so unparseable units can still match by name.
+/// Matches are weighted so that name hits rank above description hits, which
+/// rank above program/argument/environment hits.
+pub fn match_service(
+    name: &str,
+    details: Option<&ServiceDetails>,
+    query: &str,
+) -> Vec<SearchMatch> {
+    let query = query.to_lowercase();
+    let mut matches = Vec::new();
+    let mut check = |field: &'static str, value: &str, score: u32| {
+        if value.to_lowercase().contains(&query) {
+            matches.push(SearchMatch {
+                field,
+                context: value.to_string(),
+                score,
+            });
+        }
+    };
+
+    if name.eq_ignore_ascii_case(&query) {
+        check("name", name, 100);
+    } else {
+        check("name", name, 50);
+    }
+
+    let Some(details) = details else {
+        return matches;
+    };
+    if let Some(description) = &details.description {
+        check("description", description, 30);
+    }
+    for tag in &details.tags {
+        check("tag", tag, 30);
+    }
+    check("program", &details.program, 25);
+    for arg in &details.arguments {
+        check("argument", arg, 20);
+    }
+    for (key, value) in &details.env_vars {
+        check("env", &format!("{key}={value}"), 15);
+    }
+    for env_file in &details.env_files {
+        check("env file", &env_file.path, 10);
+    }
+    if let Some(wd) = &details.working_directory {
+        check("working directory", wd, 10);
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_name_above_program() {
+        let details = ServiceDetails {
+            name: "api".to_string(),
+            program: "/usr/bin/node".to_string(),
+            arguments: vec!["/srv/data/server.js".to_string()],
+            env_vars: vec![("NODE_ENV".to_string(), "production".to_string())],
+            ..Default::default()
+        };
+
+        let matches = match_service("api", Some(&details), "node");
+        let fields: Vec<_> = matches.iter().map(|m| m.field).collect();
+        assert_eq!(fields, vec!["program", "env"]);
+
+        let matches = match_service("api", Some(&details), "/srv/data");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].field, "argument");
+
+        let exact = match_service("api", None, "API");
+        assert_eq!(exact[0].score, 100);
+    }
+}
@@ -0,0 +1,347 @@
+use crate::hooks::Hooks;
+use crate::{CalendarSchedule, EnvFile, Schedule, ServiceDetails};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Project-local service definitions, discovered like `.nvmrc` or a `Procfile`.
+pub const PROJECT_FILE: &str = ".ser.toml";
+
+/// A single service written as a small TOML document, so definitions can live
+/// in a project repo and be installed on either platform with `ser install`.
+///
+/// Fields mirror [`ServiceDetails`]; a few take friendlier forms (`command` as
+/// one string, `env` as a table, `schedule` as an `OnCalendar=` expression or
+/// an interval like `15m`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServiceSpec {
+    /// Defaults to the file name without its extension.
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub owner: Option<String>,
+    /// Program and arguments in one string, split on whitespace.
+    pub command: Option<String>,
+    /// Alternative to `command` for arguments containing spaces.
+    pub program: Option<String>,
+    pub arguments: Vec<String>,
+    pub working_directory: Option<String>,
+    /// The account to run as, as accepted by `ser set`.
+    pub user: Option<String>,
+    pub run_at_load: bool,
+    pub keep_alive: bool,
+    pub env_file: Option<String>,
+    /// Several env files, loaded in order; prefix a path with `-` to skip it
+    /// when missing.
+    pub env_files: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub after: Vec<String>,
+    pub schedule: Option<String>,
+    /// Run a missed calendar run at the next opportunity (default true).
+    pub catch_up: Option<bool>,
+    pub oom_score_adjust: Option<i16>,
+    pub oom_policy: Option<String>,
+    /// Linux capabilities, e.g. `["CAP_NET_BIND_SERVICE"]` to bind ports
+    /// below 1024 without running as root.
+    pub ambient_capabilities: Vec<String>,
+    /// `"none"` drops every capability; a list keeps only those.
+    pub capability_bounding_set: Option<String>,
+    /// Network sandboxing, as accepted by `ser set`.
+    pub private_network: bool,
+    pub ip_address_allow: Vec<String>,
+    pub ip_address_deny: Vec<String>,
+    pub restrict_address_families: Vec<String>,
+    /// `PathExists=/srv/data` style conditions, as accepted by `ser set`.
+    pub conditions: Vec<String>,
+    /// Managed directory names, e.g. `state_directory = "myapp"`, as accepted
+    /// by `ser set`.
+    pub state_directory: Option<String>,
+    pub cache_directory: Option<String>,
+    pub logs_directory: Option<String>,
+    pub runtime_directory: Option<String>,
+    /// Client-side hooks, run by `ser` itself around start/stop.
+    pub hooks: Hooks,
+}
+
+impl ServiceSpec {
+    /// Whether `path` looks like a spec file rather than a native unit/plist.
+    pub fn is_spec_path(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == "toml")
+    }
+
+    pub fn from_path(path: &Path) -> Result<ServiceSpec> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        ServiceSpec::parse(&contents).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    pub fn parse(contents: &str) -> Result<ServiceSpec> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Convert to [`ServiceDetails`], using `default_name` if the spec has no `name`.
+    pub fn into_details(self, default_name: &str) -> Result<ServiceDetails> {
+        let (program, arguments) = match (self.command, self.program) {
+            (Some(_), Some(_)) => bail!("Set either `command` or `program`, not both"),
+            (Some(command), None) => {
+                let mut parts = command.split_whitespace().map(str::to_string);
+                let program = parts.next().ok_or_else(|| anyhow!("`command` is empty"))?;
+                (program, parts.chain(self.arguments).collect())
+            }
+            (None, Some(program)) => (program, self.arguments),
+            (None, None) => bail!("A service needs a `command` (or `program`)"),
+        };
+        let schedule = self.schedule.as_deref().map(parse_schedule).transpose()?;
+
+        let mut details = ServiceDetails {
+            name: self.name.unwrap_or_else(|| default_name.to_string()),
+            description: self.description,
+            tags: self.tags,
+            owner: self.owner,
+            program,
+            arguments,
+            working_directory: self.working_directory,
+            run_at_load: self.run_at_load,
+            keep_alive: self.keep_alive,
+            env_files: self
+                .env_file
+                .iter()
+                .chain(&self.env_files)
+                .map(|f| EnvFile::parse(f))
+                .collect(),
+            env_vars: self.env.into_iter().collect(),
+            after: self.after,
+            schedule,
+            catch_up: self.catch_up,
+            ..Default::default()
+        };
+        // Reuse `ser set` validation for the fields it already checks.
+        if let Some(score) = self.oom_score_adjust {
+            details.set_field("oom_score", &score.to_string())?;
+        }
+        if let Some(policy) = &self.oom_policy {
+            details.set_field("oom_policy", policy)?;
+        }
+        if !self.ambient_capabilities.is_empty() {
+            details.set_field("ambient_capabilities", &self.ambient_capabilities.join(" "))?;
+        }
+        if let Some(set) = &self.capability_bounding_set {
+            details.set_field("capability_bounding_set", set)?;
+        }
+        if let Some(user) = &self.user {
+            details.set_field("user", user)?;
+        }
+        details.private_network = self.private_network;
+        let network = [
+            ("ip_address_allow", &self.ip_address_allow),
+            ("ip_address_deny", &self.ip_address_deny),
+            ("restrict_address_families", &self.restrict_address_families),
+        ];
+        for (key, values) in network {
+            if !values.is_empty() {
+                details.set_field(key, &values.join(" "))?;
+            }
+        }
+        for condition in &self.conditions {
+            details.set_field("condition", condition)?;
+        }
+        let directories = [
+            ("state_directory", &self.state_directory),
+            ("cache_directory", &self.cache_directory),
+            ("logs_directory", &self.logs_directory),
+            ("runtime_directory", &self.runtime_directory),
+        ];
+        for (key, value) in directories {
+            if let Some(value) = value {
+                details.set_field(key, value)?;
+            }
+        }
+        Ok(details)
+    }
+}
+
+/// Find the nearest `.ser.toml` in `dir` or its ancestors.
+pub fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Load the services in a project file. The file is either a single service
+/// or a `[services.<name>]` table per service. Defaults come from the project:
+/// the name is the directory name, the working directory is the project
+/// directory, `.env` there is the env file, and relative paths are resolved
+/// against it.
+pub fn load_project(path: &Path) -> Result<Vec<ServiceDetails>> {
+    let project_dir = path.parent().unwrap_or(Path::new("."));
+    project_specs(path)?
+        .into_iter()
+        .map(|(name, spec)| {
+            let mut details = spec.into_details(&name)?;
+            apply_project_defaults(&mut details, project_dir);
+            Ok(details)
+        })
+        .collect()
+}
+
+/// The hooks defined for each service in a project file, set to run in the
+/// project directory.
+pub fn load_project_hooks(path: &Path) -> Result<BTreeMap<String, Hooks>> {
+    let project_dir = path.parent().unwrap_or(Path::new("."));
+    Ok(project_specs(path)?
+        .into_iter()
+        .map(|(name, spec)| {
+            let mut hooks = spec.hooks;
+            hooks.dir = Some(project_dir.to_path_buf());
+            (name, hooks)
+        })
+        .collect())
+}
+
+/// Each service in a project file with the name it defaults to.
+fn project_specs(path: &Path) -> Result<Vec<(String, ServiceSpec)>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let project_dir = path.parent().unwrap_or(Path::new("."));
+    let project_name = project_dir
+        .canonicalize()
+        .ok()
+        .and_then(|dir| dir.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "app".to_string());
+
+    let mut table: toml::Table =
+        toml::from_str(&contents).with_context(|| format!("Invalid {}", path.display()))?;
+    match table.remove("services") {
+        Some(toml::Value::Table(services)) => {
+            if !table.is_empty() {
+                bail!(
+                    "{}: put every setting under [services.<name>]",
+                    path.display()
+                );
+            }
+            services
+                .into_iter()
+                .map(|(name, value)| Ok((name, value.try_into::<ServiceSpec>()?)))
+                .collect::<Result<Vec<_>>>()
+        }
+        Some(_) => bail!("{}: `services` must be a table", path.display()),
+        None => Ok(vec![(project_name, toml::Value::Table(table).try_into()?)]),
+    }
+    .with_context(|| format!("Invalid {}", path.display()))
+}
+
+fn apply_project_defaults(details: &mut ServiceDetails, project_dir: &Path) {
+    let project_dir = project_dir
+        .canonicalize()
+        .unwrap_or_else(|_| project_dir.to_path_buf());
+    let absolutize = |value: &str| -> String {
+        if Path::new(value).is_absolute() {
+            value.to_string()
+        } else {
+            let value = value.strip_prefix("./").unwrap_or(value);
+            project_dir.join(value).to_string_lossy().to_string()
+        }
+    };
+
+    if details.program.starts_with("./") || details.program.starts_with("../") {
+        details.program = absolutize(&details.program);
+    }
+    details.working_directory = Some(
+        details
+            .working_directory
+            .as_deref()
+            .map(absolutize)
+            .unwrap_or_else(|| project_dir.to_string_lossy().to_string()),
+    );
+    for file in &mut details.env_files {
+        file.path = absolutize(&file.path);
+    }
+    let dotenv = project_dir.join(".env");
+    if details.env_files.is_empty() && dotenv.is_file() {
+        details.env_files.push(EnvFile {
+            path: dotenv.to_string_lossy().to_string(),
+            optional: false,
+        });
+    }
+}
+
+fn parse_schedule(value: &str) -> Result<Schedule> {
+    if let Some(secs) = Schedule::parse_interval_secs(value) {
+        return Ok(Schedule::Interval(secs));
+    }
+    CalendarSchedule::from_systemd_oncalendar(value)
+        .map(Schedule::Calendar)
+        .ok_or_else(|| anyhow!("Unrecognized schedule '{value}'; use e.g. `daily` or `15m`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_spec_to_details() {
+        let spec = ServiceSpec::parse(
+            r#"
+            description = "Web app"
+            command = "/usr/bin/app --port 8080"
+            keep_alive = true
+            schedule = "15m"
+            conditions = ["PathExists=/srv/app"]
+
+            [env]
+            RUST_LOG = "info"
+            "#,
+        )
+        .unwrap();
+        let details = spec.into_details("web").unwrap();
+        assert_eq!(details.name, "web");
+        assert_eq!(details.program, "/usr/bin/app");
+        assert_eq!(details.arguments, vec!["--port", "8080"]);
+        assert_eq!(
+            details.env_vars,
+            vec![("RUST_LOG".to_string(), "info".to_string())]
+        );
+        assert!(matches!(details.schedule, Some(Schedule::Interval(900))));
+        assert_eq!(details.conditions.len(), 1);
+    }
+
+    #[test]
+    fn loads_project_services_with_defaults() {
+        let dir = std::env::temp_dir().join(format!("ser-project-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".env"), "PORT=1\n").unwrap();
+        std::fs::write(
+            dir.join(PROJECT_FILE),
+            "[services.web]\ncommand = \"./bin/web\"\n\n[services.worker]\ncommand = \"/usr/bin/worker\"\nworking_directory = \"jobs\"\n",
+        )
+        .unwrap();
+
+        let found = find_project_file(&dir.join("nested/deeper")).unwrap();
+        let services = load_project(&found).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        assert_eq!(services[0].name, "web");
+        assert_eq!(services[0].program, dir.join("bin/web").to_string_lossy());
+        assert_eq!(
+            services[0].working_directory.as_deref(),
+            Some(&*dir.to_string_lossy())
+        );
+        assert_eq!(
+            services[0].env_files[0].path,
+            dir.join(".env").to_string_lossy()
+        );
+        assert_eq!(
+            services[1].working_directory.as_deref(),
+            Some(&*dir.join("jobs").to_string_lossy())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_missing_command() {
+        assert!(ServiceSpec::parse("comand = \"/bin/true\"").is_err());
+        let spec = ServiceSpec::parse("description = \"x\"").unwrap();
+        assert!(spec.into_details("x").is_err());
+    }
+}
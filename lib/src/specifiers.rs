@@ -0,0 +1,384 @@
+//! systemd specifiers (`%h`, `%i`, `%n`, ...) and `${VAR}` references in
+//! unit files. systemd expands them when it runs a unit; launchd expands
+//! neither, so they're resolved here for display and when converting to a
+//! plist, and anything that can't be resolved is reported rather than copied
+//! through as misleading literal text.
+
+use crate::{DirectoryKind, ServiceDetails};
+use std::path::Path;
+use std::process::Command;
+
+/// What each specifier expands to in a given context. `None` leaves the
+/// specifier unresolved.
+#[derive(Debug, Clone, Default)]
+pub struct Specifiers {
+    /// Full unit name, e.g. `web@blue.service` (`%n`).
+    pub unit: String,
+    pub home: Option<String>,
+    pub user: Option<String>,
+    pub host: Option<String>,
+    /// `%t`, `%S`, `%C`, `%L`, `%E`.
+    pub runtime_dir: Option<String>,
+    pub state_dir: Option<String>,
+    pub cache_dir: Option<String>,
+    pub logs_dir: Option<String>,
+    pub config_dir: Option<String>,
+}
+
+impl Specifiers {
+    /// What systemd would substitute for a unit named `name`, run by the
+    /// system manager or (with `user`) the current user's manager.
+    pub fn systemd(name: &str, user: bool) -> Specifiers {
+        let home = dirs::home_dir().map(|h| h.to_string_lossy().into_owned());
+        let under_home = |rest: &str| home.as_ref().map(|h| format!("{h}/{rest}"));
+        let base = Specifiers {
+            unit: format!("{name}.service"),
+            host: Some(hostname()),
+            ..Default::default()
+        };
+        if user {
+            Specifiers {
+                user: std::env::var("USER").ok(),
+                runtime_dir: std::env::var("XDG_RUNTIME_DIR").ok(),
+                state_dir: under_home(".local/state"),
+                cache_dir: under_home(".cache"),
+                logs_dir: under_home(".local/state/log"),
+                config_dir: under_home(".config"),
+                home,
+                ..base
+            }
+        } else {
+            // Without `User=`, system services run as root.
+            Specifiers {
+                home: Some("/root".to_string()),
+                user: Some("root".to_string()),
+                runtime_dir: Some("/run".to_string()),
+                state_dir: Some("/var/lib".to_string()),
+                cache_dir: Some("/var/cache".to_string()),
+                logs_dir: Some("/var/log".to_string()),
+                config_dir: Some("/etc".to_string()),
+                ..base
+            }
+        }
+    }
+
+    /// The nearest launchd equivalents for a LaunchAgent named `name`.
+    pub fn launchd(name: &str) -> Specifiers {
+        let dir = |kind: DirectoryKind| {
+            kind.launchd_base()
+                .map(|p| p.to_string_lossy().trim_end_matches('/').to_string())
+        };
+        Specifiers {
+            unit: format!("{name}.service"),
+            home: dirs::home_dir().map(|h| h.to_string_lossy().into_owned()),
+            user: std::env::var("USER").ok(),
+            host: Some(hostname()),
+            runtime_dir: dir(DirectoryKind::Runtime),
+            state_dir: dir(DirectoryKind::State),
+            cache_dir: dir(DirectoryKind::Cache),
+            logs_dir: dir(DirectoryKind::Logs),
+            config_dir: dirs::preference_dir().map(|p| p.to_string_lossy().into_owned()),
+        }
+    }
+
+    fn resolve(&self, specifier: char) -> Option<String> {
+        let stem = self.unit.rsplit_once('.').map_or(&*self.unit, |(s, _)| s);
+        let (prefix, instance) = stem.split_once('@').unwrap_or((stem, ""));
+        match specifier {
+            '%' => Some("%".to_string()),
+            'n' => Some(self.unit.clone()),
+            'N' => Some(stem.to_string()),
+            'p' => Some(prefix.to_string()),
+            // Instance names escape `/` as `-`; `%I` undoes that.
+            'i' => Some(instance.to_string()),
+            'I' => Some(instance.replace('-', "/")),
+            'h' => self.home.clone(),
+            'u' => self.user.clone(),
+            'H' => self.host.clone(),
+            't' => self.runtime_dir.clone(),
+            'S' => self.state_dir.clone(),
+            'C' => self.cache_dir.clone(),
+            'L' => self.logs_dir.clone(),
+            'E' => self.config_dir.clone(),
+            'T' => Some("/tmp".to_string()),
+            'V' => Some("/var/tmp".to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// This machine's hostname (`%H`), or `unknown`.
+pub(crate) fn hostname() -> String {
+    std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .or_else(|| {
+            let output = Command::new("hostname").output().ok()?;
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// A value with specifiers and variables expanded as far as possible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expanded {
+    pub value: String,
+    /// Specifiers and variables left as written, e.g. `%m`, `${PORT}`.
+    pub unresolved: Vec<String>,
+}
+
+/// Expand `%x` specifiers only, as systemd does for every setting that
+/// takes them (paths, `Environment=` values, ...).
+pub fn expand_specifiers(value: &str, specifiers: &Specifiers) -> Expanded {
+    expand_with(value, specifiers, None)
+}
+
+/// Expand an `ExecStart=`-style command line: `%x` specifiers, then
+/// variables from `env` the way systemd does when it runs the command.
+/// `${VAR}` is replaced anywhere, `$VAR` only as a word of its own, and
+/// `$$` is a literal `$`.
+pub fn expand(value: &str, specifiers: &Specifiers, env: &[(String, String)]) -> Expanded {
+    expand_with(value, specifiers, Some(env))
+}
+
+fn expand_with(value: &str, specifiers: &Specifiers, env: Option<&[(String, String)]>) -> Expanded {
+    let mut out = String::new();
+    let mut unresolved = Vec::new();
+    let mut chars = value.chars().peekable();
+    let mut word_start = true;
+    while let Some(c) = chars.next() {
+        let at_word_start = std::mem::replace(&mut word_start, c.is_whitespace());
+        match (c, env) {
+            ('%', _) => {
+                let Some(spec) = chars.next() else {
+                    out.push('%');
+                    break;
+                };
+                match specifiers.resolve(spec) {
+                    Some(expansion) => out.push_str(&expansion),
+                    None => {
+                        out.push('%');
+                        out.push(spec);
+                        unresolved.push(format!("%{spec}"));
+                    }
+                }
+            }
+            ('$', Some(env)) => {
+                if chars.peek() == Some(&'$') {
+                    chars.next();
+                    out.push('$');
+                    continue;
+                }
+                let braced = chars.peek() == Some(&'{');
+                if braced {
+                    chars.next();
+                }
+                let mut name = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next.is_ascii_alphanumeric() || next == '_' {
+                        name.push(next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if braced && chars.peek() == Some(&'}') {
+                    chars.next();
+                }
+                let written = if braced {
+                    format!("${{{name}}}")
+                } else {
+                    format!("${name}")
+                };
+                let whole_word = at_word_start && chars.peek().is_none_or(|c| c.is_whitespace());
+                if name.is_empty() || !(braced || whole_word) {
+                    out.push_str(&written);
+                    continue;
+                }
+                match env.iter().rev().find(|(k, _)| *k == name) {
+                    Some((_, v)) => out.push_str(v),
+                    None => {
+                        out.push_str(&written);
+                        unresolved.push(written);
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    Expanded {
+        value: out,
+        unresolved,
+    }
+}
+
+/// Expand everything launchd would otherwise take literally: specifiers in
+/// the program, arguments, working directory, env file paths, and
+/// environment values, and variables in the program and arguments. Variables
+/// come from the service's `Environment=` entries and env files, as systemd
+/// runs it. Returns a warning per reference that can't be resolved.
+pub fn translate_for_launchd(details: &ServiceDetails) -> (ServiceDetails, Vec<String>) {
+    let specifiers = Specifiers::launchd(&details.name);
+    let mut translated = details.clone();
+    let mut warnings = Vec::new();
+    let mut warn = |field: &str, expanded: &Expanded| {
+        for item in &expanded.unresolved {
+            let warning = format!("{field}: {item} has no launchd equivalent; left as written");
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    };
+    let mut expand_field = |field: &str, value: &mut String| {
+        let expanded = expand_specifiers(value, &specifiers);
+        warn(field, &expanded);
+        *value = expanded.value;
+    };
+    if let Some(dir) = &mut translated.working_directory {
+        expand_field("working directory", dir);
+    }
+    for file in &mut translated.env_files {
+        expand_field("env file", &mut file.path);
+    }
+    for (key, value) in &mut translated.env_vars {
+        expand_field(&format!("env {key}"), value);
+    }
+
+    // An env file that isn't here leaves its variables unresolved, which
+    // the warnings report.
+    let env = translated
+        .effective_env()
+        .unwrap_or_else(|_| translated.env_vars.clone());
+    let program = expand(&translated.program, &specifiers, &env);
+    warn("program", &program);
+    translated.program = program.value;
+    let mut arguments = Vec::new();
+    for arg in &translated.arguments {
+        let expanded = expand(arg, &specifiers, &env);
+        warn("arguments", &expanded);
+        // A lone `$VAR` becomes one argument per word of its value.
+        if arg.starts_with('$') && !arg.starts_with("${") && !arg.starts_with("$$") {
+            arguments.extend(expanded.value.split_whitespace().map(str::to_string));
+        } else {
+            arguments.push(expanded.value);
+        }
+    }
+    translated.arguments = arguments;
+    if !details.ambient_capabilities.is_empty() || details.capability_bounding_set.is_some() {
+        warnings.push(
+            "capabilities: launchd has no equivalent of AmbientCapabilities= or \
+             CapabilityBoundingSet=; dropped (since macOS 10.14 any user can bind ports below 1024)"
+                .to_string(),
+        );
+        translated.ambient_capabilities.clear();
+        translated.capability_bounding_set = None;
+    }
+    // A plist can only say "keep running while this path (doesn't) exist",
+    // which is ConditionPathExists= only for a job that's kept alive.
+    let (kept, dropped): (Vec<_>, Vec<_>) = translated
+        .conditions
+        .drain(..)
+        .partition(|c| c.kind == "PathExists" && details.keep_alive && details.schedule.is_none());
+    translated.conditions = kept;
+    if !dropped.is_empty() {
+        let lines: Vec<String> = dropped.iter().map(|c| c.to_systemd_line()).collect();
+        warnings.push(format!(
+            "conditions: launchd has no equivalent of {}; dropped",
+            lines.join(", ")
+        ));
+    }
+    if details.restricts_network() {
+        warnings.push(
+            "network sandbox: launchd has no equivalent of PrivateNetwork=, IPAddressAllow=/Deny=, \
+             or RestrictAddressFamilies=; dropped"
+                .to_string(),
+        );
+        translated.private_network = false;
+        translated.ip_address_allow.clear();
+        translated.ip_address_deny.clear();
+        translated.restrict_address_families.clear();
+    }
+    (translated, warnings)
+}
+
+/// Whether a unit file lives under the current user's home, i.e. is run by
+/// the user's service manager.
+pub fn is_user_unit(path: &Path) -> bool {
+    dirs::home_dir().is_some_and(|home| path.starts_with(home))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn specifiers() -> Specifiers {
+        Specifiers {
+            unit: "web@blue-green.service".to_string(),
+            home: Some("/home/kurt".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn expands_specifiers() {
+        let expanded = expand("%h/%p/%i/%I %n 100%% %m", &specifiers(), &[]);
+        assert_eq!(
+            expanded.value,
+            "/home/kurt/web/blue-green/blue/green web@blue-green.service 100% %m"
+        );
+        assert_eq!(expanded.unresolved, ["%m"]);
+    }
+
+    #[test]
+    fn expands_variables_from_the_unit_environment() {
+        let env = vec![("PORT".to_string(), "8080".to_string())];
+        let expanded = expand("--port=${PORT} --host $HOST_NAME", &specifiers(), &env);
+        assert_eq!(expanded.value, "--port=8080 --host $HOST_NAME");
+        assert_eq!(expanded.unresolved, ["$HOST_NAME"]);
+
+        let expanded = expand("echo $$PORT $PORT --p=$PORT", &specifiers(), &env);
+        assert_eq!(expanded.value, "echo $PORT 8080 --p=$PORT");
+        assert!(expanded.unresolved.is_empty());
+        assert_eq!(
+            expand_specifiers("%h/${PORT}", &specifiers()).value,
+            "/home/kurt/${PORT}"
+        );
+    }
+
+    #[test]
+    fn translates_variables_from_env_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let env_file = dir.path().join("web.env");
+        std::fs::write(&env_file, "FLAGS=--a --b\nPORT=9090\n").unwrap();
+        let details = ServiceDetails {
+            name: "web".to_string(),
+            program: "/usr/bin/web".to_string(),
+            arguments: vec!["$FLAGS".to_string(), "--port=${PORT}".to_string()],
+            env_vars: vec![("PORT".to_string(), "8080".to_string())],
+            env_files: vec![crate::EnvFile {
+                path: env_file.to_string_lossy().into_owned(),
+                optional: false,
+            }],
+            ..Default::default()
+        };
+        let (translated, warnings) = translate_for_launchd(&details);
+        assert_eq!(translated.arguments, ["--a", "--b", "--port=9090"]);
+        assert!(warnings.is_empty(), "{warnings:?}");
+
+        let gated = ServiceDetails {
+            conditions: vec![crate::Condition {
+                kind: "PathExists".to_string(),
+                value: "/srv/data".to_string(),
+                assert: false,
+            }],
+            ..details
+        };
+        let (translated, warnings) = translate_for_launchd(&gated);
+        assert!(translated.conditions.is_empty());
+        assert_eq!(
+            warnings,
+            ["conditions: launchd has no equivalent of ConditionPathExists=/srv/data; dropped"]
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use crate::guard::{checksum, FileLock};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// What `ser` remembers about services between runs, kept in
+/// `state.toml` under [`crate::config::state_dir`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Registry {
+    pub services: BTreeMap<String, ServiceState>,
+}
+
+/// Service names for shell completion (`ser __complete-names`) from the
+/// last full scan, so completing a name doesn't rescan every unit directory
+/// (slow with thousands of units). Kept in `names.toml`, apart from the
+/// registry, so other commands don't load it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NameCache {
+    /// When the scan ran, in Unix seconds.
+    pub scanned_at: u64,
+    pub names: Vec<String>,
+}
+
+impl NameCache {
+    /// How long a scan is trusted before the next completion rescans.
+    const MAX_AGE_SECS: u64 = 300;
+
+    pub fn path() -> Option<PathBuf> {
+        crate::config::state_dir().map(|dir| dir.join("names.toml"))
+    }
+
+    /// Read the cache; a missing or unreadable one is empty, which just
+    /// means the next completion scans.
+    pub fn load() -> NameCache {
+        NameCache::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Take the lock a refresh holds while it scans, if no other refresh is
+    /// running, so tab presses while the cache is stale start one scan.
+    pub fn try_lock_refresh() -> Result<Option<FileLock>> {
+        FileLock::try_acquire(&NameCache::refresh_target())
+    }
+
+    /// Wait for a refresh another process is running to finish.
+    pub fn wait_for_refresh() -> Result<()> {
+        FileLock::acquire(&NameCache::refresh_target()).map(drop)
+    }
+
+    /// The lock target for refreshing this user's cache; lock files are
+    /// shared between users, so it's named after the cache file.
+    fn refresh_target() -> PathBuf {
+        NameCache::path()
+            .map(|path| path.with_extension("refresh"))
+            .unwrap_or_else(|| PathBuf::from("names-refresh"))
+    }
+
+    /// Whether there was no scan yet, or it's old enough to redo.
+    pub fn is_stale(&self) -> bool {
+        now_secs().saturating_sub(self.scanned_at) > NameCache::MAX_AGE_SECS
+    }
+
+    /// Names to offer for completion: the last scan's, plus every service
+    /// `ser` remembers something about, sorted and without duplicates.
+    pub fn completion_names(&self, registry: &Registry) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .names
+            .iter()
+            .chain(registry.services.keys())
+            .map(|name| name.trim_end_matches(".service").to_string())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServiceState {
+    /// Checksum of the definition the last time `ser` started or restarted it.
+    pub loaded_checksum: Option<String>,
+    /// Shown first by `ser list`, with a warning when it isn't running.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Firewall rules `ser start --open-firewall` added, closed on removal.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub firewall: Vec<crate::firewall::Rule>,
+}
+
+impl Registry {
+    pub fn path() -> Option<PathBuf> {
+        crate::config::state_dir().map(|dir| dir.join("state.toml"))
+    }
+
+    /// Read the registry; a missing file is an empty registry.
+    pub fn load() -> Result<Registry> {
+        let Some(path) = Registry::path() else {
+            return Ok(Registry::default());
+        };
+        if !path.exists() {
+            return Ok(Registry::default());
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Registry::path().ok_or_else(|| anyhow!("No state directory available"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        write_atomic(&path, &toml::to_string(self)?)
+    }
+
+    /// Load, change, and save the registry while holding its lock, so
+    /// concurrent `ser` invocations don't drop each other's updates.
+    pub fn update(f: impl FnOnce(&mut Registry)) -> Result<()> {
+        let path = Registry::path().ok_or_else(|| anyhow!("No state directory available"))?;
+        let _lock = FileLock::acquire(&path)?;
+        let mut registry = Registry::load()?;
+        f(&mut registry);
+        registry.save()
+    }
+
+    pub fn service(&self, name: &str) -> Option<&ServiceState> {
+        self.services.get(name)
+    }
+
+    pub fn is_pinned(&self, name: &str) -> bool {
+        self.service(name).is_some_and(|s| s.pinned)
+    }
+
+    /// Pinned service names, sorted.
+    pub fn pinned(&self) -> Vec<&str> {
+        self.services
+            .iter()
+            .filter(|(_, s)| s.pinned)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+/// Replace `path` with `contents` through a temporary file in the same
+/// directory, so a concurrent reader sees the old file or the new one, never
+/// a truncated one. An existing file's permissions are kept.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("Failed to create a temporary file in {}", dir.display()))?;
+    tmp.write_all(contents.as_ref())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    if let Ok(meta) = fs::metadata(path) {
+        tmp.as_file()
+            .set_permissions(meta.permissions())
+            .with_context(|| format!("Failed to set permissions on {}", tmp.path().display()))?;
+    }
+    tmp.persist(path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Checksum of a service definition: the unit file/plist, plus the matching
+/// `.timer` next to a systemd unit, since either changing needs a restart.
+pub fn definition_checksum(path: &Path) -> Result<String> {
+    let mut contents =
+        fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if let Ok(timer) = fs::read(path.with_extension("timer")) {
+        contents.extend(timer);
+    }
+    Ok(format!("{:016x}", checksum(&contents)))
+}
+
+/// Remember the definition `name` was just (re)started with.
+pub fn record_loaded(name: &str, path: &Path) -> Result<()> {
+    let sum = definition_checksum(path)?;
+    Registry::update(|registry| {
+        registry
+            .services
+            .entry(name.to_string())
+            .or_default()
+            .loaded_checksum = Some(sum);
+    })
+}
+
+/// Pin or unpin `name` for `ser list`.
+pub fn set_pinned(name: &str, pinned: bool) -> Result<()> {
+    Registry::update(|registry| {
+        registry
+            .services
+            .entry(name.to_string())
+            .or_default()
+            .pinned = pinned;
+    })
+}
+
+/// Drop everything remembered about `name`, once it's been deleted.
+pub fn forget(name: &str) -> Result<()> {
+    Registry::update(|registry| {
+        registry.services.remove(name);
+    })?;
+    let mut cache = NameCache::load();
+    let before = cache.names.len();
+    cache.names.retain(|n| n != name);
+    if cache.names.len() != before {
+        cache_names(cache.names)?;
+    }
+    Ok(())
+}
+
+/// Replace the completion names with those of a scan that just ran.
+pub fn cache_names(names: Vec<String>) -> Result<()> {
+    let path = NameCache::path().ok_or_else(|| anyhow!("No state directory available"))?;
+    let cache = NameCache {
+        scanned_at: now_secs(),
+        names,
+    };
+    write_atomic(&path, &toml::to_string(&cache)?)
+}
+
+/// Whether the definition differs from the one `name` was last (re)started
+/// with. Services `ser` never started count as changed.
+pub fn definition_changed(name: &str, path: &Path) -> Result<bool> {
+    let current = definition_checksum(path)?;
+    let registry = Registry::load()?;
+    Ok(registry
+        .service(name)
+        .and_then(|s| s.loaded_checksum.as_deref())
+        != Some(current.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_roundtrips_through_toml() {
+        let mut registry = Registry::default();
+        registry.services.insert(
+            "web.service".to_string(),
+            ServiceState {
+                loaded_checksum: Some("00ff".to_string()),
+                pinned: true,
+                firewall: vec![crate::firewall::Rule {
+                    backend: crate::firewall::Backend::Ufw,
+                    target: "8080/tcp".to_string(),
+                }],
+            },
+        );
+        registry
+            .services
+            .insert("db.service".to_string(), ServiceState::default());
+        let toml = toml::to_string(&registry).unwrap();
+        assert_eq!(toml.matches("pinned").count(), 1);
+        let cache = NameCache {
+            scanned_at: 1,
+            names: vec!["web".to_string(), "cron.service".to_string()],
+        };
+        assert_eq!(cache.completion_names(&registry), ["cron", "db", "web"]);
+        let parsed: Registry = toml::from_str(&toml).unwrap();
+        assert_eq!(
+            parsed
+                .service("web.service")
+                .unwrap()
+                .loaded_checksum
+                .as_deref(),
+            Some("00ff")
+        );
+        assert_eq!(parsed.pinned(), ["web.service"]);
+        assert_eq!(parsed.service("web.service").unwrap().firewall.len(), 1);
+    }
+}
@@ -1,9 +1,17 @@
-use crate::{Schedule, ServiceDetails};
+use crate::{
+    CalendarSchedule, Condition, DirectoryKind, EnvFile, ManagedDirectory, OomPolicy, RawField,
+    Schedule, ServiceDetails, TriggerKind, TriggerUnit,
+};
 use anyhow::{bail, Result};
+use std::path::PathBuf;
 
 /// Comment added to generated service files to indicate they are managed by ser
 pub const MANAGED_BY_COMMENT: &str = "# Managed by ser";
 
+/// Custom `[Unit]` keys for ser metadata. systemd ignores keys prefixed `X-`.
+const TAGS_KEY: &str = "X-Ser-Tags=";
+const OWNER_KEY: &str = "X-Ser-Owner=";
+
 /// Generate a systemd timer file for scheduled execution.
 pub fn generate_timer_file(service: &ServiceDetails) -> Result<String> {
     let schedule = service
@@ -20,8 +28,9 @@ pub fn generate_timer_file(service: &ServiceDetails) -> Result<String> {
     match schedule {
         Schedule::Calendar(c) => {
             content.push_str(&format!("OnCalendar={}\n", c.to_systemd_oncalendar()));
-            // Catch up on runs missed while the machine was off.
-            content.push_str("Persistent=true\n");
+            // Catch up on runs missed while the machine was off, unless told not to.
+            let catch_up = service.catch_up.unwrap_or(true);
+            content.push_str(&format!("Persistent={catch_up}\n"));
         }
         Schedule::Interval(secs) => {
             let span = Schedule::interval_to_systemd(*secs);
@@ -30,35 +39,67 @@ pub fn generate_timer_file(service: &ServiceDetails) -> Result<String> {
             content.push_str(&format!("OnUnitActiveSec={span}\n"));
         }
     }
+    if let Some(jitter) = service.jitter {
+        content.push_str(&format!(
+            "RandomizedDelaySec={}\n",
+            Schedule::interval_to_systemd(jitter)
+        ));
+    }
+    if let Some(accuracy) = service.accuracy {
+        content.push_str(&format!(
+            "AccuracySec={}\n",
+            Schedule::interval_to_systemd(accuracy)
+        ));
+    }
     content.push_str("\n[Install]\n");
     content.push_str("WantedBy=timers.target\n");
 
     Ok(content)
 }
 
+/// Parse a `.service` unit. The unit file doesn't record its own name, so the
+/// returned `name` is empty; callers fill it in from the file name.
 pub fn parse_systemd(contents: &str) -> Result<ServiceDetails> {
     // Basic parsing of systemd unit file
-    let mut name = None;
+    let mut description = None;
+    let mut tags = Vec::new();
+    let mut owner = None;
     let mut program = None;
     let mut arguments = Vec::new();
     let mut working_directory = None;
+    let mut user = None;
     let mut run_at_load = false;
     let mut keep_alive = false;
-    let mut env_file = None;
+    let mut env_files = Vec::new();
     let mut env_vars = Vec::new();
     let mut after = Vec::new();
+    let mut oom_score_adjust = None;
+    let mut oom_policy = None;
+    let mut conditions = Vec::new();
+    let mut directories = Vec::new();
+    let mut ambient_capabilities = Vec::new();
+    let mut capability_bounding_set: Option<Vec<String>> = None;
+    let mut private_network = false;
+    let mut ip_address_allow = Vec::new();
+    let mut ip_address_deny = Vec::new();
+    let mut restrict_address_families = Vec::new();
 
     for line in contents.lines() {
         let line = line.trim();
-        if line.starts_with("Description=") {
-            name = line.strip_prefix("Description=").map(|s| s.to_string());
+        if let Some(value) = line.strip_prefix("Description=") {
+            // An empty assignment unsets it, as in systemd.
+            description = (!value.is_empty()).then(|| value.to_string());
+        } else if let Some(value) = line.strip_prefix(TAGS_KEY) {
+            tags = crate::parse_tags(value);
+        } else if let Some(value) = line.strip_prefix(OWNER_KEY) {
+            owner = Some(value.to_string());
         }
         if line.starts_with("ExecStart=") {
             let exec_start = line.strip_prefix("ExecStart=").unwrap_or("");
-            let mut parts = exec_start.split_whitespace();
+            let mut parts = split_words(exec_start).into_iter();
             if let Some(prog) = parts.next() {
-                program = Some(prog.to_string());
-                arguments = parts.map(|s| s.to_string()).collect();
+                program = Some(prog);
+                arguments = parts.collect();
             } else {
                 bail!("ExecStart line is empty in service file");
             }
@@ -66,47 +107,195 @@ pub fn parse_systemd(contents: &str) -> Result<ServiceDetails> {
             working_directory = line
                 .strip_prefix("WorkingDirectory=")
                 .map(|s| s.to_string());
+        } else if let Some(name) = line.strip_prefix("User=") {
+            user = (!name.trim().is_empty()).then(|| name.trim().to_string());
         } else if line == "WantedBy=multi-user.target" || line == "WantedBy=default.target" {
             run_at_load = true;
         } else if line.starts_with("Restart=") {
             keep_alive = line != "Restart=no";
         } else if line.starts_with("EnvironmentFile=") {
-            env_file = line.strip_prefix("EnvironmentFile=").map(|s| s.to_string());
+            env_files.extend(line.strip_prefix("EnvironmentFile=").map(EnvFile::parse));
         } else if line.starts_with("Environment=") {
-            // Ignored for now
             let env_line = line.strip_prefix("Environment=").unwrap();
-            let Some((a, b)) = env_line.split_once('=') else {
-                bail!("Environment line is empty in service file");
-            };
-            env_vars.push((a.to_string(), b.to_string()));
+            for assignment in split_words(env_line) {
+                let Some((a, b)) = assignment.split_once('=') else {
+                    bail!("Invalid Environment assignment in service file: {assignment}");
+                };
+                env_vars.push((a.to_string(), b.to_string()));
+            }
         } else if line.starts_with("After=") {
             let after_line = line.strip_prefix("After=").unwrap_or("");
-            after = after_line
-                .split_whitespace()
-                .map(|s| s.to_string())
-                .collect();
+            after.extend(after_line.split_whitespace().map(|s| s.to_string()));
+        } else if let Some(score) = line.strip_prefix("OOMScoreAdjust=") {
+            oom_score_adjust = score.trim().parse().ok();
+        } else if let Some(policy) = line.strip_prefix("OOMPolicy=") {
+            oom_policy = OomPolicy::parse(policy);
+        } else if let Some(caps) = line.strip_prefix("AmbientCapabilities=") {
+            if caps.trim().is_empty() {
+                ambient_capabilities.clear();
+            }
+            ambient_capabilities.extend(caps.split_whitespace().map(String::from));
+        } else if let Some(caps) = line.strip_prefix("CapabilityBoundingSet=") {
+            // An empty assignment resets the set to no capabilities at all
+            let set = capability_bounding_set.get_or_insert_with(Vec::new);
+            if caps.trim().is_empty() {
+                set.clear();
+            }
+            set.extend(caps.split_whitespace().map(String::from));
+        } else if let Some(value) = line.strip_prefix("PrivateNetwork=") {
+            private_network = matches!(value.trim(), "true" | "yes" | "1" | "on");
+        } else if let Some(value) = line.strip_prefix("RestrictAddressFamilies=") {
+            merge_address_families(&mut restrict_address_families, value);
+        } else if let Some((key, value)) = line
+            .split_once('=')
+            .filter(|(key, _)| matches!(*key, "IPAddressAllow" | "IPAddressDeny"))
+        {
+            let list = match key {
+                "IPAddressAllow" => &mut ip_address_allow,
+                _ => &mut ip_address_deny,
+            };
+            // Like the other list settings, an empty assignment resets it.
+            if value.trim().is_empty() {
+                list.clear();
+            }
+            list.extend(value.split_whitespace().map(String::from));
+        } else if line.starts_with("Condition") || line.starts_with("Assert") {
+            conditions.extend(Condition::from_systemd_line(line));
+        } else if let Some((key, value)) = line.split_once('=') {
+            if let Some(kind) = DirectoryKind::ALL.iter().find(|k| k.directive() == key) {
+                directories.extend(value.split_whitespace().map(|name| ManagedDirectory {
+                    kind: *kind,
+                    name: name.to_string(),
+                }));
+            }
         }
     }
     Ok(ServiceDetails {
-        name: name.expect("No name for service"),
-        program: program.expect("No program for service"),
+        name: String::new(),
+        description,
+        tags,
+        owner,
+        program: program.ok_or_else(|| anyhow::anyhow!("No ExecStart= in service file"))?,
         arguments,
         working_directory,
+        user,
         run_at_load,
         keep_alive,
-        env_file,
+        env_files,
         env_vars,
         after,
-        schedule: None, // Schedule is parsed from .timer file separately
+        // The schedule and its tuning are parsed from the .timer file separately
+        schedule: None,
+        jitter: None,
+        accuracy: None,
+        catch_up: None,
+        oom_score_adjust,
+        oom_policy,
+        conditions,
+        directories,
+        ambient_capabilities,
+        capability_bounding_set,
+        private_network,
+        ip_address_allow,
+        ip_address_deny,
+        restrict_address_families,
     })
 }
 
+/// Read a `.timer` unit's schedule and its tuning (`RandomizedDelaySec=`,
+/// `AccuracySec=`, `Persistent=`) into `service`. Handles both `OnCalendar=`
+/// (calendar) and `OnUnitActiveSec=` (interval) timers, and leaves `schedule`
+/// as `None` when the expression can't be represented as a [`Schedule`].
+/// Apply one `RestrictAddressFamilies=` line the way systemd does. The first
+/// decides whether the list allows families or, starting with `~`, denies
+/// them; later lines of the same kind add to it, and lines of the other kind
+/// take families back out. An empty value or `none` starts over. A deny-list
+/// keeps its `~` on the first family, as it's written in the unit.
+fn merge_address_families(families: &mut Vec<String>, value: &str) {
+    let value = value.trim();
+    if value.is_empty() || value == "none" {
+        families.clear();
+        if value == "none" {
+            families.push("none".to_string());
+        }
+        return;
+    }
+    let (deny, value) = match value.strip_prefix('~') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let named: Vec<&str> = value.split_whitespace().collect();
+    // `none` is an allow-list of nothing.
+    let denies = match families.first() {
+        Some(first) => first.starts_with('~'),
+        None => deny,
+    };
+    let mut current: Vec<String> = families
+        .iter()
+        .map(|f| f.trim_start_matches('~').to_string())
+        .filter(|f| f != "none")
+        .collect();
+    if deny == denies {
+        for family in named {
+            if !current.iter().any(|f| f == family) {
+                current.push(family.to_string());
+            }
+        }
+    } else {
+        current.retain(|f| !named.contains(&f.as_str()));
+    }
+    *families = match current.first_mut() {
+        Some(first) => {
+            if denies {
+                first.insert(0, '~');
+            }
+            current
+        }
+        // Everything denied, or nothing allowed any more.
+        None if !denies => vec!["none".to_string()],
+        None => Vec::new(),
+    };
+}
+
+pub fn parse_timer_into(service: &mut ServiceDetails, contents: &str) {
+    for line in contents.lines() {
+        let line = line.trim();
+        if let Some(expr) = line.strip_prefix("OnCalendar=") {
+            // systemd doesn't catch up unless `Persistent=` says so.
+            service.catch_up = service.catch_up.or(Some(false));
+            service.schedule = service.schedule.take().or_else(|| {
+                CalendarSchedule::from_systemd_oncalendar(expr).map(Schedule::Calendar)
+            });
+        } else if let Some(span) = line.strip_prefix("OnUnitActiveSec=") {
+            service.schedule = service
+                .schedule
+                .take()
+                .or_else(|| Schedule::parse_interval_secs(span).map(Schedule::Interval));
+        } else if let Some(span) = line.strip_prefix("RandomizedDelaySec=") {
+            service.jitter = Schedule::parse_interval_secs(span);
+        } else if let Some(value) = line.strip_prefix("Persistent=") {
+            service.catch_up = Some(matches!(value.trim(), "true" | "yes" | "1" | "on"));
+        } else if let Some(span) = line.strip_prefix("AccuracySec=") {
+            service.accuracy = Schedule::parse_interval_secs(span);
+        }
+    }
+}
+
 pub fn generate_file(service: &ServiceDetails) -> Result<String> {
     let mut unit_content = String::new();
     unit_content.push_str(MANAGED_BY_COMMENT);
     unit_content.push('\n');
     unit_content.push_str("[Unit]\n");
-    unit_content.push_str(&format!("Description={}\n", service.name));
+    unit_content.push_str(&format!(
+        "Description={}\n",
+        service.description.as_deref().unwrap_or(&service.name)
+    ));
+    if !service.tags.is_empty() {
+        unit_content.push_str(&format!("{}{}\n", TAGS_KEY, service.tags.join(",")));
+    }
+    if let Some(owner) = &service.owner {
+        unit_content.push_str(&format!("{}{}\n", OWNER_KEY, owner));
+    }
     if !service.after.is_empty() {
         unit_content.push_str("After=");
         for after in &service.after {
@@ -116,6 +305,10 @@ pub fn generate_file(service: &ServiceDetails) -> Result<String> {
         unit_content.pop(); // Remove trailing space
         unit_content.push('\n');
     }
+    for condition in &service.conditions {
+        unit_content.push_str(&condition.to_systemd_line());
+        unit_content.push('\n');
+    }
     unit_content.push_str("\n[Service]\n");
 
     // For scheduled services, use Type=oneshot
@@ -124,26 +317,71 @@ pub fn generate_file(service: &ServiceDetails) -> Result<String> {
     }
 
     unit_content.push_str("ExecStart=");
-    unit_content.push_str(&service.program);
+    unit_content.push_str(&quote_word(&service.program));
     for arg in &service.arguments {
         unit_content.push(' ');
-        unit_content.push_str(arg);
+        unit_content.push_str(&quote_word(arg));
     }
     unit_content.push('\n');
 
     if let Some(ref wd) = service.working_directory {
         unit_content.push_str(&format!("WorkingDirectory={}\n", wd));
     }
+    if let Some(ref user) = service.user {
+        unit_content.push_str(&format!("User={}\n", user));
+    }
 
     // Only add Restart for non-scheduled services
     if service.schedule.is_none() && service.keep_alive {
         unit_content.push_str("Restart=always\n");
     }
-    if let Some(file) = &service.env_file {
-        unit_content.push_str(&format!("EnvironmentFile={}\n", file));
+    for file in &service.env_files {
+        unit_content.push_str(&format!("EnvironmentFile={}\n", file.to_systemd_value()));
     }
     for (key, value) in &service.env_vars {
-        unit_content.push_str(&format!("Environment=\"{}={}\"\n", key, value));
+        let assignment = quote_env_assignment(&format!("{key}={value}"));
+        unit_content.push_str(&format!("Environment={assignment}\n"));
+    }
+    if let Some(score) = service.oom_score_adjust {
+        unit_content.push_str(&format!("OOMScoreAdjust={}\n", score));
+    }
+    if let Some(policy) = service.oom_policy {
+        unit_content.push_str(&format!("OOMPolicy={}\n", policy.as_str()));
+    }
+    if !service.ambient_capabilities.is_empty() {
+        unit_content.push_str(&format!(
+            "AmbientCapabilities={}\n",
+            service.ambient_capabilities.join(" ")
+        ));
+    }
+    if let Some(set) = &service.capability_bounding_set {
+        unit_content.push_str(&format!("CapabilityBoundingSet={}\n", set.join(" ")));
+    }
+    if service.private_network {
+        unit_content.push_str("PrivateNetwork=yes\n");
+    }
+    for (key, list) in [
+        ("IPAddressAllow", &service.ip_address_allow),
+        ("IPAddressDeny", &service.ip_address_deny),
+        (
+            "RestrictAddressFamilies",
+            &service.restrict_address_families,
+        ),
+    ] {
+        if !list.is_empty() {
+            unit_content.push_str(&format!("{key}={}\n", list.join(" ")));
+        }
+    }
+    for kind in DirectoryKind::ALL {
+        let names: Vec<&str> = service
+            .directories
+            .iter()
+            .filter(|d| d.kind == kind)
+            .map(|d| d.name.as_str())
+            .collect();
+        if !names.is_empty() {
+            unit_content.push_str(&format!("{}={}\n", kind.directive(), names.join(" ")));
+        }
     }
 
     // Only add [Install] section for non-scheduled services
@@ -154,3 +392,843 @@ pub fn generate_file(service: &ServiceDetails) -> Result<String> {
 
     Ok(unit_content)
 }
+
+/// Section order used by [`format_unit`]; other sections follow in file order.
+const SECTION_ORDER: &[&str] = &[
+    "Unit", "Service", "Socket", "Path", "Timer", "Mount", "Install",
+];
+
+/// Canonical spelling of common keys, so `execstart=` is written `ExecStart=`.
+const KNOWN_KEYS: &[&str] = &[
+    "Description",
+    "Documentation",
+    "After",
+    "Before",
+    "Requires",
+    "Wants",
+    "BindsTo",
+    "PartOf",
+    "Conflicts",
+    "Type",
+    "ExecStart",
+    "ExecStartPre",
+    "ExecStartPost",
+    "ExecReload",
+    "ExecStop",
+    "ExecStopPost",
+    "Restart",
+    "RestartSec",
+    "User",
+    "Group",
+    "WorkingDirectory",
+    "Environment",
+    "EnvironmentFile",
+    "StandardOutput",
+    "StandardError",
+    "TimeoutStartSec",
+    "TimeoutStopSec",
+    "KillMode",
+    "OOMScoreAdjust",
+    "OOMPolicy",
+    "AmbientCapabilities",
+    "CapabilityBoundingSet",
+    "PrivateNetwork",
+    "IPAddressAllow",
+    "IPAddressDeny",
+    "RestrictAddressFamilies",
+    "StateDirectory",
+    "CacheDirectory",
+    "LogsDirectory",
+    "RuntimeDirectory",
+    "OnCalendar",
+    "OnBootSec",
+    "OnUnitActiveSec",
+    "Persistent",
+    "RandomizedDelaySec",
+    "Unit",
+    "WantedBy",
+    "RequiredBy",
+    "Alias",
+    "Also",
+];
+
+struct Entry {
+    comments: Vec<String>,
+    key: String,
+    value: String,
+}
+
+struct Section {
+    name: String,
+    comments: Vec<String>,
+    entries: Vec<Entry>,
+}
+
+/// Rewrite a unit file canonically: known sections in a fixed order, known
+/// keys in their usual casing, no padding around `=`, continuation lines
+/// joined, and `Environment=` split into one quoted assignment per line,
+/// sorted by variable name. Comments stay attached to the line below them.
+pub fn format_unit(contents: &str) -> String {
+    let mut preamble = Vec::new();
+    let mut sections: Vec<Section> = Vec::new();
+    let mut current = None;
+    let mut pending = Vec::new();
+
+    let mut lines = contents.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('#') || line.starts_with(';') {
+            pending.push(line.to_string());
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if sections.is_empty() {
+                preamble.append(&mut pending);
+            }
+            // Repeated headers continue the same section, as systemd reads them.
+            current = Some(match sections.iter().position(|s| s.name == name) {
+                Some(i) => i,
+                None => {
+                    sections.push(Section {
+                        name: name.to_string(),
+                        comments: std::mem::take(&mut pending),
+                        entries: Vec::new(),
+                    });
+                    sections.len() - 1
+                }
+            });
+            continue;
+        }
+
+        let mut logical = line.to_string();
+        while logical.ends_with('\\') {
+            logical.pop();
+            let Some(next) = lines.next() else { break };
+            logical = format!("{} {}", logical.trim_end(), next.trim());
+        }
+        let Some(section) = current else {
+            // Not valid outside a section; keep it rather than drop it.
+            preamble.append(&mut pending);
+            preamble.push(logical);
+            continue;
+        };
+        let (key, value) = logical.split_once('=').unwrap_or((&logical, ""));
+        let key = canonical_key(key.trim());
+        let value = value.trim();
+        let comments = std::mem::take(&mut pending);
+        let entries = &mut sections[section].entries;
+        if key == "Environment" && !value.is_empty() {
+            for (i, assignment) in split_words(value).into_iter().enumerate() {
+                entries.push(Entry {
+                    comments: if i == 0 { comments.clone() } else { Vec::new() },
+                    key: key.clone(),
+                    value: quote_env_assignment(&assignment),
+                });
+            }
+        } else {
+            entries.push(Entry {
+                comments,
+                key,
+                value: value.to_string(),
+            });
+        }
+    }
+
+    sections.sort_by_key(|s| {
+        SECTION_ORDER
+            .iter()
+            .position(|name| *name == s.name)
+            .unwrap_or(SECTION_ORDER.len())
+    });
+
+    let mut out = String::new();
+    for line in &preamble {
+        out.push_str(line);
+        out.push('\n');
+    }
+    for (i, section) in sections.iter_mut().enumerate() {
+        sort_environment(&mut section.entries);
+        if i > 0 {
+            out.push('\n');
+        }
+        for comment in &section.comments {
+            out.push_str(comment);
+            out.push('\n');
+        }
+        out.push_str(&format!("[{}]\n", section.name));
+        for entry in &section.entries {
+            for comment in &entry.comments {
+                out.push_str(comment);
+                out.push('\n');
+            }
+            out.push_str(&format!("{}={}\n", entry.key, entry.value));
+        }
+    }
+    // Trailing comments with nothing after them.
+    for comment in &pending {
+        out.push_str(comment);
+        out.push('\n');
+    }
+    out
+}
+
+/// Every key/value in a unit file, in file order, with continuation lines
+/// joined but otherwise as written. Comments are left out.
+pub fn raw_fields(contents: &str) -> Vec<RawField> {
+    let mut fields = Vec::new();
+    let mut section = String::new();
+    let mut lines = contents.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.to_string();
+            continue;
+        }
+        let mut logical = line.to_string();
+        while logical.ends_with('\\') {
+            logical.pop();
+            let Some(next) = lines.next() else { break };
+            logical = format!("{} {}", logical.trim_end(), next.trim());
+        }
+        let (key, value) = logical.split_once('=').unwrap_or((&logical, ""));
+        fields.push(RawField {
+            section: section.clone(),
+            key: key.trim().to_string(),
+            value: value.trim().to_string(),
+        });
+    }
+    fields
+}
+
+/// A key whose values differ between two versions of a unit; no values
+/// means it's gone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectiveChange {
+    pub section: String,
+    pub key: String,
+    pub values: Vec<String>,
+}
+
+/// The keys whose values differ between `before` and `after`, with the
+/// values `after` gives them.
+pub fn changed_directives(before: &str, after: &str) -> Vec<DirectiveChange> {
+    let values = |fields: &[RawField], section: &str, key: &str| -> Vec<String> {
+        fields
+            .iter()
+            .filter(|f| f.section == section && f.key == key)
+            .map(|f| f.value.clone())
+            .collect()
+    };
+    let (before, after) = (raw_fields(before), raw_fields(after));
+    let mut changes: Vec<DirectiveChange> = Vec::new();
+    for field in after.iter().chain(&before) {
+        if changes
+            .iter()
+            .any(|c| c.section == field.section && c.key == field.key)
+        {
+            continue;
+        }
+        let new = values(&after, &field.section, &field.key);
+        if new != values(&before, &field.section, &field.key) {
+            changes.push(DirectiveChange {
+                section: field.section.clone(),
+                key: field.key.clone(),
+                values: new,
+            });
+        }
+    }
+    changes
+}
+
+/// `contents` with `changes` made in place: a changed key's first line takes
+/// its new values and its other lines go, and a new key goes at the end of
+/// its section. Everything else, comments and order included, stays as
+/// written.
+pub fn apply_directives(contents: &str, changes: &[DirectiveChange]) -> String {
+    // The lines before the first section, then each section's.
+    let mut blocks: Vec<(String, Vec<&str>)> = vec![(String::new(), Vec::new())];
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            blocks.push((name.to_string(), Vec::new()));
+        }
+        if let Some((_, lines)) = blocks.last_mut() {
+            lines.push(line);
+        }
+    }
+    let lines_for = |change: &DirectiveChange| {
+        change
+            .values
+            .iter()
+            .map(|value| format!("{}={value}", change.key))
+            .collect::<Vec<_>>()
+    };
+
+    let mut out: Vec<String> = Vec::new();
+    let mut done = vec![false; changes.len()];
+    for (section, lines) in &blocks {
+        let start = out.len();
+        let mut lines = lines.iter();
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim();
+            let key = trimmed
+                .split_once('=')
+                .filter(|_| !trimmed.starts_with(['#', ';']))
+                .map(|(key, _)| key.trim());
+            let Some(index) = key.and_then(|key| {
+                changes
+                    .iter()
+                    .position(|c| &c.section == section && c.key == key)
+            }) else {
+                out.push(line.to_string());
+                continue;
+            };
+            let mut continued = trimmed.ends_with('\\');
+            while continued {
+                continued = lines.next().is_some_and(|next| next.trim().ends_with('\\'));
+            }
+            if !done[index] {
+                done[index] = true;
+                out.extend(lines_for(&changes[index]));
+            }
+        }
+        if section.is_empty() {
+            continue;
+        }
+        // New keys go after the section's last line, before the blank lines
+        // that separate it from the next.
+        let mut at = out.len();
+        while at > start + 1 && out[at - 1].trim().is_empty() {
+            at -= 1;
+        }
+        let added: Vec<String> = changes
+            .iter()
+            .enumerate()
+            .filter(|(index, change)| &change.section == section && !done[*index])
+            .flat_map(|(_, change)| lines_for(change))
+            .collect();
+        for (index, change) in changes.iter().enumerate() {
+            done[index] |= &change.section == section;
+        }
+        out.splice(at..at, added);
+    }
+    // Sections the file doesn't have yet.
+    for (index, change) in changes.iter().enumerate() {
+        if done[index] || change.values.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push(String::new());
+        }
+        out.push(format!("[{}]", change.section));
+        for (other, later) in changes.iter().enumerate().skip(index) {
+            if later.section == change.section {
+                done[other] = true;
+                out.extend(lines_for(later));
+            }
+        }
+    }
+    let mut text = out.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Keys whose lines add up rather than override: a drop-in adds another
+/// value, and an empty assignment (`ExecStart=`) clears those before it.
+/// Condition and Assert keys work the same way.
+const LIST_KEYS: &[&str] = &[
+    "After",
+    "Before",
+    "Wants",
+    "Requires",
+    "Requisite",
+    "BindsTo",
+    "PartOf",
+    "Conflicts",
+    "WantedBy",
+    "RequiredBy",
+    "Also",
+    "Alias",
+    "Environment",
+    "EnvironmentFile",
+    "PassEnvironment",
+    "UnsetEnvironment",
+    "ExecCondition",
+    "ExecStartPre",
+    "ExecStart",
+    "ExecStartPost",
+    "ExecReload",
+    "ExecStop",
+    "ExecStopPost",
+    "ReadWritePaths",
+    "ReadOnlyPaths",
+    "InaccessiblePaths",
+    "AmbientCapabilities",
+    "CapabilityBoundingSet",
+    "IPAddressAllow",
+    "IPAddressDeny",
+    "RestrictAddressFamilies",
+    "StateDirectory",
+    "RuntimeDirectory",
+    "CacheDirectory",
+    "LogsDirectory",
+    "ConfigurationDirectory",
+    "ListenStream",
+    "ListenDatagram",
+    "ListenSequentialPacket",
+    "ListenFIFO",
+    "OnCalendar",
+    "OnActiveSec",
+    "OnBootSec",
+    "OnStartupSec",
+    "OnUnitActiveSec",
+    "OnUnitInactiveSec",
+    "PathExists",
+    "PathExistsGlob",
+    "PathChanged",
+    "PathModified",
+    "DirectoryNotEmpty",
+];
+
+/// A setting in effect once a unit's drop-ins are applied, and the file
+/// that set it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectiveField {
+    pub field: RawField,
+    pub source: PathBuf,
+}
+
+/// Apply drop-ins the way systemd loads a unit: `files` is the unit file
+/// followed by its drop-ins in order. List-valued keys accumulate (an empty
+/// value clears them); any other key takes its last value.
+pub fn merge_unit_files(files: &[(PathBuf, String)]) -> Vec<EffectiveField> {
+    let mut effective: Vec<EffectiveField> = Vec::new();
+    for (source, contents) in files {
+        for field in raw_fields(contents) {
+            let accumulates = LIST_KEYS.contains(&field.key.as_str())
+                || field.key.starts_with("Condition")
+                || field.key.starts_with("Assert");
+            if !accumulates || field.value.is_empty() {
+                effective.retain(|e| e.field.section != field.section || e.field.key != field.key);
+            }
+            if accumulates && field.value.is_empty() {
+                continue;
+            }
+            effective.push(EffectiveField {
+                field,
+                source: source.clone(),
+            });
+        }
+    }
+    effective
+}
+
+/// Unit file text with exactly these settings, for parsing merged fields
+/// with [`parse_systemd`]. Comments (including ser's managed marker) are
+/// gone, so pass the original for anything that depends on them.
+pub fn render_fields(fields: &[EffectiveField]) -> String {
+    let mut sections: Vec<(&str, Vec<&RawField>)> = Vec::new();
+    for EffectiveField { field, .. } in fields {
+        match sections.iter_mut().find(|(name, _)| *name == field.section) {
+            Some((_, section)) => section.push(field),
+            None => sections.push((&field.section, vec![field])),
+        }
+    }
+    let mut out = String::new();
+    for (name, fields) in sections {
+        out.push_str(&format!("[{name}]\n"));
+        for field in fields {
+            out.push_str(&format!("{}={}\n", field.key, field.value));
+        }
+    }
+    out
+}
+
+fn canonical_key(key: &str) -> String {
+    KNOWN_KEYS
+        .iter()
+        .find(|known| known.eq_ignore_ascii_case(key))
+        .map(|known| known.to_string())
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Sort `Environment=` entries by variable name, in place of the first one.
+/// The sort is stable, so a repeated variable keeps its last-wins order. An
+/// empty `Environment=` resets the list, so sections with one are left alone.
+fn sort_environment(entries: &mut Vec<Entry>) {
+    if entries
+        .iter()
+        .any(|e| e.key == "Environment" && e.value.is_empty())
+    {
+        return;
+    }
+    let Some(first) = entries.iter().position(|e| e.key == "Environment") else {
+        return;
+    };
+    let (mut env, rest): (Vec<Entry>, Vec<Entry>) = std::mem::take(entries)
+        .into_iter()
+        .partition(|e| e.key == "Environment");
+    env.sort_by_key(|e| env_var_name(&e.value).to_string());
+    let mut rest = rest.into_iter();
+    entries.extend(rest.by_ref().take(first));
+    entries.extend(env);
+    entries.extend(rest);
+}
+
+fn env_var_name(quoted: &str) -> &str {
+    let unquoted = quoted.trim_start_matches('"');
+    unquoted.split('=').next().unwrap_or(unquoted)
+}
+
+/// Split an `Environment=` or `ExecStart=` value into words, removing quotes
+/// the way systemd does (`A=1 "B=two words"` is two assignments, and
+/// `sh -c 'echo hi'` is two arguments after the program).
+pub fn split_words(value: &str) -> Vec<String> {
+    let mut assignments = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, c) if c.is_whitespace() => {
+                if !current.is_empty() {
+                    assignments.push(std::mem::take(&mut current));
+                }
+            }
+            (None, c) => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        assignments.push(current);
+    }
+    assignments
+}
+
+/// Read a `.timer`, `.socket`, or `.path` unit named `name` (e.g.
+/// `web.socket`): which unit it activates and its section's settings. `None`
+/// for any other kind of unit.
+pub fn parse_trigger_unit(name: &str, contents: &str) -> Option<TriggerUnit> {
+    let kind = TriggerKind::from_unit_name(name)?;
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let mut unit = None;
+    let mut accept = false;
+    let mut settings = Vec::new();
+    let mut in_section = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_section = section == kind.section();
+            continue;
+        }
+        if !in_section || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match key {
+            "Unit" => unit = Some(value.to_string()),
+            "Accept" => accept = matches!(value, "true" | "yes" | "1" | "on"),
+            _ => {}
+        }
+        if key != "Unit" {
+            settings.push((key.to_string(), value.to_string()));
+        }
+    }
+    let unit = unit.unwrap_or_else(|| {
+        if kind == TriggerKind::Socket && accept {
+            format!("{stem}@.service")
+        } else {
+            format!("{stem}.service")
+        }
+    });
+    Some(TriggerUnit {
+        name: name.to_string(),
+        kind,
+        unit,
+        settings,
+    })
+}
+
+/// Check that a unit file has the shape systemd reads: `[Section]` headers
+/// and `Key=Value` lines (continued with a trailing `\`), blank lines, and
+/// `#`/`;` comments. Doesn't judge the keys or values.
+pub fn check_syntax(contents: &str) -> Result<()> {
+    let mut in_section = false;
+    let mut continued = false;
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        let continuation = continued;
+        continued = line.ends_with('\\');
+        if continuation || line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            in_section = true;
+        } else if !line.contains('=') {
+            bail!(
+                "line {}: expected [Section] or Key=Value, got {line:?}",
+                i + 1
+            );
+        } else if !in_section {
+            bail!("line {}: {line:?} comes before any [Section]", i + 1);
+        }
+    }
+    Ok(())
+}
+
+/// Quote an `ExecStart=` word only if `split_words` would otherwise split
+/// it or drop its quotes, so plain words stay as written.
+fn quote_word(word: &str) -> String {
+    if word.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        quote_env_assignment(word)
+    } else {
+        word.to_string()
+    }
+}
+
+pub(crate) fn quote_env_assignment(assignment: &str) -> String {
+    format!(
+        "\"{}\"",
+        assignment.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_edits_only_the_changed_keys() {
+        let unit = "# Hand-written\n[Unit]\nDescription=Web\n\n[Service]\nExecStart=/usr/bin/web --port 80\nOOMScoreAdjust=100\nLimitNOFILE=65536\n\n[Install]\nWantedBy=multi-user.target\n";
+        let before = parse_systemd(unit).unwrap();
+        let mut after = before.clone();
+        after.set_field("oom_score", "").unwrap();
+        after.set_field("oom_policy", "stop").unwrap();
+        after.set_field("description", "Web app").unwrap();
+        let changes = changed_directives(
+            &generate_file(&before).unwrap(),
+            &generate_file(&after).unwrap(),
+        );
+        assert_eq!(changes.len(), 3, "{changes:?}");
+        assert_eq!(
+            apply_directives(unit, &changes),
+            "# Hand-written\n[Unit]\nDescription=Web app\n\n[Service]\nExecStart=/usr/bin/web --port 80\nLimitNOFILE=65536\nOOMPolicy=stop\n\n[Install]\nWantedBy=multi-user.target\n"
+        );
+
+        let changes = [
+            DirectiveChange {
+                section: "Service".to_string(),
+                key: "ExecStart".to_string(),
+                values: vec!["/usr/bin/web".to_string()],
+            },
+            DirectiveChange {
+                section: "Timer".to_string(),
+                key: "Persistent".to_string(),
+                values: vec!["true".to_string()],
+            },
+        ];
+        assert_eq!(
+            apply_directives(
+                "[Service]\nExecStart=/usr/bin/web \\\n  --port 80\n",
+                &changes
+            ),
+            "[Service]\nExecStart=/usr/bin/web\n\n[Timer]\nPersistent=true\n"
+        );
+    }
+
+    #[test]
+    fn merges_drop_ins() {
+        let unit = "[Service]\nExecStart=/usr/bin/web\nEnvironment=A=1\nRestart=always\nAfter=network.target\n";
+        let drop_in = "[Service]\nExecStart=\nExecStart=/usr/bin/web --port 9090\nEnvironment=B=2\nRestart=no\n";
+        let files = [
+            (PathBuf::from("web.service"), unit.to_string()),
+            (
+                PathBuf::from("web.service.d/override.conf"),
+                drop_in.to_string(),
+            ),
+        ];
+        let merged = merge_unit_files(&files);
+        let service = parse_systemd(&render_fields(&merged)).unwrap();
+        assert_eq!(service.arguments, ["--port", "9090"]);
+        assert_eq!(service.env_vars.len(), 2);
+        assert!(!service.keep_alive);
+        assert_eq!(service.after, ["network.target"]);
+        let exec = merged.iter().find(|e| e.field.key == "ExecStart").unwrap();
+        assert_eq!(exec.source, PathBuf::from("web.service.d/override.conf"));
+    }
+
+    #[test]
+    fn parses_trigger_units() {
+        let socket = "[Unit]\nDescription=Web socket\n\n[Socket]\nListenStream=8080\nAccept=yes\n\n[Install]\nWantedBy=sockets.target\n";
+        let trigger = parse_trigger_unit("web.socket", socket).unwrap();
+        assert_eq!(trigger.kind, TriggerKind::Socket);
+        assert_eq!(trigger.unit, "web@.service");
+        assert_eq!(
+            trigger.settings,
+            [
+                ("ListenStream".to_string(), "8080".to_string()),
+                ("Accept".to_string(), "yes".to_string())
+            ]
+        );
+
+        let path = "[Path]\nPathChanged=/srv/inbox\nUnit=import.service\n";
+        let trigger = parse_trigger_unit("inbox.path", path).unwrap();
+        assert_eq!(trigger.unit, "import.service");
+        assert_eq!(trigger.settings.len(), 1);
+
+        let timer = parse_trigger_unit("backup.timer", "[Timer]\nOnCalendar=daily\n").unwrap();
+        assert_eq!(timer.kind, TriggerKind::Timer);
+        assert_eq!(timer.unit, "backup.service");
+        assert!(parse_trigger_unit("web.service", socket).is_none());
+    }
+
+    #[test]
+    fn generate_parse_roundtrip() {
+        let details = ServiceDetails {
+            name: "myapp".to_string(),
+            description: Some("My application".to_string()),
+            tags: vec!["backend".to_string(), "api".to_string()],
+            owner: Some("ops@example.com".to_string()),
+            program: "/usr/bin/myapp".to_string(),
+            arguments: split_words(r#"--port 8080 --motd 'say "hi" there'"#),
+            oom_score_adjust: Some(500),
+            oom_policy: Some(OomPolicy::Stop),
+            conditions: vec![Condition {
+                kind: "PathExists".to_string(),
+                value: "/srv/data".to_string(),
+                assert: false,
+            }],
+            directories: vec![
+                ManagedDirectory {
+                    kind: DirectoryKind::State,
+                    name: "myapp".to_string(),
+                },
+                ManagedDirectory {
+                    kind: DirectoryKind::State,
+                    name: "myapp/db".to_string(),
+                },
+                ManagedDirectory {
+                    kind: DirectoryKind::Runtime,
+                    name: "myapp".to_string(),
+                },
+            ],
+            ambient_capabilities: vec!["CAP_NET_BIND_SERVICE".to_string()],
+            capability_bounding_set: Some(vec!["CAP_NET_BIND_SERVICE".to_string()]),
+            user: Some("myapp".to_string()),
+            ip_address_allow: vec!["localhost".to_string()],
+            ip_address_deny: vec!["any".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(details.arguments[3], r#"say "hi" there"#);
+        let unit = generate_file(&details).unwrap();
+        assert!(unit.contains(r#"ExecStart=/usr/bin/myapp --port 8080 --motd "say \"hi\" there""#));
+        let parsed = parse_systemd(&unit).unwrap();
+        assert_eq!(parsed.description, details.description);
+        assert_eq!(parsed.tags, details.tags);
+        assert_eq!(parsed.owner, details.owner);
+        assert_eq!(parsed.program, details.program);
+        assert_eq!(parsed.arguments, details.arguments);
+        assert_eq!(parsed.oom_score_adjust, Some(500));
+        assert_eq!(parsed.oom_policy, Some(OomPolicy::Stop));
+        assert_eq!(parsed.conditions, details.conditions);
+        assert_eq!(parsed.directories, details.directories);
+        assert_eq!(parsed.ambient_capabilities, details.ambient_capabilities);
+        assert_eq!(
+            parsed.capability_bounding_set,
+            details.capability_bounding_set
+        );
+        assert_eq!(parsed.user, details.user);
+        assert_eq!(parsed.ip_address_allow, details.ip_address_allow);
+        assert_eq!(parsed.ip_address_deny, details.ip_address_deny);
+    }
+
+    #[test]
+    fn merges_address_families_like_systemd() {
+        let families = |lines: &[&str]| {
+            let mut families = Vec::new();
+            for line in lines {
+                merge_address_families(&mut families, line);
+            }
+            families
+        };
+        assert_eq!(
+            families(&["AF_INET AF_INET6", "AF_UNIX"]),
+            ["AF_INET", "AF_INET6", "AF_UNIX"]
+        );
+        assert_eq!(
+            families(&["~AF_PACKET", "~AF_NETLINK"]),
+            ["~AF_PACKET", "AF_NETLINK"]
+        );
+        assert_eq!(families(&["AF_INET AF_UNIX", "~AF_INET"]), ["AF_UNIX"]);
+        assert_eq!(families(&["~AF_PACKET AF_INET", "AF_PACKET"]), ["~AF_INET"]);
+        assert_eq!(families(&["AF_UNIX", "~AF_UNIX"]), ["none"]);
+        assert_eq!(families(&["none", "AF_UNIX"]), ["AF_UNIX"]);
+        assert_eq!(families(&["~AF_PACKET", ""]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn raw_fields_keep_unmodeled_keys() {
+        let fields = raw_fields("[Unit]\nDescription=x\n# note\n[Service]\nExecStart=/bin/a \\\n  --b\nLimitNOFILE=4096\n");
+        let pairs: Vec<_> = fields
+            .iter()
+            .map(|f| (f.section.as_str(), f.key.as_str(), f.value.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("Unit", "Description", "x"),
+                ("Service", "ExecStart", "/bin/a --b"),
+                ("Service", "LimitNOFILE", "4096"),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_exec_start_is_an_error() {
+        assert!(parse_systemd("[Unit]\nDescription=nothing\n").is_err());
+    }
+
+    #[test]
+    fn format_unit_is_canonical_and_idempotent() {
+        let messy = "# Managed by ser\n[Install]\nwantedby = default.target\n\n[Service]\nexecstart=/usr/bin/app \\\n  --flag\n# db settings\nEnvironment=B=2 \"A=one two\"\n\n[Unit]\nDescription = demo\n";
+        let formatted = format_unit(messy);
+        assert_eq!(
+            formatted,
+            "# Managed by ser\n[Unit]\nDescription=demo\n\n[Service]\nExecStart=/usr/bin/app --flag\nEnvironment=\"A=one two\"\n# db settings\nEnvironment=\"B=2\"\n\n[Install]\nWantedBy=default.target\n"
+        );
+        assert_eq!(format_unit(&formatted), formatted);
+
+        let parsed = parse_systemd(&formatted).unwrap();
+        assert_eq!(
+            parsed.env_vars,
+            vec![
+                ("A".to_string(), "one two".to_string()),
+                ("B".to_string(), "2".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_unit_syntax() {
+        assert!(check_syntax("# hi\n[Service]\nExecStart=/bin/a \\\n  --flag\n; note\n").is_ok());
+        let garbage = check_syntax("[Service]\nExecStart=/bin/a\n\u{0}\u{1}junk\n").unwrap_err();
+        assert!(garbage.to_string().starts_with("line 3:"), "{garbage}");
+        assert!(check_syntax("ExecStart=/bin/a\n").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Writing YAML for `--format yaml`: block style, for anything serde can turn
+//! into JSON. ser reads no YAML itself, so there's no parser.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+/// `value` as a YAML document.
+pub fn to_string<T: Serialize>(value: &T) -> Result<String> {
+    let value = serde_json::to_value(value)?;
+    let mut out = String::new();
+    write_block(&mut out, &value, 0);
+    Ok(out)
+}
+
+fn write_block(out: &mut String, value: &Value, indent: usize) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                out.push_str(&format!("{pad}{}:", scalar(key)));
+                if is_collection(value) {
+                    out.push('\n');
+                    write_block(out, value, indent + 2);
+                } else {
+                    out.push_str(&format!(" {}\n", inline(value)));
+                }
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for item in items {
+                // Write the item one level in, then hang its first line off
+                // the dash: `- name: web`.
+                let mut block = String::new();
+                write_block(&mut block, item, indent + 2);
+                out.push_str(&format!("{pad}- {}", &block[indent + 2..]));
+            }
+        }
+        _ => out.push_str(&format!("{pad}{}\n", inline(value))),
+    }
+}
+
+/// A non-empty object or array, which gets lines of its own.
+fn is_collection(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => false,
+    }
+}
+
+fn inline(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => scalar(s),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+    }
+}
+
+/// `s` unquoted only when it's plainly a word or path (`web`, `/usr/bin`,
+/// `web.service`), and double-quoted (JSON escapes are valid YAML)
+/// otherwise. Anything looser risks a YAML 1.1 reader taking it for a
+/// number, date, or other type: `0x1F`, `1_000`, `.inf`, `12:30`,
+/// `2026-10-16`, `<<`.
+fn scalar(s: &str) -> String {
+    let reserved = matches!(
+        s.to_ascii_lowercase().as_str(),
+        "null" | "true" | "false" | "yes" | "no" | "on" | "off" | "y" | "n"
+    );
+    let plain = !reserved
+        && s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '/')
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_./-".contains(c));
+    if plain {
+        s.to_string()
+    } else {
+        serde_json::to_string(s).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn writes_block_style() {
+        let value = json!({
+            "name": "web",
+            "enabled": true,
+            "arguments": ["--port", "8080", "0x1F", "2026-10-16", "12:30", ".inf", "<<", "No"],
+            "env": [{"key": "PATH", "value": "/usr/bin: /bin"}],
+            "tags": [],
+            "trigger": null,
+        });
+        assert_eq!(
+            to_string(&value).unwrap(),
+            "arguments:\n  - \"--port\"\n  - \"8080\"\n  - \"0x1F\"\n  - \"2026-10-16\"\n  - \"12:30\"\n  - \".inf\"\n  - \"<<\"\n  - \"No\"\nenabled: true\nenv:\n  - key: PATH\n    value: \"/usr/bin: /bin\"\nname: web\ntags: []\ntrigger: null\n"
+        );
+    }
+}
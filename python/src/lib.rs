@@ -0,0 +1,124 @@
+//! The `ser` Python module: the CLI's service management for ops scripts and
+//! Ansible modules.
+//!
+//! ```python
+//! import ser
+//! ser.create({"name": "web", "program": "/usr/bin/python3", "arguments": ["-m", "http.server"]})
+//! ser.start("web")
+//! print(ser.get_service_details("web")["running"])
+//! ```
+//!
+//! Services come back as plain dicts and lists (the serde form of serlib's
+//! types), so they're easy to log or compare. Failures raise `ser.SerError`;
+//! bad arguments raise `ValueError`.
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use serlib::platform::{self, ListLevel};
+use serlib::ServiceDetails;
+
+create_exception!(ser, SerError, PyException, "A service operation failed.");
+
+fn failed(e: anyhow::Error) -> PyErr {
+    SerError::new_err(format!("{e:#}"))
+}
+
+/// Convert a serlib value to Python objects through its JSON form.
+fn to_python<'py>(py: Python<'py>, value: &impl serde::Serialize) -> PyResult<Bound<'py, PyAny>> {
+    let json = serde_json::to_string(value).map_err(|e| failed(e.into()))?;
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+/// List services as dicts of `name`, `path`, `enabled`, and `source`.
+/// `level` is `"default"`, `"user"`, or `"system"`.
+#[pyfunction]
+#[pyo3(signature = (level = "default"))]
+fn list_services<'py>(py: Python<'py>, level: &str) -> PyResult<Bound<'py, PyAny>> {
+    let level = match level {
+        "default" => ListLevel::Default,
+        "user" => ListLevel::User,
+        "system" => ListLevel::System,
+        _ => return Err(PyValueError::new_err(format!("unknown level {level:?}"))),
+    };
+    let services = py
+        .detach(|| platform::list_services(level))
+        .map_err(failed)?;
+    to_python(py, &services)
+}
+
+/// A service's definition and state: a dict of `service` (the definition),
+/// `path`, `enabled`, and `running`.
+#[pyfunction]
+fn get_service_details<'py>(py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyAny>> {
+    let details = py
+        .detach(|| platform::get_service_details(name))
+        .map_err(failed)?;
+    to_python(py, &details)
+}
+
+#[pyfunction]
+fn start(py: Python<'_>, name: &str) -> PyResult<()> {
+    py.detach(|| platform::start_service(name)).map_err(failed)
+}
+
+#[pyfunction]
+fn stop(py: Python<'_>, name: &str) -> PyResult<()> {
+    py.detach(|| platform::stop_service(name)).map_err(failed)
+}
+
+/// Create or update a service from a dict with the fields of a service
+/// definition (as returned in `get_service_details(...)["service"]`). Only
+/// `name` and `program` are required.
+#[pyfunction]
+fn create(py: Python<'_>, details: &Bound<'_, PyDict>) -> PyResult<()> {
+    let json: String = py
+        .import("json")?
+        .call_method1("dumps", (details,))?
+        .extract()?;
+    let details: ServiceDetails = serde_json::from_str(&json)
+        .map_err(|e| PyValueError::new_err(format!("invalid service details: {e}")))?;
+    if details.name.is_empty() || details.program.is_empty() {
+        return Err(PyValueError::new_err(
+            "service details need a name and a program",
+        ));
+    }
+    py.detach(|| platform::create_service(&details))
+        .map_err(failed)
+}
+
+#[pymodule(name = "ser")]
+fn ser_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add("SerError", m.py().get_type::<SerError>())?;
+    m.add_function(wrap_pyfunction!(list_services, m)?)?;
+    m.add_function(wrap_pyfunction!(get_service_details, m)?)?;
+    m.add_function(wrap_pyfunction!(start, m)?)?;
+    m.add_function(wrap_pyfunction!(stop, m)?)?;
+    m.add_function(wrap_pyfunction!(create, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_bad_arguments() {
+        Python::initialize();
+        Python::attach(|py| {
+            let err = list_services(py, "everything").unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+
+            let details = PyDict::new(py);
+            details.set_item("name", "web").unwrap();
+            let err = create(py, &details).unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+
+            let err = get_service_details(py, "ser-no-such-service").unwrap_err();
+            assert!(err.is_instance_of::<SerError>(py));
+        });
+    }
+}